@@ -1,47 +1,116 @@
 use anyhow::Result;
-use clap::Parser;
-use log::error;
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use futures::stream::{self, StreamExt};
+use indicatif::{ProgressBar, ProgressStyle};
 use moz_cli_version_check::VersionChecker;
+use output::OutputFormat;
+use searchfox_lib::cassette::{Cassette, CassetteMode};
+use searchfox_lib::session::Session;
+use searchfox_lib::stats::{group_by_directory, parse_group_by, DirectoryCount, SearchStats};
+use searchfox_lib::watch::{parse_interval, WatchReport};
+use searchfox_lib::wrap::{line_number_prefix_len, terminal_width, wrap_hanging};
 use searchfox_lib::{
-    call_graph::{format_call_graph_markdown, CallGraphQuery},
+    annotation::format_annotation_audit,
+    batch::{format_batch_results, format_batch_summary, parse_batch_queries, BatchResult},
+    blame::format_line_blame,
+    bug_component::{format_component_files, format_component_of, BugComponent},
+    call_graph::{format_call_graph_markdown, CallGraphQuery, SymbolGraphCollection},
     can_gc::GcInfo,
     categorize_spec_ref,
-    field_layout::{format_field_layout, FieldLayoutQuery},
+    checkout::{checkout_root, detect_repository},
+    class_diagram::{format_class_diagram, DiagramFormat},
+    class_hierarchy::format_class_hierarchy,
+    complete::format_completions,
+    contract::format_registrations,
+    crash_frame::{frame_candidates, normalize_frame},
+    demangle::{demangle, demangle_text},
+    duplicates::format_duplicates,
+    error_report,
+    examples::format_usage_examples,
+    field_layout::{
+        format_class_layout, format_field_layout, format_layout_diff, parse_field_layout,
+        sort_fields, FieldLayoutQuery, LayoutSort,
+    },
+    file_reader::{is_binary, parse_line_range, select_lines, FileBackend},
+    impact::format_impact_report,
+    implementations::format_implementations,
+    includes::format_include_report,
+    log_module::format_log_module,
+    lsif::format_lsif,
+    module_graph::format_module_graph,
     nesting::NestingContext,
+    overrides::{format_overrides, OverrideDirection},
     parse_commit_header,
-    search::SearchOptions,
-    searchfox_url_repo, spec_ref_category_names, CategoryFilter, SearchfoxClient,
+    regex_check::check_regex,
+    rename::format_rename_plan,
+    report::format_symbol_report,
+    repos::format_tree_list,
+    resolve::format_resolved_symbols,
+    search::{
+        files_with_matches, parse_extensions, ResultSummary, SearchMetadata, SearchOptions,
+        SearchResult, SortOrder,
+    },
+    searchfox_url_repo,
+    similar::format_similar_code,
+    spec_ref_category_names,
+    style::{color_enabled, highlight_ranges, set_color_enabled},
+    tags::{format_ctags, format_etags},
+    tests_for::format_test_files,
+    tokens::estimate_tokens,
+    unused::format_unused_candidates,
+    uses::format_uses,
+    utils::fallback_repos,
+    wpt::{format_wpt_candidates, WebIdlFeature},
+    BinaryFileError, CategoryFilter, Lang, NoMatchesError, SearchfoxClient,
 };
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::ffi::OsString;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+
+mod mcp;
+mod output;
+mod shell;
+
+const DOWNLOAD_CONCURRENCY: usize = 8;
 
 #[derive(Parser, Debug)]
 #[command(
     name = "searchfox-cli",
+    args_override_self = true,
     about = "Searchfox CLI for Mozilla code search",
-    long_about = "A command-line interface for searching Mozilla codebases using searchfox.org.\n\nExamples:\n  searchfox-cli -q AudioStream\n  searchfox-cli -q AudioStream -C -l 10\n  searchfox-cli -q '^Audio.*' -r\n  searchfox-cli -q AudioStream -p ^dom/media\n  searchfox-cli -p PContent.ipdl  # Search for files by path only\n  searchfox-cli --get-file dom/media/AudioStream.h\n  searchfox-cli --symbol AudioContext\n  searchfox-cli --symbol 'AudioContext::CreateGain'\n  searchfox-cli --id main\n  searchfox-cli -q 'path:dom/media AudioStream'\n  searchfox-cli -q 'symbol:AudioContext' --context 3\n  searchfox-cli --define 'AudioContext::CreateGain'\n  searchfox-cli --calls-from 'mozilla::dom::AudioContext::CreateGain' --depth 2\n  searchfox-cli --calls-to 'mozilla::dom::AudioContext::CreateGain' --depth 3\n  searchfox-cli --calls-between 'AudioContext,AudioNode' --depth 2\n  searchfox-cli --field-layout 'mozilla::dom::AudioContext'"
+    long_about = "A command-line interface for searching Mozilla codebases using searchfox.org.\n\nExamples:\n  searchfox-cli -q AudioStream\n  searchfox-cli -q AudioStream -C -l 10\n  searchfox-cli -q '^Audio.*' -r\n  searchfox-cli -q AudioStream -p ^dom/media\n  searchfox-cli -p PContent.ipdl  # Search for files by path only\n  searchfox-cli file dom/media/AudioStream.h\n  searchfox-cli file dom/media/AudioStream.h --rev <HASH>\n  searchfox-cli --get-dir dom/media/webaudio --out ./webaudio --lang cpp\n  searchfox-cli --symbol AudioContext\n  searchfox-cli --symbol 'AudioContext::CreateGain'\n  searchfox-cli --id main\n  searchfox-cli -q 'path:dom/media AudioStream'\n  searchfox-cli -q 'symbol:AudioContext' --context 3\n  searchfox-cli define 'AudioContext::CreateGain'\n  searchfox-cli --report 'mozilla::dom::AudioContext::CreateGain'\n  searchfox-cli calls from 'mozilla::dom::AudioContext::CreateGain' --depth 2\n  searchfox-cli calls to 'mozilla::dom::AudioContext::CreateGain' --depth 3\n  searchfox-cli calls between AudioContext AudioNode --depth 2\n  searchfox-cli layout 'mozilla::dom::AudioContext'\n  searchfox-cli blame dom/media/AudioStream.cpp --lines 100,120-140\n  searchfox-cli shell\n  searchfox-cli serve-mcp"
 )]
 struct Args {
     #[arg(short, long, help = "Search query string")]
     query: Option<String>,
 
     #[arg(
+        global = true,
         short = 'R',
         long,
         default_value = "mozilla-central",
         help = "Repository to search in",
-        long_help = "Repository to search in. Available repositories:\n  mozilla-central (default) - Main Firefox development\n  mozilla-beta - Beta release branch\n  mozilla-release - Release branch\n  mozilla-esr115 - ESR 115 branch\n  mozilla-esr128 - ESR 128 branch\n  mozilla-esr140 - ESR 140 branch\n  comm-central - Thunderbird development"
+        long_help = "Repository to search in. Available repositories:\n  mozilla-central (default) - Main Firefox development\n  mozilla-beta - Beta release branch\n  mozilla-release - Release branch\n  mozilla-esr115 - ESR 115 branch\n  mozilla-esr128 - ESR 128 branch\n  mozilla-esr140 - ESR 140 branch\n  comm-central - Thunderbird development\nOther trees of the server, listed by --list-repos, can be passed as-is.\nWhen not given and run inside a Mozilla checkout, the repository the checkout tracks is used:\nthe hg.mozilla.org default path of an hg clone, or the upstream branch (main, beta, release,\nesrNNN) of a git clone of the Firefox repository."
     )]
     repo: String,
 
     #[arg(
-        short,
-        long,
+        global = true,
+        short = 'p',
+        long = "path",
+        value_name = "PATH",
         help = "Filter results by path prefix (e.g., ^dom/media) or search for files by path",
-        long_help = "Filter search results by file path prefix or search for files by path pattern.\nUse regex patterns to match specific directories or files.\nCan be used alone to search for files without a query.\nExamples:\n  -p ^dom/media (with query) - filters results to files starting with dom/media/\n  -p PContent.ipdl (alone) - finds all files matching PContent.ipdl"
+        long_help = "Filter search results by file path prefix or search for files by path pattern.\nUse regex patterns to match specific directories or files.\nCan be used alone to search for files without a query.\nGiven several times, searches run once per path and their results are merged without\nduplicates; other commands use the first path.\nExamples:\n  -p ^dom/media (with query) - filters results to files starting with dom/media/\n  -p PContent.ipdl (alone) - finds all files matching PContent.ipdl\n  -p ^dom/media -p ^media/libcubeb - results from either directory"
     )]
-    path: Option<String>,
+    paths: Vec<String>,
 
     #[arg(
+        global = true,
         short = 'C',
         long,
         default_value_t = false,
@@ -50,15 +119,25 @@ struct Args {
     case: bool,
 
     #[arg(
+        global = true,
         short,
         long,
         default_value_t = false,
         help = "Enable regular expression search",
-        long_help = "Enable regular expression search mode.\nAllows using regex patterns in the query string.\nExample: '^Audio.*' matches identifiers starting with 'Audio'"
+        long_help = "Enable regular expression search mode.\nAllows using regex patterns in the query string.\nThe pattern is checked locally first: common PCRE syntax such as\n(?<name>...), (?>...) or possessive quantifiers is translated, and\nlookarounds, backreferences and \\n are rejected with an explanation.\nExample: '^Audio.*' matches identifiers starting with 'Audio'"
     )]
     regexp: bool,
 
     #[arg(
+        global = true,
+        long,
+        help = "Send the query exactly as given",
+        long_help = "Send the query to searchfox exactly as given, percent-encoded, without the local regex\ncheck or the context:/text: prefixes otherwise added. Searchfox still interprets its own\nprefixes (path:, symbol:, ...) in the query.\nExample: --raw -q '/* Copyright'"
+    )]
+    raw: bool,
+
+    #[arg(
+        global = true,
         short,
         long,
         default_value_t = 50,
@@ -67,19 +146,231 @@ struct Args {
     limit: usize,
 
     #[arg(
+        global = true,
+        long,
+        value_name = "N",
+        conflicts_with = "page",
+        help = "Skip the first N results",
+        long_help = "Skip the first N results, to walk through a large result set --limit results at a time.\nSearchfox has no paging: the whole response is fetched, and results are windowed locally\nin a stable order: normal, test, then generated results.\nExample: -q AudioStream -l 50 --offset 100"
+    )]
+    offset: Option<usize>,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Show the Nth page of --limit results",
+        long_help = "Show the Nth page of --limit results, starting at 1: same as --offset (N-1)*limit.\nExample: -q AudioStream -l 20 --page 3"
+    )]
+    page: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "REGEX",
+        help = "Keep only results whose line matches REGEX (applied locally)",
+        long_help = "Refine results client-side: only matched lines whose text matches REGEX are kept\n(the file path is matched for path-only searches). No extra request is made, so\ncombined with the response cache this is a cheap way to narrow a broad query.\nExample: -q AudioStream --filter 'Init\\('"
+    )]
+    filter: Option<String>,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Order results by relevance: definitions, declarations, uses, then text matches",
+        long_help = "Order search results by relevance instead of the order of searchfox's response:\ndefinitions first, then declarations, then other uses, then plain text matches. Within\neach group, files outside tests and generated code come first, then shorter paths.\nThe -l limit applies after ranking, so it keeps the most relevant hits.\nExample: -q AudioSink --rank -l 10"
+    )]
+    rank: bool,
+
+    #[arg(
+        long,
+        value_name = "ORDER",
+        conflicts_with = "rank",
+        value_parser = clap::builder::PossibleValuesParser::new(SortOrder::names()),
+        help = "Sort search results by path, line, category or matches",
+        long_help = "Sort search results instead of keeping the order of searchfox's response: by path, by line\nnumber, by category (normal, then test, then generated) or by matches (files with the most\nresults first). Ties are broken by path then line, so the output is reproducible.\nThe -l limit applies after sorting.\nExample: -q AudioSink --sort matches -l 20"
+    )]
+    sort: Option<String>,
+
+    #[arg(
+        long,
+        help = "List a line once per kind of match instead of merging its duplicates",
+        long_help = "Searchfox lists a line once per kind of match, e.g. under both Definitions and Uses.\nResults are deduplicated by path and line, the kinds of the duplicates being kept with the\nfirst one (other_kinds in --format json); this lists each occurrence as searchfox does.\nIgnored with --rank, whose results are always deduplicated.\nExample: --id AudioContext --keep-duplicates"
+    )]
+    keep_duplicates: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Take at most N matching lines of each file",
+        long_help = "Take at most N matching lines of each file, then move on to the next one, so that one noisy\nfile (often generated code) does not use up the whole -l limit. Lines skipped this way\ndo not count toward -l.\nExample: -q AudioStream -l 40 --max-per-file 3"
+    )]
+    max_per_file: Option<usize>,
+
+    #[arg(
+        hide = true,
         long,
-        help = "Fetch and display the contents of a specific file",
-        long_help = "Fetch and display the contents of a specific file from the repository.\nProvide the file path relative to the repository root.\nExample: --get-file dom/media/AudioStream.h"
+        value_name = "PATH",
+        num_args = 1..,
+        help = "Fetch and display the contents of one or more files",
+        long_help = "Fetch and display the contents of files from the repository.\nProvide the file paths relative to the repository root, or - to read them from stdin, one per line.\nSeveral files are fetched concurrently and printed one after the other, each under a ==> PATH <== header.\nExample: --get-file dom/media/AudioStream.h dom/media/AudioStream.cpp"
     )]
-    get_file: Option<String>,
+    get_file: Vec<String>,
 
     #[arg(
+        hide = true,
+        long,
+        help = "Show the commit that last changed each line of a file"
+    )]
+    blame_file: Option<String>,
+
+    #[arg(
+        hide = true,
         long,
         help = "Line range for --get-file (e.g., 10-20, 10, 10-)",
-        long_help = "Specify line range when using --get-file.\nFormats:\n  --lines 10-20  (lines 10 through 20)\n  --lines 10     (just line 10)\n  --lines 10-    (from line 10 to end)\n  --lines -20    (from start to line 20)\nExample: --get-file dom/media/AudioStream.h --lines 100-150"
+        long_help = "Specify line range when using --get-file.\nFormats:\n  --lines 10-20  (lines 10 through 20)\n  --lines 10     (just line 10)\n  --lines 10-    (from line 10 to end)\n  --lines -20    (from start to line 20)\nExample: --get-file dom/media/AudioStream.h --lines 100-150\nThe blame subcommand also takes comma-separated ranges: --lines 100,120-140"
     )]
     lines: Option<String>,
 
+    #[arg(
+        hide = true,
+        long,
+        requires = "get_file",
+        conflicts_with = "blame",
+        help = "Fetch --get-file content as of a specific revision",
+        long_help = "Fetch the file as it was at a specific revision instead of the latest indexed one.\nAccepts the commit hash searchfox uses in its permalinks (see --permalink), or with\n--file-backend hg, an hg.mozilla.org changeset (or a revision of the configured raw URL).\nUseful to reproduce old bugs or look at code as it was when a regression landed.\nExample: --get-file dom/media/AudioStream.h --rev 3c4f1e8a..."
+    )]
+    rev: Option<String>,
+
+    #[arg(
+        hide = true,
+        long,
+        requires = "get_file",
+        help = "Print --get-file content even when it is a binary file",
+        long_help = "Binary files (images, fonts, wasm, ...) are refused by --get-file so they do not garble the terminal.\nWith --binary-ok, their raw bytes are written to stdout anyway, e.g. to pipe them to another program.\nExample: --get-file browser/branding/official/firefox.icns --binary-ok > firefox.icns"
+    )]
+    binary_ok: bool,
+
+    #[arg(
+        hide = true,
+        long,
+        value_name = "FILE",
+        requires = "get_file",
+        conflicts_with_all = ["lines", "rev", "blame", "binary_ok"],
+        help = "Save the raw --get-file content to FILE instead of printing it",
+        long_help = "Write the raw bytes of the --get-file file to FILE instead of printing it.\nWorks for text and binary files alike.\nExample: --get-file browser/branding/official/firefox.icns --output firefox.icns"
+    )]
+    output: Option<PathBuf>,
+
+    #[arg(
+        hide = true,
+        long,
+        requires = "get_file",
+        conflicts_with_all = ["lines", "rev", "blame", "output"],
+        help = "Print the raw bytes of --get-file content, without decoding it",
+        long_help = "Write the file's bytes to stdout exactly as stored, instead of decoding them as UTF-8.\nUseful for files in another encoding (e.g. Latin-1), whose invalid UTF-8 is otherwise replaced with a warning.\nBinary files still need --binary-ok.\nExample: --get-file <FILE> --bytes | iconv -f latin1"
+    )]
+    bytes: bool,
+
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with_all = ["get_file", "define"],
+        help = "Download every file under a directory into a local folder",
+        long_help = "Enumerate all files under a repository directory and download them concurrently.\nLanguage filters (--lang) and category filters (--exclude-tests, ...) restrict which files are fetched.\nFiles keep their layout relative to DIR inside the --out folder.\nExample: --get-dir dom/media/webaudio --out ./webaudio --lang cpp"
+    )]
+    get_dir: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "DIR",
+        requires = "get_dir",
+        help = "Destination folder for --get-dir (defaults to the directory's name)"
+    )]
+    out: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Bundle the full content of every matched file into a .tar.gz",
+        long_help = "Fetch the full content of every file appearing in the search results and write them,\nwith their repository paths, into a gzip-compressed tarball.\nHandy for sharing a self-contained slice of the code base.\nExample: -q AudioStream -p ^dom/media --lang cpp --archive audiostream.tar.gz"
+    )]
+    archive: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Run the searches listed in FILE, one per line (- for stdin)",
+        long_help = "Run many searches in one process, a few at a time, reading one query per line from FILE,\nor from standard input when FILE is - (same as -q -). Blank lines and lines starting with #\nare skipped. Other options (-p, -C, -r, -l, language and category filters) apply to every\nquery. Each result line is prefixed with its query and a tab; failed queries are reported\nin place and make the command fail once all have run.\nExample: --batch queries.txt -p ^dom/media -l 20"
+    )]
+    batch: Option<PathBuf>,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["archive", "link", "permalink", "blame"],
+        help = "Report hit counts per category and kind instead of listing results",
+        long_help = "Count the hits of a search without listing them, broken down by searchfox category\n(normal, test, generated) and by kind of match (Definitions, Declarations, Uses, text matches, ...).\nThe -l limit does not apply; language and category filters do.\nUseful to decide which filtered follow-up query to run.\nExample: --id AudioContext --stats-only"
+    )]
+    stats_only: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["archive", "link", "permalink", "blame", "stats_only", "watch", "emit_tags", "files_with_matches", "format", "html_report"],
+        help = "Print the number of results instead of listing them",
+        long_help = "Print only how many results the search found, like grep -c. The -l limit applies, so raise\nit to count past the default; see --stats-only for totals broken down by category and kind.\nWith --batch, prints query<TAB>count per query.\nExample: -q 'AudioContext::' -p ^dom/media -l 10000 --count"
+    )]
+    count: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["archive", "link", "permalink", "blame", "stats_only", "watch", "emit_tags", "format", "html_report"],
+        help = "Print the paths of the files with results, once each",
+        long_help = "Print only the paths of the files with results, one per line, in the order the results\ncome, like grep -l; pipe them to xargs or an editor. The -l limit applies to the results\nbefore they are reduced to files. With --batch, prints query<TAB>path lines.\nExample: --id AudioStream --files-with-matches | xargs $EDITOR"
+    )]
+    files_with_matches: bool,
+
+    #[arg(
+        long,
+        value_name = "dir[:DEPTH]",
+        conflicts_with_all = ["archive", "link", "permalink", "blame", "stats_only", "watch", "emit_tags", "count", "files_with_matches", "format", "html_report"],
+        help = "Count the hits of a search per directory, most hits first",
+        long_help = "Count the hits of a search per directory instead of listing them, and print a table of\nhits and files per directory, most hits first, to see where in the tree something is used\nbefore drilling in. Directories are cut to DEPTH components (2 by default, e.g. dom/media).\nAs with --stats-only, the -l limit does not apply; language and category filters do.\nExample: --id AudioContext --group-by dir:3"
+    )]
+    group_by: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["archive", "link", "permalink", "blame", "stats_only"],
+        help = "Write the definitions matched by a search to a ctags/etags file",
+        long_help = "Write the definitions found by a symbol, id or text search into a tags file, so that\nvim or Emacs can jump to remote-indexed symbols as if a local tags file existed.\nA file named TAGS is written in Emacs' etags format, anything else in ctags format.\nEach definition is tagged both by its short and its qualified name. Paths are relative\nto the tags file, so write it at the root of your checkout or pass --checkout.\nThe -l limit applies to the number of definitions.\nExample: --id AudioContext -p ^dom/media -l 500 --emit-tags tags"
+    )]
+    emit_tags: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "DIR",
+        requires = "emit_tags",
+        help = "Local checkout the paths of --emit-tags point into",
+        long_help = "Prefix the paths written by --emit-tags with the directory of a local checkout.\nExample: --id AudioContext --emit-tags ~/tags --checkout ~/src/firefox"
+    )]
+    checkout: Option<String>,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["archive", "link", "permalink"],
+        help = "Compare search results with the previous run and report new/removed matches",
+        long_help = "Re-run a search and compare its results with the ones stored by the previous --watch run\nof the same query. Matches are compared by file and line text, so code moving around does not\nshow up as a change. The first run stores a baseline.\nWithout --interval, runs once (suitable for cron); with --interval, keeps running.\nEvery match is compared, whatever -l.\nExample: -q 'NS_DEPRECATED_API' --watch --interval 24h"
+    )]
+    watch: bool,
+
+    #[arg(
+        long,
+        requires = "watch",
+        value_name = "DURATION",
+        help = "Re-run a --watch query periodically (e.g., 30m, 24h, 7d)"
+    )]
+    interval: Option<String>,
+
     #[arg(
         long,
         help = "Number of context lines to show around matches",
@@ -87,10 +378,30 @@ struct Args {
     )]
     context: Option<usize>,
 
+    #[arg(
+        global = true,
+        short = 'A',
+        long,
+        value_name = "N",
+        help = "Show N lines after each match, read from the files",
+        long_help = "Show N lines after each match, like grep -A, for any kind of search. The files of the\nresults are fetched (concurrently) after the search, or read from the current checkout\nwhen it tracks the searched repository. Unlike --context, this needs no text: query.\nExample: -q AudioStream -p ^dom/media -A 3 -B 1"
+    )]
+    after_context: Option<usize>,
+
+    #[arg(
+        global = true,
+        short = 'B',
+        long,
+        value_name = "N",
+        help = "Show N lines before each match, read from the files",
+        long_help = "Show N lines before each match, like grep -B; see -A. (-C is --case: pass both -A and\n-B for context on both sides.)\nExample: --id AudioContext -B 2"
+    )]
+    before_context: Option<usize>,
+
     #[arg(
         long,
         help = "Search by mangled symbol name (or plain name for C functions)",
-        long_help = "Search by mangled symbol name using searchfox's symbol: query.\nMangled names are returned by --calls-from, --calls-to, and --calls-between.\nC functions are unmangled, so their plain name works directly.\nFor C++/JS use --id instead.\nExample: --symbol '_ZN7mozilla3dom24BaseAudioContext_BindingL10createGainE...'\nExample: --symbol 'PR_Sleep'  (C function, no mangling)"
+        long_help = "Search by mangled symbol name using searchfox's symbol: query.\nMangled names are returned by the calls subcommand with --no-demangle.\nC functions are unmangled, so their plain name works directly.\nFor C++/JS use --id instead.\nExample: --symbol '_ZN7mozilla3dom24BaseAudioContext_BindingL10createGainE...'\nExample: --symbol 'PR_Sleep'  (C function, no mangling)"
     )]
     symbol: Option<String>,
 
@@ -102,6 +413,23 @@ struct Args {
     id: Option<String>,
 
     #[arg(
+        long,
+        value_name = "SYMBOL",
+        help = "List the indexed uses of a symbol with their enclosing function",
+        long_help = "List the places searchfox's index records as using a symbol (calls, references, reads and\nwrites) with the function containing each one. Unlike a text search, comments, strings and\nunrelated identifiers with the same name are left out. -p, -l, --offset, the language and\ncategory filters apply.\nExample: --uses 'AudioContext::CreateGain' -p ^dom/media"
+    )]
+    uses: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "List the concrete symbols a name stands for, with their signatures",
+        long_help = "Look up the definitions and declarations of NAME and list the symbols searchfox knows\nthem by (mangled for C++), one per overload or same-named function, as\nSYMBOL<TAB>SIGNATURE<TAB>definition|declaration<TAB>PATH:LINE. Pass the right one to\n--symbol or to a call graph query. -p and the language and category filters apply.\nExample: --resolve-symbol 'AudioContext::CreateGain'"
+    )]
+    resolve_symbol: Option<String>,
+
+    #[arg(
+        hide = true,
         long,
         help = "Find and display the definition of a symbol",
         long_help = "Find the definition of a symbol using searchfox's structured data.\nSearches for symbol definitions and class/struct declarations.\nDisplays the complete method/function body or class declaration.\nExample: --define 'AudioContext::CreateGain' or --define 'AudioContext'"
@@ -109,6 +437,177 @@ struct Args {
     define: Option<String>,
 
     #[arg(
+        long,
+        value_name = "SYMBOL",
+        conflicts_with = "define",
+        help = "List every definition of a symbol",
+        long_help = "List every definition of an overloaded or multiply-defined symbol, where --define\nonly shows the first one: path, line and first line of each, one per line.\nAdd --bodies to print the source of each definition under its line.\nExample: --define-all 'AudioNode::Connect' --bodies"
+    )]
+    define_all: Option<String>,
+
+    #[arg(
+        long,
+        requires = "define_all",
+        help = "Print the source of each definition listed by --define-all"
+    )]
+    bodies: bool,
+
+    #[arg(
+        long,
+        value_name = "SYMBOL",
+        conflicts_with_all = ["define", "define_all"],
+        help = "Show the declaration of a symbol rather than its definition",
+        long_help = "Show where a symbol is declared, usually in a header, rather than its definition: the\ndeclaration with its doc comment and default arguments, under the line opening its class.\nSymbols without a separate declaration (inline methods) show their definition instead.\nExample: --declare 'AudioNode::Connect'"
+    )]
+    declare: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "List all distinct definitions sharing an identifier name",
+        long_help = "List every distinct definition whose unqualified name is NAME, grouped by\nenclosing namespace or class. Useful when a stack trace only gives an unqualified name.\nCombine with --path to narrow the search.\nExample: --duplicates Shutdown -p ^dom/media"
+    )]
+    duplicates: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "PREFIX",
+        help = "List indexed identifiers starting with a prefix",
+        long_help = "List the indexed identifiers starting with PREFIX, like the website's autocomplete,\none per line as NAME<TAB>KIND<TAB>PATH:LINE. Matching ignores case and applies to any\ncomponent of qualified names; a qualified prefix narrows to a namespace or class.\nThe kind is guessed from the definition line. -l limits the number of results;\n--path and language filters apply; --format json is supported.\nExample: --complete AudioCont or --complete 'mozilla::dom::Audio'"
+    )]
+    complete: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "Find files whose name looks like NAME, best match first",
+        long_help = "Find files from a half-remembered name, like an editor's Ctrl-P: the characters of NAME\nare looked for in order in each file name (AudioStrm.h finds AudioStream.h), then in the\nwhole path, and a file name a few typos away also matches. Candidates come from path\nsearches for the words of NAME. -l limits the number of results; language and category\nfilters apply.\nExample: --find-file AudioStrm.h"
+    )]
+    find_file: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "SYMBOL",
+        help = "Show ranked call sites of a symbol with surrounding context",
+        long_help = "Fetch the call sites of a symbol, rank them (non-test code first, diverse directories,\nshort enclosing functions) and print the best ones with a few lines of context.\nUse -n to choose how many.\nExample: --examples 'mozilla::MozPromise::Then' -n 5"
+    )]
+    examples: Option<String>,
+
+    #[arg(
+        short = 'n',
+        long,
+        default_value_t = 5,
+        help = "Number of call sites to show with --examples"
+    )]
+    num_examples: usize,
+
+    #[arg(
+        long,
+        value_name = "HEADER",
+        help = "List files that #include a header, grouped by directory",
+        long_help = "Search for #include directives referencing a header, either by its path in the tree\nor by its exported name (e.g. mozilla/AudioStream.h), and summarize the including files by directory.\nCombine with --path or category filters to narrow the search.\nExample: --included-by dom/media/AudioStream.h"
+    )]
+    included_by: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "SYMBOL",
+        help = "List test files referencing a symbol",
+        long_help = "Find the test files (gtest, mochitest, xpcshell, web-platform, ...) referencing a symbol,\nusing searchfox's test category and test-path heuristics, grouped by harness with the most\nreferences first.\nExample: --tests-for 'mozilla::AudioStream::Init'"
+    )]
+    tests_for: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "INTERFACE[.MEMBER]",
+        help = "List web-platform-tests exercising a WebIDL interface or member",
+        long_help = "Find the web-platform-tests (under testing/web-platform, or -p) using a WebIDL member as a\nwhole word, listing first the files that also name the interface in their text or path,\nthen those only using the member name, which may belong to another interface. Without a\nmember, the files naming the interface. Ends with the directories holding the likely tests\nand a `./mach wpt` command.\nExample: --wpt-for 'AudioContext.createGain'"
+    )]
+    wpt_for: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "INTERFACE",
+        help = "Find the classes implementing an XPCOM interface",
+        long_help = "Find the concrete classes implementing an XPCOM/XPIDL interface, from the NS_DECL_ macro\ndeclaring its methods and from the QueryInterface implementations (NS_IMPL_ISUPPORTS,\nNS_INTERFACE_MAP_BEGIN ... END) listing it, with every interface each QueryInterface\nanswers to. Classes inheriting the interface from an implementing base are not listed.\nExample: --implementations nsIStreamListener -p ^netwerk/"
+    )]
+    implementations: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "CONTRACT_ID",
+        help = "Find where an XPCOM contract ID is registered, and its class and constructor",
+        long_help = "Find where an XPCOM contract ID is registered, following components.conf entries,\nstatic module tables (#define ..._CONTRACTID mapped to a CID and a constructor) and\nchrome manifest contract lines, and print the implementing class, constructor, headers\nand CID of each registration.\nExample: --contract '@mozilla.org/network/protocol;1?name=http'"
+    )]
+    contract: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "MODULE",
+        help = "Find a MOZ_LOG module's declaration and call sites",
+        long_help = "Find where the LazyLogModule named MODULE is declared and every MOZ_LOG call site using\nit, directly or through a wrapper macro (#define LOG(...) MOZ_LOG(...)) defined in the\nsame file, grouped by file with the log level of each site.\nExample: --log-module cubeb"
+    )]
+    log_module: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "FRAME",
+        help = "Resolve a crash report frame to its definition, with blame",
+        long_help = "Take a Socorro-style crash frame, normalize it (module prefix, arguments, template\narguments, anonymous namespaces, lambdas and Rust hashes are dropped), and print the\ndefinition of the resulting symbol with the last change of each line.\nExample: --crash-frame 'mozilla::dom::AudioContext::CreateGain(mozilla::ErrorResult&)'"
+    )]
+    crash_frame: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "URL",
+        help = "Find the JS modules and files importing an ES module",
+        long_help = "Find the files importing a JS module (ChromeUtils.importESModule, defineESModuleGetters,\nstatic import, ChromeUtils.import), and with --depth N the importers of those importers,\nprinted as a tree. Modules above the first level are matched by file name.\nExample: --module-graph 'resource://gre/modules/AppConstants.sys.mjs' --depth 2"
+    )]
+    module_graph: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "COMPONENT",
+        help = "List the files owned by a Bugzilla component",
+        long_help = "List the files a Bugzilla component owns, from the BUG_COMPONENT metadata of the\nmoz.build files, with the moz.build blocks assigning it and a -p pattern scoping\nsearches to its directories. Files reassigned by a later or deeper block are excluded.\nExample: --component-files 'Core :: Audio/Video: cubeb'"
+    )]
+    component_files: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Show the Bugzilla component a file belongs to",
+        long_help = "Show the Bugzilla component a file is filed under, and the moz.build block that sets it.\nExample: --component-of media/libcubeb/src/cubeb.c"
+    )]
+    component_of: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "List every occurrence of a name to rename, as a checklist",
+        long_help = "Collect every occurrence of NAME for a rename: indexed definitions, declarations and uses,\nthen whole-word text matches classified as string literals, build files, docs, comments\nor other text. Prints a checklist with the lines to change in each file, or a JSON plan\nwith --rename-json.\nExample: --rename-plan AudioSink -p ^dom/media"
+    )]
+    rename_plan: Option<String>,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        requires = "rename_plan",
+        help = "Print the --rename-plan inventory as JSON",
+        long_help = "Print the --rename-plan inventory as JSON for other tools: the name and, for each kind of\noccurrence in checklist order, the files and line numbers to change.\nExample: --rename-plan AudioSink --rename-json"
+    )]
+    rename_json: bool,
+
+    #[arg(
+        long,
+        value_name = "PATH:START-END",
+        help = "Find code similar to a range of lines, e.g. copy-pasted logic",
+        long_help = "Find near-duplicates of lines START-END of PATH elsewhere in the tree. PATH is read from\nthe local checkout when it exists there, so uncommitted code works, and fetched from\nsearchfox otherwise. The snippet's distinctive identifiers are searched for, hits close\ntogether in a file form candidate regions, and the best candidates are ranked by the share\nof the snippet's token trigrams they contain. -p, language filters and -l apply.\nExample: --similar-to dom/media/AudioStream.cpp:120-150 -p ^dom/"
+    )]
+    similar_to: Option<String>,
+
+    #[arg(
+        global = true,
         long,
         help = "Enable request logging with timing and size information",
         long_help = "Log all HTTP requests made to searchfox with detailed timing information:\n- Request start/end timestamps\n- Response size and duration\n- Network latency measurement via ping\nUseful for performance analysis and infrastructure planning"
@@ -116,12 +615,105 @@ struct Args {
     log_requests: bool,
 
     #[arg(
+        global = true,
+        long,
+        value_name = "BACKEND",
+        default_value = "searchfox",
+        help = "Where to fetch file contents from: searchfox or hg",
+        long_help = "Choose where file, define and other commands reading files fetch contents from:\n  searchfox (default) - text extracted from searchfox's source pages\n  hg - raw files from hg.mozilla.org (mozilla-central, comm-central, and releases/* for beta, release and ESR trees),\n       or from the `raw` URL of the repository in config.toml\nGenerated files (__GENERATED__/...), and files of repositories with no known mirror, are always fetched from searchfox.\nExample: file dom/media/AudioStream.h --file-backend hg -R mozilla-esr128"
+    )]
+    file_backend: FileBackend,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "URL",
+        help = "Base URL of the mozsearch instance to query",
+        long_help = "Query a self-hosted mozsearch deployment instead of https://searchfox.org. Every request and\nprinted link uses this base URL; --repo names a tree of that instance. The `server` key of the\n[network] table of config.toml sets a default.\nExample: --server https://searchfox.example.com -R my-project -q Parser"
+    )]
+    server: Option<String>,
+
+    #[arg(
+        global = true,
+        long,
+        help = "Print errors as JSON objects on stderr",
+        long_help = "Print errors as a single-line JSON object on stderr instead of free-form text:\n{\"kind\": ..., \"message\": ..., \"url\": ..., \"status\": ...}\nkind is one of not_found, rate_limited, client_error, server_error, network, binary_file,\nno_matches or other; url and status are null when the error is not about a request.\nUseful for wrappers that need to react to rate limiting differently from missing files.\nThe exit status is 1 when nothing matched, 2 for usage errors and 3 for network or server errors."
+    )]
+    json_errors: bool,
+
+    #[arg(
+        global = true,
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        default_value = "text",
+        help = "Output format: text, json, grep, csv or markdown",
+        long_help = "Print the result of search, --batch, --uses, --complete, define, file, blame, calls and layout as one JSON document\ninstead of text. Every document has schema_version, command and repo fields, then the fields\nof the command, as documented in the README; errors are printed as with --json-errors.\nFields may be added within a schema version, never renamed or removed.\nWith grep, search, --batch and --uses print path:line:column:text lines, the column being\nthat of the match, for vim -q, :cexpr and other errorformat consumers.\nWith csv, search, --batch, --uses and blame print a header row then path, line, category\nand text columns, followed by those of the command (query, context, commit, bug, ...).\nWith markdown, search and define print their hits grouped by file, each in a fenced code\nblock after a permalink to its lines, ready to paste into a bug.\nExample: define 'AudioContext::CreateGain' --format json\nExample: -q AudioStream -p ^dom/media --format grep > hits; vim -q hits"
+    )]
+    format: OutputFormat,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "FILE",
+        help = "Write search or call graph results to a standalone HTML file",
+        long_help = "Render the results of a search or of a calls query as a single HTML file, to share an\nanalysis with people without the tool: one collapsible section per file, search hits as\nsyntax-highlighted snippets with their -A/-B context, and permalinks to searchfox at the\nindexed revision. Styles are inline, the file needs nothing else to be opened.\nExample: -q AudioStream -p ^dom/media -A 2 -B 2 --html-report audiostream.html\nExample: calls to 'AudioContext::CreateGain' --depth 2 --html-report callers.html"
+    )]
+    html_report: Option<PathBuf>,
+
+    #[arg(
+        global = true,
+        long,
+        value_enum,
+        value_name = "WHEN",
+        default_value = "auto",
+        help = "Color matches and table headers: auto, always or never",
+        long_help = "Highlight the matched text of search results, and color table headers. auto colors\nwhen stdout is a terminal, following NO_COLOR, CLICOLOR and CLICOLOR_FORCE; always\nand never override them, e.g. to keep colors through a pager.\nExample: -q AudioStream --color always | less -R"
+    )]
+    color: ColorWhen,
+
+    #[arg(
+        global = true,
+        long,
+        help = "When a file or definition is missing, look for it on other branches",
+        long_help = "When file gets a 404 or define finds nothing on the selected repository (e.g. the file\nwas renamed or removed), try the other branches: older ones first (beta, release, ESRs),\nthen newer ones. The branch that served the content is reported on stderr.\nExample: file dom/media/AudioStream.h -R mozilla-central --branch-fallback"
+    )]
+    branch_fallback: bool,
+
+    #[arg(
+        global = true,
+        long,
+        help = "Report an estimate of the output's token count on stderr",
+        long_help = "After the command has run, print an estimate of how many LLM tokens its output\n(stdout) takes on stderr, e.g. `~1234 tokens (5678 bytes)`. The estimate\napproximates BPE tokenizers and is meant for budgeting context, not exact accounting.\nExample: -q AudioStream -p ^dom/media --count-tokens"
+    )]
+    count_tokens: bool,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "N",
+        help = "Print nothing and fail if the output is estimated over N tokens",
+        long_help = "Refuse to print the output when its estimated token count (see --count-tokens)\nis over N: an error says how large it was, so the query can be narrowed\n(-p, -l, language filters) instead of flooding a context window.\nExample: --define 'AudioContext::CreateGain' --max-tokens 2000"
+    )]
+    max_tokens: Option<usize>,
+
+    #[arg(
+        global = true,
+        long,
+        help = "Print mangled C++ symbols as they are instead of demangling them",
+        long_help = "Call graphs, symbol reports and --symbol results show mangled C++ symbols\n(_ZN7mozilla...) as their demangled signature by default. With --no-demangle they\nare printed as searchfox returns them, e.g. to copy one into --symbol.\nJSON output always has both forms.\nExample: calls from 'AudioContext::CreateGain' --no-demangle"
+    )]
+    no_demangle: bool,
+
+    #[arg(
+        global = true,
         long = "no-cache",
         help = "Disable cache reads and writes for this invocation"
     )]
     no_cache: bool,
 
     #[arg(
+        global = true,
         long = "force-refetch",
         help = "Bypass cached file content and fetch fresh content from searchfox"
     )]
@@ -134,42 +726,140 @@ struct Args {
     clear_cache: bool,
 
     #[arg(
+        long,
+        value_name = "SYMBOL",
+        help = "Print a one-shot report about a symbol",
+        long_help = "Combine everything known about a symbol into a single markdown report:\ndefinition, declarations, field layout (for C++ classes), direct callers and callees,\nand usage counts per kind and category. Language and category filters apply.\nExample: --report 'mozilla::dom::AudioContext::CreateGain'"
+    )]
+    report: Option<String>,
+
+    #[arg(
+        long,
+        help = "Show which revision the repository was last indexed at",
+        long_help = "Report the revision the selected repository's index was built from and its commit date,\nto judge how stale search results may be.\nExample: --index-info -R mozilla-beta"
+    )]
+    index_info: bool,
+
+    #[arg(
+        long,
+        help = "List the repositories the server indexes, with their indexed revisions",
+        long_help = "Fetch the list of trees from the server's front page and print each one with the revision\nit was last indexed at and that revision's commit date. Any of these names can be passed to\n--repo. With --server, lists the trees of that instance.\nExample: --list-repos"
+    )]
+    list_repos: bool,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "FILE",
+        conflicts_with = "replay_session",
+        help = "Append this invocation and the requests it issues to a session file",
+        long_help = "Record this invocation (its arguments) and every request it issues into a JSON session file.\nIf the file already exists, the invocation is appended, so an investigation spanning several\ncommands builds up a single session that can be shared and replayed with --replay-session.\nExample: -q AudioStream --save-session audio.json"
+    )]
+    save_session: Option<PathBuf>,
+
+    #[arg(
+        global = true,
+        long,
+        requires = "save_session",
+        help = "Also store response bodies in the --save-session file"
+    )]
+    session_responses: bool,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "FILE",
+        help = "Re-run every invocation recorded in a session file",
+        long_help = "Re-execute, in order, every invocation recorded with --save-session against the current index.\nRecorded responses are not reused: each query is issued again, so results reflect any index updates.\nExample: --replay-session audio.json"
+    )]
+    replay_session: Option<PathBuf>,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["replay", "replay_session"],
+        help = "Record every HTTP exchange of this invocation into a cassette file",
+        long_help = "Record every request this invocation issues, with its response, into a JSON cassette.\nReplaying the cassette with --replay reproduces the output without network access,\nfor sharing an investigation or writing deterministic tests. The cache is bypassed\nwhile recording so that the cassette holds every request the command needs.\nExample: --define 'AudioContext::CreateGain' --record gain.json"
+    )]
+    record: Option<PathBuf>,
+
+    #[arg(
+        global = true,
+        long,
+        value_name = "FILE",
+        conflicts_with = "replay_session",
+        help = "Answer requests from a cassette recorded with --record, offline",
+        long_help = "Serve every request from a cassette recorded with --record instead of the network.\nRun the same command that was recorded; a request missing from the cassette is an error.\nUnlike --replay-session, nothing is fetched again, so the output is exactly the recorded one.\nExample: --define 'AudioContext::CreateGain' --replay gain.json"
+    )]
+    replay: Option<PathBuf>,
+
+    #[arg(
+        global = true,
+        long = "lang",
+        value_name = "LANGS",
+        help = "Filter results to files of these languages, e.g. rust,cpp,idl",
+        long_help = "Filter results to files of the given comma-separated languages:\ncpp (.cc, .cpp, .cxx, .h, .hh, .hpp, .mm), c (.c, .h), js (.js, .mjs, .cjs, .jsx, .ts, .tsx),\nwebidl, idl (XPIDL), ipdl (.ipdl, .ipdlh), java, kotlin (.kt, .kts), rust, python,\nhtml (.html, .xhtml, .htm), css, toml, yaml (.yaml, .yml).\n--cpp, --c, --webidl, --js and --java remain as shorthands.\nExample: -q AudioStream --lang rust,cpp"
+    )]
+    lang: Option<String>,
+
+    #[arg(
+        global = true,
+        long = "ext",
+        value_name = "EXTS",
+        help = "Filter results to files with these extensions, e.g. .rs,.toml",
+        long_help = "Filter results to files ending with one of the given comma-separated extensions,\nwhich may span several dots (.sys.mjs). Combined with --lang, files matching either pass.\nExample: -q AudioStream --ext .sjs,.jsm"
+    )]
+    ext: Option<String>,
+
+    #[arg(
+        global = true,
+        hide = true,
         long = "cpp",
         help = "Filter results to C++ files only",
-        long_help = "Filter results to C++ files only (.cc, .cpp, .h, .hh, .hpp)"
+        long_help = "Filter results to C++ files only (.cc, .cpp, .cxx, .h, .hh, .hpp, .mm), same as --lang cpp"
     )]
     cpp: bool,
 
     #[arg(
+        global = true,
+        hide = true,
         long = "c",
         help = "Filter results to C files only",
-        long_help = "Filter results to C files only (.c, .h)"
+        long_help = "Filter results to C files only (.c, .h), same as --lang c"
     )]
     c_lang: bool,
 
     #[arg(
+        global = true,
+        hide = true,
         long = "webidl",
         help = "Filter results to WebIDL files only",
-        long_help = "Filter results to WebIDL files only (.webidl)"
+        long_help = "Filter results to WebIDL files only (.webidl), same as --lang webidl"
     )]
     webidl: bool,
 
     #[arg(
+        global = true,
+        hide = true,
         long = "js",
         help = "Filter results to JavaScript files only",
-        long_help = "Filter results to JavaScript files only (.js, .mjs, .ts, .cjs, .jsx, .tsx)"
+        long_help = "Filter results to JavaScript files only (.js, .mjs, .ts, .cjs, .jsx, .tsx), same as --lang js"
     )]
     js: bool,
 
     #[arg(
+        global = true,
+        hide = true,
         long = "java",
         visible_aliases = ["kt"],
         help = "Filter results to Java/Kotlin files only",
-        long_help = "Filter results to Java/Kotlin files only (.java, .kt)"
+        long_help = "Filter results to Java/Kotlin files only (.java, .kt, .kts), same as --lang java,kotlin"
     )]
     java: bool,
 
     #[arg(
+        hide = true,
         long = "calls-from",
         help = "Find functions called by the specified symbol",
         long_help = "Search for functions called by the specified symbol using call graph analysis.\nExample: --calls-from 'mozilla::dom::AudioContext::CreateGain'"
@@ -177,6 +867,7 @@ struct Args {
     calls_from: Option<String>,
 
     #[arg(
+        hide = true,
         long = "calls-to",
         help = "Find functions that call the specified symbol",
         long_help = "Search for functions that call the specified symbol using call graph analysis.\nExample: --calls-to 'mozilla::dom::AudioContext::CreateGain'"
@@ -184,6 +875,7 @@ struct Args {
     calls_to: Option<String>,
 
     #[arg(
+        hide = true,
         long = "calls-between",
         help = "Find function calls between two symbols or classes",
         long_help = "Find function calls between two symbols or classes.\nExample: --calls-between 'AudioContext,AudioNode'"
@@ -193,20 +885,145 @@ struct Args {
     #[arg(
         long = "depth",
         default_value_t = 1,
-        help = "Set traversal depth for call graph searches",
-        long_help = "Set the depth of traversal for call graph searches. Higher values show more indirect calls.\nDefault is 1. Example: --depth 3"
+        help = "Set traversal depth for call graph and module graph searches",
+        long_help = "Set the depth of traversal for call graph searches. Higher values show more indirect calls.\nWith --module-graph, the number of levels of importers to follow; with --class-hierarchy,\nthe levels of base classes and subclasses (or of related classes for --class-diagram); with --overrides and --overridden-by, the levels\nof overrides.\nDefault is 1. Example: --depth 3"
     )]
     depth: u32,
 
     #[arg(
+        long,
+        value_name = "SYMBOL",
+        help = "List transitive callers of a symbol grouped by file and directory",
+        long_help = "Estimate the blast radius of changing a symbol: compute its transitive callers up to --depth\nlevels (calls-to traversal) and list the affected files and directories with counts.\nCategory filters apply, e.g. --exclude-tests ignores callers defined in test files.\nExample: --impact 'mozilla::dom::AudioContext::CreateGain' --depth 3 --exclude-tests"
+    )]
+    impact: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "CLASS_OR_PATH",
+        help = "Flag functions of a class or file that have no non-test callers",
+        long_help = "Enumerate the functions defined in a class (its header and matching .cpp) or in a file,\nusing searchfox's outline data, and list those with no callers outside of tests.\nHeuristic: virtual overrides, callbacks and functions used through pointers are listed too.\nExample: --unused-in 'mozilla::AudioStream'\nExample: --unused-in dom/media/AudioStream.cpp"
+    )]
+    unused_in: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "ATTRIBUTE",
+        help = "Report which functions carry a static-analysis attribute under a path",
+        long_help = "Audit a Gecko static-analysis attribute (MOZ_CAN_RUN_SCRIPT, MOZ_MUST_USE, ...) under the\npath given with --in: find the files using it, then list every function they and their\nheader or implementation file define, by outline, marked as annotated (on the definition or\non a declaration) or not. Overloads are not told apart.\nExample: --annotation MOZ_CAN_RUN_SCRIPT --in ^dom/media"
+    )]
+    annotation: Option<String>,
+
+    #[arg(
+        long = "in",
+        value_name = "PATH",
+        requires = "annotation",
+        help = "Path (regex) audited by --annotation",
+        long_help = "Path (regex) audited by --annotation, e.g. ^dom/media. Defaults to -p.\nExample: --annotation MOZ_CAN_RUN_SCRIPT --in ^dom/media/webaudio"
+    )]
+    annotation_in: Option<String>,
+
+    #[arg(
+        long = "export-lsif",
+        value_name = "SYMBOL",
+        help = "Export a class or namespace's definitions, references and calls as LSIF",
+        long_help = "Write an LSIF dump (JSON lines, on stdout) of a class or namespace and its members:\ntheir definitions, declarations and references, plus the functions each member calls,\nso that other code-intelligence tools (Sourcegraph-compatible viewers) can ingest\nsearchfox's data for a slice of the tree. LSIF has no call edges: callees are listed in\neach function's hover and included with their definitions. -p and language filters apply.\nExample: --export-lsif 'mozilla::dom::AudioContext' > audiocontext.lsif"
+    )]
+    export_lsif: Option<String>,
+
+    #[arg(
+        hide = true,
         long = "field-layout",
         visible_aliases = ["class-layout", "struct-layout"],
-        help = "Display the field layout of a C++ class or struct",
-        long_help = "Display the field layout of a C++ class or struct, showing size and offset information.\nExample: --field-layout 'soundtouch::SoundTouch' or --field-layout 'mozilla::dom::AudioContext'"
+        help = "Display the field layout of a C++ or Rust class or struct",
+        long_help = "Display the field layout of a C++ or Rust class or struct, showing size and offset information.\nFor Rust types, only the data searchfox indexed is shown, which may lack field offsets.\nExample: --field-layout 'soundtouch::SoundTouch' or --field-layout 'mozilla::dom::AudioContext'"
     )]
     field_layout: Option<String>,
 
     #[arg(
+        long,
+        value_name = "OLD,NEW",
+        conflicts_with = "field_layout",
+        help = "Compare the field layouts of two classes",
+        long_help = "Align the field layouts of two classes by field name and show the size change and the\nfields that were added, removed, moved, resized or retyped, then what regressed: a larger\nsize, alignment or padding, and fields that grew.\nWith --compare-repo (or --against), OLD is looked up in that repository and NEW in --repo;\na single class is then compared with itself.\nExample: --field-layout-diff 'ns::OldClass,ns::NewClass'\nExample: --field-layout-diff 'mozilla::dom::AudioContext' -R mozilla-central --against mozilla-esr128"
+    )]
+    field_layout_diff: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "CLASS",
+        help = "Show the base classes and subclasses of a class as trees",
+        long_help = "Show the classes a C++ class derives from and the classes deriving from it, as two\nindented trees with the location of each class, from searchfox's inheritance diagram.\n--depth sets how many levels to follow in each direction.\nExample: --class-hierarchy 'mozilla::dom::AudioNode' --depth 3"
+    )]
+    class_hierarchy: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "CLASS",
+        help = "Show the classes related to a class: bases, member types and other uses",
+        long_help = "Show the graph of the classes related to a C++ class, from searchfox's class diagram:\nwhich ones it derives from, has fields of, or otherwise uses, --depth steps away.\nPrint it as text, or as Graphviz or Mermaid source with --diagram-format.\nExample: --class-diagram 'mozilla::dom::AudioNode' --depth 2 --diagram-format dot | dot -Tsvg > audionode.svg"
+    )]
+    class_diagram: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        default_value = "text",
+        requires = "class_diagram",
+        help = "Output of --class-diagram: text, dot or mermaid"
+    )]
+    diagram_format: DiagramFormat,
+
+    #[arg(
+        long,
+        value_name = "METHOD",
+        conflicts_with = "overridden_by",
+        help = "Show the base class methods a virtual method overrides",
+        long_help = "Show the base class methods a virtual method overrides, with their locations, from\nsearchfox's structured data. --depth follows that many levels of bases.\nExample: --overrides 'mozilla::dom::GainNode::DestroyMediaTrack'"
+    )]
+    overrides: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "METHOD",
+        help = "Show the subclass methods overriding a virtual method",
+        long_help = "Show every subclass method overriding a virtual method, with their locations, from\nsearchfox's structured data. --depth follows that many levels of subclasses.\nExample: --overridden-by 'mozilla::dom::AudioNode::DestroyMediaTrack' --depth 3"
+    )]
+    overridden_by: Option<String>,
+
+    #[arg(
+        hide = true,
+        long,
+        value_name = "N",
+        default_value = "0",
+        requires = "field_layout",
+        help = "Inline the layouts of class-typed fields, N levels deep",
+        long_help = "Expand fields whose type is itself a class into that class's layout, shown indented\nunder the field with offsets relative to the outer class. Each level issues one more\nfield-layout query per distinct field type.\nExample: --field-layout 'mozilla::dom::AudioContext' --expand-depth 1"
+    )]
+    expand_depth: usize,
+
+    #[arg(
+        hide = true,
+        long,
+        value_name = "ORDER",
+        default_value = "offset",
+        requires = "field_layout",
+        help = "Order of the --field-layout fields table: offset, size or name",
+        long_help = "Sort the rows of the --field-layout fields table by offset (default), by size\n(largest first) or by name. Expanded fields stay under their parent field.\nExample: --field-layout 'mozilla::dom::AudioContext' --sort-by size"
+    )]
+    sort_by: LayoutSort,
+
+    #[arg(
+        long,
+        visible_alias = "against",
+        value_name = "REPO",
+        help = "Repository to compare --field-layout or --field-layout-diff against",
+        long_help = "Compare a class layout between two repositories: the layout in REPO is the old one,\nthe layout in --repo the new one. Useful to check that a refactor did not grow a hot class.\nExample: --field-layout 'mozilla::dom::AudioContext' --compare-repo mozilla-release\nExample: --field-layout-diff 'mozilla::dom::AudioContext' -R mozilla-central --against mozilla-esr128"
+    )]
+    compare_repo: Option<String>,
+
+    #[arg(
+        global = true,
         long = "exclude-tests",
         help = "Exclude test files from results",
         conflicts_with_all = ["only_tests", "only_generated", "only_normal"]
@@ -214,6 +1031,7 @@ struct Args {
     exclude_tests: bool,
 
     #[arg(
+        global = true,
         long = "exclude-generated",
         help = "Exclude generated files from results",
         conflicts_with_all = ["only_tests", "only_generated", "only_normal"]
@@ -221,70 +1039,357 @@ struct Args {
     exclude_generated: bool,
 
     #[arg(
+        global = true,
         long = "only-tests",
         help = "Show only test files",
         conflicts_with_all = ["exclude_tests", "exclude_generated", "only_generated", "only_normal"]
     )]
     only_tests: bool,
 
-    #[arg(
-        long = "only-generated",
-        help = "Show only generated files",
-        conflicts_with_all = ["exclude_tests", "exclude_generated", "only_tests", "only_normal"]
-    )]
-    only_generated: bool,
+    #[arg(
+        global = true,
+        long = "only-generated",
+        help = "Show only generated files",
+        conflicts_with_all = ["exclude_tests", "exclude_generated", "only_tests", "only_normal"]
+    )]
+    only_generated: bool,
+
+    #[arg(
+        global = true,
+        long = "only-normal",
+        help = "Show only normal (non-test, non-generated) files",
+        conflicts_with_all = ["exclude_tests", "exclude_generated", "only_tests", "only_generated"]
+    )]
+    only_normal: bool,
+
+    #[arg(
+        long = "can-gc",
+        help = "Check if a function can trigger garbage collection",
+        long_help = "Check whether a function can trigger garbage collection (GC) in SpiderMonkey.\nShows 'can GC' or 'cannot GC', and the GC call path when applicable.\nOnly works for C++ functions analyzed by the GC hazard analysis.\nExample: --can-gc 'mozilla::dom::AudioContext::CreateGain'"
+    )]
+    can_gc: Option<String>,
+
+    #[arg(
+        long = "function-at",
+        help = "Show which function/class contains the given line",
+        long_help = "Show which function or class contains a specific source line.\nFormat: path:line (e.g. dom/media/AudioStream.cpp:42)\nOutputs nesting context from innermost to outermost.\nWorks across all languages supported by searchfox."
+    )]
+    function_at: Option<String>,
+
+    #[arg(
+        long = "blame",
+        default_value_t = false,
+        help = "Show blame/history info for results",
+        long_help = "Augment query results with blame information.\nShows commit hash, author, date, and bug for each result line.\nWorks with --define, --symbol, -q, --get-file, etc."
+    )]
+    blame: bool,
+
+    #[arg(
+        hide = true,
+        long = "with-blame",
+        default_value_t = false,
+        requires = "define",
+        conflicts_with = "blame",
+        help = "Annotate each line of --define output with its last change",
+        long_help = "Prefix each line of the extracted definition with the short hash and bug\nnumber of the commit that last changed it, like `hg annotate`.\nUnlike --blame, which groups lines by commit under the code, this shows\nat a glance which part of a function changed recently.\nExample: --define 'AudioContext::CreateGain' --with-blame"
+    )]
+    with_blame: bool,
+
+    #[arg(
+        long = "spec-refs",
+        help = "Find Gecko source lines referencing a spec section URL",
+        long_help = "Search mozilla-central for source lines that cite a spec section URL.\nResults are grouped into Code, Test, and Web-Platform Test categories.\nAccepts any full spec URL with a # fragment.\nExample: --spec-refs 'https://html.spec.whatwg.org/#navigate'\nExample: --spec-refs 'https://tc39.es/ecma262/#sec-HostLoadImportedModule'"
+    )]
+    spec_refs: Option<String>,
+
+    #[arg(
+        global = true,
+        long = "link",
+        default_value_t = false,
+        help = "Output searchfox links to results instead of content",
+        conflicts_with = "permalink"
+    )]
+    link: bool,
+
+    #[arg(
+        global = true,
+        long = "permalink",
+        default_value_t = false,
+        help = "Output searchfox permalinks (with commit hash) to results instead of content",
+        conflicts_with = "link"
+    )]
+    permalink: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Search the repository (same as -q)
+    #[command(
+        long_about = "Search the repository for text, or a regular expression with -r. Path, language and\ncategory filters apply. -q remains to combine a query with --stats-only, --archive, --watch...\nExample: searchfox-cli search AudioStream -p ^dom/media --rank -l 10"
+    )]
+    Search(SearchCommand),
+    /// Show the full definition of a symbol (same as --define)
+    #[command(
+        long_about = "Find the definition of a symbol and print it: whole functions and methods, class\ndeclarations, or the line with some context otherwise.\nExample: searchfox-cli define 'AudioContext::CreateGain' --with-blame"
+    )]
+    Define(DefineCommand),
+    /// Print a file, or a range of its lines (same as --get-file)
+    #[command(
+        long_about = "Fetch a file of the repository, as indexed or at a revision, and print it or save it.\nExample: searchfox-cli file dom/media/AudioStream.h --lines 100-150"
+    )]
+    File(FileCommand),
+    /// Call graph of a function (same as --calls-from, --calls-to and --calls-between)
+    #[command(
+        long_about = "Functions a function calls (from), functions calling it (to), or the call paths between\ntwo classes or functions (between), up to --depth levels.\nExample: searchfox-cli calls from 'mozilla::dom::AudioContext::CreateGain' --depth 2\nExample: searchfox-cli calls between AudioContext AudioNode"
+    )]
+    Calls(CallsCommand),
+    /// Memory layout of a C++ or Rust class or struct (same as --field-layout)
+    #[command(
+        long_about = "Size, alignment, bases and fields with offsets of a class, its padding holes and a tighter\nfield order. Use --field-layout-diff to compare two classes.\nExample: searchfox-cli layout 'mozilla::dom::AudioContext' --sort-by size"
+    )]
+    Layout(LayoutCommand),
+    /// Last change of each line of a file: commit, bug, author and date
+    #[command(
+        long_about = "Print a file, or some ranges of its lines, each followed by the commit that last changed it:\nshort hash, bug, message, author and date. --lines takes comma-separated ranges.\nExample: searchfox-cli blame dom/media/AudioStream.cpp --lines 100,120-140"
+    )]
+    Blame(BlameCommand),
+    /// Interactive prompt running queries with one warm client and a history
+    #[command(
+        long_about = "Read commands at a prompt, with line editing and a history, and run them with one client\nper repository, kept between commands along with its connections and caches. Commands:\nsearch, define, calls from|to|between, file, or any command line; `set <OPTION> [VALUE]`\nadds an option to every command (`set repo mozilla-beta`, `set path ^dom/media`).\nWhen standard input is not a terminal, runs its lines without a prompt.\nExample: printf 'define AudioContext::CreateGain\\nsearch AudioStream -l 5\\n' | searchfox-cli shell"
+    )]
+    Shell,
+    /// Serve search, define, call graphs, field layouts and files as MCP tools over stdio
+    #[command(
+        long_about = "Run a Model Context Protocol server on stdio, so that LLM agents can query searchfox\nthrough tools returning structured JSON instead of running the CLI and parsing its text:\nsearch, define, call_graph, field_layout and get_file. Each tool takes an optional repo.\nExample MCP client configuration: {\"command\": \"searchfox-cli\", \"args\": [\"serve-mcp\"]}"
+    )]
+    ServeMcp,
+}
+
+#[derive(clap::Args, Debug)]
+struct SearchCommand {
+    /// Text to search for
+    query: String,
+
+    /// Lines of context around each result
+    #[arg(long, value_name = "N")]
+    context: Option<usize>,
+
+    /// Keep only results whose line matches REGEX, applied locally
+    #[arg(long, value_name = "REGEX")]
+    filter: Option<String>,
+
+    /// Order results by relevance: definitions, declarations, uses, then text
+    #[arg(long)]
+    rank: bool,
+
+    /// Sort results by path, line, category or matches (files with the most first)
+    #[arg(
+        long,
+        value_name = "ORDER",
+        conflicts_with = "rank",
+        value_parser = clap::builder::PossibleValuesParser::new(SortOrder::names())
+    )]
+    sort: Option<String>,
+
+    /// Take at most N matching lines of each file (same as --max-per-file)
+    #[arg(long, value_name = "N")]
+    max_per_file: Option<usize>,
+
+    /// Print the number of results only (same as --count)
+    #[arg(long, conflicts_with = "files_with_matches")]
+    count: bool,
+
+    /// Print the paths of the files with results only (same as --files-with-matches)
+    #[arg(long)]
+    files_with_matches: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct DefineCommand {
+    /// Qualified symbol name, e.g. 'AudioContext::CreateGain'
+    symbol: String,
+
+    /// Show the commits that last changed the definition, under the code
+    #[arg(long, conflicts_with = "with_blame")]
+    blame: bool,
+
+    /// Prefix each line with the hash and bug of its last change
+    #[arg(long)]
+    with_blame: bool,
+
+    /// List every definition instead of showing the first one (same as --define-all)
+    #[arg(long, conflicts_with_all = ["blame", "with_blame"])]
+    all: bool,
+
+    /// With --all, print the source of each definition
+    #[arg(long, requires = "all")]
+    bodies: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct FileCommand {
+    /// Paths from the root of the repository, or - to read them from stdin,
+    /// one per line
+    #[arg(required = true)]
+    paths: Vec<String>,
+
+    /// Line range: 10-20, 10, 10- or -20
+    #[arg(long, value_name = "RANGE")]
+    lines: Option<String>,
+
+    /// Revision to fetch the file at: a searchfox permalink hash, or an hg
+    /// changeset with --file-backend hg
+    #[arg(long, value_name = "HASH")]
+    rev: Option<String>,
+
+    /// Print the file even when it is binary
+    #[arg(long)]
+    binary_ok: bool,
+
+    /// Save the raw bytes of the file to FILE
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["lines", "rev", "binary_ok"])]
+    output: Option<PathBuf>,
+
+    /// Print the raw bytes of the file, without decoding them
+    #[arg(long, conflicts_with_all = ["lines", "rev", "output"])]
+    bytes: bool,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum CallDirection {
+    From,
+    To,
+    Between,
+}
+
+#[derive(clap::Args, Debug)]
+struct CallsCommand {
+    /// Calls made by the function (from), made to it (to), or paths between two names (between)
+    direction: CallDirection,
+
+    /// Qualified function name, or the source and target of `between`
+    #[arg(required = true, num_args = 1..=2)]
+    symbols: Vec<String>,
+
+    /// Levels of calls to follow
+    #[arg(long, default_value_t = 1)]
+    depth: u32,
+}
+
+#[derive(clap::Args, Debug)]
+struct LayoutCommand {
+    /// Qualified class name, e.g. 'mozilla::dom::AudioContext'
+    class: String,
 
-    #[arg(
-        long = "only-normal",
-        help = "Show only normal (non-test, non-generated) files",
-        conflicts_with_all = ["exclude_tests", "exclude_generated", "only_tests", "only_generated"]
-    )]
-    only_normal: bool,
+    /// Inline the layouts of class-typed fields, N levels deep
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    expand_depth: usize,
 
-    #[arg(
-        long = "can-gc",
-        help = "Check if a function can trigger garbage collection",
-        long_help = "Check whether a function can trigger garbage collection (GC) in SpiderMonkey.\nShows 'can GC' or 'cannot GC', and the GC call path when applicable.\nOnly works for C++ functions analyzed by the GC hazard analysis.\nExample: --can-gc 'mozilla::dom::AudioContext::CreateGain'"
-    )]
-    can_gc: Option<String>,
+    /// Order of the fields table: offset, size or name
+    #[arg(long, value_name = "ORDER", default_value = "offset")]
+    sort_by: LayoutSort,
 
-    #[arg(
-        long = "function-at",
-        help = "Show which function/class contains the given line",
-        long_help = "Show which function or class contains a specific source line.\nFormat: path:line (e.g. dom/media/AudioStream.cpp:42)\nOutputs nesting context from innermost to outermost.\nWorks across all languages supported by searchfox."
-    )]
-    function_at: Option<String>,
+    /// Compare with the layout of the class in another repository
+    #[arg(long, visible_alias = "against", value_name = "REPO")]
+    compare_repo: Option<String>,
+}
 
-    #[arg(
-        long = "blame",
-        default_value_t = false,
-        help = "Show blame/history info for results",
-        long_help = "Augment query results with blame information.\nShows commit hash, author, date, and bug for each result line.\nWorks with --define, --symbol, -q, --get-file, etc."
-    )]
-    blame: bool,
+#[derive(clap::Args, Debug)]
+struct BlameCommand {
+    /// Path from the root of the repository
+    path: String,
 
-    #[arg(
-        long = "spec-refs",
-        help = "Find Gecko source lines referencing a spec section URL",
-        long_help = "Search mozilla-central for source lines that cite a spec section URL.\nResults are grouped into Code, Test, and Web-Platform Test categories.\nAccepts any full spec URL with a # fragment.\nExample: --spec-refs 'https://html.spec.whatwg.org/#navigate'\nExample: --spec-refs 'https://tc39.es/ecma262/#sec-HostLoadImportedModule'"
-    )]
-    spec_refs: Option<String>,
+    /// Lines to show, as comma-separated ranges: 100,120-140, 10-, -20
+    #[arg(long, value_name = "RANGES")]
+    lines: Option<String>,
+}
 
-    #[arg(
-        long = "link",
-        default_value_t = false,
-        help = "Output searchfox links to results instead of content",
-        conflicts_with = "permalink"
-    )]
-    link: bool,
+impl Args {
+    /// The first --path, used by the commands taking a single one.
+    fn path(&self) -> Option<&str> {
+        self.paths.first().map(String::as_str)
+    }
 
-    #[arg(
-        long = "permalink",
-        default_value_t = false,
-        help = "Output searchfox permalinks (with commit hash) to results instead of content",
-        conflicts_with = "link"
-    )]
-    permalink: bool,
+    /// Fold the operation subcommands into the flags they stand for, which
+    /// `run` dispatches on. `shell` and `serve-mcp` are left in place.
+    fn apply_command(&mut self) -> Result<()> {
+        match self.command.take() {
+            Some(Command::Search(c)) => {
+                self.query = Some(c.query);
+                self.context = c.context.or(self.context);
+                self.filter = c.filter.or(self.filter.take());
+                self.rank |= c.rank;
+                self.sort = c.sort.or(self.sort.take());
+                self.max_per_file = c.max_per_file.or(self.max_per_file);
+                self.count |= c.count;
+                self.files_with_matches |= c.files_with_matches;
+            }
+            Some(Command::Define(c)) if c.all => {
+                self.define_all = Some(c.symbol);
+                self.bodies |= c.bodies;
+            }
+            Some(Command::Define(c)) => {
+                self.define = Some(c.symbol);
+                self.blame |= c.blame;
+                self.with_blame |= c.with_blame;
+            }
+            Some(Command::File(c)) => {
+                self.get_file = c.paths;
+                self.lines = c.lines;
+                self.rev = c.rev;
+                self.binary_ok = c.binary_ok;
+                self.output = c.output;
+                self.bytes = c.bytes;
+            }
+            Some(Command::Calls(c)) => {
+                let symbols = c.symbols;
+                match (c.direction, symbols.as_slice()) {
+                    (CallDirection::From, [symbol]) => self.calls_from = Some(symbol.clone()),
+                    (CallDirection::To, [symbol]) => self.calls_to = Some(symbol.clone()),
+                    (CallDirection::Between, [a, b]) => {
+                        self.calls_between = Some(format!("{a},{b}"))
+                    }
+                    (CallDirection::Between, _) => {
+                        anyhow::bail!("calls between expects a source and a target")
+                    }
+                    _ => anyhow::bail!("calls from and calls to expect a single function"),
+                }
+                self.depth = c.depth;
+            }
+            Some(Command::Layout(c)) => {
+                self.field_layout = Some(c.class);
+                self.expand_depth = c.expand_depth;
+                self.sort_by = c.sort_by;
+                self.compare_repo = c.compare_repo;
+            }
+            Some(Command::Blame(c)) => {
+                self.blame_file = Some(c.path);
+                self.lines = c.lines;
+            }
+            command => self.command = command,
+        }
+        let interactive = matches!(self.command, Some(Command::Shell | Command::ServeMcp));
+        // The output is only counted once the command is done.
+        if (self.count_tokens || self.max_tokens.is_some())
+            && (interactive || (self.watch && self.interval.is_some()))
+        {
+            anyhow::bail!(
+                "--count-tokens and --max-tokens cannot be used with shell, serve-mcp or --watch --interval"
+            );
+        }
+        // Only the one-shot client of main records and replays.
+        if interactive
+            && (self.save_session.is_some() || self.record.is_some() || self.replay.is_some())
+        {
+            anyhow::bail!(
+                "--save-session, --record and --replay cannot be used with shell or serve-mcp"
+            );
+        }
+        Ok(())
+    }
 }
 
 fn is_llm_environment() -> bool {
@@ -297,22 +1402,90 @@ fn is_llm_environment() -> bool {
 fn print_llm_help() {
     print!(
         r#"searchfox-cli: Mozilla code search
--q <Q> query|-p <P> path filter|-C case|-r regex|-l <N> limit(50)|--context <N>
---symbol <mangled> (from calls-to/from output)|--id <ID> identifier|--define <S> full definition
---get-file <F> [--lines <R>] R=10-20|10|10-|-20
---calls-from <S>|--calls-to <S>|--calls-between <A,B> [--depth <N>]
+-q <Q> query|-p <P> path filter|-C case|-r regex (RE2, checked locally; PCRE-isms translated, lookarounds/backrefs rejected)|-l <N> limit(50)|--context <N>
+--filter <RE> keep only result lines matching RE (client-side, no extra request)
+--rank order results defs > decls > uses > text, non-test and shorter paths first (-l applies after)
+--sort path|line|category|matches reproducible result order (-l applies after)
+--keep-duplicates list a line once per kind (Definitions, Uses...) instead of once
+--max-per-file N at most N lines per file, skipped lines do not count toward -l
+Subcommands (global options like -R, -p, --lang go before or after; old --define/--get-file/--calls-*/--field-layout flags still work):
+search <Q>|define <S> full definition [--with-blame per-line hash/bug gutter] [--all every overload/definition, path:line: signature; --bodies adds sources]|blame <F> [--lines <R>,<R>] per-line commit/bug/author/date
+file <F> [--lines <R>] [--rev <HASH>] R=10-20|10|10-|-20
+  several files (or - for paths on stdin) are fetched concurrently, each under ==> F <==
+  binary files are refused: --binary-ok prints them anyway, --output <FILE> saves raw bytes
+  --bytes prints raw bytes undecoded (non-UTF-8 text is otherwise decoded lossily, with a warning)
+calls from|to <S> [--depth <N>]|calls between <A> <B>
+layout <C> C++/Rust class memory layout, padding holes and a tighter field order
+  [--expand-depth <N>] inline layouts of class-typed fields N levels deep
+  [--sort-by offset|size|name] order of the fields table; bitfields show bit offset and width
+--symbol <mangled> (from calls output)|--id <ID> identifier
+--resolve-symbol <NAME> concrete symbols of a name: symbol TAB signature TAB definition|declaration TAB path:line; feed one to calls/--symbol
+--uses <S> indexed uses only (no comments/strings), path:line: [enclosing function] text
+--find-file <NAME> fuzzy file name match (AudioStrm.h finds dom/media/AudioStream.h), best first
+--get-dir <D> [--out <DIR>] download a directory (honors file type/category filters)
+--file-backend searchfox|hg where file contents come from (hg.mozilla.org or configured raw files)
+--server <URL> self-hosted mozsearch instance instead of searchfox.org
+--list-repos trees indexed by the server, with their indexed revision and date
+--batch <FILE|-> (or -q -) one query per line, run concurrently, each result line prefixed by query<TAB>
+--archive <F.tar.gz> bundle full content of all matched files
+--stats-only hit counts per category (normal/test/generated) and kind (Definitions/Uses/...)
+--group-by dir[:DEPTH] hits and files per directory (default depth 2), most hits first
+--count number of results only (-l applies)|--files-with-matches unique paths, one per line
+--emit-tags <FILE> [--checkout <DIR>] write matched definitions as ctags (etags if FILE is TAGS)
+--watch [--interval 24h] report new/removed matches since the previous run of a query
+serve-mcp: MCP server on stdio with tools search, define, call_graph, field_layout, get_file (structured JSON)
+shell: prompt (or piped lines) of subcommands or flags; set <OPT> <V> applies to all, one warm client
+--save-session <F.json> [--session-responses] record invocation+requests|--replay-session <F.json> re-run them|--record <F.json> save HTTP exchanges|--replay <F.json> answer requests from it offline
+--impact <S> [--depth <N>] [--exclude-tests] transitive callers grouped by file/dir
+--unused-in <CLASS|PATH> functions with no non-test callers (heuristic)
+--annotation <ATTR> --in <PATH> functions of files using a static-analysis attribute, annotated or not
+--export-lsif <CLASS|NS> LSIF dump (stdout) of members' defs/decls/refs, callees in hovers
+--duplicates <NAME> [-p <P>] all definitions of an unqualified name, grouped by scope
+--declare <S> header declaration with doc comment/default args, under its class line (falls back to the definition)
+--complete <PREFIX> identifiers starting with PREFIX, one per line: name TAB kind TAB path:line
+--examples <S> [-n 5] ranked call sites with context
+--included-by <HEADER> files #including a header, grouped by directory
+--tests-for <S> test files referencing a symbol, grouped by harness
+--wpt-for <Iface[.member]> web-platform-tests using a WebIDL member, those naming the interface first, + dirs
+--implementations <nsIFoo> classes implementing an XPCOM interface (NS_DECL_/QI heuristics) with their QI entries
+--contract <@mozilla.org/...;1> registration (components.conf, static module, manifest), class, constructor, CID
+--log-module <NAME> LazyLogModule declaration + MOZ_LOG call sites (incl. wrapper macros) by file, with levels
+--crash-frame '<Socorro frame>' normalize a crash frame, print its definition with per-line blame
+--module-graph <resource://...mjs> [--depth <N>] tree of files importing a JS module
+--component-files 'Product :: Component' files owned by a Bugzilla component (moz.build BUG_COMPONENT)
+--component-of <PATH> Bugzilla component of a file, with the moz.build block setting it
+--rename-plan <NAME> [--rename-json] occurrences by kind (def/decl/use/string/build/docs/comment) as a checklist
+--similar-to <PATH:START-END> near-duplicates of a snippet (local file or tree), ranked by token-trigram overlap
+--report <S> definition+declarations+layout+callers+callees+usage counts in one report
 --can-gc <S> check if function can trigger GC
 --function-at <path:line> show which function/class contains a line
---field-layout <C> C++ class memory layout
---cpp|--c|--webidl|--js|--java/--kt file type filters
+--field-layout-diff <OLD,NEW>|<C> --against <R> added/removed/moved fields, size change and regressions (OLD in R)
+--class-hierarchy <C> [--depth <N>] base classes and subclasses as indented trees with locations
+--class-diagram <C> [--depth <N>] [--diagram-format text|dot|mermaid] inherits/has (fields)/uses relations between classes
+--overrides <M>|--overridden-by <M> [--depth <N>] base methods a virtual method overrides / subclass overrides of it
+--lang cpp,c,js,webidl,idl,ipdl,java,kotlin,rust,python,html,css,toml,yaml file type filter (--cpp|--c|--webidl|--js|--java shorthands)
+--ext .sjs,.sys.mjs extension filter (union with --lang)
+-p may repeat: one search per path, results merged
+--raw send -q exactly as given (no regex check, no context:/text: rewriting)
+--offset N|--page N walk large result sets -l results at a time
 --exclude-tests|--exclude-generated|--only-tests|--only-generated|--only-normal
+--branch-fallback on a 404 (file) or missing definition (define), retry on other branches (older first), report which served it
 --no-cache disable reads/writes|--force-refetch bypass cached file content|--clear-cache delete cache db
--R <repo> mozilla-central(default)|mozilla-beta|mozilla-release|mozilla-esr*|comm-central
---blame commit info|--log-requests
+-R <repo> mozilla-central(default, or the current checkout's)|mozilla-beta|mozilla-release|mozilla-esr*|comm-central
+--blame commit info|--log-requests|--json-errors errors as JSON (kind,message,url,status) on stderr; exit 0 found, 1 no match, 2 usage, 3 network/server|--index-info indexed revision and its date
 --link output searchfox links|--permalink output links with commit hash
-Ex: -q AudioStream|-q '^Audio.*' -r|-q AudioStream -p ^dom/media --cpp|--get-file dom/media/AudioStream.h --force-refetch
-Ex: --define 'Cls::Method'|--calls-from 'Cls::Method' --depth 2|--field-layout 'ns::Cls'
-Ex: --define 'AudioContext::AudioContext' --link|--clear-cache
+--format json one JSON document (schema_version, command, repo, results/definitions/lines/edges/layout) for search, --batch, --uses, --complete, define, file, blame, calls, layout
+--format grep path:line:column:text per hit (search, --batch, --uses), for vim -q / :cexpr
+--format csv header + path,line,category,text[,...] rows for search, --batch, --uses, blame (spreadsheets)
+--format markdown search/define hits grouped by file, fenced code + permalinks (bugs, docs)
+--html-report FILE standalone HTML page of search/calls results: per-file sections, highlighted snippets, permalinks
+-A <N> / -B <N> N lines after/before each hit, from the files (any query; -C is --case)
+--color auto|always|never highlight matches of search results (bounds from searchfox)
+--no-demangle keep mangled C++ symbols (_ZN...) in calls/report/--symbol output, e.g. to reuse with --symbol
+--count-tokens estimated token count of stdout, on stderr|--max-tokens <N> print nothing and fail when over N tokens
+Ex: -q AudioStream|-q '^Audio.*' -r|-q AudioStream -p ^dom/media --lang cpp|file dom/media/AudioStream.h --force-refetch
+Ex: define 'Cls::Method'|calls from 'Cls::Method' --depth 2|layout 'ns::Cls'
+Ex: define 'AudioContext::AudioContext' --link|--clear-cache
 --spec-refs <url> find Gecko source lines referencing a spec section URL (grouped by Code/Test/Test262/WebAssembly Test/Web-Platform Test)
 Ex: --spec-refs 'https://html.spec.whatwg.org/#navigate'|--spec-refs 'https://tc39.es/ecma262/#await'
 "#
@@ -342,13 +1515,44 @@ async fn main() -> Result<()> {
 
     let mut builder = env_logger::Builder::from_default_env();
     if std::env::var("RUST_LOG").is_err() {
-        builder.filter_level(log::LevelFilter::Error);
+        builder.filter_level(log::LevelFilter::Warn);
     }
     builder.init();
-    let args = Args::parse();
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    if let Err(e) = args.apply_command() {
+        fail(&args, e);
+    }
+    match args.color {
+        ColorWhen::Always => set_color_enabled(true),
+        ColorWhen::Never => set_color_enabled(false),
+        ColorWhen::Auto => {}
+    }
+    // Inside a Mozilla checkout, search the repository it tracks unless
+    // --repo says otherwise.
+    if matches.value_source("repo") == Some(ValueSource::DefaultValue) {
+        if let Some(repo) = std::env::current_dir()
+            .ok()
+            .and_then(|dir| detect_repository(&dir))
+        {
+            log::info!("Using repository {} of the current checkout", repo);
+            args.repo = repo;
+        }
+    }
+
+    if args.count_tokens || args.max_tokens.is_some() {
+        if let Err(e) = print_counting_tokens(&args) {
+            fail(&args, e);
+        }
+        version_checker.print_warning();
+        return Ok(());
+    }
 
     if args.clear_cache {
-        let removed = searchfox_lib::cache::clear()?;
+        let removed = match searchfox_lib::cache::clear() {
+            Ok(removed) => removed,
+            Err(e) => fail(&args, e.into()),
+        };
         if removed {
             println!("Cache cleared.");
         } else {
@@ -358,18 +1562,290 @@ async fn main() -> Result<()> {
         return Ok(());
     }
 
-    let mut client = SearchfoxClient::new(args.repo.clone(), args.log_requests)?;
+    if let Some(ref path) = args.replay_session {
+        if let Err(e) = replay_session(path).await {
+            fail(&args, e);
+        }
+        version_checker.print_warning();
+        return Ok(());
+    }
+
+    if let Some(Command::ServeMcp) = args.command {
+        if let Err(e) = mcp::serve_mcp(&args).await {
+            fail(&args, e);
+        }
+        return Ok(());
+    }
+
+    if let Some(Command::Shell) = args.command {
+        if let Err(e) = shell::run_shell(&args).await {
+            fail(&args, e);
+        }
+        version_checker.print_warning();
+        return Ok(());
+    }
+
+    let mut client = match make_client(&args).await {
+        Ok(client) => client,
+        Err(e) => fail(&args, e),
+    };
+    if args.save_session.is_some() {
+        client.enable_session_recording(args.session_responses);
+    }
+    if args.record.is_some() {
+        client.set_cassette(CassetteMode::Record, Arc::default());
+    } else if let Some(ref path) = args.replay {
+        match Cassette::load(path) {
+            Ok(cassette) => {
+                client.set_cassette(CassetteMode::Replay, Arc::new(Mutex::new(cassette)))
+            }
+            Err(e) => fail(&args, e),
+        }
+    }
+
+    if !args.list_repos {
+        if let Err(e) = client.validate_repo().await {
+            fail(&args, e);
+        }
+    }
+
+    let result = run(&args, &client).await;
+    if let Err(e) = save_recordings(&args, &client) {
+        fail(&args, e);
+    }
+    if let Err(e) = result {
+        fail(&args, e);
+    }
+
+    version_checker.print_warning();
+    Ok(())
+}
+
+/// Write the --record cassette and the --save-session entry of this run.
+fn save_recordings(args: &Args, client: &SearchfoxClient) -> Result<()> {
+    if let (Some(path), Some((_, cassette))) = (&args.record, client.cassette()) {
+        if let Ok(cassette) = cassette.lock() {
+            cassette.save(path)?;
+        }
+    }
+
+    if let Some(ref path) = args.save_session {
+        let mut session = Session::load_or_default(path)?;
+        session.push(
+            session_args(std::env::args().skip(1)),
+            &args.repo,
+            client.take_recorded_requests(),
+        );
+        session.save(path)?;
+    }
+    Ok(())
+}
+
+const EXIT_NO_MATCHES: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_REQUEST: i32 = 3;
+
+/// Exit status for `e`: 1 when the query matched nothing, 3 when searchfox
+/// could not be reached or answered with an error, 2 for usage and other
+/// errors. Success, something matched, is 0, and clap exits with 2 for bad
+/// arguments itself.
+fn exit_code(e: &anyhow::Error) -> i32 {
+    match error_report(e).kind {
+        "no_matches" => EXIT_NO_MATCHES,
+        "not_found" | "rate_limited" | "client_error" | "server_error" | "network" => EXIT_REQUEST,
+        _ => EXIT_USAGE,
+    }
+}
+
+/// Ok when something matched, the error exiting with status 1 otherwise.
+fn matched(found: bool) -> Result<()> {
+    if found {
+        Ok(())
+    } else {
+        Err(NoMatchesError::default().into())
+    }
+}
+
+/// Whether any query of a batch matched something.
+fn batch_matched(batch: &[BatchResult]) -> bool {
+    batch
+        .iter()
+        .any(|entry| entry.results.as_ref().is_ok_and(|r| !r.is_empty()))
+}
+
+/// Whether `e` is a query having matched nothing, which its output already
+/// said, so that there is nothing to print.
+fn already_reported(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<NoMatchesError>()
+        .is_some_and(|e| e.message.is_none())
+}
+
+/// The error of a batch some queries of which failed, caused by the first
+/// request failure if any, so that it exits like one, None when every query
+/// ran.
+fn batch_error(batch: Vec<BatchResult>) -> Option<anyhow::Error> {
+    let total = batch.len();
+    let mut failures: Vec<anyhow::Error> =
+        batch.into_iter().filter_map(|b| b.results.err()).collect();
+    let failed = failures.len();
+    let cause = failures
+        .iter()
+        .position(|e| exit_code(e) == EXIT_REQUEST)
+        .unwrap_or(0);
+    (failed > 0).then(|| {
+        failures
+            .swap_remove(cause)
+            .context(format!("{failed} of {total} queries failed"))
+    })
+}
+
+/// Report a fatal error and exit with its status, as JSON on stderr with
+/// --json-errors or --format json.
+fn fail(args: &Args, e: anyhow::Error) -> ! {
+    if !already_reported(&e) {
+        if args.json_errors || args.format == OutputFormat::Json {
+            match serde_json::to_string(&error_report(&e)) {
+                Ok(report) => eprintln!("{report}"),
+                Err(_) => eprintln!("Error: {e:?}"),
+            }
+        } else {
+            eprintln!("Error: {e:?}");
+        }
+    }
+    std::process::exit(exit_code(&e));
+}
+
+fn new_client(args: &Args, repo: &str) -> Result<SearchfoxClient> {
+    let mut client = SearchfoxClient::new(repo.to_string(), args.log_requests)?;
     client.set_cache_enabled(!args.no_cache);
     client.set_force_refetch(args.force_refetch);
+    client.set_file_backend(args.file_backend);
+    if let Some(server) = &args.server {
+        client.set_base_url(server);
+    }
+    Ok(client)
+}
+
+async fn make_client(args: &Args) -> Result<SearchfoxClient> {
+    let client = new_client(args, &args.repo)?;
 
     if args.log_requests {
         eprintln!("=== REQUEST LOGGING ENABLED ===");
         if let Err(e) = client.ping().await {
-            eprintln!("[PING] Warning: Could not ping searchfox.org: {e}");
+            eprintln!("[PING] Warning: Could not ping {}: {e}", client.base_url());
         }
         eprintln!("================================");
     }
 
+    Ok(client)
+}
+
+async fn replay_session(path: &Path) -> Result<()> {
+    let session = Session::load(path)?;
+    for entry in &session.entries {
+        println!("=== searchfox-cli {} ===", entry.args.join(" "));
+        // Entries recorded by older versions may no longer parse: report
+        // them and go on with the others.
+        let parsed = Args::try_parse_from(
+            std::iter::once("searchfox-cli".to_string()).chain(entry.args.iter().cloned()),
+        )
+        .map_err(anyhow::Error::from)
+        .and_then(|mut args| args.apply_command().map(|_| args));
+        let args = match parsed {
+            Ok(args) if args.command.is_some() => {
+                eprintln!("Error: shell and serve-mcp cannot be replayed");
+                println!();
+                continue;
+            }
+            Ok(args) => args,
+            Err(e) => {
+                eprintln!("Error: {e}");
+                println!();
+                continue;
+            }
+        };
+        let result = match make_client(&args).await {
+            Ok(client) => run(&args, &client).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            if !already_reported(&e) {
+                eprintln!("Error: {e}");
+            }
+        }
+        println!();
+    }
+    Ok(())
+}
+
+/// The arguments worth recording in a session: everything except the
+/// session flags themselves, so replaying does not write to the session.
+fn session_args(args: impl Iterator<Item = String>) -> Vec<String> {
+    let mut recorded = Vec::new();
+    let mut skip_value = false;
+    for arg in args {
+        if skip_value {
+            skip_value = false;
+        } else if arg == "--save-session" {
+            skip_value = true;
+        } else if !arg.starts_with("--save-session=") && arg != "--session-responses" {
+            recorded.push(arg);
+        }
+    }
+    recorded
+}
+
+/// Run this command again with its output captured, then print it with an
+/// estimate of its token count, unless that is over --max-tokens.
+fn print_counting_tokens(args: &Args) -> Result<()> {
+    let output = std::process::Command::new(std::env::current_exe()?)
+        .args(token_args(std::env::args_os().skip(1)))
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()?;
+    let tokens = estimate_tokens(&String::from_utf8_lossy(&output.stdout));
+    if let Some(max) = args.max_tokens {
+        if tokens > max {
+            anyhow::bail!(
+                "Output is ~{} tokens ({} bytes), over --max-tokens {}; narrow the query with -p, -l or a language filter",
+                tokens,
+                output.stdout.len(),
+                max
+            );
+        }
+    }
+    std::io::stdout().write_all(&output.stdout)?;
+    if !output.status.success() {
+        std::process::exit(output.status.code().unwrap_or(1));
+    }
+    if args.count_tokens {
+        eprintln!("~{} tokens ({} bytes)", tokens, output.stdout.len());
+    }
+    Ok(())
+}
+
+/// The arguments of the command whose output is counted: everything
+/// except the token flags themselves.
+fn token_args(args: impl Iterator<Item = OsString>) -> Vec<OsString> {
+    let mut kept = Vec::new();
+    let mut skip_value = false;
+    for arg in args {
+        if skip_value {
+            skip_value = false;
+        } else if arg == "--max-tokens" {
+            skip_value = true;
+        } else if !arg
+            .to_str()
+            .is_some_and(|arg| arg.starts_with("--max-tokens="))
+            && arg != "--count-tokens"
+        {
+            kept.push(arg);
+        }
+    }
+    kept
+}
+
+async fn run(args: &Args, client: &SearchfoxClient) -> Result<()> {
     let category_filter = if args.only_tests {
         CategoryFilter::OnlyTests
     } else if args.only_generated {
@@ -386,38 +1862,254 @@ async fn main() -> Result<()> {
         CategoryFilter::All
     };
 
+    if args.raw && args.context.is_some() {
+        anyhow::bail!(
+            "--raw sends the query as is: write context:N in the query instead of --context"
+        );
+    }
+
+    // Catch regex syntax searchfox rejects or silently never matches before
+    // sending the query.
+    let query = match &args.query {
+        Some(query) if args.regexp && !args.raw => {
+            let checked = check_regex(query)?;
+            for note in &checked.notes {
+                eprintln!("Note: {note}");
+            }
+            Some(checked.pattern)
+        }
+        query => query.clone(),
+    };
+
     let search_options = SearchOptions {
-        query: args.query.clone(),
-        path: args.path.clone(),
+        query,
+        path: args.paths.first().cloned(),
+        extra_paths: args.paths.iter().skip(1).cloned().collect(),
         case: args.case,
         regexp: args.regexp,
+        verbatim: args.raw,
         limit: args.limit,
+        offset: result_offset(args),
         context: args.context,
         symbol: args.symbol.clone(),
         id: args.id.clone(),
-        lang: {
-            let mut langs = Vec::new();
-            if args.cpp {
-                langs.push(searchfox_lib::Lang::Cpp);
-            }
-            if args.c_lang {
-                langs.push(searchfox_lib::Lang::C);
-            }
-            if args.webidl {
-                langs.push(searchfox_lib::Lang::WebIdl);
-            }
-            if args.js {
-                langs.push(searchfox_lib::Lang::Js);
-            }
-            if args.java {
-                langs.push(searchfox_lib::Lang::Java);
-            }
-            langs
-        },
+        lang: languages(args)?,
+        extensions: args
+            .ext
+            .as_deref()
+            .map(parse_extensions)
+            .unwrap_or_default(),
         category_filter,
+        filter: args
+            .filter
+            .as_deref()
+            .map(regex::Regex::new)
+            .transpose()
+            .map_err(|e| anyhow::anyhow!("Invalid --filter regex: {}", e))?,
+        rank: args.rank,
+        sort: args.sort.as_deref().and_then(SortOrder::parse),
+        keep_duplicates: args.keep_duplicates,
+        max_per_file: args.max_per_file,
     };
 
-    if let Some(symbol) = &args.define {
+    if let Some(path) = &args.html_report {
+        return output::write_html_report(args, client, &search_options, path).await;
+    }
+    match args.format {
+        OutputFormat::Json => return output::print_json(args, client, &search_options).await,
+        OutputFormat::Grep => return output::print_grep(args, client, &search_options).await,
+        OutputFormat::Csv => return output::print_csv(args, client, &search_options).await,
+        OutputFormat::Markdown => {
+            return output::print_markdown(args, client, &search_options).await
+        }
+        OutputFormat::Text => {}
+    }
+
+    if args.list_repos {
+        print!("{}", format_tree_list(&client.list_trees().await?));
+    } else if args.index_info {
+        let info = client.get_index_info().await?;
+        println!(
+            "Repository: {} ({})",
+            client.repo,
+            searchfox_url_repo(&client.repo)
+        );
+        println!("Indexed revision: {}", info.revision);
+        println!("Commit date: {}", info.date);
+        match info.bug_number {
+            Some(bug) => println!("Commit: Bug {}: {}", bug, info.summary),
+            None => println!("Commit: {}", info.summary),
+        }
+    } else if let Some(symbol) = &args.report {
+        let report = client.symbol_report(symbol, &search_options).await?;
+        print!("{}", demangled(args, &format_symbol_report(&report)));
+    } else if let Some(name) = &args.find_file {
+        let files = client.find_files(name, &search_options).await?;
+        if files.is_empty() {
+            return Err(NoMatchesError::new(format!("No file matches '{name}'")).into());
+        }
+        for file in files {
+            println!("{}", file);
+        }
+    } else if let Some(prefix) = &args.complete {
+        let completions = client.complete(prefix, &search_options).await?;
+        let completions: Vec<_> = completions.into_iter().take(args.limit).collect();
+        print!("{}", format_completions(&completions));
+        return matched(!completions.is_empty());
+    } else if let Some(name) = &args.duplicates {
+        let by_scope = client.find_duplicates(name, &search_options).await?;
+        print!("{}", format_duplicates(name, &by_scope));
+        return matched(by_scope.values().any(|d| !d.is_empty()));
+    } else if let Some(symbol) = &args.examples {
+        let examples = client
+            .usage_examples(symbol, args.num_examples, &search_options)
+            .await?;
+        print!("{}", format_usage_examples(symbol, &examples));
+        return matched(!examples.is_empty());
+    } else if let Some(header) = &args.included_by {
+        let report = client.included_by(header, &search_options).await?;
+        print!("{}", format_include_report(header, &report));
+        return matched(!report.includers.is_empty());
+    } else if let Some(symbol) = &args.tests_for {
+        let files = client.tests_for(symbol, &search_options).await?;
+        print!("{}", format_test_files(symbol, &files));
+        return matched(!files.is_empty());
+    } else if let Some(feature) = &args.wpt_for {
+        let feature = WebIdlFeature::parse(feature)?;
+        let candidates = client.wpt_for(&feature, &search_options).await?;
+        print!(
+            "{}",
+            format_wpt_candidates(&feature, &candidates, args.limit)
+        );
+        return matched(!candidates.is_empty());
+    } else if let Some(interface) = &args.implementations {
+        let implementations = client
+            .find_implementations(interface, &search_options)
+            .await?;
+        print!("{}", format_implementations(interface, &implementations));
+        return matched(!implementations.is_empty());
+    } else if let Some(contract) = &args.contract {
+        let registrations = client.find_contract(contract, &search_options).await?;
+        print!("{}", format_registrations(contract, &registrations));
+        return matched(!registrations.is_empty());
+    } else if let Some(module) = &args.log_module {
+        let report = client.log_module(module, &search_options).await?;
+        print!("{}", format_log_module(module, &report));
+        return matched(!report.declarations.is_empty());
+    } else if let Some(name) = &args.rename_plan {
+        let plan = client.rename_plan(name, &search_options).await?;
+        if args.rename_json {
+            println!("{}", serde_json::to_string_pretty(&plan)?);
+        } else {
+            print!("{}", format_rename_plan(&plan));
+        }
+        return matched(!plan.groups.is_empty());
+    } else if let Some(location) = &args.similar_to {
+        let (path, range) = location.rsplit_once(':').ok_or_else(|| {
+            anyhow::anyhow!(
+                "Expected PATH:START-END for --similar-to, got '{}'",
+                location
+            )
+        })?;
+        let source = match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(_) => fetch_file(client, path, None).await?,
+        };
+        let lines: Vec<&str> = source.lines().collect();
+        let (start, end) = parse_line_range(range, lines.len())?;
+        let snippet = lines[start - 1..end].join("\n");
+        let regions = client
+            .similar_code(path, start, end, &snippet, &search_options)
+            .await?;
+        print!("{}", format_similar_code(location, &regions, args.limit));
+        return matched(!regions.is_empty());
+    } else if let Some(component) = &args.component_files {
+        let component = BugComponent::parse(component)?;
+        let (declarations, files) = client.component_files(&component, &search_options).await?;
+        print!(
+            "{}",
+            format_component_files(&component, &declarations, &files)
+        );
+        return matched(!declarations.is_empty());
+    } else if let Some(path) = &args.component_of {
+        let found = client.component_of(path).await?;
+        print!("{}", format_component_of(path, found.as_ref()));
+        return matched(found.is_some());
+    } else if let Some(url) = &args.module_graph {
+        let edges = client
+            .module_graph(url, args.depth, &search_options)
+            .await?;
+        print!("{}", format_module_graph(url, &edges));
+        return matched(!edges.is_empty());
+    } else if let Some(frame) = &args.crash_frame {
+        let symbol = normalize_frame(frame)?;
+        println!("Frame: {}", frame.trim());
+        println!("Symbol: {}\n", symbol);
+        let mut found = false;
+        for candidate in frame_candidates(&symbol) {
+            found = show_definition(
+                client,
+                &candidate,
+                &search_options,
+                args.path(),
+                DefinitionBlame::Annotated,
+            )
+            .await?;
+            if found {
+                break;
+            }
+        }
+        if !found {
+            return Err(NoMatchesError::new(format!(
+                "No definition found for {symbol} (from frame '{frame}')"
+            ))
+            .into());
+        }
+    } else if let Some(symbol) = &args.uses {
+        let uses = client.find_uses(symbol, &search_options).await?;
+        if uses.is_empty() {
+            return Err(NoMatchesError::new(format!("No uses found for {symbol}")).into());
+        }
+        print!("{}", format_uses(&uses));
+    } else if let Some(name) = &args.resolve_symbol {
+        let symbols = client.resolve_symbol(name, &search_options).await?;
+        if symbols.is_empty() {
+            return Err(NoMatchesError::new(format!(
+                "No definition or declaration found for {name}"
+            ))
+            .into());
+        }
+        print!("{}", format_resolved_symbols(&symbols));
+    } else if let Some(symbol) = &args.declare {
+        let declarations = client
+            .find_declarations(symbol, args.path(), &search_options)
+            .await?;
+        if declarations.is_empty() {
+            return Err(NoMatchesError::new(format!("No declaration found for {symbol}")).into());
+        }
+        for declaration in &declarations {
+            println!("{}:{}", declaration.path, declaration.line);
+            print_definition(declaration.source.as_deref().unwrap_or_default());
+            println!();
+        }
+    } else if let Some(symbol) = &args.define_all {
+        let definitions = client
+            .find_all_definitions(symbol, args.path(), &search_options, args.bodies)
+            .await?;
+        if definitions.is_empty() {
+            return Err(NoMatchesError::new(format!("No definition found for {symbol}")).into());
+        }
+        for definition in &definitions {
+            println!(
+                "{}:{}: {}",
+                definition.path, definition.line, definition.signature
+            );
+            if let Some(source) = &definition.source {
+                print_definition(source);
+                println!();
+            }
+        }
+    } else if let Some(symbol) = &args.define {
         if args.link || args.permalink {
             let hash = if args.permalink {
                 Some(client.get_head_hash().await?)
@@ -426,8 +2118,13 @@ async fn main() -> Result<()> {
             };
 
             let file_locations = client
-                .find_symbol_locations(symbol, args.path.as_deref(), &search_options)
+                .find_symbol_locations(symbol, args.path(), &search_options)
                 .await?;
+            if file_locations.is_empty() {
+                return Err(
+                    NoMatchesError::new(format!("No definition found for {symbol}")).into(),
+                );
+            }
 
             let is_ctor = symbol.rfind("::").is_some_and(|pos| {
                 let class_part = &symbol[..pos];
@@ -437,79 +2134,140 @@ async fn main() -> Result<()> {
             });
             let context_lines = if is_ctor { 2 } else { 10 };
 
+            let progress = progress_bar(file_locations.len(), "Resolving definitions");
             for (file_path, line_number) in &file_locations {
                 if let Ok(context) = client
                     .get_definition_context(file_path, *line_number, context_lines, Some(symbol))
                     .await
                 {
                     if let Some((start, end)) = extract_line_range_from_output(&context) {
-                        println!(
-                            "{}",
-                            generate_link(&client.repo, file_path, start, end, hash.as_deref())
-                        );
+                        progress.suspend(|| {
+                            println!(
+                                "{}",
+                                generate_link(
+                                    client.base_url(),
+                                    &client.repo,
+                                    file_path,
+                                    start,
+                                    end,
+                                    hash.as_deref()
+                                )
+                            )
+                        });
                     }
                 }
+                progress.inc(1);
             }
+            progress.finish_and_clear();
         } else {
-            let result = client
-                .find_and_display_definition(symbol, args.path.as_deref(), &search_options)
-                .await?;
-            if !result.is_empty() {
-                if args.blame {
-                    let file_locations = client
-                        .find_symbol_locations(symbol, args.path.as_deref(), &search_options)
-                        .await?;
-
-                    if let Some((file_path, _)) = file_locations.first() {
-                        let line_numbers = extract_line_numbers_from_definition(&result);
-
-                        if !line_numbers.is_empty() {
-                            let blame_map =
-                                client.get_blame_for_lines(file_path, &line_numbers).await?;
-                            print_definition_with_grouped_blame(&result, &blame_map);
-                        } else {
-                            println!("{}", result);
-                        }
-                    } else {
-                        println!("{}", result);
+            let blame = if args.with_blame {
+                DefinitionBlame::Annotated
+            } else if args.blame {
+                DefinitionBlame::Grouped
+            } else {
+                DefinitionBlame::None
+            };
+            let mut found =
+                show_definition(client, symbol, &search_options, args.path(), blame).await?;
+            if !found && args.branch_fallback {
+                for repo in fallback_repos(&args.repo) {
+                    let other = new_client(args, repo)?;
+                    let path = args.path();
+                    if show_definition(&other, symbol, &search_options, path, blame).await? {
+                        eprintln!(
+                            "{} is not defined on {}; shown from {}",
+                            symbol, args.repo, repo
+                        );
+                        found = true;
+                        break;
                     }
-                } else {
-                    println!("{}", result);
                 }
             }
+            if !found {
+                return Err(
+                    NoMatchesError::new(format!("No definition found for {symbol}")).into(),
+                );
+            }
         }
-    } else if let Some(path) = &args.get_file {
+    } else if let Some(path) = &args.blame_file {
+        let content = fetch_file(client, path, None).await?;
+        let lines = select_lines(&content, args.lines.as_deref())?;
+        let numbers: Vec<usize> = lines.iter().map(|(n, _)| *n).collect();
+        let blame_map = client.get_blame_for_lines(path, &numbers).await?;
+        print!("{}", format_line_blame(&lines, &blame_map));
+    } else if let Some(paths) = several_files(args)? {
+        print_files(args, client, &paths).await?;
+    } else if let Some(path) = args.get_file.first() {
         if args.link || args.permalink {
-            let hash = if args.permalink {
+            let hash = if let Some(ref rev) = args.rev {
+                Some(rev.clone())
+            } else if args.permalink {
                 Some(client.get_head_hash().await?)
             } else {
                 None
             };
             let (start, end) = if let Some(ref range) = args.lines {
-                let content = client.get_file(path).await?;
+                let content = fetch_file(client, path, args.rev.as_deref()).await?;
                 parse_line_range(range, content.lines().count())?
             } else {
                 (0, 0)
             };
             println!(
                 "{}",
-                generate_link(&client.repo, path, start, end, hash.as_deref())
+                generate_link(
+                    client.base_url(),
+                    &client.repo,
+                    path,
+                    start,
+                    end,
+                    hash.as_deref()
+                )
             );
+        } else if args.bytes {
+            let bytes = client.get_file_bytes(path).await?;
+            if is_binary(&bytes) && !args.binary_ok {
+                return Err(anyhow::Error::new(BinaryFileError { path: path.clone() })
+                    .context("Not printing a binary file, use --binary-ok or --output FILE"));
+            }
+            std::io::stdout().write_all(&bytes)?;
+        } else if let Some(ref output) = args.output {
+            let bytes = client.get_file_bytes(path).await?;
+            std::fs::write(output, &bytes)?;
+            println!("Wrote {} bytes to {}", bytes.len(), output.display());
         } else {
-            let content = client.get_file(path).await?;
-
-            let (start_line, end_line) = if let Some(ref range) = args.lines {
-                parse_line_range(range, content.lines().count())?
-            } else {
-                (1, content.lines().count())
+            let mut fallback = None;
+            let fetched = match &args.rev {
+                Some(rev) => client.get_file_at_revision(path, rev).await,
+                None => client.get_file_covering(path, args.lines.as_deref()).await,
+            };
+            let content = match fetched {
+                Err(e)
+                    if args.branch_fallback
+                        && args.rev.is_none()
+                        && error_report(&e).kind == "not_found" =>
+                {
+                    let Some((other, content)) = fetch_file_on_other_branch(args, path).await?
+                    else {
+                        return Err(e);
+                    };
+                    fallback = Some(other);
+                    content
+                }
+                Err(e) if e.is::<BinaryFileError>() && args.binary_ok => {
+                    let bytes = client.get_file_bytes(path).await?;
+                    std::io::stdout().write_all(&bytes)?;
+                    return Ok(());
+                }
+                Err(e) if e.is::<BinaryFileError>() => {
+                    return Err(
+                        e.context("Not printing a binary file, use --binary-ok or --output FILE")
+                    );
+                }
+                result => result?,
             };
+            let client = fallback.as_ref().unwrap_or(client);
 
-            let filtered_lines: Vec<(usize, &str)> = content
-                .lines()
-                .enumerate()
-                .map(|(idx, line)| (idx + 1, line))
-                .filter(|(line_num, _)| *line_num >= start_line && *line_num <= end_line)
-                .collect();
+            let filtered_lines = select_lines(&content, args.lines.as_deref())?;
 
             if args.blame {
                 let line_numbers: Vec<usize> = filtered_lines.iter().map(|(num, _)| *num).collect();
@@ -529,6 +2287,18 @@ async fn main() -> Result<()> {
                 }
             }
         }
+    } else if let Some(dir) = &args.get_dir {
+        let files = client.list_directory_files(dir, &search_options).await?;
+        if files.is_empty() {
+            return Err(NoMatchesError::new(format!("No files found under {dir}")).into());
+        }
+        let out_dir = args.out.clone().unwrap_or_else(|| default_out_dir(dir));
+        let written = download_directory(client, dir, &files, &out_dir).await?;
+        println!(
+            "Downloaded {written}/{} files into {}",
+            files.len(),
+            out_dir.display()
+        );
     } else if args.calls_from.is_some() || args.calls_to.is_some() || args.calls_between.is_some() {
         let query_text = if let Some(ref symbol) = args.calls_from {
             format!("calls-from:'{}' depth:{}", symbol, args.depth)
@@ -551,9 +2321,9 @@ async fn main() -> Result<()> {
         };
 
         let query = CallGraphQuery {
-            calls_from: args.calls_from,
-            calls_to: args.calls_to,
-            calls_between: args.calls_between.map(|s| {
+            calls_from: args.calls_from.clone(),
+            calls_to: args.calls_to.clone(),
+            calls_between: args.calls_between.as_ref().map(|s| {
                 let parts: Vec<&str> = s.split(',').collect();
                 if parts.len() == 2 {
                     (parts[0].trim().to_string(), parts[1].trim().to_string())
@@ -571,12 +2341,31 @@ async fn main() -> Result<()> {
             if std::env::var("DEBUG_JSON").is_ok() {
                 println!("{}", serde_json::to_string_pretty(&result)?);
             } else {
-                let markdown = format_call_graph_markdown(&query_text, &result);
-                print!("{}", markdown);
+                let graph = SymbolGraphCollection::from_json(&result)?;
+                let markdown = format_call_graph_markdown(&query_text, &graph);
+                print!("{}", demangled(args, &markdown));
             }
         } else {
-            println!("No call graph results found for the query.");
+            return Err(NoMatchesError::new("No call graph results found for the query.").into());
         }
+    } else if let Some(ref symbol) = args.impact {
+        let report = client.impact(symbol, args.depth, category_filter).await?;
+        let found = !report.callers.is_empty();
+        let report = format_impact_report(symbol, args.depth, &report);
+        print!("{}", demangled(args, &report));
+        return matched(found);
+    } else if let Some(ref target) = args.unused_in {
+        let candidates = client.find_unused(target, &search_options).await?;
+        print!("{}", format_unused_candidates(target, &candidates));
+    } else if let Some(ref attribute) = args.annotation {
+        let Some(path) = args.annotation_in.as_ref().or(args.paths.first()) else {
+            anyhow::bail!("--annotation needs a path to audit, e.g. --in ^dom/media");
+        };
+        let functions = client
+            .annotation_audit(attribute, path, &search_options)
+            .await?;
+        print!("{}", format_annotation_audit(attribute, path, &functions));
+        return matched(!functions.is_empty());
     } else if let Some(ref location) = args.function_at {
         let (path, line) = parse_path_line(location)?;
         let contexts = client.get_function_at_line(&path, line).await?;
@@ -591,15 +2380,65 @@ async fn main() -> Result<()> {
     } else if let Some(symbol) = &args.can_gc {
         let results = client.get_gc_info(symbol).await?;
         if results.is_empty() {
-            println!(
-                "No GC information found for '{}'. GC analysis is only available for C++ functions.",
-                symbol
-            );
-        } else {
-            for info in &results {
-                print_gc_info(info);
+            return Err(NoMatchesError::new(format!(
+                "No GC information found for '{symbol}'. GC analysis is only available for C++ functions."
+            ))
+            .into());
+        }
+        for info in &results {
+            print_gc_info(info);
+        }
+    } else if args.field_layout_diff.is_some() || args.compare_repo.is_some() {
+        let (old_class, new_class) = match (&args.field_layout_diff, &args.field_layout) {
+            (Some(pair), _) => match pair.split_once(',') {
+                Some((old, new)) => (old.trim().to_string(), new.trim().to_string()),
+                None if args.compare_repo.is_some() => {
+                    (pair.trim().to_string(), pair.trim().to_string())
+                }
+                None => anyhow::bail!(
+                    "--field-layout-diff expects OLD,NEW, or one class with --against, got '{}'",
+                    pair
+                ),
+            },
+            (None, Some(class_name)) => (class_name.clone(), class_name.clone()),
+            (None, None) => {
+                anyhow::bail!("--compare-repo requires --field-layout or --field-layout-diff")
+            }
+        };
+        let old_repo = args.compare_repo.as_deref().unwrap_or(&args.repo);
+        let old_client = match args.compare_repo {
+            Some(ref repo) => {
+                let mut old_client = new_client(args, repo)?;
+                if let Some((mode, cassette)) = client.cassette() {
+                    old_client.set_cassette(mode, cassette);
+                }
+                Some(old_client)
             }
-        }
+            None => None,
+        };
+        let old_layout = old_client
+            .as_ref()
+            .unwrap_or(client)
+            .class_layout(&old_class)
+            .await?;
+        let new_layout = client.class_layout(&new_class).await?;
+        let (Some(old_layout), Some(new_layout)) = (old_layout, new_layout) else {
+            return Err(NoMatchesError::new(format!(
+                "No field layout information found for '{old_class}' in {old_repo} or '{new_class}' in {}. \
+                 Field layout is only available for C++ and Rust classes and structs.",
+                args.repo
+            ))
+            .into());
+        };
+        print!(
+            "{}",
+            format_layout_diff(
+                &format!("{} ({})", old_class, old_repo),
+                &old_layout,
+                &format!("{} ({})", new_class, args.repo),
+                &new_layout,
+            )
+        );
     } else if let Some(class_name) = &args.field_layout {
         let query = FieldLayoutQuery {
             class_name: class_name.clone(),
@@ -611,51 +2450,193 @@ async fn main() -> Result<()> {
         {
             if std::env::var("DEBUG_JSON").is_ok() {
                 println!("{}", serde_json::to_string_pretty(&result)?);
+            } else if let Some(mut layout) = parse_field_layout(class_name, &result) {
+                if args.expand_depth > 0 {
+                    client
+                        .expand_field_layouts(&mut layout, args.expand_depth)
+                        .await;
+                }
+                sort_fields(&mut layout, args.sort_by);
+                print!("{}", format_class_layout(class_name, &layout));
             } else {
                 let formatted = format_field_layout(class_name, &result);
                 print!("{}", formatted);
             }
         } else {
-            println!("No field layout information found for '{}'.", class_name);
-            println!("Note: Field layout is only available for C++ classes and structs.");
+            return Err(NoMatchesError::new(format!(
+                "No field layout information found for '{class_name}'. \
+                 Field layout is only available for C++ and Rust classes and structs."
+            ))
+            .into());
+        }
+    } else if let Some(class_name) = &args.class_diagram {
+        let diagram = client.class_diagram(class_name, args.depth).await?;
+        print!("{}", format_class_diagram(&diagram, args.diagram_format));
+        return matched(!diagram.relations.is_empty());
+    } else if let Some(class_name) = &args.class_hierarchy {
+        match client.class_hierarchy(class_name, args.depth).await? {
+            Some(hierarchy) => print!("{}", format_class_hierarchy(&hierarchy)),
+            None => {
+                return Err(NoMatchesError::new(format!(
+                    "No inheritance information found for '{class_name}'"
+                ))
+                .into())
+            }
+        }
+    } else if let Some((method, direction)) = args
+        .overrides
+        .as_ref()
+        .map(|m| (m, OverrideDirection::Overrides))
+        .or(args
+            .overridden_by
+            .as_ref()
+            .map(|m| (m, OverrideDirection::OverriddenBy)))
+    {
+        let methods = client
+            .method_overrides(method, direction, args.depth)
+            .await?;
+        if methods.is_empty() {
+            return Err(NoMatchesError::new(format!("No method found for '{method}'")).into());
         }
+        print!(
+            "{}",
+            demangled(args, &format_overrides(&methods, direction))
+        );
+    } else if let Some(ref root) = args.export_lsif {
+        let neighborhood = client.symbol_neighborhood(root, &search_options).await?;
+        print!("{}", format_lsif(&neighborhood));
     } else if let Some(ref spec_url) = args.spec_refs {
         let results = client.search_spec_refs(spec_url, args.limit).await?;
 
         if results.is_empty() {
-            println!("No references found in {}.", args.repo);
-        } else {
-            println!(
-                "## Gecko references for {spec_url}\n\n{} reference(s) in {}\n",
-                results.len(),
-                args.repo
+            return Err(
+                NoMatchesError::new(format!("No references found in {}.", args.repo)).into(),
             );
-            for category in spec_ref_category_names() {
-                let group: Vec<&searchfox_lib::search::SearchResult> = results
-                    .iter()
-                    .filter(|r| categorize_spec_ref(&r.path) == *category)
-                    .collect();
-                if group.is_empty() {
-                    continue;
-                }
-                println!("### {category}\n");
-                for r in group {
-                    println!(
-                        "- {}:{} — https://searchfox.org/{}/source/{}#{}",
-                        r.path, r.line_number, args.repo, r.path, r.line_number
-                    );
-                }
-                println!();
+        }
+        println!(
+            "## Gecko references for {spec_url}\n\n{} reference(s) in {}\n",
+            results.len(),
+            args.repo
+        );
+        for category in spec_ref_category_names() {
+            let group: Vec<&SearchResult> = results
+                .iter()
+                .filter(|r| categorize_spec_ref(&r.path) == *category)
+                .collect();
+            if group.is_empty() {
+                continue;
+            }
+            println!("### {category}\n");
+            for r in group {
+                println!(
+                    "- {}:{} — {}/{}/source/{}#{}",
+                    r.path,
+                    r.line_number,
+                    client.base_url(),
+                    args.repo,
+                    r.path,
+                    r.line_number
+                );
             }
+            println!();
+        }
+    } else if let Some(queries) = batch_queries(args)? {
+        let batch = client.search_batch(&queries, &search_options).await;
+        match result_summary(args) {
+            Some(summary) => print!("{}", format_batch_summary(&batch, summary)),
+            None => print!("{}", format_batch_results(&batch)),
         }
+        let found = batch_matched(&batch);
+        if let Some(e) = batch_error(batch) {
+            return Err(e);
+        }
+        return matched(found);
     } else if args.query.is_some()
         || args.symbol.is_some()
         || args.id.is_some()
-        || args.path.is_some()
+        || !args.paths.is_empty()
     {
-        let results = client.search(&search_options).await?;
+        if let Some(ref out) = args.emit_tags {
+            let entries = client.tag_entries(&search_options).await?;
+            let root = args.checkout.as_deref();
+            let tags = if out.file_name().is_some_and(|name| name == "TAGS") {
+                format_etags(&entries, root)
+            } else {
+                format_ctags(&entries, root)
+            };
+            std::fs::write(out, tags)?;
+            println!("Wrote {} definitions to {}", entries.len(), out.display());
+            return Ok(());
+        }
 
-        if args.link || args.permalink {
+        if args.stats_only {
+            let stats = client.search_stats(&search_options).await?;
+            print_search_stats(&stats);
+            return matched(stats.total > 0);
+        }
+
+        if let Some(ref spec) = args.group_by {
+            let depth = parse_group_by(spec)?;
+            let stats = client.search_stats(&search_options).await?;
+            print_directory_counts(&group_by_directory(&stats, depth), &stats);
+            return matched(stats.total > 0);
+        }
+
+        if args.watch {
+            let interval = args.interval.as_deref().map(parse_interval).transpose()?;
+            loop {
+                match client.watch_search(&search_options).await {
+                    Ok(report) => print_watch_report(&report),
+                    // A long-running watch outlives timeouts and server
+                    // errors: try again at the next round.
+                    Err(e) if interval.is_some() => {
+                        log::warn!("Watch of the query failed, retrying later: {e:#}")
+                    }
+                    Err(e) => return Err(e),
+                }
+                match interval {
+                    Some(interval) => tokio::time::sleep(interval).await,
+                    None => return Ok(()),
+                }
+            }
+        }
+
+        let (mut results, metadata) = client.search_with_metadata(&search_options).await?;
+        warn_if_partial(&metadata);
+        match result_summary(args) {
+            Some(ResultSummary::Count) => {
+                println!("{}", results.len());
+                return matched(!results.is_empty());
+            }
+            Some(ResultSummary::FilesWithMatches) => {
+                for path in files_with_matches(&results) {
+                    println!("{path}");
+                }
+                return matched(!results.is_empty());
+            }
+            None => {}
+        }
+        add_grep_context(args, client, &mut results).await;
+        if let Some(signature) = args.symbol.as_deref().and_then(demangle) {
+            if !args.no_demangle && args.archive.is_none() && !args.link && !args.permalink {
+                println!("Symbol: {signature}");
+            }
+        }
+
+        if let Some(ref out) = args.archive {
+            let mut files: Vec<String> = Vec::new();
+            for result in &results {
+                if !files.contains(&result.path) {
+                    files.push(result.path.clone());
+                }
+            }
+            let written = write_archive(client, &files, out).await?;
+            println!(
+                "Archived {written}/{} files into {}",
+                files.len(),
+                out.display()
+            );
+        } else if args.link || args.permalink {
             let hash = if args.permalink {
                 Some(client.get_head_hash().await?)
             } else {
@@ -665,6 +2646,7 @@ async fn main() -> Result<()> {
                 println!(
                     "{}",
                     generate_link(
+                        client.base_url(),
                         &client.repo,
                         &result.path,
                         result.line_number,
@@ -675,6 +2657,7 @@ async fn main() -> Result<()> {
             }
         } else {
             let mut count = 0;
+            let color = color_enabled();
             if args.blame {
                 // Group results by file for efficient blame fetching
                 let mut results_by_file: HashMap<String, Vec<(usize, String)>> = HashMap::new();
@@ -683,50 +2666,62 @@ async fn main() -> Result<()> {
                         results_by_file
                             .entry(result.path.clone())
                             .or_default()
-                            .push((result.line_number, result.line.clone()));
+                            .push((result.line_number, result_text(result, color)));
                     }
                 }
 
                 // Fetch and display results with blame
+                let progress = progress_bar(results_by_file.len(), "Fetching blame");
                 for (path, lines) in results_by_file {
                     let line_numbers: Vec<usize> = lines.iter().map(|(ln, _)| *ln).collect();
                     let blame_map = client.get_blame_for_lines(&path, &line_numbers).await?;
 
-                    for (line_number, line_text) in lines {
-                        println!("{}:{}: {}", path, line_number, line_text);
-
-                        if let Some(blame_info) = blame_map.get(&line_number) {
-                            if let Some(ref commit_info) = blame_info.commit_info {
-                                let parsed = parse_commit_header(&commit_info.header);
-                                let short_hash = &blame_info.commit_hash[..8];
-                                if let Some(bug) = parsed.bug_number {
-                                    println!(
-                                        "  [{}] Bug {}: {} ({}, {})",
-                                        short_hash, bug, parsed.message, parsed.author, parsed.date
-                                    );
-                                } else {
-                                    println!(
-                                        "  [{}] {} ({}, {})",
-                                        short_hash, parsed.message, parsed.author, parsed.date
-                                    );
+                    progress.suspend(|| {
+                        for (line_number, line_text) in lines {
+                            println!("{}:{}: {}", path, line_number, line_text);
+
+                            if let Some(blame_info) = blame_map.get(&line_number) {
+                                if let Some(ref commit_info) = blame_info.commit_info {
+                                    let parsed = parse_commit_header(&commit_info.header);
+                                    let short_hash = &blame_info.commit_hash[..8];
+                                    if let Some(bug) = parsed.bug_number {
+                                        println!(
+                                            "  [{}] Bug {}: {} ({}, {})",
+                                            short_hash,
+                                            bug,
+                                            parsed.message,
+                                            parsed.author,
+                                            parsed.date
+                                        );
+                                    } else {
+                                        println!(
+                                            "  [{}] {} ({}, {})",
+                                            short_hash, parsed.message, parsed.author, parsed.date
+                                        );
+                                    }
                                 }
                             }
+                            count += 1;
                         }
-                        count += 1;
-                    }
+                    });
+                    progress.inc(1);
                 }
+                progress.finish_and_clear();
             } else {
                 // Original output without blame
+                let width = terminal_width();
                 for result in &results {
                     if result.line_number == 0 {
                         println!("{}", result.path);
                     } else {
                         for line in &result.context_before {
-                            println!("  {}", line.trim_end());
+                            print_wrapped(&format!("  {}", line.trim_end()), 4, width);
                         }
-                        println!("{}:{}: {}", result.path, result.line_number, result.line);
+                        let prefix = format!("{}:{}: ", result.path, result.line_number);
+                        let text = result_text(result, color);
+                        print_wrapped(&format!("{}{}", prefix, text), prefix.len(), width);
                         for line in &result.context_after {
-                            println!("  {}", line.trim_end());
+                            print_wrapped(&format!("  {}", line.trim_end()), 4, width);
                         }
                     }
                     count += 1;
@@ -734,18 +2729,396 @@ async fn main() -> Result<()> {
             }
             println!("Total matches: {count}");
         }
+        return matched(!results.is_empty());
     } else {
-        error!(
-            "Either --query, --symbol, --id, --get-file, --define, --calls-from, --calls-to, --calls-between, --can-gc, --spec-refs, or --path must be provided"
+        anyhow::bail!(
+            "Either a subcommand (search, define, file, calls, layout, blame, shell, serve-mcp) or one of --query, --batch, --symbol, --id, --uses, --resolve-symbol, --define-all, --declare, --get-dir, --complete, --duplicates, --examples, --included-by, --tests-for, --wpt-for, --implementations, --contract, --log-module, --crash-frame, --module-graph, --component-files, --component-of, --rename-plan, --similar-to, --report, --index-info, --field-layout-diff, --impact, --class-hierarchy, --class-diagram, --overrides, --overridden-by, --unused-in, --annotation, --export-lsif, --can-gc, --spec-refs, or --path must be provided"
         );
-        std::process::exit(1);
     }
 
-    version_checker.print_warning();
     Ok(())
 }
 
+/// The paths of `--get-file` when there are several of them, or they are
+/// read from stdin; None for a single path.
+fn several_files(args: &Args) -> Result<Option<Vec<String>>> {
+    let paths = match args.get_file.as_slice() {
+        [] => return Ok(None),
+        [path] if path == "-" => std::io::read_to_string(std::io::stdin())?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect(),
+        [_] => return Ok(None),
+        paths => paths.to_vec(),
+    };
+    if args.link || args.permalink || args.blame || args.bytes || args.output.is_some() {
+        anyhow::bail!("--link, --permalink, --blame, --bytes and --output take a single file");
+    }
+    Ok(Some(paths))
+}
+
+/// Print each file under a `==> PATH <==` header, with line numbers when
+/// --lines selects part of them. Files that cannot be fetched are reported
+/// on stderr, and make the command fail once the others are printed.
+async fn print_files(args: &Args, client: &SearchfoxClient, paths: &[String]) -> Result<()> {
+    let mut failed = 0;
+    let mut first = true;
+    for (path, content) in client.get_files(paths, args.rev.as_deref()).await {
+        let lines = content.and_then(|content| {
+            Ok(select_lines(&content, args.lines.as_deref())?
+                .into_iter()
+                .map(|(number, line)| (number, line.to_string()))
+                .collect::<Vec<_>>())
+        });
+        let lines = match lines {
+            Ok(lines) => lines,
+            Err(e) => {
+                eprintln!("Failed to fetch {path}: {e}");
+                failed += 1;
+                continue;
+            }
+        };
+        if !first {
+            println!();
+        }
+        first = false;
+        println!("==> {path} <==");
+        for (number, line) in lines {
+            if args.lines.is_some() {
+                println!("{:4}: {}", number, line);
+            } else {
+                println!("{}", line);
+            }
+        }
+    }
+    if failed > 0 {
+        anyhow::bail!("{} of {} files could not be fetched", failed, paths.len());
+    }
+    Ok(())
+}
+
+/// Queries of `--batch FILE`, or of standard input with `--batch -` or
+/// `-q -`, checked like `-q` with `-r`.
+fn batch_queries(args: &Args) -> Result<Option<Vec<String>>> {
+    let text = match (&args.batch, args.query.as_deref()) {
+        (Some(path), _) if path != Path::new("-") => std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Cannot read {}: {}", path.display(), e))?,
+        (Some(_), _) | (None, Some("-")) => std::io::read_to_string(std::io::stdin())?,
+        _ => return Ok(None),
+    };
+    let queries = parse_batch_queries(&text);
+    if queries.is_empty() {
+        anyhow::bail!("No query to run in the batch");
+    }
+    if !args.regexp || args.raw {
+        return Ok(Some(queries));
+    }
+    let queries = queries
+        .iter()
+        .map(|query| check_regex(query).map(|checked| checked.pattern))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Some(queries))
+}
+
+async fn fetch_file(client: &SearchfoxClient, path: &str, rev: Option<&str>) -> Result<String> {
+    match rev {
+        Some(rev) => client.get_file_at_revision(path, rev).await,
+        None => client.get_file(path).await,
+    }
+}
+
+/// After a 404 on the selected repository, fetch `path` from the first
+/// other branch that has it, with a client for that branch.
+async fn fetch_file_on_other_branch(
+    args: &Args,
+    path: &str,
+) -> Result<Option<(SearchfoxClient, String)>> {
+    for repo in fallback_repos(&args.repo) {
+        let client = new_client(args, repo)?;
+        match client.get_file(path).await {
+            Ok(content) => {
+                eprintln!("{} is not on {}; shown from {}", path, args.repo, repo);
+                return Ok(Some((client, content)));
+            }
+            Err(e) if error_report(&e).kind == "not_found" => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(None)
+}
+
+fn print_search_stats(stats: &SearchStats) {
+    let width = stats
+        .by_category
+        .keys()
+        .chain(stats.by_kind.keys())
+        .map(|k| k.len())
+        .max()
+        .unwrap_or(0);
+    println!("By category:");
+    for (category, count) in &stats.by_category {
+        println!("  {category:<width$}  {count:>6}");
+    }
+    println!("By kind:");
+    for (kind, count) in &stats.by_kind {
+        println!("  {kind:<width$}  {count:>6}");
+    }
+    println!("Total: {} hits in {} files", stats.total, stats.files);
+}
+
+fn print_directory_counts(groups: &[DirectoryCount], stats: &SearchStats) {
+    println!("{:>6}  {:>5}  Directory", "Hits", "Files");
+    for group in groups {
+        println!("{:>6}  {:>5}  {}", group.hits, group.files, group.directory);
+    }
+    println!(
+        "Total: {} hits in {} files under {} directories",
+        stats.total,
+        stats.files,
+        groups.len()
+    );
+}
+
+fn print_watch_report(report: &WatchReport) {
+    if report.previous_at.is_none() {
+        println!(
+            "Stored {} matches as the baseline; later runs will report changes.",
+            report.total
+        );
+        return;
+    }
+    for added in &report.added {
+        println!("+ {added}");
+    }
+    for removed in &report.removed {
+        println!("- {removed}");
+    }
+    println!(
+        "{} new, {} removed ({} matches)",
+        report.added.len(),
+        report.removed.len(),
+        report.total
+    );
+}
+
+/// Print a line wrapped to `width` with a hanging indent, or as is when
+/// stdout is not a terminal.
+fn print_wrapped(line: &str, indent: usize, width: Option<usize>) {
+    match width {
+        Some(width) => println!("{}", wrap_hanging(line, indent, width)),
+        None => println!("{}", line),
+    }
+}
+
+/// Give search results the lines of -A and -B, from the current checkout
+/// when it tracks the searched repository, so its files need no fetching.
+async fn add_grep_context(args: &Args, client: &SearchfoxClient, results: &mut [SearchResult]) {
+    if args.after_context.is_none() && args.before_context.is_none() {
+        return;
+    }
+    let cwd = std::env::current_dir().ok();
+    let checkout = cwd
+        .as_deref()
+        .filter(|dir| detect_repository(dir).as_deref() == Some(client.repo.as_str()))
+        .and_then(checkout_root);
+    client
+        .add_context(
+            results,
+            args.before_context.unwrap_or(0),
+            args.after_context.unwrap_or(0),
+            checkout,
+        )
+        .await;
+}
+
+/// When to color the output, for --color.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum ColorWhen {
+    /// When stdout is a terminal and the environment does not say otherwise
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// The text of a search result, its match highlighted when colors are on.
+fn result_text(result: &SearchResult, color: bool) -> String {
+    if color {
+        highlight_ranges(&result.line, &result.bounds)
+    } else {
+        result.line.clone()
+    }
+}
+
+/// How to show blame alongside a definition.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum DefinitionBlame {
+    None,
+    /// Lines grouped by commit, as with --blame.
+    Grouped,
+    /// A hash and bug gutter on each line, as with --with-blame.
+    Annotated,
+}
+
+/// Print the definition of `symbol` with the requested blame. Returns
+/// whether a definition was found.
+async fn show_definition(
+    client: &SearchfoxClient,
+    symbol: &str,
+    search_options: &SearchOptions,
+    path: Option<&str>,
+    blame: DefinitionBlame,
+) -> Result<bool> {
+    let result = client
+        .find_and_display_definition(symbol, path, search_options)
+        .await?;
+    if result.is_empty() {
+        return Ok(false);
+    }
+    if blame == DefinitionBlame::None {
+        print_definition(&result);
+        return Ok(true);
+    }
+
+    let file_locations = client
+        .find_symbol_locations(symbol, path, search_options)
+        .await?;
+    let line_numbers = extract_line_numbers_from_definition(&result);
+    match file_locations.first() {
+        Some((file_path, _)) if !line_numbers.is_empty() => {
+            let blame_map = client.get_blame_for_lines(file_path, &line_numbers).await?;
+            if blame == DefinitionBlame::Annotated {
+                print!("{}", annotate_definition_with_blame(&result, &blame_map));
+            } else {
+                print_definition_with_grouped_blame(&result, &blame_map);
+            }
+        }
+        _ => print_definition(&result),
+    }
+    Ok(true)
+}
+
+/// Print `--define` output, aligning wrapped code with the code column.
+fn print_definition(text: &str) {
+    let Some(width) = terminal_width() else {
+        println!("{}", text);
+        return;
+    };
+    for line in text.lines() {
+        let indent = line_number_prefix_len(line).unwrap_or(0);
+        println!("{}", wrap_hanging(line, indent, width));
+    }
+}
+
+/// Progress bar on stderr for operations issuing many requests. Hidden when
+/// stderr is not a terminal, so that logs and pipes stay clean.
+fn progress_bar(len: usize, message: &'static str) -> ProgressBar {
+    if !std::io::stderr().is_terminal() {
+        return ProgressBar::hidden();
+    }
+    let progress = ProgressBar::new(len as u64);
+    progress.set_style(
+        ProgressStyle::with_template("{msg} [{bar:30}] {pos}/{len} (ETA {eta})")
+            .expect("valid progress template")
+            .progress_chars("=> "),
+    );
+    progress.set_message(message);
+    progress
+}
+
+fn default_out_dir(dir: &str) -> PathBuf {
+    let name = dir.trim_end_matches('/').rsplit('/').next().unwrap_or(dir);
+    PathBuf::from(name)
+}
+
+async fn download_directory(
+    client: &SearchfoxClient,
+    dir: &str,
+    files: &[String],
+    out_dir: &Path,
+) -> Result<usize> {
+    let prefix = format!("{}/", dir.trim_end_matches('/'));
+    let fetched = fetch_files(client, files).await;
+    let mut written = 0;
+    for (path, content) in &fetched {
+        let Some(relative) = relative_download_path(path, &prefix) else {
+            eprintln!("Skipping {path}: it would be written outside of the output directory");
+            continue;
+        };
+        let dest = out_dir.join(relative);
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&dest, content)?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+/// Where `path` goes under the output directory of --get-dir, relative to
+/// it, `prefix` stripped. None for absolute paths and paths with `..`,
+/// which the server should never send.
+fn relative_download_path<'a>(path: &'a str, prefix: &str) -> Option<&'a Path> {
+    let relative = Path::new(path.strip_prefix(prefix).unwrap_or(path));
+    relative
+        .components()
+        .all(|c| matches!(c, std::path::Component::Normal(_)))
+        .then_some(relative)
+}
+
+async fn write_archive(client: &SearchfoxClient, files: &[String], out: &Path) -> Result<usize> {
+    let fetched = fetch_files(client, files).await;
+    let file = std::fs::File::create(out)?;
+    searchfox_lib::archive::write_tar_gz(file, &fetched)?;
+    Ok(fetched.len())
+}
+
+/// Fetch the raw bytes of `files` concurrently, binary files included,
+/// reporting failures on stderr. Successful fetches are returned sorted by
+/// path.
+async fn fetch_files(client: &SearchfoxClient, files: &[String]) -> Vec<(String, Vec<u8>)> {
+    let mut fetches = stream::iter(files)
+        .map(|path| async move { (path, client.get_file_bytes(path).await) })
+        .buffer_unordered(DOWNLOAD_CONCURRENCY);
+
+    let progress = progress_bar(files.len(), "Fetching files");
+    let mut fetched = Vec::new();
+    while let Some((path, result)) = fetches.next().await {
+        match result {
+            Ok(content) => fetched.push((path.clone(), content)),
+            Err(e) => progress.suspend(|| eprintln!("Failed to fetch {path}: {e}")),
+        }
+        progress.inc(1);
+    }
+    progress.finish_and_clear();
+    fetched.sort_by(|a, b| a.0.cmp(&b.0));
+    fetched
+}
+
+/// The summary --count or --files-with-matches asks for, if any.
+fn result_summary(args: &Args) -> Option<ResultSummary> {
+    if args.count {
+        Some(ResultSummary::Count)
+    } else if args.files_with_matches {
+        Some(ResultSummary::FilesWithMatches)
+    } else {
+        None
+    }
+}
+
+/// `text` with its mangled C++ symbols demangled, unless --no-demangle.
+fn demangled<'a>(args: &Args, text: &'a str) -> Cow<'a, str> {
+    if args.no_demangle {
+        Cow::Borrowed(text)
+    } else {
+        demangle_text(text)
+    }
+}
+
 fn generate_link(
+    base_url: &str,
     repo: &str,
     path: &str,
     start_line: usize,
@@ -761,14 +3134,8 @@ fn generate_link(
     };
     let rev_repo = searchfox_url_repo(repo);
     match hash {
-        Some(h) => format!(
-            "https://searchfox.org/{}/rev/{}/{}{}",
-            rev_repo, h, path, fragment
-        ),
-        None => format!(
-            "https://searchfox.org/{}/source/{}{}",
-            rev_repo, path, fragment
-        ),
+        Some(h) => format!("{}/{}/rev/{}/{}{}", base_url, rev_repo, h, path, fragment),
+        None => format!("{}/{}/source/{}{}", base_url, rev_repo, path, fragment),
     }
 }
 
@@ -821,62 +3188,81 @@ fn parse_line_number_from_output(line: &str) -> Option<usize> {
     }
 }
 
-/// Parse line range string (e.g., "10-20", "10", "10-", "-20")
-/// Returns (start_line, end_line) inclusive
-fn parse_line_range(range: &str, total_lines: usize) -> Result<(usize, usize)> {
-    let range = range.trim();
-
-    if range.contains('-') {
-        let parts: Vec<&str> = range.split('-').collect();
-        if parts.len() != 2 {
-            anyhow::bail!(
-                "Invalid line range format: '{}'. Expected formats: 10-20, 10, 10-, -20",
-                range
-            );
-        }
-
-        let start = if parts[0].is_empty() {
-            1
-        } else {
-            parts[0]
-                .parse::<usize>()
-                .map_err(|_| anyhow::anyhow!("Invalid start line number: '{}'", parts[0]))?
-        };
-
-        let end = if parts[1].is_empty() {
-            total_lines
-        } else {
-            parts[1]
-                .parse::<usize>()
-                .map_err(|_| anyhow::anyhow!("Invalid end line number: '{}'", parts[1]))?
-        };
-
-        if start < 1 {
-            anyhow::bail!("Start line must be >= 1");
-        }
-        if end > total_lines {
-            anyhow::bail!("End line {} exceeds file length {}", end, total_lines);
-        }
-        if start > end {
-            anyhow::bail!("Start line {} is greater than end line {}", start, end);
-        }
+/// Tell on stderr that searchfox returned only part of the results, so that
+/// few or no results are not mistaken for the complete answer.
+fn warn_if_partial(metadata: &SearchMetadata) {
+    if metadata.timed_out {
+        eprintln!("Warning: searchfox timed out, results are partial; narrow the query with -p or a more specific pattern");
+    } else if metadata.is_partial() {
+        let flags: Vec<&str> = metadata
+            .other
+            .iter()
+            .filter(|(_, value)| value.as_bool() == Some(true))
+            .map(|(key, _)| key.as_str())
+            .collect();
+        eprintln!(
+            "Warning: searchfox reported {}, results may be partial",
+            flags.join(", ")
+        );
+    }
+}
 
-        Ok((start, end))
-    } else {
-        // Single line number
-        let line_num = range
-            .parse::<usize>()
-            .map_err(|_| anyhow::anyhow!("Invalid line number: '{}'", range))?;
+/// Number of results skipped by --offset or --page.
+fn result_offset(args: &Args) -> usize {
+    match (args.offset, args.page) {
+        (Some(offset), _) => offset,
+        (None, Some(page)) => (page as usize - 1).saturating_mul(args.limit),
+        (None, None) => 0,
+    }
+}
 
-        if line_num < 1 {
-            anyhow::bail!("Line number must be >= 1");
-        }
-        if line_num > total_lines {
-            anyhow::bail!("Line {} exceeds file length {}", line_num, total_lines);
+/// The languages of --lang, followed by those of the --cpp style shorthands.
+fn languages(args: &Args) -> Result<Vec<Lang>> {
+    let mut langs = match args.lang {
+        Some(ref spec) => Lang::parse_list(spec)?,
+        None => Vec::new(),
+    };
+    let shorthands = [
+        (args.cpp, Lang::Cpp),
+        (args.c_lang, Lang::C),
+        (args.webidl, Lang::WebIdl),
+        (args.js, Lang::Js),
+        (args.java, Lang::Java),
+        (args.java, Lang::Kotlin),
+    ];
+    for (set, lang) in shorthands {
+        if set && !langs.contains(&lang) {
+            langs.push(lang);
         }
+    }
+    Ok(langs)
+}
 
-        Ok((line_num, line_num))
+/// Prefix each numbered line of a definition with the short hash and bug of
+/// the commit that last changed it. Other lines get a blank gutter.
+fn annotate_definition_with_blame(
+    definition: &str,
+    blame_map: &HashMap<usize, searchfox_lib::BlameInfo>,
+) -> String {
+    let mut output = String::new();
+    for line in definition.lines() {
+        let blame = parse_line_number_from_output(line).and_then(|n| blame_map.get(&n));
+        let gutter = match blame {
+            Some(blame_info) => {
+                let short_hash = &blame_info.commit_hash[..8.min(blame_info.commit_hash.len())];
+                let bug = blame_info
+                    .commit_info
+                    .as_ref()
+                    .and_then(|info| parse_commit_header(&info.header).bug_number)
+                    .map(|bug| format!("Bug {bug}"))
+                    .unwrap_or_default();
+                format!("{short_hash} {bug:<11}")
+            }
+            None => " ".repeat(20),
+        };
+        output.push_str(&format!("{gutter}  {line}\n"));
     }
+    output
 }
 
 /// Print definition with blame info, grouping consecutive lines with the same commit
@@ -1063,4 +3449,341 @@ mod tests {
         assert_eq!(categorize_spec_ref("js/src/builtin/Promise.cpp"), "Code");
         assert_eq!(categorize_spec_ref("dom/navigation/Navigation.h"), "Code");
     }
+
+    #[test]
+    fn subcommands_map_to_flags() {
+        let parse = |line: &str| {
+            let mut args =
+                Args::try_parse_from(std::iter::once("searchfox-cli").chain(line.split(' ')))
+                    .unwrap();
+            args.apply_command().map(|_| args)
+        };
+        let args = parse("define AudioContext::CreateGain -R mozilla-beta --with-blame").unwrap();
+        assert_eq!(args.define.as_deref(), Some("AudioContext::CreateGain"));
+        assert_eq!(args.repo, "mozilla-beta");
+        assert!(args.with_blame && args.command.is_none());
+
+        let args = parse("define AudioNode::Connect --all --bodies").unwrap();
+        assert_eq!(args.define_all.as_deref(), Some("AudioNode::Connect"));
+        assert!(args.define.is_none() && args.bodies);
+        assert!(Args::try_parse_from([
+            "searchfox-cli",
+            "define",
+            "AudioNode::Connect",
+            "--bodies"
+        ])
+        .is_err());
+
+        let args = parse("blame dom/media/AudioStream.cpp --lines 100,120-140").unwrap();
+        assert_eq!(
+            args.blame_file.as_deref(),
+            Some("dom/media/AudioStream.cpp")
+        );
+        assert_eq!(args.lines.as_deref(), Some("100,120-140"));
+
+        let args = parse("calls to Foo::Bar --depth 3").unwrap();
+        assert_eq!(
+            (args.calls_to.as_deref(), args.depth),
+            (Some("Foo::Bar"), 3)
+        );
+        assert!(parse("calls between Foo").is_err());
+
+        let args = parse("search AudioStream --sort matches").unwrap();
+        assert_eq!(args.sort.as_deref(), Some("matches"));
+
+        let args = parse("search AudioStream --files-with-matches").unwrap();
+        assert_eq!(result_summary(&args), Some(ResultSummary::FilesWithMatches));
+        for line in [
+            "search AudioStream --count --files-with-matches",
+            "-q AudioStream --count --stats-only",
+            "search AudioStream --sort size",
+            "search AudioStream --sort path --rank",
+        ] {
+            assert!(
+                Args::try_parse_from(std::iter::once("searchfox-cli").chain(line.split(' ')))
+                    .is_err()
+            );
+        }
+
+        let args =
+            parse("file dom/media/AudioStream.h dom/media/AudioStream.cpp --lines 1-5").unwrap();
+        assert_eq!(
+            several_files(&args).unwrap().unwrap(),
+            ["dom/media/AudioStream.h", "dom/media/AudioStream.cpp"]
+        );
+        let args = parse("file dom/media/AudioStream.h dom/media/AudioStream.cpp --bytes").unwrap();
+        assert!(several_files(&args).is_err());
+        assert!(
+            several_files(&parse("file dom/media/AudioStream.h").unwrap())
+                .unwrap()
+                .is_none()
+        );
+
+        // The flags the subcommands replace still work.
+        let args = parse("--get-file dom/media/AudioStream.h --lines 1-5").unwrap();
+        assert_eq!(args.lines.as_deref(), Some("1-5"));
+        assert!(matches!(
+            parse("shell").unwrap().command,
+            Some(Command::Shell)
+        ));
+        assert!(parse("shell --record http.json").is_err());
+        assert!(parse("shell --count-tokens").is_err());
+        assert!(parse("-q AudioStream --watch --interval 1h --max-tokens 500").is_err());
+        assert!(parse("-q AudioStream --watch --count-tokens").is_ok());
+        assert!(parse("serve-mcp --replay http.json").is_err());
+    }
+
+    #[test]
+    fn shorthands_add_to_lang() {
+        let args = Args::try_parse_from([
+            "searchfox-cli",
+            "-q",
+            "Foo",
+            "--lang",
+            "rust,cpp",
+            "--cpp",
+            "--java",
+        ])
+        .unwrap();
+        assert_eq!(
+            languages(&args).unwrap(),
+            vec![Lang::Rust, Lang::Cpp, Lang::Java, Lang::Kotlin]
+        );
+        let args = Args::try_parse_from(["searchfox-cli", "-q", "Foo", "--lang", "cobol"]).unwrap();
+        assert!(languages(&args).is_err());
+    }
+
+    #[test]
+    fn pages_are_offsets() {
+        let parse = |line: &str| {
+            Args::try_parse_from(std::iter::once("searchfox-cli").chain(line.split(' '))).unwrap()
+        };
+        assert_eq!(result_offset(&parse("-q Foo -l 20 --page 3")), 40);
+        assert_eq!(result_offset(&parse("-q Foo --offset 7")), 7);
+        assert_eq!(result_offset(&parse("-q Foo")), 0);
+        assert!(Args::try_parse_from(["searchfox-cli", "-q", "Foo", "--page", "0"]).is_err());
+        assert!(Args::try_parse_from([
+            "searchfox-cli",
+            "-q",
+            "Foo",
+            "--page",
+            "2",
+            "--offset",
+            "1"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn default_out_dir_uses_last_component() {
+        assert_eq!(
+            default_out_dir("dom/media/webaudio/"),
+            PathBuf::from("webaudio")
+        );
+        assert_eq!(default_out_dir("layout"), PathBuf::from("layout"));
+    }
+
+    #[test]
+    fn annotates_definition_lines_with_blame() {
+        let blame = |hash: &str, header: &str| searchfox_lib::BlameInfo {
+            commit_hash: hash.to_string(),
+            original_path: "%".to_string(),
+            original_line: 1,
+            commit_info: Some(searchfox_lib::CommitInfo {
+                header: header.to_string(),
+                parent: None,
+                date: String::new(),
+                fulldiff: None,
+                phab: None,
+            }),
+        };
+        let blame_map = HashMap::from([
+            (
+                469,
+                blame(
+                    "88a286dcec9ba069397bd4c4c35b3e317bf66f4f",
+                    "Bug 1234567: Add it",
+                ),
+            ),
+            (
+                470,
+                blame("0123456789abcdef0123456789abcdef01234567", "No bug: Fix it"),
+            ),
+        ]);
+        let definition = "dom/media/AudioStream.cpp\n>>>  469: void Foo() {\n     470:   Bar();\n";
+        assert_eq!(
+            annotate_definition_with_blame(definition, &blame_map),
+            format!(
+                "{}  dom/media/AudioStream.cpp\n\
+                 88a286dc Bug 1234567  >>>  469: void Foo() {{\n\
+                 01234567                   470:   Bar();\n",
+                " ".repeat(20)
+            )
+        );
+    }
+
+    #[test]
+    fn token_args_drop_token_flags() {
+        let args = [
+            "-q",
+            "AudioStream",
+            "--count-tokens",
+            "--max-tokens",
+            "500",
+            "--max-tokens=600",
+            "-l",
+            "5",
+        ];
+        assert_eq!(
+            token_args(args.iter().map(OsString::from)),
+            vec!["-q", "AudioStream", "-l", "5"]
+        );
+    }
+
+    #[test]
+    fn session_args_drop_session_flags() {
+        let argv = [
+            "-q",
+            "AudioStream",
+            "--save-session",
+            "s.json",
+            "--session-responses",
+            "--save-session=t.json",
+            "--cpp",
+        ];
+        assert_eq!(
+            session_args(argv.iter().map(|s| s.to_string())),
+            vec!["-q", "AudioStream", "--cpp"]
+        );
+    }
+
+    #[test]
+    fn downloads_stay_in_the_output_directory() {
+        assert_eq!(
+            relative_download_path("dom/media/webaudio/AudioNode.cpp", "dom/media/"),
+            Some(Path::new("webaudio/AudioNode.cpp"))
+        );
+        assert_eq!(
+            relative_download_path("dom/media/../../.bashrc", "dom/media/"),
+            None
+        );
+        assert_eq!(relative_download_path("/etc/passwd", "dom/media/"), None);
+    }
+
+    #[test]
+    fn exit_codes_tell_outcomes_apart() {
+        let no_matches = anyhow::Error::new(NoMatchesError::default());
+        assert_eq!(exit_code(&no_matches), 1);
+        assert!(already_reported(&no_matches));
+        let no_uses = anyhow::Error::new(NoMatchesError::new("No uses found for Foo"));
+        assert_eq!(exit_code(&no_uses), 1);
+        assert!(!already_reported(&no_uses));
+
+        assert_eq!(exit_code(&anyhow::anyhow!("Invalid --filter regex")), 2);
+        for status in [404, 429, 503] {
+            let e = anyhow::Error::new(searchfox_lib::RequestError::new(
+                "https://searchfox.org/mozilla-central/search",
+                status.try_into().unwrap(),
+            ))
+            .context("Search failed");
+            assert_eq!(exit_code(&e), 3);
+        }
+
+        let empty = BatchResult {
+            query: "a".into(),
+            results: Ok(Vec::new()),
+        };
+        let failed = BatchResult {
+            query: "b".into(),
+            results: Err(anyhow::anyhow!("Request failed")),
+        };
+        assert!(!batch_matched(&[empty, failed]));
+
+        let batch = vec![
+            BatchResult {
+                query: "a".into(),
+                results: Err(anyhow::anyhow!("Invalid regex")),
+            },
+            BatchResult {
+                query: "b".into(),
+                results: Err(searchfox_lib::RequestError::new(
+                    "https://searchfox.org/mozilla-central/search",
+                    503.try_into().unwrap(),
+                )
+                .into()),
+            },
+            BatchResult {
+                query: "c".into(),
+                results: Ok(Vec::new()),
+            },
+        ];
+        let e = batch_error(batch).unwrap();
+        assert_eq!(
+            format!("{e:#}"),
+            "2 of 3 queries failed: Request failed: 503 Service Unavailable"
+        );
+        assert_eq!(exit_code(&e), 3);
+    }
+
+    /// Run `line` against a server answering each `(path, body)` request.
+    async fn run_replayed(line: &str, responses: &[(&str, &str)]) -> Result<()> {
+        let mut args = Args::try_parse_from(
+            ["searchfox-cli", "--server", "http://searchfox.test"]
+                .into_iter()
+                .chain(line.split(' ')),
+        )
+        .unwrap();
+        args.apply_command()?;
+        let mut client = new_client(&args, &args.repo)?;
+        let mut cassette = Cassette::default();
+        cassette.interactions = responses
+            .iter()
+            .map(|(path, body)| searchfox_lib::cassette::Interaction {
+                method: "GET".into(),
+                url: format!("http://searchfox.test{path}"),
+                status: 200,
+                final_url: None,
+                headers: Default::default(),
+                body: searchfox_lib::cassette::Body::Text(body.to_string()),
+            })
+            .collect();
+        client.set_cassette(CassetteMode::Replay, Arc::new(Mutex::new(cassette)));
+        run(&args, &client).await
+    }
+
+    #[tokio::test]
+    async fn commands_finding_nothing_exit_with_no_matches() {
+        const EMPTY: &str = r#"{"*title*": "Nothing", "*timedout*": false}"#;
+        let search = "/mozilla-central/search?case=false&regexp=false";
+
+        let e = run_replayed(
+            "--find-file Nothing",
+            &[
+                (
+                    "/mozilla-central/search?q=&case=false&regexp=false&path=Nothing",
+                    EMPTY,
+                ),
+                (
+                    "/mozilla-central/search?q=&case=false&regexp=false&path=Not",
+                    EMPTY,
+                ),
+            ],
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(exit_code(&e), 1);
+        assert_eq!(e.to_string(), "No file matches 'Nothing'");
+
+        for (line, query) in [
+            ("--duplicates Nothing", "id%3ANothing"),
+            ("--tests-for Nothing", "id%3ANothing"),
+            ("--complete Noth", "Noth"),
+            ("--complete Noth --format json", "Noth"),
+        ] {
+            let path = search.replace("?", &format!("?q={query}&"));
+            let e = run_replayed(line, &[(&path, EMPTY)]).await.unwrap_err();
+            assert_eq!(exit_code(&e), 1, "{line}");
+            assert!(already_reported(&e), "{line}");
+        }
+    }
 }
@@ -0,0 +1,432 @@
+use crate::output::{layout_json, search_result_json};
+use crate::{new_client, Args};
+use anyhow::{anyhow, bail, Result};
+use searchfox_lib::{
+    call_graph::{format_call_graph_markdown, CallGraphQuery, SymbolGraphCollection},
+    demangle::demangle_text,
+    field_layout::format_class_layout,
+    file_reader::parse_line_range,
+    regex_check::check_regex,
+    SearchOptions, SearchfoxClient,
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::Write;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+/// Protocol revisions the server speaks, newest last. A client asking for
+/// another one gets the newest.
+const PROTOCOL_VERSIONS: &[&str] = &["2024-11-05", "2025-03-26", "2025-06-18"];
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Schemas of the tools, as listed by `tools/list`.
+fn tools() -> Value {
+    let repo = json!({
+        "type": "string",
+        "description": "Repository, e.g. mozilla-central (the default), mozilla-beta, comm-central"
+    });
+    json!([
+        {
+            "name": "search",
+            "description": "Full-text search of a Mozilla repository. Returns matching lines with their path and line number.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": {"type": "string", "description": "Text to search for, or a regular expression with regexp"},
+                    "path": {"type": "string", "description": "Regex on paths, e.g. ^dom/media"},
+                    "regexp": {"type": "boolean", "description": "Treat query as a regular expression"},
+                    "case": {"type": "boolean", "description": "Case-sensitive search"},
+                    "limit": {"type": "integer", "description": "Maximum number of results (default 50)"},
+                    "repo": repo
+                },
+                "required": ["query"]
+            }
+        },
+        {
+            "name": "define",
+            "description": "Find the definition of a C++, Rust, JS or WebIDL symbol such as mozilla::dom::AudioContext::CreateGain, with its source.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "symbol": {"type": "string", "description": "Qualified symbol name"},
+                    "path": {"type": "string", "description": "Regex on paths of the definitions considered"},
+                    "repo": repo
+                },
+                "required": ["symbol"]
+            }
+        },
+        {
+            "name": "call_graph",
+            "description": "Functions called by a function (calls_from), calling it (calls_to), or on the paths between two classes or functions (calls_between).",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "calls_from": {"type": "string", "description": "Qualified function name"},
+                    "calls_to": {"type": "string", "description": "Qualified function name"},
+                    "calls_between": {
+                        "type": "array",
+                        "items": {"type": "string"},
+                        "minItems": 2,
+                        "maxItems": 2,
+                        "description": "Source and target class or function"
+                    },
+                    "depth": {"type": "integer", "description": "Levels of calls to follow (default 1)"},
+                    "repo": repo
+                }
+            }
+        },
+        {
+            "name": "field_layout",
+            "description": "Memory layout of a C++ or Rust class or struct: size, alignment, bases and fields with offsets and sizes.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "class": {"type": "string", "description": "Qualified class name, e.g. mozilla::dom::AudioContext"},
+                    "repo": repo
+                },
+                "required": ["class"]
+            }
+        },
+        {
+            "name": "get_file",
+            "description": "Contents of a file of the repository, or of a range of its lines.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "path": {"type": "string", "description": "Path from the root of the repository"},
+                    "lines": {"type": "string", "description": "Line range: 10-20, 10, 10- or -20"},
+                    "rev": {"type": "string", "description": "Revision to read the file at"},
+                    "repo": repo
+                },
+                "required": ["path"]
+            }
+        }
+    ])
+}
+
+fn optional_str<'a>(arguments: &'a Value, name: &str) -> Option<&'a str> {
+    arguments.get(name).and_then(Value::as_str)
+}
+
+fn required_str<'a>(arguments: &'a Value, name: &str) -> Result<&'a str> {
+    optional_str(arguments, name).ok_or_else(|| anyhow!("Missing string argument '{}'", name))
+}
+
+fn optional_bool(arguments: &Value, name: &str) -> bool {
+    arguments
+        .get(name)
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+}
+
+fn optional_u64(arguments: &Value, name: &str) -> Option<u64> {
+    arguments.get(name).and_then(Value::as_u64)
+}
+
+fn response(id: Value, result: Value) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "result": result})
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+}
+
+/// Result of a tool call: the text shown to the model and the same data as
+/// JSON.
+fn tool_result(text: String, structured: Value) -> Value {
+    json!({
+        "content": [{"type": "text", "text": text}],
+        "structuredContent": structured,
+        "isError": false,
+    })
+}
+
+fn tool_error(e: &anyhow::Error) -> Value {
+    json!({
+        "content": [{"type": "text", "text": format!("Error: {e}")}],
+        "isError": true,
+    })
+}
+
+struct Server<'a> {
+    /// The arguments the server was started with: default repository and
+    /// client settings.
+    args: &'a Args,
+    /// One client per repository, kept between calls.
+    clients: HashMap<String, SearchfoxClient>,
+}
+
+impl Server<'_> {
+    fn client(&mut self, arguments: &Value) -> Result<&SearchfoxClient> {
+        let repo = optional_str(arguments, "repo").unwrap_or(&self.args.repo);
+        if !self.clients.contains_key(repo) {
+            let client = new_client(self.args, repo)?;
+            self.clients.insert(repo.to_string(), client);
+        }
+        Ok(&self.clients[repo])
+    }
+
+    async fn search(&mut self, arguments: &Value) -> Result<Value> {
+        let query = required_str(arguments, "query")?;
+        let regexp = optional_bool(arguments, "regexp");
+        let query = if regexp {
+            check_regex(query)?.pattern
+        } else {
+            query.to_string()
+        };
+        let options = SearchOptions {
+            query: Some(query),
+            path: optional_str(arguments, "path").map(String::from),
+            regexp,
+            case: optional_bool(arguments, "case"),
+            limit: optional_u64(arguments, "limit").unwrap_or(50) as usize,
+            ..SearchOptions::default()
+        };
+        let client = self.client(arguments)?;
+        let (results, metadata) = client.search_with_metadata(&options).await?;
+        let mut text: String = results
+            .iter()
+            .map(|r| format!("{}:{}: {}\n", r.path, r.line_number, r.line.trim()))
+            .collect();
+        if metadata.is_partial() {
+            text.push_str("(searchfox returned partial results)\n");
+        }
+        let results: Vec<Value> = results
+            .iter()
+            .map(|r| search_result_json(client.base_url(), &client.repo, r))
+            .collect();
+        Ok(tool_result(
+            if text.is_empty() {
+                "No results.".to_string()
+            } else {
+                text
+            },
+            json!({ "results": results, "partial": metadata.is_partial() }),
+        ))
+    }
+
+    async fn define(&mut self, arguments: &Value) -> Result<Value> {
+        let symbol = required_str(arguments, "symbol")?;
+        let path = optional_str(arguments, "path");
+        let options = SearchOptions::default();
+        let client = self.client(arguments)?;
+        let locations = client.find_symbol_locations(symbol, path, &options).await?;
+        let definition = client
+            .find_and_display_definition(symbol, path, &options)
+            .await?;
+        let structured = json!({
+            "symbol": symbol,
+            "locations": locations
+                .iter()
+                .map(|(path, line)| json!({"path": path, "line": line}))
+                .collect::<Vec<_>>(),
+            "definition": definition,
+        });
+        let text = if definition.is_empty() {
+            format!("No definition found for '{symbol}'.")
+        } else {
+            definition
+        };
+        Ok(tool_result(text, structured))
+    }
+
+    async fn call_graph(&mut self, arguments: &Value) -> Result<Value> {
+        let depth = optional_u64(arguments, "depth").unwrap_or(1) as u32;
+        let between = match arguments.get("calls_between") {
+            Some(Value::Array(pair)) => match pair.as_slice() {
+                [Value::String(a), Value::String(b)] => Some((a.clone(), b.clone())),
+                _ => bail!("calls_between expects two names"),
+            },
+            _ => None,
+        };
+        let query = CallGraphQuery {
+            calls_from: optional_str(arguments, "calls_from").map(String::from),
+            calls_to: optional_str(arguments, "calls_to").map(String::from),
+            calls_between: between,
+            depth,
+        };
+        let query_text = match (&query.calls_from, &query.calls_to, &query.calls_between) {
+            (Some(symbol), None, None) => format!("calls-from:'{symbol}' depth:{depth}"),
+            (None, Some(symbol), None) => format!("calls-to:'{symbol}' depth:{depth}"),
+            (None, None, Some((a, b))) => {
+                format!("calls-between-source:'{a}' calls-between-target:'{b}' depth:{depth}")
+            }
+            _ => bail!("Give exactly one of calls_from, calls_to and calls_between"),
+        };
+        let graph = self.client(arguments)?.search_call_graph(&query).await?;
+        let mut text =
+            format_call_graph_markdown(&query_text, &SymbolGraphCollection::from_json(&graph)?);
+        if !self.args.no_demangle {
+            text = demangle_text(&text).into_owned();
+        }
+        Ok(tool_result(
+            text,
+            json!({"query": query_text, "graph": graph}),
+        ))
+    }
+
+    async fn field_layout(&mut self, arguments: &Value) -> Result<Value> {
+        let class = required_str(arguments, "class")?;
+        let Some(layout) = self.client(arguments)?.class_layout(class).await? else {
+            bail!("No field layout found for '{}'", class);
+        };
+        Ok(tool_result(
+            format_class_layout(class, &layout),
+            layout_json(class, &layout),
+        ))
+    }
+
+    async fn get_file(&mut self, arguments: &Value) -> Result<Value> {
+        let path = required_str(arguments, "path")?;
+        let rev = optional_str(arguments, "rev");
+        let range = optional_str(arguments, "lines");
+        let client = self.client(arguments)?;
+        let content = match rev {
+            Some(rev) => client.get_file_at_revision(path, rev).await?,
+            None => client.get_file_covering(path, range).await?,
+        };
+        let lines: Vec<&str> = content.lines().collect();
+        let (start, end) = match range {
+            Some(range) => parse_line_range(range, lines.len())?,
+            None => (1, lines.len()),
+        };
+        let text = if lines.is_empty() {
+            String::new()
+        } else {
+            lines[start - 1..end].join("\n")
+        };
+        let structured = json!({"path": path, "start": start, "end": end, "content": text});
+        Ok(tool_result(text, structured))
+    }
+
+    /// Answer one JSON-RPC message, or nothing for notifications.
+    async fn handle(&mut self, message: Value) -> Option<Value> {
+        let id = message.get("id").cloned()?;
+        let method = message.get("method").and_then(Value::as_str).unwrap_or("");
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let result = match method {
+            "initialize" => {
+                let requested = params.get("protocolVersion").and_then(Value::as_str);
+                let version = requested
+                    .filter(|v| PROTOCOL_VERSIONS.contains(v))
+                    .unwrap_or(PROTOCOL_VERSIONS[PROTOCOL_VERSIONS.len() - 1]);
+                json!({
+                    "protocolVersion": version,
+                    "capabilities": {"tools": {}},
+                    "serverInfo": {"name": "searchfox-cli", "version": env!("CARGO_PKG_VERSION")},
+                })
+            }
+            "ping" => json!({}),
+            "tools/list" => json!({ "tools": tools() }),
+            "tools/call" => {
+                let name = params.get("name").and_then(Value::as_str).unwrap_or("");
+                let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
+                let result = match name {
+                    "search" => self.search(&arguments).await,
+                    "define" => self.define(&arguments).await,
+                    "call_graph" => self.call_graph(&arguments).await,
+                    "field_layout" => self.field_layout(&arguments).await,
+                    "get_file" => self.get_file(&arguments).await,
+                    _ => {
+                        return Some(error_response(
+                            id,
+                            INVALID_PARAMS,
+                            &format!("Unknown tool '{name}'"),
+                        ))
+                    }
+                };
+                result.unwrap_or_else(|e| tool_error(&e))
+            }
+            _ => {
+                return Some(error_response(
+                    id,
+                    METHOD_NOT_FOUND,
+                    &format!("Unknown method '{method}'"),
+                ))
+            }
+        };
+        Some(response(id, result))
+    }
+}
+
+/// Serve searchfox queries as MCP tools over stdio: one JSON-RPC message
+/// per line on standard input, answers on standard output.
+pub async fn serve_mcp(args: &Args) -> Result<()> {
+    let mut server = Server {
+        args,
+        clients: HashMap::new(),
+    };
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let answer = match serde_json::from_str::<Value>(&line) {
+            Ok(message) => server.handle(message).await,
+            Err(e) => Some(error_response(Value::Null, PARSE_ERROR, &e.to_string())),
+        };
+        if let Some(answer) = answer {
+            let mut stdout = std::io::stdout().lock();
+            writeln!(stdout, "{answer}")?;
+            stdout.flush()?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[tokio::test]
+    async fn answers_protocol_messages() {
+        let args = Args::try_parse_from(["searchfox-cli", "serve-mcp"]).unwrap();
+        let mut server = Server {
+            args: &args,
+            clients: HashMap::new(),
+        };
+
+        let init = server
+            .handle(json!({"jsonrpc": "2.0", "id": 1, "method": "initialize",
+                "params": {"protocolVersion": "2025-03-26", "capabilities": {}}}))
+            .await
+            .unwrap();
+        assert_eq!(init["result"]["protocolVersion"], "2025-03-26");
+        assert!(server
+            .handle(json!({"jsonrpc": "2.0", "method": "notifications/initialized"}))
+            .await
+            .is_none());
+
+        let list = server
+            .handle(json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list"}))
+            .await
+            .unwrap();
+        let names: Vec<&str> = list["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            vec!["search", "define", "call_graph", "field_layout", "get_file"]
+        );
+
+        let missing = server
+            .handle(json!({"jsonrpc": "2.0", "id": 3, "method": "tools/call",
+                "params": {"name": "call_graph", "arguments": {}}}))
+            .await
+            .unwrap();
+        assert_eq!(missing["result"]["isError"], true);
+
+        let unknown = server
+            .handle(json!({"jsonrpc": "2.0", "id": 4, "method": "resources/list"}))
+            .await
+            .unwrap();
+        assert_eq!(unknown["error"]["code"], METHOD_NOT_FOUND);
+    }
+}
@@ -0,0 +1,1196 @@
+use crate::{
+    add_grep_context, batch_error, batch_matched, batch_queries, fetch_file, generate_link,
+    matched, several_files, warn_if_partial, Args,
+};
+use anyhow::{bail, Result};
+use searchfox_lib::{
+    call_graph::{call_graph_edges, CallGraphEdge, CallGraphNode, CallGraphQuery},
+    definition::parse_definition_lines,
+    demangle::demangle,
+    field_layout::{ClassLayout, LayoutField},
+    file_reader::{parse_line_range, select_lines},
+    html::{render_html_report, HtmlItem, HtmlSection, HtmlSnippet},
+    parse_commit_header,
+    search::SearchResult,
+    types::BlameInfo,
+    utils::{language_id, path_category, searchfox_url_repo},
+    SearchOptions, SearchfoxClient,
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use url::Url;
+
+/// Version of the `--format json` schema. Fields may be added without
+/// changing it; renaming, removing or changing the type of a field bumps it.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Text meant for people (and LLMs)
+    #[default]
+    Text,
+    /// One JSON document, following the documented schema
+    Json,
+    /// `path:line:column:text` lines, for vim -q and other errorformat users
+    Grep,
+    /// CSV with a header row, for spreadsheets
+    Csv,
+    /// Hits grouped by file with code snippets and permalinks, for bugs and
+    /// documents
+    Markdown,
+}
+
+/// The envelope of every JSON document: schema version, command and
+/// repository, followed by the fields of `body`.
+fn document(command: &str, repo: &str, body: Value) -> Value {
+    let mut document = json!({
+        "schema_version": SCHEMA_VERSION,
+        "command": command,
+        "repo": repo,
+    });
+    if let (Some(fields), Value::Object(body)) = (document.as_object_mut(), body) {
+        fields.extend(body);
+    }
+    document
+}
+
+pub fn search_result_json(base_url: &str, repo: &str, result: &SearchResult) -> Value {
+    // Path searches have no line.
+    let line = (result.line_number > 0).then_some(result.line_number);
+    json!({
+        "path": result.path,
+        "line": line,
+        "text": line.map(|_| &result.line),
+        "category": result.category,
+        "kind": result.kind,
+        "other_kinds": result.other_kinds,
+        "bounds": result.bounds,
+        "context_before": result.context_before,
+        "context_after": result.context_after,
+        "url": generate_link(base_url, repo, &result.path, result.line_number, result.line_number, None),
+    })
+}
+
+fn blame_json(blame: &BlameInfo) -> Value {
+    let parsed = blame
+        .commit_info
+        .as_ref()
+        .map(|info| parse_commit_header(&info.header));
+    json!({
+        "commit": blame.commit_hash,
+        "bug": parsed.as_ref().and_then(|p| p.bug_number),
+        "message": parsed.as_ref().map(|p| &p.message),
+        "author": parsed.as_ref().map(|p| &p.author),
+        "date": parsed.as_ref().map(|p| &p.date),
+        "original_path": blame.original_path,
+        "original_line": blame.original_line,
+    })
+}
+
+/// Numbered source lines, with a `blame` field (null when unknown) when
+/// `blame` is given.
+fn lines_json(lines: &[(usize, String)], blame: Option<&HashMap<usize, BlameInfo>>) -> Value {
+    lines
+        .iter()
+        .map(|(number, text)| {
+            let mut line = json!({"line": number, "text": text});
+            if let Some(blame) = blame {
+                line["blame"] = blame.get(number).map(blame_json).unwrap_or(Value::Null);
+            }
+            line
+        })
+        .collect()
+}
+
+fn call_graph_node_json(node: &CallGraphNode) -> Value {
+    json!({
+        "symbol": node.symbol,
+        "demangled": demangle(&node.symbol),
+        "pretty": node.pretty,
+        "definition": node.definition,
+        "declaration": node.declaration,
+        "parent": node.parent,
+        "process": node.process.map(|p| p.to_string()),
+    })
+}
+
+fn field_json(field: &LayoutField) -> Value {
+    json!({
+        "name": field.name,
+        "type": field.type_name,
+        "offset": field.offset,
+        "size": field.size,
+        "bits": field.bits.map(|b| json!({"begin": b.begin, "width": b.width})),
+    })
+}
+
+pub fn layout_json(class: &str, layout: &ClassLayout) -> Value {
+    json!({
+        "class": class,
+        "size": layout.size,
+        "alignment": layout.alignment,
+        "offsets_known": layout.offsets_known,
+        "bases": layout
+            .bases
+            .iter()
+            .map(|b| json!({"type": b.type_name, "offset": b.offset, "size": b.size}))
+            .collect::<Vec<_>>(),
+        "fields": layout.fields.iter().map(field_json).collect::<Vec<_>>(),
+    })
+}
+
+async fn define_json(
+    args: &Args,
+    client: &SearchfoxClient,
+    symbol: &str,
+    options: &SearchOptions,
+) -> Result<Value> {
+    let definitions = client
+        .find_definitions(symbol, args.path(), options)
+        .await?;
+    let mut values = Vec::new();
+    for (path, line, context) in definitions {
+        let lines = parse_definition_lines(&context);
+        let blame = if args.blame || args.with_blame {
+            let numbers: Vec<usize> = lines.iter().map(|(n, _)| *n).collect();
+            Some(client.get_blame_for_lines(&path, &numbers).await?)
+        } else {
+            None
+        };
+        let start = lines.first().map_or(line, |(n, _)| *n);
+        let end = lines.last().map_or(line, |(n, _)| *n);
+        values.push(json!({
+            "path": path,
+            "line": line,
+            "url": generate_link(client.base_url(), &client.repo, &path, start, end, None),
+            "lines": lines_json(&lines, blame.as_ref()),
+        }));
+    }
+    Ok(json!({"symbol": symbol, "definitions": values}))
+}
+
+/// Every definition candidate of `symbol`, with `signature` and, with
+/// --bodies, the source `lines` of each one.
+async fn define_all_json(
+    args: &Args,
+    client: &SearchfoxClient,
+    symbol: &str,
+    options: &SearchOptions,
+) -> Result<Value> {
+    let definitions = client
+        .find_all_definitions(symbol, args.path(), options, args.bodies)
+        .await?;
+    let values: Vec<Value> = definitions
+        .iter()
+        .map(|definition| {
+            let lines = definition
+                .source
+                .as_deref()
+                .map(parse_definition_lines)
+                .unwrap_or_default();
+            let start = lines.first().map_or(definition.line, |(n, _)| *n);
+            let end = lines.last().map_or(definition.line, |(n, _)| *n);
+            json!({
+                "path": definition.path,
+                "line": definition.line,
+                "signature": definition.signature,
+                "url": generate_link(client.base_url(), &client.repo, &definition.path, start, end, None),
+                "lines": lines_json(&lines, None),
+            })
+        })
+        .collect();
+    Ok(json!({"symbol": symbol, "definitions": values}))
+}
+
+async fn file_json(args: &Args, client: &SearchfoxClient, path: &str) -> Result<Value> {
+    if args.bytes || args.output.is_some() || args.binary_ok {
+        bail!("--format json prints text files only, without --bytes, --output or --binary-ok");
+    }
+    let content = fetch_file(client, path, args.rev.as_deref()).await?;
+    let total = content.lines().count();
+    let (start, end) = match args.lines {
+        Some(ref range) => parse_line_range(range, total)?,
+        None => (1, total),
+    };
+    let lines: Vec<(usize, String)> = content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.to_string()))
+        .filter(|(n, _)| *n >= start && *n <= end)
+        .collect();
+    let blame = if args.blame {
+        let numbers: Vec<usize> = lines.iter().map(|(n, _)| *n).collect();
+        Some(client.get_blame_for_lines(path, &numbers).await?)
+    } else {
+        None
+    };
+    Ok(json!({
+        "path": path,
+        "rev": args.rev,
+        "start": start,
+        "end": end,
+        "total_lines": total,
+        "url": generate_link(client.base_url(), &client.repo, path, start, end, args.rev.as_deref()),
+        "lines": lines_json(&lines, blame.as_ref()),
+    }))
+}
+
+/// The files of a multi-file `--get-file`, as a map of path to content,
+/// restricted to --lines, and a map of path to error for those that could
+/// not be fetched.
+async fn files_json(args: &Args, client: &SearchfoxClient, paths: &[String]) -> Result<Value> {
+    let mut files = serde_json::Map::new();
+    let mut errors = serde_json::Map::new();
+    for (path, content) in client.get_files(paths, args.rev.as_deref()).await {
+        let text = content.and_then(|content| {
+            let lines = select_lines(&content, args.lines.as_deref())?;
+            Ok(lines.iter().map(|(_, line)| format!("{line}\n")).collect())
+        });
+        match text {
+            Ok(text) => files.insert(path, Value::String(text)),
+            Err(e) => errors.insert(path, Value::String(format!("{e:#}"))),
+        };
+    }
+    Ok(json!({"rev": args.rev, "files": files, "errors": errors}))
+}
+
+async fn blame_file_json(args: &Args, client: &SearchfoxClient, path: &str) -> Result<Value> {
+    let content = fetch_file(client, path, None).await?;
+    let lines: Vec<(usize, String)> = select_lines(&content, args.lines.as_deref())?
+        .into_iter()
+        .map(|(n, line)| (n, line.to_string()))
+        .collect();
+    let numbers: Vec<usize> = lines.iter().map(|(n, _)| *n).collect();
+    let blame = client.get_blame_for_lines(path, &numbers).await?;
+    Ok(json!({
+        "path": path,
+        "lines": lines_json(&lines, Some(&blame)),
+    }))
+}
+
+/// The call graph query of `args`.
+fn call_graph_query(args: &Args) -> Result<CallGraphQuery> {
+    let between = match args.calls_between.as_deref().map(|s| s.split_once(',')) {
+        Some(Some((source, target))) => {
+            Some((source.trim().to_string(), target.trim().to_string()))
+        }
+        Some(None) => bail!("calls between expects a source and a target"),
+        None => None,
+    };
+    Ok(CallGraphQuery {
+        calls_from: args.calls_from.clone(),
+        calls_to: args.calls_to.clone(),
+        calls_between: between,
+        depth: args.depth,
+    })
+}
+
+async fn calls_json(args: &Args, client: &SearchfoxClient) -> Result<Value> {
+    let query = call_graph_query(args)?;
+    let (direction, symbols) = match (&query.calls_from, &query.calls_to, &query.calls_between) {
+        (Some(symbol), _, _) => ("from", vec![symbol.clone()]),
+        (_, Some(symbol), _) => ("to", vec![symbol.clone()]),
+        (_, _, Some((source, target))) => ("between", vec![source.clone(), target.clone()]),
+        _ => unreachable!("calls_json needs a call graph query"),
+    };
+    let graph = client.call_graph(&query).await?;
+    let edges: Vec<Value> = call_graph_edges(&graph)
+        .iter()
+        .map(|edge| {
+            json!({
+                "caller": call_graph_node_json(&edge.caller),
+                "callee": call_graph_node_json(&edge.callee),
+                "ipc": edge.ipc,
+            })
+        })
+        .collect();
+    Ok(json!({
+        "direction": direction,
+        "symbols": symbols,
+        "depth": args.depth,
+        "edges": edges,
+    }))
+}
+
+/// Run the command of `args` and print its result as a JSON document.
+pub async fn print_json(
+    args: &Args,
+    client: &SearchfoxClient,
+    options: &SearchOptions,
+) -> Result<()> {
+    let repo = &client.repo;
+    let base_url = client.base_url();
+    let mut found = true;
+    let document = if let Some(symbol) = &args.uses {
+        let uses: Vec<Value> = client
+            .find_uses(symbol, options)
+            .await?
+            .iter()
+            .map(|u| {
+                json!({
+                    "path": u.path,
+                    "line": u.line_number,
+                    "text": u.line,
+                    "category": u.category,
+                    "symbol": u.symbol,
+                    "context": u.context,
+                    "contextsym": u.context_symbol,
+                    "url": generate_link(base_url, repo, &u.path, u.line_number, u.line_number, None),
+                })
+            })
+            .collect();
+        found = !uses.is_empty();
+        document("uses", repo, json!({"symbol": symbol, "uses": uses}))
+    } else if let Some(prefix) = &args.complete {
+        let completions: Vec<Value> = client
+            .complete(prefix, options)
+            .await?
+            .iter()
+            .take(args.limit)
+            .map(|c| {
+                json!({
+                    "name": c.name,
+                    "kind": c.kind,
+                    "path": c.path,
+                    "line": c.line,
+                    "url": generate_link(base_url, repo, &c.path, c.line, c.line, None),
+                })
+            })
+            .collect();
+        found = !completions.is_empty();
+        document(
+            "complete",
+            repo,
+            json!({"prefix": prefix, "completions": completions}),
+        )
+    } else if let Some(symbol) = &args.define_all {
+        document(
+            "define",
+            repo,
+            define_all_json(args, client, symbol, options).await?,
+        )
+    } else if let Some(symbol) = &args.define {
+        document(
+            "define",
+            repo,
+            define_json(args, client, symbol, options).await?,
+        )
+    } else if let Some(path) = &args.blame_file {
+        document("blame", repo, blame_file_json(args, client, path).await?)
+    } else if let Some(paths) = several_files(args)? {
+        document("file", repo, files_json(args, client, &paths).await?)
+    } else if let Some(path) = args.get_file.first() {
+        let command = if args.blame { "blame" } else { "file" };
+        document(command, repo, file_json(args, client, path).await?)
+    } else if args.calls_from.is_some() || args.calls_to.is_some() || args.calls_between.is_some() {
+        document("calls", repo, calls_json(args, client).await?)
+    } else if let Some(class) = &args.field_layout {
+        if args.compare_repo.is_some() {
+            bail!("--format json does not support --compare-repo");
+        }
+        let layout = client.class_layout(class).await?;
+        found = layout.is_some();
+        let body = match layout {
+            Some(layout) => json!({ "layout": layout_json(class, &layout) }),
+            None => json!({ "layout": null }),
+        };
+        document("layout", repo, body)
+    } else if let Some(queries) = batch_queries(args)? {
+        let batch = client.search_batch(&queries, options).await;
+        found = batch_matched(&batch);
+        let queries: Vec<Value> = batch
+            .iter()
+            .map(|entry| match &entry.results {
+                Ok(results) => json!({
+                    "query": entry.query,
+                    "results": results
+                        .iter()
+                        .map(|r| search_result_json(base_url, repo, r))
+                        .collect::<Vec<_>>(),
+                    "error": null,
+                }),
+                Err(e) => json!({"query": entry.query, "results": [], "error": e.to_string()}),
+            })
+            .collect();
+        document("batch", repo, json!({ "queries": queries }))
+    } else if args.query.is_some()
+        || args.symbol.is_some()
+        || args.id.is_some()
+        || !args.paths.is_empty()
+    {
+        if args.stats_only || args.watch || args.archive.is_some() || args.emit_tags.is_some() {
+            bail!("--format json cannot be combined with --stats-only, --watch, --archive or --emit-tags");
+        }
+        let (mut results, metadata) = client.search_with_metadata(options).await?;
+        found = !results.is_empty();
+        add_grep_context(args, client, &mut results).await;
+        let results: Vec<Value> = results
+            .iter()
+            .map(|r| search_result_json(base_url, repo, r))
+            .collect();
+        document(
+            "search",
+            repo,
+            json!({
+                "query": options.query,
+                "symbol": options.symbol,
+                "symbol_demangled": options.symbol.as_deref().and_then(demangle),
+                "id": options.id,
+                "path": options.path,
+                "paths": options.path.iter().chain(&options.extra_paths).collect::<Vec<_>>(),
+                "offset": options.offset,
+                "partial": metadata.is_partial(),
+                "timed_out": metadata.timed_out,
+                "results": results,
+            }),
+        )
+    } else {
+        bail!(
+            "--format json is supported by search, --batch, --uses, --complete, define, file, blame, calls and layout"
+        );
+    };
+    println!("{}", serde_json::to_string_pretty(&document)?);
+    matched(found)
+}
+
+/// A result as grep -n --column prints it, which vim's quickfix list and
+/// errorformat parsers read: the column is that of the match, or 1 when
+/// searchfox gave no bounds. Path-only results point at their first line.
+pub fn grep_line(result: &SearchResult) -> String {
+    let column = result.bounds.first().map_or(1, |(start, _)| start + 1);
+    format!(
+        "{}:{}:{}:{}",
+        result.path,
+        result.line_number.max(1),
+        column,
+        result.line
+    )
+}
+
+/// Run the search, batch or uses query of `args` and print its results as
+/// `path:line:column:text` lines, with nothing else on stdout.
+pub async fn print_grep(
+    args: &Args,
+    client: &SearchfoxClient,
+    options: &SearchOptions,
+) -> Result<()> {
+    let found = if let Some(symbol) = &args.uses {
+        let uses = client.find_uses(symbol, options).await?;
+        for symbol_use in &uses {
+            println!(
+                "{}:{}:1:{}",
+                symbol_use.path, symbol_use.line_number, symbol_use.line
+            );
+        }
+        !uses.is_empty()
+    } else if let Some(queries) = batch_queries(args)? {
+        let batch = client.search_batch(&queries, options).await;
+        for entry in &batch {
+            match &entry.results {
+                Ok(results) => results.iter().for_each(|r| println!("{}", grep_line(r))),
+                Err(e) => eprintln!("{}: {}", entry.query, e),
+            }
+        }
+        let found = batch_matched(&batch);
+        if let Some(e) = batch_error(batch) {
+            return Err(e);
+        }
+        found
+    } else if args.query.is_some()
+        || args.symbol.is_some()
+        || args.id.is_some()
+        || !args.paths.is_empty()
+    {
+        if args.stats_only || args.watch || args.archive.is_some() || args.emit_tags.is_some() {
+            bail!("--format grep cannot be combined with --stats-only, --watch, --archive or --emit-tags");
+        }
+        let (results, metadata) = client.search_with_metadata(options).await?;
+        warn_if_partial(&metadata);
+        for result in &results {
+            println!("{}", grep_line(result));
+        }
+        !results.is_empty()
+    } else {
+        bail!("--format grep is supported by search, --batch and --uses");
+    };
+    matched(found)
+}
+
+/// A CSV record ending with CRLF, fields quoted as RFC 4180 says when they
+/// contain a comma, a quote or a line break.
+fn csv_record<S: AsRef<str>>(fields: &[S]) -> String {
+    let mut record = fields
+        .iter()
+        .map(|field| {
+            let field = field.as_ref();
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    record.push_str("\r\n");
+    record
+}
+
+/// Run the search, batch, uses or blame query of `args` and print its
+/// results as CSV: path, line, category and text, then the columns of the
+/// command.
+pub async fn print_csv(
+    args: &Args,
+    client: &SearchfoxClient,
+    options: &SearchOptions,
+) -> Result<()> {
+    let mut output = String::new();
+    let mut found = true;
+    let blame_path = args
+        .blame_file
+        .as_ref()
+        .or(args.get_file.first().filter(|_| args.blame));
+    if let Some(symbol) = &args.uses {
+        output.push_str(&csv_record(&[
+            "path", "line", "category", "text", "context",
+        ]));
+        let uses = client.find_uses(symbol, options).await?;
+        found = !uses.is_empty();
+        for u in uses {
+            let line = u.line_number.to_string();
+            let context = u.context.unwrap_or_default();
+            output.push_str(&csv_record(&[
+                &u.path,
+                &line,
+                &u.category,
+                &u.line,
+                &context,
+            ]));
+        }
+    } else if let Some(path) = blame_path {
+        if several_files(args)?.is_some() {
+            bail!("--format csv blames one file at a time");
+        }
+        let content = fetch_file(client, path, None).await?;
+        let lines = select_lines(&content, args.lines.as_deref())?;
+        let numbers: Vec<usize> = lines.iter().map(|(n, _)| *n).collect();
+        let blame = client.get_blame_for_lines(path, &numbers).await?;
+        output.push_str(&csv_record(&[
+            "path", "line", "category", "text", "commit", "bug", "author", "date", "message",
+        ]));
+        for (number, text) in lines {
+            let info = blame.get(&number);
+            let parsed = info
+                .and_then(|b| b.commit_info.as_ref())
+                .map(|c| parse_commit_header(&c.header));
+            let commit = info.map(|b| b.commit_hash.as_str()).unwrap_or_default();
+            let bug = parsed
+                .as_ref()
+                .and_then(|p| p.bug_number)
+                .map(|bug| bug.to_string())
+                .unwrap_or_default();
+            let (author, date, message) = parsed
+                .as_ref()
+                .map(|p| (p.author.as_str(), p.date.as_str(), p.message.as_str()))
+                .unwrap_or_default();
+            output.push_str(&csv_record(&[
+                path.as_str(),
+                &number.to_string(),
+                path_category(path),
+                text,
+                commit,
+                &bug,
+                author,
+                date,
+                message,
+            ]));
+        }
+    } else if let Some(queries) = batch_queries(args)? {
+        let batch = client.search_batch(&queries, options).await;
+        output.push_str(&csv_record(&["path", "line", "category", "text", "query"]));
+        for entry in &batch {
+            match &entry.results {
+                Ok(results) => {
+                    for r in results {
+                        let line = r.line_number.to_string();
+                        output.push_str(&csv_record(&[
+                            &r.path,
+                            &line,
+                            &r.category,
+                            &r.line,
+                            &entry.query,
+                        ]));
+                    }
+                }
+                Err(e) => eprintln!("{}: {}", entry.query, e),
+            }
+        }
+        print!("{output}");
+        let found = batch_matched(&batch);
+        if let Some(e) = batch_error(batch) {
+            return Err(e);
+        }
+        return matched(found);
+    } else if args.query.is_some()
+        || args.symbol.is_some()
+        || args.id.is_some()
+        || !args.paths.is_empty()
+    {
+        if args.stats_only || args.watch || args.archive.is_some() || args.emit_tags.is_some() {
+            bail!("--format csv cannot be combined with --stats-only, --watch, --archive or --emit-tags");
+        }
+        let (results, metadata) = client.search_with_metadata(options).await?;
+        warn_if_partial(&metadata);
+        found = !results.is_empty();
+        output.push_str(&csv_record(&["path", "line", "category", "text"]));
+        for r in &results {
+            // Path searches have no line.
+            let line = if r.line_number > 0 {
+                r.line_number.to_string()
+            } else {
+                String::new()
+            };
+            output.push_str(&csv_record(&[&r.path, &line, &r.category, &r.line]));
+        }
+    } else {
+        bail!("--format csv is supported by search, --batch, --uses and blame");
+    }
+    print!("{output}");
+    matched(found)
+}
+
+/// `lines` in a fenced code block tagged with the language of `path`. The
+/// fence is longer than any run of backticks in the code.
+fn fenced<S: AsRef<str>>(path: &str, lines: &[S]) -> String {
+    let longest = lines
+        .iter()
+        .flat_map(|line| line.as_ref().split(|c| c != '`'))
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    let language = match language_id(path) {
+        "plaintext" => "",
+        language => language,
+    };
+    let mut block = format!("{fence}{language}\n");
+    for line in lines {
+        block.push_str(line.as_ref());
+        block.push('\n');
+    }
+    block.push_str(&fence);
+    block.push('\n');
+    block
+}
+
+/// Search results as markdown: a section per file, in the order the files
+/// first appear, and under it each hit with its context in a code block,
+/// after a link to those lines made by `link(path, start, end)`.
+pub fn search_markdown(
+    title: &str,
+    results: &[SearchResult],
+    link: impl Fn(&str, usize, usize) -> String,
+) -> String {
+    let mut files: Vec<(&str, Vec<&SearchResult>)> = Vec::new();
+    for result in results {
+        match files.iter_mut().find(|(path, _)| *path == result.path) {
+            Some((_, hits)) => hits.push(result),
+            None => files.push((&result.path, vec![result])),
+        }
+    }
+    let mut output = format!("# {title}\n\n");
+    if files.is_empty() {
+        output.push_str("No results.\n");
+        return output;
+    }
+    for (path, hits) in files {
+        output.push_str(&format!("## [{}]({})\n", path, link(path, 0, 0)));
+        // Path searches have no line.
+        for hit in hits.iter().filter(|hit| hit.line_number > 0) {
+            let start = hit.line_number - hit.context_before.len();
+            let end = hit.line_number + hit.context_after.len();
+            let lines: Vec<&str> = hit
+                .context_before
+                .iter()
+                .chain([&hit.line])
+                .chain(&hit.context_after)
+                .map(String::as_str)
+                .collect();
+            output.push_str(&format!(
+                "\n[Line {}]({})\n\n{}",
+                hit.line_number,
+                link(path, start, end),
+                fenced(path, &lines)
+            ));
+        }
+        output.push('\n');
+    }
+    output
+}
+
+/// A definition as `--format markdown` prints it.
+pub struct MarkdownDefinition {
+    pub path: String,
+    /// Known with `--all` only.
+    pub signature: Option<String>,
+    /// Numbered source lines, none when the body was not fetched.
+    pub lines: Vec<(usize, String)>,
+}
+
+/// Definitions as markdown, each under a heading linking to its lines,
+/// followed by its signature when known and its source lines.
+pub fn definitions_markdown(
+    symbol: &str,
+    definitions: &[MarkdownDefinition],
+    link: impl Fn(&str, usize, usize) -> String,
+) -> String {
+    let mut output = format!("# Definition of `{symbol}`\n\n");
+    if definitions.is_empty() {
+        output.push_str("No definition found.\n");
+        return output;
+    }
+    for MarkdownDefinition {
+        path,
+        signature,
+        lines,
+    } in definitions
+    {
+        let start = lines.first().map_or(0, |(n, _)| *n);
+        let end = lines.last().map_or(0, |(n, _)| *n);
+        let location = if start == 0 {
+            path.clone()
+        } else {
+            format!("{path}#{start}")
+        };
+        output.push_str(&format!(
+            "## [{}]({})\n\n",
+            location,
+            link(path, start, end)
+        ));
+        if let Some(signature) = signature {
+            output.push_str(&format!("`{signature}`\n\n"));
+        }
+        if !lines.is_empty() {
+            let code: Vec<&str> = lines.iter().map(|(_, text)| text.as_str()).collect();
+            output.push_str(&fenced(path, &code));
+            output.push('\n');
+        }
+    }
+    output
+}
+
+/// Run the search or define query of `args` and print its results as
+/// markdown, linking to each hit with a permalink to the indexed revision.
+pub async fn print_markdown(
+    args: &Args,
+    client: &SearchfoxClient,
+    options: &SearchOptions,
+) -> Result<()> {
+    let definitions = if let Some(symbol) = &args.define_all {
+        let found = client
+            .find_all_definitions(symbol, args.path(), options, args.bodies)
+            .await?;
+        Some((
+            symbol,
+            found
+                .into_iter()
+                .map(|d| {
+                    let lines = d
+                        .source
+                        .as_deref()
+                        .map(parse_definition_lines)
+                        .unwrap_or_default();
+                    MarkdownDefinition {
+                        path: d.path,
+                        signature: Some(d.signature),
+                        lines,
+                    }
+                })
+                .collect::<Vec<_>>(),
+        ))
+    } else if let Some(symbol) = &args.define {
+        let found = client
+            .find_definitions(symbol, args.path(), options)
+            .await?;
+        Some((
+            symbol,
+            found
+                .into_iter()
+                .map(|(path, _, context)| MarkdownDefinition {
+                    path,
+                    signature: None,
+                    lines: parse_definition_lines(&context),
+                })
+                .collect(),
+        ))
+    } else if args.query.is_some()
+        || args.symbol.is_some()
+        || args.id.is_some()
+        || !args.paths.is_empty()
+    {
+        if args.stats_only || args.watch || args.archive.is_some() || args.emit_tags.is_some() {
+            bail!("--format markdown cannot be combined with --stats-only, --watch, --archive or --emit-tags");
+        }
+        None
+    } else {
+        bail!("--format markdown is supported by search and define");
+    };
+
+    let hash = client.get_head_hash().await?;
+    let link = |path: &str, start: usize, end: usize| {
+        generate_link(
+            client.base_url(),
+            &client.repo,
+            path,
+            start,
+            end,
+            Some(&hash),
+        )
+    };
+    let (markdown, found) = match definitions {
+        Some((symbol, definitions)) => (
+            definitions_markdown(symbol, &definitions, link),
+            !definitions.is_empty(),
+        ),
+        None => {
+            let (mut results, metadata) = client.search_with_metadata(options).await?;
+            warn_if_partial(&metadata);
+            add_grep_context(args, client, &mut results).await;
+            let markdown = search_markdown(
+                &format!("Search for `{}`", options.build_query()),
+                &results,
+                link,
+            );
+            (markdown, !results.is_empty())
+        }
+    };
+    print!("{markdown}");
+    matched(found)
+}
+
+/// The results of a search as report sections, one per file, each hit with
+/// its context as a snippet.
+pub fn search_sections(
+    results: &[SearchResult],
+    link: impl Fn(&str, usize, usize) -> String,
+) -> Vec<HtmlSection> {
+    let mut sections: Vec<HtmlSection> = Vec::new();
+    for result in results {
+        let index = match sections.iter().position(|s| s.title == result.path) {
+            Some(index) => index,
+            None => {
+                sections.push(HtmlSection {
+                    title: result.path.clone(),
+                    url: Some(link(&result.path, 0, 0)),
+                    items: Vec::new(),
+                });
+                sections.len() - 1
+            }
+        };
+        // Path searches have no line.
+        if result.line_number == 0 {
+            continue;
+        }
+        let start = result.line_number - result.context_before.len();
+        let end = result.line_number + result.context_after.len();
+        sections[index].items.push(HtmlItem {
+            label: format!("Line {}", result.line_number),
+            url: link(&result.path, start, end),
+            snippet: Some(HtmlSnippet {
+                path: result.path.clone(),
+                first_line: start,
+                lines: result
+                    .context_before
+                    .iter()
+                    .chain([&result.line])
+                    .chain(&result.context_after)
+                    .cloned()
+                    .collect(),
+                hit_line: Some(result.line_number),
+            }),
+        });
+    }
+    sections
+}
+
+/// The calls of a call graph as report sections, one per file of the
+/// callers, or of the callees for `--calls-from`, each call linking to the
+/// definition of that side. Functions without a known location are listed
+/// last, linking to a search for their symbol.
+pub fn call_graph_sections(
+    edges: &[CallGraphEdge],
+    callees: bool,
+    link: impl Fn(&str, usize) -> String,
+    search_link: impl Fn(&str) -> String,
+) -> Vec<HtmlSection> {
+    const UNKNOWN: &str = "Unknown location";
+    let mut sections: Vec<HtmlSection> = Vec::new();
+    for edge in edges {
+        let node = if callees { &edge.callee } else { &edge.caller };
+        let location = node.definition.as_deref().or(node.declaration.as_deref());
+        let (title, url) = match location.and_then(|l| l.split_once('#')) {
+            Some((path, line)) => (path, link(path, line.parse().unwrap_or(0))),
+            None => (UNKNOWN, search_link(&node.symbol)),
+        };
+        let index = match sections.iter().position(|s| s.title == title) {
+            Some(index) => index,
+            None => {
+                sections.push(HtmlSection {
+                    title: title.to_string(),
+                    url: (title != UNKNOWN).then(|| link(title, 0)),
+                    items: Vec::new(),
+                });
+                sections.len() - 1
+            }
+        };
+        let ipc = if edge.ipc { " (IPC)" } else { "" };
+        sections[index].items.push(HtmlItem {
+            label: format!("{} → {}{}", edge.caller.pretty, edge.callee.pretty, ipc),
+            url,
+            snippet: None,
+        });
+    }
+    if let Some(index) = sections.iter().position(|s| s.title == UNKNOWN) {
+        let unknown = sections.remove(index);
+        sections.push(unknown);
+    }
+    sections
+}
+
+/// Run the search or call graph query of `args` and write its results to
+/// `path` as a standalone HTML page, linking to the indexed revision.
+pub async fn write_html_report(
+    args: &Args,
+    client: &SearchfoxClient,
+    options: &SearchOptions,
+    path: &Path,
+) -> Result<()> {
+    let is_calls =
+        args.calls_from.is_some() || args.calls_to.is_some() || args.calls_between.is_some();
+    let is_search = args.query.is_some()
+        || args.symbol.is_some()
+        || args.id.is_some()
+        || !args.paths.is_empty();
+    if !is_calls && !is_search {
+        bail!("--html-report is supported by search and calls");
+    }
+    if args.stats_only || args.watch || args.archive.is_some() || args.emit_tags.is_some() {
+        bail!(
+            "--html-report cannot be combined with --stats-only, --watch, --archive or --emit-tags"
+        );
+    }
+
+    let base_url = client.base_url();
+    let tree = searchfox_url_repo(&client.repo);
+    let hash = client.get_head_hash().await?;
+    let link = |path: &str, start: usize, end: usize| {
+        generate_link(base_url, &client.repo, path, start, end, Some(&hash))
+    };
+    let (title, sections) = if is_calls {
+        let query = call_graph_query(args)?;
+        let graph = client.call_graph(&query).await?;
+        let title = match (&query.calls_from, &query.calls_to, &query.calls_between) {
+            (Some(symbol), _, _) => format!("Calls from {symbol}"),
+            (_, Some(symbol), _) => format!("Calls to {symbol}"),
+            (_, _, Some((source, target))) => format!("Calls from {source} to {target}"),
+            _ => unreachable!("checked above"),
+        };
+        let search_link = |symbol: &str| {
+            Url::parse_with_params(
+                &format!("{base_url}/{tree}/search"),
+                [("q", format!("symbol:{symbol}"))],
+            )
+            .map(String::from)
+            .unwrap_or_default()
+        };
+        let sections = call_graph_sections(
+            &call_graph_edges(&graph),
+            query.calls_from.is_some(),
+            |path, line| link(path, line, line),
+            search_link,
+        );
+        (title, sections)
+    } else {
+        let (mut results, metadata) = client.search_with_metadata(options).await?;
+        warn_if_partial(&metadata);
+        add_grep_context(args, client, &mut results).await;
+        let title = format!("Search for {}", options.build_query());
+        (title, search_sections(&results, link))
+    };
+
+    let footer = format!("{} at revision {}, from {}", client.repo, hash, base_url);
+    std::fs::write(path, render_html_report(&title, &sections, &footer))?;
+    let count: usize = sections.iter().map(|s| s.items.len()).sum();
+    eprintln!(
+        "Wrote {} result(s) in {} section(s) to {}",
+        count,
+        sections.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use searchfox_lib::DEFAULT_SERVER;
+
+    #[test]
+    fn quotes_csv_fields_when_needed() {
+        assert_eq!(
+            csv_record(&["dom/a.cpp", "12", "normal", r#"f(a, "b");"#]),
+            "dom/a.cpp,12,normal,\"f(a, \"\"b\"\");\"\r\n"
+        );
+        assert_eq!(csv_record(&["x\ny", ""]), "\"x\ny\",\r\n");
+    }
+
+    #[test]
+    fn documents_share_an_envelope() {
+        let result = SearchResult {
+            path: "dom/media/AudioStream.h".into(),
+            line_number: 42,
+            line: "class AudioStream final {".into(),
+            context_before: vec![],
+            context_after: vec![],
+            category: "normal".into(),
+            kind: Some("Definitions (mozilla::AudioStream)".into()),
+            bounds: Vec::new(),
+            other_kinds: Vec::new(),
+        };
+        let doc = document(
+            "search",
+            "mozilla-central",
+            json!({"results": [search_result_json(DEFAULT_SERVER, "mozilla-central", &result)]}),
+        );
+        assert_eq!(doc["schema_version"], SCHEMA_VERSION);
+        assert_eq!(doc["command"], "search");
+        assert_eq!(doc["results"][0]["line"], 42);
+        assert_eq!(doc["results"][0]["category"], "normal");
+        assert!(doc["results"][0]["url"]
+            .as_str()
+            .unwrap()
+            .ends_with("/source/dom/media/AudioStream.h#42"));
+
+        assert_eq!(
+            grep_line(&result),
+            "dom/media/AudioStream.h:42:1:class AudioStream final {"
+        );
+        let matched = SearchResult {
+            bounds: vec![(6, 17)],
+            ..result.clone()
+        };
+        assert_eq!(
+            grep_line(&matched),
+            "dom/media/AudioStream.h:42:7:class AudioStream final {"
+        );
+
+        let path_only = SearchResult {
+            line_number: 0,
+            line: String::new(),
+            kind: None,
+            ..result
+        };
+        let value = search_result_json(DEFAULT_SERVER, "mozilla-central", &path_only);
+        assert!(value["line"].is_null() && value["text"].is_null());
+    }
+
+    #[test]
+    fn blame_is_present_only_when_asked() {
+        let lines = vec![(10, "int a;".to_string()), (11, "int b;".to_string())];
+        assert!(lines_json(&lines, None)[0].get("blame").is_none());
+        let mut blame = HashMap::new();
+        blame.insert(
+            10,
+            BlameInfo {
+                commit_hash: "0123456789abcdef".into(),
+                original_path: "dom/a.cpp".into(),
+                original_line: 8,
+                commit_info: None,
+            },
+        );
+        let value = lines_json(&lines, Some(&blame));
+        assert_eq!(value[0]["blame"]["commit"], "0123456789abcdef");
+        assert!(value[0]["blame"]["bug"].is_null());
+        assert!(value[1]["blame"].is_null());
+    }
+
+    #[test]
+    fn markdown_groups_hits_by_file() {
+        let hit = |path: &str, line_number, line: &str| SearchResult {
+            path: path.into(),
+            line_number,
+            line: line.into(),
+            context_before: vec![],
+            context_after: vec![],
+            category: "normal".into(),
+            kind: None,
+            bounds: Vec::new(),
+            other_kinds: Vec::new(),
+        };
+        let results = vec![
+            SearchResult {
+                context_before: vec!["void f() {".into()],
+                ..hit("dom/a.cpp", 3, "  g();")
+            },
+            hit("dom/b.js", 7, "let s = `${x}`;"),
+            hit("dom/a.cpp", 9, "  g();"),
+        ];
+        let link = |path: &str, start: usize, end: usize| format!("{path}@{start}-{end}");
+        assert_eq!(
+            search_markdown("Search for `g`", &results, link),
+            "# Search for `g`\n\n\
+             ## [dom/a.cpp](dom/a.cpp@0-0)\n\n\
+             [Line 3](dom/a.cpp@2-3)\n\n```cpp\nvoid f() {\n  g();\n```\n\n\
+             [Line 9](dom/a.cpp@9-9)\n\n```cpp\n  g();\n```\n\n\
+             ## [dom/b.js](dom/b.js@0-0)\n\n\
+             [Line 7](dom/b.js@7-7)\n\n```javascript\nlet s = `${x}`;\n```\n\n"
+        );
+
+        let definitions = vec![MarkdownDefinition {
+            path: "dom/a.cpp".into(),
+            signature: None,
+            lines: vec![(2, "void f() {".into()), (3, "}".into())],
+        }];
+        assert_eq!(
+            definitions_markdown("f", &definitions, link),
+            "# Definition of `f`\n\n## [dom/a.cpp#2](dom/a.cpp@2-3)\n\n```cpp\nvoid f() {\n}\n```\n\n"
+        );
+        assert_eq!(fenced("x.txt", &["```"]), "````\n```\n````\n");
+    }
+
+    #[test]
+    fn call_graph_sections_group_by_file() {
+        let node = |pretty: &str, definition: Option<&str>| CallGraphNode {
+            symbol: format!("_Z{pretty}"),
+            pretty: pretty.into(),
+            definition: definition.map(String::from),
+            declaration: None,
+            parent: None,
+            process: None,
+        };
+        let target = node("Target", Some("dom/t.cpp#5"));
+        let edge = |caller, ipc| CallGraphEdge {
+            caller,
+            callee: target.clone(),
+            ipc,
+        };
+        let edges = vec![
+            edge(node("Unknown", None), false),
+            edge(node("A", Some("dom/a.cpp#10")), false),
+            edge(node("B", Some("dom/a.cpp#20")), true),
+        ];
+        let sections = call_graph_sections(
+            &edges,
+            false,
+            |path, line| format!("{path}@{line}"),
+            |symbol| format!("search:{symbol}"),
+        );
+        let titles: Vec<&str> = sections.iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(titles, ["dom/a.cpp", "Unknown location"]);
+        assert_eq!(sections[0].url.as_deref(), Some("dom/a.cpp@0"));
+        assert_eq!(sections[0].items[1].label, "B → Target (IPC)");
+        assert_eq!(sections[0].items[1].url, "dom/a.cpp@20");
+        assert_eq!(sections[1].items[0].url, "search:_ZUnknown");
+        assert!(sections[1].url.is_none());
+    }
+}
@@ -0,0 +1,243 @@
+use crate::{already_reported, new_client, run, Args};
+use anyhow::{anyhow, bail, Result};
+use clap::Parser;
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use searchfox_lib::SearchfoxClient;
+use std::collections::HashMap;
+use std::io::{BufRead, IsTerminal};
+use std::path::PathBuf;
+
+const SHELL_HELP: &str = "Commands:
+  search <QUERY> [OPTIONS]            full-text search
+  define <SYMBOL> [OPTIONS]           definition of a symbol
+  calls from|to <SYMBOL> [OPTIONS]    call graph of a function
+  calls between <A> <B> [OPTIONS]     call paths between two names
+  file <PATH> [OPTIONS]               contents of a file
+  layout <CLASS>, blame <PATH>, ...   any other command line, without the program name
+  set <OPTION> [VALUE]                add --OPTION VALUE to every command, e.g. set repo mozilla-beta
+  unset <OPTION>                      stop adding --OPTION
+  set                                 list the options set
+  help                                this help, <COMMAND> --help for its options
+  exit, quit                          leave the shell (or Ctrl-D)
+Arguments are split like a POSIX shell: quote queries containing spaces.
+";
+
+/// Options added to every command of the shell, in the order they were set.
+#[derive(Debug, Default)]
+struct Settings {
+    options: Vec<(String, Option<String>)>,
+}
+
+impl Settings {
+    fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        for (name, value) in &self.options {
+            args.push(format!("--{name}"));
+            args.extend(value.clone());
+        }
+        args
+    }
+
+    fn set(&mut self, name: &str, value: Option<String>) {
+        self.options.retain(|(n, _)| n != name);
+        self.options.push((name.to_string(), value));
+    }
+}
+
+/// Where the shell history is kept, next to the cache database.
+fn history_path() -> Option<PathBuf> {
+    Some(
+        searchfox_lib::cache::cache_path()?
+            .parent()?
+            .join("shell_history"),
+    )
+}
+
+struct Shell<'a> {
+    /// The arguments the shell was started with, for client settings.
+    args: &'a Args,
+    settings: Settings,
+    /// One client per repository, kept warm between commands.
+    clients: HashMap<String, SearchfoxClient>,
+}
+
+impl Shell<'_> {
+    fn parse(&self, command: &[String]) -> Result<Args, clap::Error> {
+        Args::try_parse_from(
+            std::iter::once("searchfox-cli".to_string())
+                .chain(self.settings.args())
+                .chain(command.iter().cloned()),
+        )
+    }
+
+    fn prompt(&self) -> String {
+        let repo = self
+            .settings
+            .options
+            .iter()
+            .find(|(name, _)| name == "repo")
+            .and_then(|(_, value)| value.as_deref())
+            .unwrap_or(&self.args.repo);
+        format!("searchfox:{repo}> ")
+    }
+
+    /// Run one line of input. Returns false when the shell should exit.
+    async fn execute(&mut self, line: &str) -> Result<bool> {
+        let words = shlex::split(line).ok_or_else(|| anyhow!("Unbalanced quotes"))?;
+        match words.first().map(String::as_str) {
+            None => {}
+            Some("exit" | "quit") => return Ok(false),
+            Some("help") => print!("{SHELL_HELP}"),
+            Some("set") if words.len() == 1 => {
+                for (name, value) in &self.settings.options {
+                    println!("{} {}", name, value.as_deref().unwrap_or(""));
+                }
+            }
+            Some("set") => {
+                let name = words[1].trim_start_matches('-');
+                let previous = self.settings.options.clone();
+                self.settings.set(name, words.get(2).cloned());
+                if let Err(e) = self.parse(&[]) {
+                    self.settings.options = previous;
+                    bail!("{}", e.to_string().trim_end());
+                }
+            }
+            Some("unset") => {
+                let Some(name) = words.get(1) else {
+                    bail!("Usage: unset <OPTION>");
+                };
+                let name = name.trim_start_matches('-');
+                self.settings.options.retain(|(n, _)| n != name);
+            }
+            Some(_) => {
+                let mut args = match self.parse(&words) {
+                    Ok(args) => args,
+                    Err(e) => {
+                        // Includes --help and --version output.
+                        e.print()?;
+                        return Ok(true);
+                    }
+                };
+                args.apply_command()?;
+                if args.command.is_some() {
+                    bail!("'{}' cannot be run from the shell", words[0]);
+                }
+                if !self.clients.contains_key(&args.repo) {
+                    let client = new_client(self.args, &args.repo)?;
+                    self.clients.insert(args.repo.clone(), client);
+                }
+                match run(&args, &self.clients[&args.repo]).await {
+                    Err(e) if already_reported(&e) => {}
+                    result => result?,
+                }
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Read commands from the terminal, with line editing and a history, or
+/// from standard input when it is not a terminal, and run them with one
+/// client per repository.
+pub async fn run_shell(args: &Args) -> Result<()> {
+    let mut shell = Shell {
+        args,
+        settings: Settings::default(),
+        clients: HashMap::new(),
+    };
+    shell.settings.set("repo", Some(args.repo.clone()));
+
+    if !std::io::stdin().is_terminal() {
+        for line in std::io::stdin().lock().lines() {
+            match shell.execute(&line?).await {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(e) => eprintln!("Error: {e}"),
+            }
+        }
+        return Ok(());
+    }
+
+    let mut editor = DefaultEditor::new()?;
+    let history = history_path();
+    if let Some(ref path) = history {
+        // No history yet on the first run.
+        let _ = editor.load_history(path);
+    }
+    println!("searchfox-cli shell, 'help' lists the commands");
+    loop {
+        let line = match editor.readline(&shell.prompt()) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+        if !line.trim().is_empty() {
+            editor.add_history_entry(line.as_str())?;
+        }
+        match shell.execute(&line).await {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => eprintln!("Error: {e}"),
+        }
+    }
+    if let Some(ref path) = history {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        editor.save_history(path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(line: &str) -> Vec<String> {
+        shlex::split(line).unwrap()
+    }
+
+    #[test]
+    fn runs_subcommands_and_flags() {
+        let args = Args::try_parse_from(["searchfox-cli", "shell"]).unwrap();
+        let shell = Shell {
+            args: &args,
+            settings: Settings::default(),
+            clients: HashMap::new(),
+        };
+        let mut parsed = shell
+            .parse(&words(
+                "calls between AudioContext AudioNode --depth 2 -p ^dom/media",
+            ))
+            .unwrap();
+        parsed.apply_command().unwrap();
+        assert_eq!(
+            parsed.calls_between.as_deref(),
+            Some("AudioContext,AudioNode")
+        );
+        assert_eq!(parsed.depth, 2);
+        assert_eq!(parsed.path(), Some("^dom/media"));
+        let parsed = shell.parse(&words("--field-layout mozilla::dom::AudioContext"));
+        assert!(parsed.unwrap().field_layout.is_some());
+        assert!(shell.parse(&words("frobnicate x")).is_err());
+    }
+
+    #[test]
+    fn settings_apply_to_every_command() {
+        let args = Args::try_parse_from(["searchfox-cli", "shell"]).unwrap();
+        let mut shell = Shell {
+            args: &args,
+            settings: Settings::default(),
+            clients: HashMap::new(),
+        };
+        shell.settings.set("repo", Some("mozilla-central".into()));
+        shell.settings.set("limit", Some("20".into()));
+        shell.settings.set("repo", Some("mozilla-beta".into()));
+        assert_eq!(shell.prompt(), "searchfox:mozilla-beta> ");
+        let parsed = shell.parse(&words("search AudioStream -l 5")).unwrap();
+        assert_eq!(parsed.repo, "mozilla-beta");
+        assert_eq!(parsed.limit, 5);
+    }
+}
@@ -0,0 +1,350 @@
+use crate::client::SearchfoxClient;
+use crate::nesting::OutlineEntry;
+use crate::progress::ProgressEvent;
+use crate::search::SearchOptions;
+use crate::unused::is_function;
+use anyhow::{bail, Result};
+use futures::stream::{self, StreamExt};
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+
+const CONCURRENT_QUERIES: usize = 8;
+
+/// Lines after an attribute searched for the name of the function it
+/// applies to, for attributes on a line of their own.
+const DECLARATION_LINES: usize = 3;
+
+/// Whether a function carries the audited attribute.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnnotationStatus {
+    /// On the definition itself.
+    Annotated,
+    /// On a declaration of the same name, in the file or its header (or
+    /// implementation file).
+    Declared {
+        path: String,
+        line: usize,
+    },
+    Missing,
+}
+
+/// A function of an audited file and its annotation status.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditedFunction {
+    pub sym: String,
+    pub path: String,
+    pub line: usize,
+    pub status: AnnotationStatus,
+}
+
+/// An attribute found in the source: where, and on which function name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    pub path: String,
+    pub line: usize,
+    pub name: String,
+}
+
+fn attribute_regex(attribute: &str) -> Regex {
+    Regex::new(&format!(r"\b{}\b", regex::escape(attribute))).expect("valid regex")
+}
+
+/// Name of the function `attribute` applies to on line `line` (1-based) of
+/// `lines`: the last identifier before the parameter list, which may be on
+/// one of the next lines. Macro definitions, comments and attributes on
+/// anything but a function are ignored.
+pub fn annotated_name(lines: &[&str], line: usize, attribute: &str) -> Option<String> {
+    let text = lines.get(line.checked_sub(1)?)?;
+    let trimmed = text.trim_start();
+    if ["#", "//", "/*", "*"]
+        .iter()
+        .any(|p| trimmed.starts_with(p))
+    {
+        return None;
+    }
+    let at = attribute_regex(attribute).find(text)?;
+    let mut rest = text[at.end()..].to_string();
+    for next in lines.iter().skip(line).take(DECLARATION_LINES) {
+        if rest.contains('(') {
+            break;
+        }
+        rest.push(' ');
+        rest.push_str(next);
+    }
+    let (signature, _) = rest.split_once('(')?;
+    if signature.contains([';', '{', '}', '=']) {
+        return None;
+    }
+    let identifier = Regex::new(r"~?[A-Za-z_][A-Za-z0-9_]*").expect("valid regex");
+    identifier
+        .find_iter(signature)
+        .last()
+        .map(|m| m.as_str().to_string())
+}
+
+/// The header of an implementation file and vice versa.
+fn paired_file(path: &str) -> Option<String> {
+    if let Some(stem) = path.strip_suffix(".h") {
+        Some(format!("{stem}.cpp"))
+    } else {
+        path.strip_suffix(".cpp").map(|stem| format!("{stem}.h"))
+    }
+}
+
+/// Status of each function: annotated when the attribute names it at most
+/// a few lines above its definition, declared when it names it elsewhere
+/// in the file or its pair, missing otherwise. Overloads are not told
+/// apart.
+pub fn classify_functions(
+    functions: &[(String, OutlineEntry)],
+    annotations: &[Annotation],
+) -> Vec<AuditedFunction> {
+    let mut audited: Vec<AuditedFunction> = functions
+        .iter()
+        .map(|(path, entry)| {
+            let name = entry.sym.rsplit("::").next().unwrap_or(&entry.sym);
+            let pair = paired_file(path);
+            let named: Vec<&Annotation> = annotations
+                .iter()
+                .filter(|a| a.name == name)
+                .filter(|a| a.path == *path || Some(&a.path) == pair.as_ref())
+                .collect();
+            let on_definition = named.iter().any(|a| {
+                a.path == *path && a.line <= entry.line && a.line + DECLARATION_LINES >= entry.line
+            });
+            let status = if on_definition {
+                AnnotationStatus::Annotated
+            } else if let Some(a) = named.first() {
+                AnnotationStatus::Declared {
+                    path: a.path.clone(),
+                    line: a.line,
+                }
+            } else {
+                AnnotationStatus::Missing
+            };
+            AuditedFunction {
+                sym: entry.sym.clone(),
+                path: path.clone(),
+                line: entry.line,
+                status,
+            }
+        })
+        .collect();
+    audited.sort_by(|a, b| a.path.cmp(&b.path).then(a.line.cmp(&b.line)));
+    audited
+}
+
+impl SearchfoxClient {
+    /// Audit the use of a static-analysis attribute (`MOZ_CAN_RUN_SCRIPT`,
+    /// `MOZ_MUST_USE`, ...) under `path`: find the files using it, and report
+    /// for every function they (and their header or implementation file)
+    /// define, by outline, whether it carries the attribute.
+    pub async fn annotation_audit(
+        &self,
+        attribute: &str,
+        path: &str,
+        options: &SearchOptions,
+    ) -> Result<Vec<AuditedFunction>> {
+        if attribute.is_empty()
+            || !attribute
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            bail!("Expected an attribute macro name, got '{}'", attribute);
+        }
+        let pattern = attribute_regex(attribute).as_str().to_string();
+        let hits = self
+            .regex_lines(pattern, Some(path.to_string()), options)
+            .await?;
+        let annotated_files: BTreeSet<String> = hits.iter().map(|(p, _, _)| p.clone()).collect();
+        let mut files: BTreeSet<String> = annotated_files.clone();
+        files.extend(annotated_files.iter().filter_map(|p| paired_file(p)));
+
+        let operation = format!("annotation {attribute}");
+        let total = files.len();
+        self.emit(ProgressEvent::Started {
+            operation: operation.clone(),
+            total,
+        });
+
+        let mut fetches = stream::iter(files)
+            .map(|file| {
+                let has_hits = annotated_files.contains(&file);
+                async move {
+                    let outline = self.get_outline(&file).await;
+                    let source = if has_hits {
+                        Some(self.get_file(&file).await)
+                    } else {
+                        None
+                    };
+                    (file, has_hits, outline, source)
+                }
+            })
+            .buffered(CONCURRENT_QUERIES);
+
+        let mut functions = Vec::new();
+        let mut annotations = Vec::new();
+        let mut completed = 0;
+        while let Some((file, has_hits, outline, source)) = fetches.next().await {
+            completed += 1;
+            self.emit(ProgressEvent::Advanced {
+                operation: operation.clone(),
+                completed,
+                total,
+            });
+            let outline = match outline {
+                Ok(outline) => outline,
+                // The pair guessed for a file may not exist.
+                Err(_) if !has_hits => continue,
+                Err(e) => return Err(e),
+            };
+            functions.extend(
+                outline
+                    .into_iter()
+                    .filter(is_function)
+                    .map(|entry| (file.clone(), entry)),
+            );
+            if let Some(source) = source {
+                let source = source?;
+                let lines: Vec<&str> = source.lines().collect();
+                for (_, line, _) in hits.iter().filter(|(p, _, _)| *p == file) {
+                    if let Some(name) = annotated_name(&lines, *line, attribute) {
+                        annotations.push(Annotation {
+                            path: file.clone(),
+                            line: *line,
+                            name,
+                        });
+                    }
+                }
+            }
+        }
+        self.emit(ProgressEvent::Finished { operation });
+
+        Ok(classify_functions(&functions, &annotations))
+    }
+}
+
+pub fn format_annotation_audit(
+    attribute: &str,
+    path: &str,
+    functions: &[AuditedFunction],
+) -> String {
+    let mut output = format!("# {} in {}\n\n", attribute, path);
+    if functions.is_empty() {
+        output.push_str(&format!("No function found using {}.\n", attribute));
+        return output;
+    }
+    let mut by_file: BTreeMap<&str, Vec<&AuditedFunction>> = BTreeMap::new();
+    for function in functions {
+        by_file.entry(&function.path).or_default().push(function);
+    }
+    let carrying = |functions: &[&AuditedFunction]| {
+        functions
+            .iter()
+            .filter(|f| f.status != AnnotationStatus::Missing)
+            .count()
+    };
+    let all: Vec<&AuditedFunction> = functions.iter().collect();
+    output.push_str(&format!(
+        "{} of {} function(s) annotated in {} file(s)\n",
+        carrying(&all),
+        all.len(),
+        by_file.len()
+    ));
+    for (file, functions) in by_file {
+        output.push_str(&format!(
+            "\n## {} ({}/{})\n\n",
+            file,
+            carrying(&functions),
+            functions.len()
+        ));
+        for function in functions {
+            match &function.status {
+                AnnotationStatus::Annotated => output.push_str(&format!(
+                    "- [x] {} (line {})\n",
+                    function.sym, function.line
+                )),
+                AnnotationStatus::Declared { path, line } => output.push_str(&format!(
+                    "- [x] {} (line {}, declared at {}:{})\n",
+                    function.sym, function.line, path, line
+                )),
+                AnnotationStatus::Missing => output.push_str(&format!(
+                    "- [ ] {} (line {})\n",
+                    function.sym, function.line
+                )),
+            }
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "class AudioSink {
+ public:
+  MOZ_CAN_RUN_SCRIPT void Start(const PlaybackParams& aParams);
+  MOZ_CAN_RUN_SCRIPT
+  RefPtr<EndedPromise> Shutdown();
+  // MOZ_CAN_RUN_SCRIPT void Commented();
+  MOZ_CAN_RUN_SCRIPT_BOUNDARY void Boundary();
+};
+#define SINK_METHOD MOZ_CAN_RUN_SCRIPT void Macro();
+";
+
+    #[test]
+    fn finds_annotated_names() {
+        let lines: Vec<&str> = HEADER.lines().collect();
+        let name = |line| annotated_name(&lines, line, "MOZ_CAN_RUN_SCRIPT");
+        assert_eq!(name(3).as_deref(), Some("Start"));
+        assert_eq!(name(4).as_deref(), Some("Shutdown"));
+        assert_eq!(name(6), None);
+        assert_eq!(name(7), None);
+        assert_eq!(name(9), None);
+    }
+
+    #[test]
+    fn classifies_definitions_and_declarations() {
+        let function = |path: &str, sym: &str, line| {
+            (
+                path.to_string(),
+                OutlineEntry {
+                    sym: sym.into(),
+                    pretty_line: String::new(),
+                    line,
+                },
+            )
+        };
+        let functions = vec![
+            function("dom/media/AudioSink.cpp", "mozilla::AudioSink::Start", 40),
+            function("dom/media/AudioSink.cpp", "mozilla::AudioSink::Stop", 60),
+            function("dom/media/AudioSink.cpp", "mozilla::AudioSink::Drain", 81),
+        ];
+        let annotation = |path: &str, line, name: &str| Annotation {
+            path: path.into(),
+            line,
+            name: name.into(),
+        };
+        let annotations = vec![
+            annotation("dom/media/AudioSink.h", 3, "Start"),
+            annotation("dom/media/AudioSink.cpp", 80, "Drain"),
+            annotation("dom/media/Other.h", 10, "Stop"),
+        ];
+        let statuses: Vec<AnnotationStatus> = classify_functions(&functions, &annotations)
+            .into_iter()
+            .map(|f| f.status)
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                AnnotationStatus::Declared {
+                    path: "dom/media/AudioSink.h".into(),
+                    line: 3
+                },
+                AnnotationStatus::Missing,
+                AnnotationStatus::Annotated,
+            ]
+        );
+    }
+}
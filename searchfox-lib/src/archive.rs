@@ -0,0 +1,72 @@
+use anyhow::Result;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::Write;
+
+/// Write `(path, content)` pairs as a gzip-compressed tarball.
+///
+/// Entries get a fixed mode and mtime so the same set of files always
+/// produces the same archive.
+pub fn write_tar_gz<W: Write, C: AsRef<[u8]>>(writer: W, files: &[(String, C)]) -> Result<()> {
+    let encoder = GzEncoder::new(writer, Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+
+    for (path, content) in files {
+        let mut header = tar::Header::new_gnu();
+        let content = content.as_ref();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(0);
+        builder.append_data(&mut header, path.trim_start_matches('/'), content)?;
+    }
+
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn round_trips_paths_and_contents() {
+        let files = vec![
+            (
+                "dom/media/AudioStream.h".to_string(),
+                "class AudioStream;\n".to_string(),
+            ),
+            (
+                "/dom/media/AudioStream.cpp".to_string(),
+                "// impl\n".to_string(),
+            ),
+        ];
+        let mut buf = Vec::new();
+        write_tar_gz(&mut buf, &files).unwrap();
+
+        let mut archive = tar::Archive::new(GzDecoder::new(buf.as_slice()));
+        let mut entries = Vec::new();
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let path = entry.path().unwrap().to_string_lossy().into_owned();
+            let mut content = String::new();
+            entry.read_to_string(&mut content).unwrap();
+            entries.push((path, content));
+        }
+
+        assert_eq!(
+            entries,
+            vec![
+                (
+                    "dom/media/AudioStream.h".to_string(),
+                    "class AudioStream;\n".to_string()
+                ),
+                (
+                    "dom/media/AudioStream.cpp".to_string(),
+                    "// impl\n".to_string()
+                ),
+            ]
+        );
+    }
+}
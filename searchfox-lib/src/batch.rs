@@ -0,0 +1,178 @@
+use crate::client::SearchfoxClient;
+use crate::progress::ProgressEvent;
+use crate::search::{files_with_matches, ResultSummary, SearchOptions, SearchResult};
+use anyhow::Result;
+use futures::stream::{self, StreamExt};
+
+const CONCURRENT_QUERIES: usize = 8;
+
+/// The outcome of one query of a batch.
+pub struct BatchResult {
+    pub query: String,
+    pub results: Result<Vec<SearchResult>>,
+}
+
+/// Queries of a batch file, one per line. Blank lines and lines starting
+/// with `#` are skipped.
+pub fn parse_batch_queries(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect()
+}
+
+impl SearchfoxClient {
+    /// Run every query with the other settings of `options`, a few at a
+    /// time. Results come in the order of `queries`; a failing query does
+    /// not stop the others.
+    pub async fn search_batch(
+        &self,
+        queries: &[String],
+        options: &SearchOptions,
+    ) -> Vec<BatchResult> {
+        let operation = "batch".to_string();
+        let total = queries.len();
+        self.emit(ProgressEvent::Started {
+            operation: operation.clone(),
+            total,
+        });
+
+        let mut searches = stream::iter(queries)
+            .map(|query| async move {
+                let options = SearchOptions {
+                    query: Some(query.clone()),
+                    ..options.clone()
+                };
+                BatchResult {
+                    query: query.clone(),
+                    results: self.search(&options).await,
+                }
+            })
+            .buffered(CONCURRENT_QUERIES);
+
+        let mut batch = Vec::with_capacity(total);
+        while let Some(result) = searches.next().await {
+            batch.push(result);
+            self.emit(ProgressEvent::Advanced {
+                operation: operation.clone(),
+                completed: batch.len(),
+                total,
+            });
+        }
+        self.emit(ProgressEvent::Finished { operation });
+        batch
+    }
+}
+
+/// Results as `query<TAB>path:line: text` lines, so that they can be
+/// filtered with grep and cut; failed queries as `query<TAB>error: ...`.
+pub fn format_batch_results(batch: &[BatchResult]) -> String {
+    let mut output = String::new();
+    for entry in batch {
+        match &entry.results {
+            Ok(results) if results.is_empty() => {
+                output.push_str(&format!("{}\t(no results)\n", entry.query));
+            }
+            Ok(results) => {
+                for result in results {
+                    if result.line_number == 0 {
+                        output.push_str(&format!("{}\t{}\n", entry.query, result.path));
+                    } else {
+                        output.push_str(&format!(
+                            "{}\t{}:{}: {}\n",
+                            entry.query,
+                            result.path,
+                            result.line_number,
+                            result.line.trim()
+                        ));
+                    }
+                }
+            }
+            Err(e) => output.push_str(&format!("{}\terror: {}\n", entry.query, e)),
+        }
+    }
+    output
+}
+
+/// The `summary` of each query's results, as `query<TAB>count` lines or
+/// one `query<TAB>path` line per file; failed queries as with
+/// `format_batch_results`.
+pub fn format_batch_summary(batch: &[BatchResult], summary: ResultSummary) -> String {
+    let mut output = String::new();
+    for entry in batch {
+        match (&entry.results, summary) {
+            (Ok(results), ResultSummary::Count) => {
+                output.push_str(&format!("{}\t{}\n", entry.query, results.len()));
+            }
+            (Ok(results), ResultSummary::FilesWithMatches) => {
+                for path in files_with_matches(results) {
+                    output.push_str(&format!("{}\t{}\n", entry.query, path));
+                }
+            }
+            (Err(e), _) => output.push_str(&format!("{}\terror: {}\n", entry.query, e)),
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_one_query_per_line() {
+        let text = "AudioStream\n\n# media\n  path:dom/media AudioSink  \n";
+        assert_eq!(
+            parse_batch_queries(text),
+            vec!["AudioStream", "path:dom/media AudioSink"]
+        );
+    }
+
+    #[test]
+    fn tags_results_with_their_query() {
+        let result = |path: &str, line_number, line: &str| SearchResult {
+            path: path.into(),
+            line_number,
+            line: line.into(),
+            context_before: vec![],
+            context_after: vec![],
+            category: "normal".into(),
+            kind: None,
+            bounds: Vec::new(),
+            other_kinds: Vec::new(),
+        };
+        let batch = vec![
+            BatchResult {
+                query: "AudioStream".into(),
+                results: Ok(vec![result(
+                    "dom/media/AudioStream.h",
+                    42,
+                    "  class AudioStream",
+                )]),
+            },
+            BatchResult {
+                query: "Nothing".into(),
+                results: Ok(vec![]),
+            },
+            BatchResult {
+                query: "Broken".into(),
+                results: Err(anyhow::anyhow!("Request failed: 500")),
+            },
+        ];
+        assert_eq!(
+            format_batch_results(&batch),
+            "AudioStream\tdom/media/AudioStream.h:42: class AudioStream\n\
+             Nothing\t(no results)\n\
+             Broken\terror: Request failed: 500\n"
+        );
+        assert_eq!(
+            format_batch_summary(&batch, ResultSummary::Count),
+            "AudioStream\t1\nNothing\t0\nBroken\terror: Request failed: 500\n"
+        );
+        assert_eq!(
+            format_batch_summary(&batch, ResultSummary::FilesWithMatches),
+            "AudioStream\tdom/media/AudioStream.h\nBroken\terror: Request failed: 500\n"
+        );
+    }
+}
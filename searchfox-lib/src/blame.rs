@@ -1,5 +1,6 @@
 use crate::client::SearchfoxClient;
-use crate::types::{BlameInfo, CommitInfo, ParsedCommitInfo};
+use crate::progress::ProgressEvent;
+use crate::types::{BlameInfo, CommitInfo, IndexInfo, ParsedCommitInfo};
 use crate::utils::searchfox_url_repo;
 use anyhow::Result;
 use regex::Regex;
@@ -8,19 +9,45 @@ use std::collections::HashMap;
 
 impl SearchfoxClient {
     pub async fn get_head_hash(&self) -> anyhow::Result<String> {
-        let url = format!(
-            "https://searchfox.org/{}/commit-info/HEAD",
-            searchfox_url_repo(&self.repo)
-        );
+        let tree = searchfox_url_repo(&self.repo);
+        let commit = self.get_head_commit(tree).await?;
+        self.resolve_head_hash(tree, &commit).await
+    }
+
+    /// Report which revision the repository's index was built from, and when
+    /// that revision was committed.
+    pub async fn get_index_info(&self) -> Result<IndexInfo> {
+        self.tree_index_info(searchfox_url_repo(&self.repo)).await
+    }
+
+    /// Same as `get_index_info`, for the searchfox tree named `tree`.
+    pub(crate) async fn tree_index_info(&self, tree: &str) -> Result<IndexInfo> {
+        let commit = self.get_head_commit(tree).await?;
+        let revision = self.resolve_head_hash(tree, &commit).await?;
+        let info: CommitInfo = serde_json::from_value(commit)
+            .map_err(|_| anyhow::anyhow!("Failed to parse HEAD commit info"))?;
+        let parsed = parse_commit_header(&info.header);
+        Ok(IndexInfo {
+            revision,
+            date: info.date,
+            summary: parsed.message,
+            bug_number: parsed.bug_number,
+        })
+    }
+
+    async fn get_head_commit(&self, tree: &str) -> Result<serde_json::Value> {
+        let url = format!("{}/{}/commit-info/HEAD", self.base_url, tree);
         let response = self.get_raw(&url).await?;
         let json: serde_json::Value = serde_json::from_str(&response)
             .map_err(|_| anyhow::anyhow!("Failed to parse HEAD commit info"))?;
 
-        let commit = json
-            .as_array()
+        json.as_array()
             .and_then(|arr| arr.first())
-            .ok_or_else(|| anyhow::anyhow!("Could not find HEAD commit-info entry"))?;
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Could not find HEAD commit-info entry"))
+    }
 
+    async fn resolve_head_hash(&self, tree: &str, commit: &serde_json::Value) -> Result<String> {
         // For repos with HG history (e.g. firefox-main), fulldiff contains an HG hash.
         // Searchfox's hgrev endpoint (web-server.rs) redirects to /rev/<git-hash>/ via
         // `git cinnabar hg2git`, giving the git hash Searchfox uses for permalinks.
@@ -30,11 +57,7 @@ impl SearchfoxClient {
             .and_then(|s| s.rsplit('/').next())
             .filter(|h| h.len() == 40 && h.chars().all(|c| c.is_ascii_hexdigit()))
         {
-            let hgrev_url = format!(
-                "https://searchfox.org/{}/hgrev/{}",
-                searchfox_url_repo(&self.repo),
-                hg_hash
-            );
+            let hgrev_url = format!("{}/{}/hgrev/{}", self.base_url, tree, hg_hash);
             if let Ok(final_url) = self.get_final_url(&hgrev_url).await {
                 if let Some(git_hash) = final_url
                     .split("/rev/")
@@ -66,7 +89,7 @@ impl SearchfoxClient {
         lines: &[usize],
     ) -> Result<HashMap<usize, BlameInfo>> {
         // Fetch the HTML page for the file
-        let url = format!("https://searchfox.org/{}/source/{}", self.repo, path);
+        let url = format!("{}/{}/source/{}", self.base_url, self.repo, path);
         let html = self.get_html(&url).await?;
 
         // Parse blame data from HTML
@@ -131,19 +154,29 @@ impl SearchfoxClient {
         // Each hash is 40 chars + 1 comma, so ~50 hashes should be safe
         const BATCH_SIZE: usize = 50;
 
+        let operation = "commit-info".to_string();
+        let total = revs.len();
+        self.emit(ProgressEvent::Started {
+            operation: operation.clone(),
+            total,
+        });
+
         let mut all_infos = Vec::new();
 
         for chunk in revs.chunks(BATCH_SIZE) {
             let revs_str = chunk.join(",");
-            let url = format!(
-                "https://searchfox.org/{}/commit-info/{}",
-                self.repo, revs_str
-            );
+            let url = format!("{}/{}/commit-info/{}", self.base_url, self.repo, revs_str);
 
             let response = self.get_raw(&url).await?;
             let mut commit_infos: Vec<CommitInfo> = serde_json::from_str(&response)?;
             all_infos.append(&mut commit_infos);
+            self.emit(ProgressEvent::Advanced {
+                operation: operation.clone(),
+                completed: all_infos.len().min(total),
+                total,
+            });
         }
+        self.emit(ProgressEvent::Finished { operation });
 
         Ok(all_infos)
     }
@@ -223,6 +256,40 @@ pub fn parse_commit_header(header: &str) -> ParsedCommitInfo {
     }
 }
 
+/// Source lines, each followed by the commit that last changed it: short
+/// hash, bug, message, author and date. Gaps between the lines are marked
+/// with `...`.
+pub fn format_line_blame(lines: &[(usize, &str)], blame: &HashMap<usize, BlameInfo>) -> String {
+    let mut output = String::new();
+    let mut previous = None;
+    for &(number, text) in lines {
+        if previous.is_some_and(|p| p + 1 != number) {
+            output.push_str("...\n");
+        }
+        previous = Some(number);
+        output.push_str(&format!("{:4}: {}\n", number, text));
+        let Some(info) = blame.get(&number) else {
+            continue;
+        };
+        let short_hash = &info.commit_hash[..info.commit_hash.len().min(8)];
+        match info.commit_info {
+            Some(ref commit_info) => {
+                let parsed = parse_commit_header(&commit_info.header);
+                let bug = parsed
+                    .bug_number
+                    .map(|bug| format!("Bug {}: ", bug))
+                    .unwrap_or_default();
+                output.push_str(&format!(
+                    "      [{}] {}{} ({}, {})\n",
+                    short_hash, bug, parsed.message, parsed.author, parsed.date
+                ));
+            }
+            None => output.push_str(&format!("      [{}]\n", short_hash)),
+        }
+    }
+    output
+}
+
 fn strip_html_tags(html: &str) -> String {
     let tag_re = Regex::new(r"<[^>]+>").unwrap();
     let without_tags = tag_re.replace_all(html, "");
@@ -284,6 +351,37 @@ mod tests {
         assert_eq!(result.date, "2021-05-15");
     }
 
+    #[test]
+    fn formats_blame_per_line() {
+        let blame = |hash: &str, header: Option<&str>| BlameInfo {
+            commit_hash: hash.into(),
+            original_path: "dom/media/AudioStream.cpp".into(),
+            original_line: 1,
+            commit_info: header.map(|header| CommitInfo {
+                header: header.into(),
+                parent: None,
+                date: String::new(),
+                fulldiff: None,
+                phab: None,
+            }),
+        };
+        let mut map = HashMap::new();
+        map.insert(
+            100,
+            blame(
+                "0123456789abcdef",
+                Some("Bug 123456: Fix audio issue\n<i>John Doe, 2021-05-15</i>"),
+            ),
+        );
+        map.insert(120, blame("fedcba9876543210", None));
+        let lines = [(100, "  Init();"), (120, "  Start();"), (121, "}")];
+        assert_eq!(
+            format_line_blame(&lines, &map),
+            " 100:   Init();\n      [01234567] Bug 123456: Fix audio issue (John Doe, 2021-05-15)\n\
+             ...\n 120:   Start();\n      [fedcba98]\n 121: }\n"
+        );
+    }
+
     #[test]
     fn test_strip_html_tags() {
         let html = "Bug <a href=\"url\">123</a>: message";
@@ -0,0 +1,425 @@
+use crate::client::SearchfoxClient;
+use crate::error::error_report;
+use crate::search::SearchOptions;
+use anyhow::{bail, Result};
+use futures::stream::{self, StreamExt};
+use regex::Regex;
+use std::collections::BTreeMap;
+
+const CONCURRENT_FETCHES: usize = 8;
+
+/// A Bugzilla product and component, e.g. `Core :: Audio/Video: cubeb`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BugComponent {
+    pub product: String,
+    pub component: String,
+}
+
+impl BugComponent {
+    /// Parse the `Product :: Component` form Bugzilla displays.
+    pub fn parse(text: &str) -> Result<Self> {
+        let Some((product, component)) = text.split_once("::") else {
+            bail!("Expected 'Product :: Component', got '{}'", text);
+        };
+        Ok(Self {
+            product: product.trim().to_string(),
+            component: component.trim().to_string(),
+        })
+    }
+}
+
+impl std::fmt::Display for BugComponent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} :: {}", self.product, self.component)
+    }
+}
+
+/// A `with Files(...):` block of a moz.build file setting `BUG_COMPONENT`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilesBlock {
+    /// Patterns, relative to the directory of the moz.build file.
+    pub patterns: Vec<String>,
+    pub component: BugComponent,
+    /// Line of the `with Files(...)` statement.
+    pub line: usize,
+}
+
+/// Where a file's component comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComponentMatch {
+    pub component: BugComponent,
+    /// The moz.build file and line of the block that set it.
+    pub mozbuild: String,
+    pub line: usize,
+    pub pattern: String,
+}
+
+fn quoted_strings(text: &str) -> Vec<String> {
+    let re = Regex::new(r#""([^"]*)"|'([^']*)'"#).expect("valid regex");
+    re.captures_iter(text)
+        .filter_map(|c| c.get(1).or_else(|| c.get(2)))
+        .map(|m| m.as_str().to_string())
+        .collect()
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// The `with Files(...)` blocks of a moz.build file that set
+/// `BUG_COMPONENT`, in file order.
+pub fn parse_files_blocks(source: &str) -> Vec<FilesBlock> {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut blocks = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        let Some(args) = line.trim_start().strip_prefix("with Files(") else {
+            continue;
+        };
+        let indent = indentation(line);
+        let body: Vec<&str> = lines[index + 1..]
+            .iter()
+            .take_while(|l| l.trim().is_empty() || indentation(l) > indent)
+            .copied()
+            .collect();
+        let Some(start) = body.iter().position(|l| l.contains("BUG_COMPONENT")) else {
+            continue;
+        };
+        // The tuple can span several lines.
+        let mut value = String::new();
+        for l in &body[start..] {
+            value.push_str(l);
+            if l.contains(')') {
+                break;
+            }
+        }
+        let value = value.split_once('=').map(|(_, v)| v).unwrap_or_default();
+        if let [product, component, ..] = quoted_strings(value).as_slice() {
+            blocks.push(FilesBlock {
+                patterns: quoted_strings(args.rsplit_once(')').map_or(args, |(a, _)| a)),
+                component: BugComponent {
+                    product: product.clone(),
+                    component: component.clone(),
+                },
+                line: index + 1,
+            });
+        }
+    }
+    blocks
+}
+
+/// Whether `path`, relative to a moz.build directory, matches a `Files`
+/// pattern, with mozbuild's semantics: `*` stays within a path segment,
+/// `**` spans directories, and a pattern naming a directory matches
+/// everything below it.
+pub fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let mut re = String::from("^");
+    let segments: Vec<&str> = pattern.split('/').collect();
+    for (i, segment) in segments.iter().enumerate() {
+        let last = i + 1 == segments.len();
+        if *segment == "**" {
+            re.push_str(if last { ".*" } else { "(?:[^/]+/)*" });
+            continue;
+        }
+        for c in segment.chars() {
+            match c {
+                '*' => re.push_str("[^/]*"),
+                '?' => re.push_str("[^/]"),
+                c => re.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        if !last {
+            re.push('/');
+        }
+    }
+    re.push_str("(?:/.*)?$");
+    Regex::new(&re).is_ok_and(|re| re.is_match(path))
+}
+
+fn mozbuild_dir(mozbuild: &str) -> &str {
+    mozbuild
+        .strip_suffix("moz.build")
+        .unwrap_or(mozbuild)
+        .trim_end_matches('/')
+}
+
+/// The component of `path`, given the parsed moz.build files of its
+/// directory and its ancestors. As in mozbuild, blocks apply from the root
+/// down and in file order, the last match winning.
+pub fn resolve_component(
+    path: &str,
+    mozbuilds: &BTreeMap<String, Vec<FilesBlock>>,
+) -> Option<ComponentMatch> {
+    let mut found = None;
+    let mut dirs = vec![""];
+    dirs.extend(
+        path.match_indices('/')
+            .map(|(i, _)| &path[..i])
+            .filter(|d| !d.is_empty()),
+    );
+    for dir in dirs {
+        let mozbuild = if dir.is_empty() {
+            "moz.build".to_string()
+        } else {
+            format!("{dir}/moz.build")
+        };
+        let Some(blocks) = mozbuilds.get(&mozbuild) else {
+            continue;
+        };
+        let relative = if dir.is_empty() {
+            path
+        } else {
+            &path[dir.len() + 1..]
+        };
+        for block in blocks {
+            if let Some(pattern) = block.patterns.iter().find(|p| pattern_matches(p, relative)) {
+                found = Some(ComponentMatch {
+                    component: block.component.clone(),
+                    mozbuild: mozbuild.clone(),
+                    line: block.line,
+                    pattern: pattern.clone(),
+                });
+            }
+        }
+    }
+    found
+}
+
+impl SearchfoxClient {
+    /// The `BUG_COMPONENT` blocks of a moz.build file, or none when the
+    /// file does not exist.
+    async fn files_blocks(&self, mozbuild: &str) -> Result<Vec<FilesBlock>> {
+        match self.get_file(mozbuild).await {
+            Ok(source) => Ok(parse_files_blocks(&source)),
+            Err(e) if error_report(&e).kind == "not_found" => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn fetch_files_blocks(
+        &self,
+        mozbuilds: Vec<String>,
+    ) -> Result<BTreeMap<String, Vec<FilesBlock>>> {
+        stream::iter(mozbuilds)
+            .map(|mozbuild| async move {
+                let blocks = self.files_blocks(&mozbuild).await?;
+                Ok((mozbuild, blocks))
+            })
+            .buffered(CONCURRENT_FETCHES)
+            .collect::<Vec<Result<_>>>()
+            .await
+            .into_iter()
+            .collect()
+    }
+
+    /// The Bugzilla component `path` is filed under, from the moz.build
+    /// files of its directory and its ancestors.
+    pub async fn component_of(&self, path: &str) -> Result<Option<ComponentMatch>> {
+        let path = path.trim_matches('/');
+        let mut mozbuilds = vec!["moz.build".to_string()];
+        mozbuilds.extend(
+            path.match_indices('/')
+                .map(|(i, _)| format!("{}/moz.build", &path[..i])),
+        );
+        let blocks = self.fetch_files_blocks(mozbuilds).await?;
+        Ok(resolve_component(path, &blocks))
+    }
+
+    /// The moz.build blocks assigning `component`, and the files it owns:
+    /// those under these blocks whose component is not overridden by a
+    /// later block or a deeper moz.build file.
+    pub async fn component_files(
+        &self,
+        component: &BugComponent,
+        options: &SearchOptions,
+    ) -> Result<(Vec<(String, FilesBlock)>, Vec<String>)> {
+        let mentions = self
+            .text_lines(
+                &format!("\"{}\"", component.component),
+                &SearchOptions {
+                    path: Some(r"(^|/)moz\.build$".to_string()),
+                    ..options.clone()
+                },
+            )
+            .await?;
+        let mut mentioning: Vec<String> = mentions.into_iter().map(|(path, _, _)| path).collect();
+        mentioning.dedup();
+
+        let mut declarations = Vec::new();
+        for (mozbuild, blocks) in self.fetch_files_blocks(mentioning).await? {
+            for block in blocks {
+                if block.component == *component {
+                    declarations.push((mozbuild.clone(), block));
+                }
+            }
+        }
+
+        // Directories holding matching blocks; nested ones are listed with
+        // their ancestor.
+        let mut roots: Vec<&str> = declarations
+            .iter()
+            .map(|(mozbuild, _)| mozbuild_dir(mozbuild))
+            .collect();
+        roots.sort();
+        roots.dedup();
+        let roots: Vec<&str> = roots
+            .iter()
+            .filter(|root| {
+                !roots
+                    .iter()
+                    .any(|other| other != *root && root.starts_with(&format!("{other}/")))
+            })
+            .copied()
+            .collect();
+
+        let mut candidates = Vec::new();
+        for root in roots {
+            candidates.extend(self.list_directory_files(root, options).await?);
+        }
+        candidates.sort();
+        candidates.dedup();
+
+        let mozbuilds: Vec<String> = candidates
+            .iter()
+            .filter(|path| path.ends_with("/moz.build") || *path == "moz.build")
+            .cloned()
+            .collect();
+        let blocks = self.fetch_files_blocks(mozbuilds).await?;
+        let files = candidates
+            .into_iter()
+            .filter(|path| {
+                resolve_component(path, &blocks).is_some_and(|m| m.component == *component)
+            })
+            .collect();
+        Ok((declarations, files))
+    }
+}
+
+pub fn format_component_of(path: &str, found: Option<&ComponentMatch>) -> String {
+    match found {
+        Some(m) => format!(
+            "{}: {}\n  from {}:{} (Files(\"{}\"))\n",
+            path, m.component, m.mozbuild, m.line, m.pattern
+        ),
+        None => format!("{}: no BUG_COMPONENT found\n", path),
+    }
+}
+
+pub fn format_component_files(
+    component: &BugComponent,
+    declarations: &[(String, FilesBlock)],
+    files: &[String],
+) -> String {
+    let mut output = format!("# Files of {}\n\n", component);
+    if declarations.is_empty() {
+        output.push_str("No moz.build file assigns this component.\n");
+        return output;
+    }
+    output.push_str("Declared in:\n");
+    for (mozbuild, block) in declarations {
+        let patterns: Vec<String> = block.patterns.iter().map(|p| format!("\"{p}\"")).collect();
+        output.push_str(&format!(
+            "- {}:{} (Files({}))\n",
+            mozbuild,
+            block.line,
+            patterns.join(", ")
+        ));
+    }
+    output.push('\n');
+    for file in files {
+        output.push_str(file);
+        output.push('\n');
+    }
+    let dirs: Vec<String> = declarations
+        .iter()
+        .map(|(mozbuild, _)| regex::escape(mozbuild_dir(mozbuild)))
+        .filter(|dir| !dir.is_empty())
+        .collect();
+    output.push_str(&format!("\n{} file(s)", files.len()));
+    if !dirs.is_empty() {
+        output.push_str(&format!(
+            ". Scope searches with: -p '^({})/'",
+            dirs.join("|")
+        ));
+    }
+    output.push('\n');
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOZBUILD: &str = r#"
+with Files("**"):
+    BUG_COMPONENT = ("Core", "Audio/Video: cubeb")
+
+with Files("test/**"):
+    BUG_COMPONENT = (
+        "Core",
+        "Audio/Video: Playback",
+    )
+
+with Files("*.py", "mach_commands.py"):
+    SCHEDULES.exclusive = ["linux"]
+
+DIRS += ["src"]
+"#;
+
+    #[test]
+    fn parses_bug_component_blocks() {
+        let blocks = parse_files_blocks(MOZBUILD);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].patterns, vec!["**"]);
+        assert_eq!(blocks[0].line, 2);
+        assert_eq!(
+            blocks[1].component,
+            BugComponent::parse("Core :: Audio/Video: Playback").unwrap()
+        );
+        assert_eq!(blocks[1].patterns, vec!["test/**"]);
+    }
+
+    #[test]
+    fn matches_mozbuild_patterns() {
+        assert!(pattern_matches("**", "src/cubeb.c"));
+        assert!(pattern_matches("test/**", "test/gtest/foo.cpp"));
+        assert!(!pattern_matches("test/**", "src/test.c"));
+        assert!(pattern_matches("*.h", "cubeb.h"));
+        assert!(!pattern_matches("*.h", "include/cubeb.h"));
+        assert!(pattern_matches("include", "include/cubeb.h"));
+        assert!(pattern_matches("**/*.py", "a/b/c.py"));
+        assert!(pattern_matches("**/*.py", "c.py"));
+    }
+
+    #[test]
+    fn deeper_and_later_blocks_win() {
+        let mut mozbuilds = BTreeMap::new();
+        mozbuilds.insert(
+            "moz.build".to_string(),
+            parse_files_blocks(
+                "with Files(\"**\"):\n    BUG_COMPONENT = (\"Core\", \"General\")\n",
+            ),
+        );
+        mozbuilds.insert(
+            "media/libcubeb/moz.build".to_string(),
+            parse_files_blocks(MOZBUILD),
+        );
+        let component = |path| {
+            resolve_component(path, &mozbuilds)
+                .unwrap()
+                .component
+                .component
+        };
+        assert_eq!(
+            component("media/libcubeb/src/cubeb.c"),
+            "Audio/Video: cubeb"
+        );
+        assert_eq!(
+            component("media/libcubeb/test/test_audio.cpp"),
+            "Audio/Video: Playback"
+        );
+        assert_eq!(component("media/other/file.cpp"), "General");
+        let found = resolve_component("media/libcubeb/test/a.cpp", &mozbuilds).unwrap();
+        assert_eq!(found.mozbuild, "media/libcubeb/moz.build");
+        assert_eq!(found.pattern, "test/**");
+    }
+}
@@ -1,5 +1,5 @@
 use rusqlite::{params, Connection};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 pub const FRESH_SECS: u64 = 3600;
@@ -14,7 +14,12 @@ pub struct CacheEntry {
 
 impl CacheEntry {
     pub fn is_fresh(&self) -> bool {
-        now().saturating_sub(self.cached_at) < FRESH_SECS
+        self.is_fresh_within(FRESH_SECS)
+    }
+
+    /// Whether the entry is younger than `ttl` seconds.
+    pub fn is_fresh_within(&self, ttl: u64) -> bool {
+        now().saturating_sub(self.cached_at) < ttl
     }
 }
 
@@ -25,7 +30,17 @@ fn now() -> u64 {
         .as_secs()
 }
 
+/// Path of the cache database, in the `cache_dir` of the `[network]`
+/// configuration when set.
 pub fn cache_path() -> Option<PathBuf> {
+    cache_path_in(crate::config::global().network.cache_dir.as_deref())
+}
+
+/// Path of the cache database in `dir`, or in the XDG cache directory.
+pub fn cache_path_in(dir: Option<&Path>) -> Option<PathBuf> {
+    if let Some(dir) = dir {
+        return Some(dir.join("cache.db"));
+    }
     let base = if let Ok(xdg) = std::env::var("XDG_CACHE_HOME") {
         PathBuf::from(xdg)
     } else {
@@ -34,10 +49,9 @@ pub fn cache_path() -> Option<PathBuf> {
     Some(base.join("searchfox-cli").join("cache.db"))
 }
 
-pub fn open() -> Option<Connection> {
-    let path = cache_path()?;
+pub fn open(path: &Path) -> Option<Connection> {
     std::fs::create_dir_all(path.parent()?).ok()?;
-    let conn = Connection::open(&path).ok()?;
+    let conn = Connection::open(path).ok()?;
     init(&conn)?;
     Some(conn)
 }
@@ -72,6 +86,24 @@ pub fn set(
     );
 }
 
+/// Last stored `--watch` snapshot for `key` and when it was taken. Unlike
+/// cache entries, snapshots are never pruned.
+pub fn watch_get(conn: &Connection, key: &str) -> Option<(String, u64)> {
+    conn.query_row(
+        "SELECT snapshot, taken_at FROM watch WHERE key = ?1",
+        params![key],
+        |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as u64)),
+    )
+    .ok()
+}
+
+pub fn watch_set(conn: &Connection, key: &str, snapshot: &str) {
+    let _ = conn.execute(
+        "INSERT OR REPLACE INTO watch (key, snapshot, taken_at) VALUES (?1, ?2, ?3)",
+        params![key, snapshot, now() as i64],
+    );
+}
+
 fn init(conn: &Connection) -> Option<()> {
     conn.execute_batch(
         "CREATE TABLE IF NOT EXISTS cache (
@@ -80,6 +112,11 @@ fn init(conn: &Connection) -> Option<()> {
             etag TEXT,
             last_modified TEXT,
             cached_at INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS watch (
+            key TEXT PRIMARY KEY,
+            snapshot TEXT NOT NULL,
+            taken_at INTEGER NOT NULL
         );",
     )
     .ok()
@@ -200,6 +237,22 @@ mod tests {
         assert!(!get(&conn, "https://example.com/file").unwrap().is_fresh());
     }
 
+    #[test]
+    fn freshness_follows_ttl() {
+        let conn = db();
+        set_with_timestamp(
+            &conn,
+            "https://example.com/file",
+            "x",
+            None,
+            None,
+            now() as i64 - 60,
+        );
+        let entry = get(&conn, "https://example.com/file").unwrap();
+        assert!(entry.is_fresh_within(120));
+        assert!(!entry.is_fresh_within(30));
+    }
+
     #[test]
     fn set_replaces_existing_entry() {
         let conn = db();
@@ -241,6 +294,16 @@ mod tests {
         assert_eq!(get(&conn, "https://example.com/b").unwrap().content, "bbb");
     }
 
+    #[test]
+    fn watch_snapshots_survive_prune() {
+        let conn = db();
+        assert!(watch_get(&conn, "q").is_none());
+        watch_set(&conn, "q", "[\"a\"]");
+        prune(&conn);
+        let (snapshot, _) = watch_get(&conn, "q").unwrap();
+        assert_eq!(snapshot, "[\"a\"]");
+    }
+
     #[test]
     fn clear_removes_database_file() {
         let _guard = env_lock();
@@ -1,7 +1,11 @@
 use crate::client::SearchfoxClient;
+use crate::error::RequestError;
+use crate::process::{is_ipc_edge, process_tag, ProcessTag};
 use anyhow::Result;
 use reqwest::Url;
+use serde::{Deserialize, Serialize};
 use serde_json;
+use std::collections::BTreeMap;
 
 pub struct CallGraphQuery {
     pub calls_from: Option<String>,
@@ -10,209 +14,394 @@ pub struct CallGraphQuery {
     pub depth: u32,
 }
 
-pub fn format_call_graph_markdown(query_text: &str, json: &serde_json::Value) -> String {
-    use std::collections::{BTreeMap, BTreeSet};
+/// The `SymbolGraphCollection` searchfox returns for call graph queries.
+/// Fields missing from a response are left empty.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SymbolGraphCollection {
+    /// Graphs of calls-from and calls-to queries.
+    #[serde(default)]
+    pub graphs: Vec<Graph>,
+    /// Graphs of calls-between queries, nesting symbols by class or file.
+    #[serde(default, rename = "hierarchicalGraphs")]
+    pub hierarchical_graphs: Vec<Node>,
+    /// What is known about each symbol of the graphs, by mangled name.
+    #[serde(default)]
+    pub jumprefs: BTreeMap<String, JumpRef>,
+}
 
-    let mut output = String::new();
-    output.push_str(&format!("# {}\n\n", query_text));
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Graph {
+    #[serde(default)]
+    pub edges: Vec<Edge>,
+}
 
-    let is_calls_between = query_text.contains("calls-between");
+/// A node of a hierarchical graph, with the calls made within it and its
+/// nested nodes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Node {
+    #[serde(default)]
+    pub edges: Vec<Edge>,
+    #[serde(default)]
+    pub children: Vec<Node>,
+}
 
-    if is_calls_between {
-        if let Some(hierarchical_graphs) = json.get("hierarchicalGraphs").and_then(|v| v.as_array())
-        {
-            let jumprefs = json.get("jumprefs").and_then(|v| v.as_object());
-
-            let mut all_edges = Vec::new();
-
-            fn collect_edges(node: &serde_json::Value, edges: &mut Vec<(String, String)>) {
-                if let Some(node_edges) = node.get("edges").and_then(|e| e.as_array()) {
-                    for edge in node_edges {
-                        if let Some(edge_obj) = edge.as_object() {
-                            let from = edge_obj.get("from").and_then(|f| f.as_str()).unwrap_or("");
-                            let to = edge_obj.get("to").and_then(|t| t.as_str()).unwrap_or("");
-                            if !from.is_empty() && !to.is_empty() {
-                                edges.push((from.to_string(), to.to_string()));
-                            }
-                        }
-                    }
-                }
+/// A call, between mangled symbols.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Edge {
+    #[serde(default)]
+    pub from: String,
+    #[serde(default)]
+    pub to: String,
+}
 
-                if let Some(children) = node.get("children").and_then(|c| c.as_array()) {
-                    for child in children {
-                        collect_edges(child, edges);
-                    }
-                }
-            }
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct JumpRef {
+    /// Mangled symbol, as accepted by --symbol.
+    #[serde(default)]
+    pub sym: Option<String>,
+    #[serde(default)]
+    pub pretty: Option<String>,
+    #[serde(default)]
+    pub jumps: Jumps,
+    #[serde(default)]
+    pub meta: SymbolMeta,
+}
+
+/// Locations as `path#line`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Jumps {
+    #[serde(default)]
+    pub def: Option<String>,
+    #[serde(default)]
+    pub decl: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SymbolMeta {
+    /// Enclosing class or namespace, as `T_mozilla::dom::AudioContext`.
+    #[serde(default)]
+    pub parentsym: Option<String>,
+    /// Whether the function can GC, for Servo's analysis.
+    #[serde(default, rename = "canGC")]
+    pub can_gc: Option<bool>,
+    #[serde(default, rename = "gcPath")]
+    pub gc_path: Option<String>,
+    /// Direct base classes of a class.
+    #[serde(default)]
+    pub supers: Vec<SymbolRef>,
+    /// Fields of a class.
+    #[serde(default)]
+    pub fields: Vec<FieldRef>,
+    /// Methods of base classes a method overrides.
+    #[serde(default)]
+    pub overrides: Vec<SymbolRef>,
+    /// Methods of subclasses overriding a method.
+    #[serde(default, rename = "overriddenBy")]
+    pub overridden_by: Vec<SymbolRef>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FieldRef {
+    /// Qualified name, as `mozilla::dom::AudioNode::mContext`.
+    #[serde(default)]
+    pub pretty: Option<String>,
+    #[serde(default, rename = "type")]
+    pub type_name: Option<String>,
+}
+
+/// A symbol named in the metadata of another: a base class, an overridden
+/// method.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SymbolRef {
+    /// Symbol, as `T_mozilla::DOMEventTargetHelper` for a class.
+    #[serde(default)]
+    pub sym: String,
+    #[serde(default)]
+    pub pretty: Option<String>,
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().filter(|s| !s.is_empty())
+}
+
+impl JumpRef {
+    pub fn pretty(&self) -> Option<&str> {
+        non_empty(&self.pretty)
+    }
 
-            for hg in hierarchical_graphs {
-                collect_edges(hg, &mut all_edges);
+    pub fn definition(&self) -> Option<&str> {
+        non_empty(&self.jumps.def)
+    }
+
+    pub fn declaration(&self) -> Option<&str> {
+        non_empty(&self.jumps.decl)
+    }
+
+    /// The definition, or the declaration when there is none.
+    pub fn location(&self) -> Option<&str> {
+        self.definition().or(self.declaration())
+    }
+
+    /// Enclosing class or namespace, None for free functions.
+    pub fn parent(&self) -> Option<&str> {
+        non_empty(&self.meta.parentsym).map(|p| p.strip_prefix("T_").unwrap_or(p))
+    }
+}
+
+impl SymbolGraphCollection {
+    /// Parse the JSON returned by `search_call_graph`. Anything but an
+    /// object, which searchfox returns when there is no graph, is empty.
+    pub fn from_json(json: &serde_json::Value) -> Result<Self> {
+        if !json.is_object() {
+            return Ok(Self::default());
+        }
+        Ok(serde_json::from_value(json.clone())?)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.graphs.is_empty() && self.hierarchical_graphs.is_empty() && self.jumprefs.is_empty()
+    }
+
+    /// Pretty name of a mangled symbol, or the symbol itself when unknown.
+    pub fn pretty<'a>(&'a self, symbol: &'a str) -> &'a str {
+        self.jumprefs
+            .get(symbol)
+            .and_then(JumpRef::pretty)
+            .unwrap_or(symbol)
+    }
+
+    /// Every distinct call, flat or hierarchical, in the order of the
+    /// response. Calls with an unknown end are skipped.
+    pub fn edges(&self) -> Vec<&Edge> {
+        fn collect<'a>(node: &'a Node, edges: &mut Vec<&'a Edge>) {
+            edges.extend(&node.edges);
+            for child in &node.children {
+                collect(child, edges);
             }
+        }
+        let mut edges: Vec<&Edge> = self.graphs.iter().flat_map(|g| &g.edges).collect();
+        for node in &self.hierarchical_graphs {
+            collect(node, &mut edges);
+        }
+        let mut seen = std::collections::HashSet::new();
+        edges.retain(|edge| !edge.from.is_empty() && !edge.to.is_empty() && seen.insert(*edge));
+        edges
+    }
+}
 
-            if all_edges.is_empty() {
-                output.push_str("No direct calls found between source and target.\n");
-            } else {
-                output.push_str("## Direct calls from source to target\n\n");
-
-                for (from_sym, to_sym) in all_edges {
-                    let from_pretty = if let Some(jumprefs) = jumprefs {
-                        jumprefs
-                            .get(&from_sym)
-                            .and_then(|s| s.get("pretty"))
-                            .and_then(|p| p.as_str())
-                            .unwrap_or(&from_sym)
-                    } else {
-                        &from_sym
-                    };
-
-                    let from_location = if let Some(jumprefs) = jumprefs {
-                        jumprefs
-                            .get(&from_sym)
-                            .and_then(|s| s.get("jumps"))
-                            .and_then(|j| j.get("def"))
-                            .and_then(|d| d.as_str())
-                            .unwrap_or("")
-                    } else {
-                        ""
-                    };
-
-                    let to_pretty = if let Some(jumprefs) = jumprefs {
-                        jumprefs
-                            .get(&to_sym)
-                            .and_then(|s| s.get("pretty"))
-                            .and_then(|p| p.as_str())
-                            .unwrap_or(&to_sym)
-                    } else {
-                        &to_sym
-                    };
-
-                    let to_location = if let Some(jumprefs) = jumprefs {
-                        jumprefs
-                            .get(&to_sym)
-                            .and_then(|s| s.get("jumps"))
-                            .and_then(|j| j.get("def"))
-                            .and_then(|d| d.as_str())
-                            .unwrap_or("")
-                    } else {
-                        ""
-                    };
-
-                    output.push_str(&format!(
-                        "- **{}** ({}) calls **{}** ({})\n",
-                        from_pretty, from_location, to_pretty, to_location
-                    ));
-                    output.push_str(&format!("  - From: `{}`\n", from_sym));
-                    output.push_str(&format!("  - To: `{}`\n", to_sym));
-                }
+/// Path of a searchfox jump location such as `dom/ipc/ContentParent.cpp#42`.
+fn location_path(location: &str) -> &str {
+    location.split('#').next().unwrap_or(location)
+}
+
+/// ` [content process]` after a node known to run in one process.
+fn process_suffix(pretty: &str, location: &str) -> String {
+    process_tag(pretty, location_path(location))
+        .map(|tag| format!(" [{}]", tag))
+        .unwrap_or_default()
+}
+
+/// A function of a call graph, as described by the response's jumprefs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallGraphNode {
+    /// Mangled symbol, as accepted by --symbol.
+    pub symbol: String,
+    pub pretty: String,
+    /// Locations as `path#line`.
+    pub definition: Option<String>,
+    pub declaration: Option<String>,
+    /// Enclosing class or namespace, None for free functions.
+    pub parent: Option<String>,
+    /// Process the function is known to run in, if any.
+    pub process: Option<ProcessTag>,
+}
+
+/// A call from `caller` to `callee`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallGraphEdge {
+    pub caller: CallGraphNode,
+    pub callee: CallGraphNode,
+    /// Whether the call crosses a process boundary.
+    pub ipc: bool,
+}
+
+fn call_graph_node(graph: &SymbolGraphCollection, symbol: &str) -> CallGraphNode {
+    let info = graph.jumprefs.get(symbol);
+    let pretty = graph.pretty(symbol).to_string();
+    let location = info.and_then(JumpRef::location).unwrap_or("");
+    let process = process_tag(&pretty, location_path(location));
+    CallGraphNode {
+        symbol: info
+            .and_then(|i| non_empty(&i.sym))
+            .unwrap_or(symbol)
+            .to_string(),
+        definition: info.and_then(JumpRef::definition).map(String::from),
+        declaration: info.and_then(JumpRef::declaration).map(String::from),
+        parent: info.and_then(JumpRef::parent).map(String::from),
+        pretty,
+        process,
+    }
+}
+
+/// Every distinct call of a call graph, flat or hierarchical (calls-between),
+/// in the order of the response.
+pub fn call_graph_edges(graph: &SymbolGraphCollection) -> Vec<CallGraphEdge> {
+    graph
+        .edges()
+        .into_iter()
+        .map(|edge| {
+            let caller = call_graph_node(graph, &edge.from);
+            let callee = call_graph_node(graph, &edge.to);
+            let location = |node: &CallGraphNode| {
+                location_path(
+                    node.definition
+                        .as_deref()
+                        .or(node.declaration.as_deref())
+                        .unwrap_or(""),
+                )
+                .to_string()
+            };
+            let ipc = is_ipc_edge(
+                (&caller.pretty, &location(&caller)),
+                (&callee.pretty, &location(&callee)),
+            );
+            CallGraphEdge {
+                caller,
+                callee,
+                ipc,
             }
+        })
+        .collect()
+}
+
+pub fn format_call_graph_markdown(query_text: &str, graph: &SymbolGraphCollection) -> String {
+    use std::collections::BTreeSet;
 
+    let mut output = String::new();
+    output.push_str(&format!("# {}\n\n", query_text));
+
+    let is_calls_between = query_text.contains("calls-between");
+
+    if is_calls_between && (!graph.hierarchical_graphs.is_empty() || graph.graphs.is_empty()) {
+        let edges = graph.edges();
+        if edges.is_empty() {
+            output.push_str("No direct calls found between source and target.\n");
             return output;
         }
+        output.push_str("## Direct calls from source to target\n\n");
+        for edge in edges {
+            let definition = |symbol: &str| {
+                graph
+                    .jumprefs
+                    .get(symbol)
+                    .and_then(JumpRef::definition)
+                    .unwrap_or("")
+            };
+            let (from_pretty, from_location) = (graph.pretty(&edge.from), definition(&edge.from));
+            let (to_pretty, to_location) = (graph.pretty(&edge.to), definition(&edge.to));
+            output.push_str(&format!(
+                "- **{}** ({}){} calls **{}** ({}){}\n",
+                from_pretty,
+                from_location,
+                process_suffix(from_pretty, from_location),
+                to_pretty,
+                to_location,
+                process_suffix(to_pretty, to_location)
+            ));
+            output.push_str(&format!("  - From: `{}`\n", edge.from));
+            output.push_str(&format!("  - To: `{}`\n", edge.to));
+            if is_ipc_edge(
+                (from_pretty, location_path(from_location)),
+                (to_pretty, location_path(to_location)),
+            ) {
+                output.push_str("  - IPC: crosses a process boundary\n");
+            }
+        }
+        return output;
     }
 
     let mut grouped_by_parent: BTreeMap<String, BTreeSet<(String, String, String, String)>> =
         BTreeMap::new();
 
-    let jumprefs = json.get("jumprefs").and_then(|v| v.as_object());
-
     let is_calls_to = query_text.contains("calls-to:");
 
-    if let Some(graphs) = json.get("graphs").and_then(|v| v.as_array()) {
-        for graph in graphs {
-            if let Some(edges) = graph.get("edges").and_then(|v| v.as_array()) {
-                for edge in edges {
-                    if let Some(edge_obj) = edge.as_object() {
-                        let target_sym = if is_calls_to {
-                            edge_obj.get("from").and_then(|v| v.as_str()).unwrap_or("")
-                        } else {
-                            edge_obj.get("to").and_then(|v| v.as_str()).unwrap_or("")
-                        };
-
-                        if let Some(jumprefs) = jumprefs {
-                            if let Some(symbol_info) = jumprefs.get(target_sym) {
-                                let pretty_name = symbol_info
-                                    .get("pretty")
-                                    .and_then(|v| v.as_str())
-                                    .unwrap_or("");
-                                let mangled = symbol_info
-                                    .get("sym")
-                                    .and_then(|v| v.as_str())
-                                    .unwrap_or(target_sym);
-
-                                let jumps = symbol_info.get("jumps");
-
-                                let decl_location = jumps
-                                    .and_then(|j| j.get("decl"))
-                                    .and_then(|v| v.as_str())
-                                    .unwrap_or("");
-
-                                let def_location = jumps
-                                    .and_then(|j| j.get("def"))
-                                    .and_then(|v| v.as_str())
-                                    .unwrap_or("");
-
-                                let location = if !def_location.is_empty()
-                                    && !decl_location.is_empty()
-                                    && def_location != decl_location
-                                {
-                                    format!("{} (decl: {})", def_location, decl_location)
-                                } else if !def_location.is_empty() {
-                                    def_location.to_string()
-                                } else if !decl_location.is_empty() {
-                                    decl_location.to_string()
-                                } else {
-                                    String::new()
-                                };
-
-                                let parent_sym = symbol_info
-                                    .get("meta")
-                                    .and_then(|m| m.get("parentsym"))
-                                    .and_then(|v| v.as_str())
-                                    .unwrap_or("Free functions");
-
-                                let parent_sym_clean =
-                                    parent_sym.strip_prefix("T_").unwrap_or(parent_sym);
-
-                                if !pretty_name.is_empty() && !location.is_empty() {
-                                    grouped_by_parent
-                                        .entry(parent_sym_clean.to_string())
-                                        .or_default()
-                                        .insert((
-                                            pretty_name.to_string(),
-                                            mangled.to_string(),
-                                            location,
-                                            String::new(),
-                                        ));
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
+    for edge in graph.graphs.iter().flat_map(|g| &g.edges) {
+        let (target_sym, other_sym) = if is_calls_to {
+            (edge.from.as_str(), edge.to.as_str())
+        } else {
+            (edge.to.as_str(), edge.from.as_str())
+        };
+        let Some(symbol_info) = graph.jumprefs.get(target_sym) else {
+            continue;
+        };
+        let Some(pretty_name) = symbol_info.pretty() else {
+            continue;
+        };
+        let mangled = non_empty(&symbol_info.sym).unwrap_or(target_sym);
+
+        let location = match (symbol_info.definition(), symbol_info.declaration()) {
+            (Some(def), Some(decl)) if def != decl => format!("{} (decl: {})", def, decl),
+            (Some(location), _) | (None, Some(location)) => location.to_string(),
+            (None, None) => continue,
+        };
+
+        let target = (pretty_name, location_path(&location));
+        let other = (
+            graph.pretty(other_sym),
+            graph
+                .jumprefs
+                .get(other_sym)
+                .and_then(JumpRef::location)
+                .map(location_path)
+                .unwrap_or(""),
+        );
+        let (caller, callee) = if is_calls_to {
+            (target, other)
+        } else {
+            (other, target)
+        };
+        let tag = process_tag(target.0, target.1);
+        let annotation = match (is_ipc_edge(caller, callee), tag) {
+            (true, Some(tag)) => format!(" [IPC, {}]", tag),
+            (true, None) => " [IPC]".to_string(),
+            (false, Some(tag)) => format!(" [{}]", tag),
+            (false, None) => String::new(),
+        };
+        grouped_by_parent
+            .entry(symbol_info.parent().unwrap_or("Free functions").to_string())
+            .or_default()
+            .insert((
+                pretty_name.to_string(),
+                mangled.to_string(),
+                location,
+                annotation,
+            ));
     }
 
+    // Mangled name, location and process annotation of an overload.
+    type Overload = (String, String, String);
+
     for (parent_sym, items) in grouped_by_parent {
         output.push_str(&format!("## {}\n\n", parent_sym));
 
-        let mut grouped_items: Vec<(String, Vec<(String, String)>)> = Vec::new();
+        let mut grouped_items: Vec<(String, Vec<Overload>)> = Vec::new();
 
-        for (pretty_name, mangled, location, _) in items {
+        for (pretty_name, mangled, location, annotation) in items {
             if let Some((last_pretty, last_overloads)) = grouped_items.last_mut() {
                 if last_pretty == &pretty_name {
-                    last_overloads.push((mangled, location));
+                    last_overloads.push((mangled, location, annotation));
                     continue;
                 }
             }
-            grouped_items.push((pretty_name, vec![(mangled, location)]));
+            grouped_items.push((pretty_name, vec![(mangled, location, annotation)]));
         }
 
         for (pretty_name, overloads) in grouped_items {
             if overloads.len() == 1 {
-                let (mangled, location) = &overloads[0];
+                let (mangled, location, annotation) = &overloads[0];
                 output.push_str(&format!(
-                    "- {} (`{}`, {})\n",
-                    pretty_name, mangled, location
+                    "- {} (`{}`, {}){}\n",
+                    pretty_name, mangled, location, annotation
                 ));
             } else {
                 output.push_str(&format!(
@@ -220,8 +409,8 @@ pub fn format_call_graph_markdown(query_text: &str, json: &serde_json::Value) ->
                     pretty_name,
                     overloads.len()
                 ));
-                for (mangled, location) in overloads {
-                    output.push_str(&format!("  - `{}`, {}\n", mangled, location));
+                for (mangled, location, annotation) in overloads {
+                    output.push_str(&format!("  - `{}`, {}{}\n", mangled, location, annotation));
                 }
             }
         }
@@ -261,17 +450,20 @@ impl SearchfoxClient {
         } else {
             anyhow::bail!("No call graph query specified");
         };
+        self.query_symbol_graph(&query_string).await
+    }
 
-        let mut url = Url::parse(&format!(
-            "https://searchfox.org/{}/query/default",
-            self.repo
-        ))?;
-        url.query_pairs_mut().append_pair("q", &query_string);
+    /// Run a graph query (`calls-from:`, `inheritance-diagram:`, ...) with
+    /// `graph-format:json`, and return its `SymbolGraphCollection`, or the
+    /// whole response when it has none.
+    pub(crate) async fn query_symbol_graph(&self, query_string: &str) -> Result<serde_json::Value> {
+        let mut url = Url::parse(&format!("{}/{}/query/default", self.base_url, self.repo))?;
+        url.query_pairs_mut().append_pair("q", query_string);
 
         let response = self.get(url).await?;
 
         if !response.status().is_success() {
-            anyhow::bail!("Request failed: {}", response.status());
+            return Err(RequestError::new(response.url().as_str(), response.status()).into());
         }
 
         let response_text = response.text().await?;
@@ -293,4 +485,104 @@ impl SearchfoxClient {
             Ok(json)
         }
     }
+
+    /// `search_call_graph`, parsed into a `SymbolGraphCollection`.
+    pub async fn call_graph(&self, query: &CallGraphQuery) -> Result<SymbolGraphCollection> {
+        SymbolGraphCollection::from_json(&self.search_call_graph(query).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marks_process_boundaries() {
+        let json = serde_json::json!({
+            "graphs": [{
+                "edges": [
+                    {"from": "_ZChildDoFoo", "to": "_ZSendFoo"},
+                    {"from": "_ZChildDoFoo", "to": "_ZChildBar"}
+                ]
+            }],
+            "jumprefs": {
+                "_ZChildDoFoo": {
+                    "pretty": "mozilla::dom::BrowserChild::DoFoo",
+                    "jumps": {"def": "dom/ipc/BrowserChild.cpp#10"}
+                },
+                "_ZSendFoo": {
+                    "pretty": "mozilla::dom::PBrowserChild::SendFoo",
+                    "sym": "_ZSendFoo",
+                    "jumps": {"def": "ipc/ipdl/PBrowserChild.cpp#200"},
+                    "meta": {"parentsym": "T_mozilla::dom::PBrowserChild"}
+                },
+                "_ZChildBar": {
+                    "pretty": "mozilla::dom::BrowserChild::Bar",
+                    "sym": "_ZChildBar",
+                    "jumps": {"def": "dom/ipc/BrowserChild.cpp#30"},
+                    "meta": {"parentsym": "T_mozilla::dom::BrowserChild"}
+                }
+            }
+        });
+        let graph = SymbolGraphCollection::from_json(&json).unwrap();
+        let output = format_call_graph_markdown(
+            "calls-from:'mozilla::dom::BrowserChild::DoFoo' depth:1",
+            &graph,
+        );
+        assert!(output.contains(
+            "- mozilla::dom::PBrowserChild::SendFoo (`_ZSendFoo`, ipc/ipdl/PBrowserChild.cpp#200) [IPC, content process]\n"
+        ));
+        assert!(output.contains(
+            "- mozilla::dom::BrowserChild::Bar (`_ZChildBar`, dom/ipc/BrowserChild.cpp#30) [content process]\n"
+        ));
+
+        let edges = call_graph_edges(&graph);
+        assert_eq!(edges.len(), 2);
+        assert_eq!(edges[0].caller.pretty, "mozilla::dom::BrowserChild::DoFoo");
+        assert_eq!(edges[0].callee.symbol, "_ZSendFoo");
+        assert_eq!(
+            edges[0].callee.parent.as_deref(),
+            Some("mozilla::dom::PBrowserChild")
+        );
+        assert!(edges[0].ipc);
+        assert!(!edges[1].ipc);
+        assert_eq!(edges[1].callee.process, Some(ProcessTag::Content));
+        assert_eq!(edges[1].caller.parent, None);
+    }
+
+    #[test]
+    fn parses_calls_between() {
+        let json = serde_json::json!({
+            "hierarchicalGraphs": [{
+                "children": [{
+                    "edges": [
+                        {"from": "_ZA", "to": "_ZB"},
+                        {"from": "_ZA", "to": "_ZB"}
+                    ],
+                    "label": "ignored"
+                }]
+            }],
+            "jumprefs": {
+                "_ZA": {"pretty": "ns::A", "jumps": {"def": "dom/A.cpp#3", "decl": ""}},
+                "_ZB": {"pretty": "ns::B", "meta": {"parentsym": "T_ns", "canGC": true}}
+            }
+        });
+        let graph = SymbolGraphCollection::from_json(&json).unwrap();
+        assert_eq!(graph.edges().len(), 1);
+        assert_eq!(graph.jumprefs["_ZA"].location(), Some("dom/A.cpp#3"));
+        assert_eq!(graph.jumprefs["_ZB"].parent(), Some("ns"));
+        assert_eq!(graph.jumprefs["_ZB"].meta.can_gc, Some(true));
+        assert_eq!(graph.pretty("_ZC"), "_ZC");
+        assert_eq!(
+            format_call_graph_markdown("calls-between-source:'A' calls-between-target:'B'", &graph),
+            "# calls-between-source:'A' calls-between-target:'B'\n\n\
+             ## Direct calls from source to target\n\n\
+             - **ns::A** (dom/A.cpp#3) calls **ns::B** ()\n  \
+             - From: `_ZA`\n  \
+             - To: `_ZB`\n"
+        );
+        assert!(SymbolGraphCollection::from_json(&serde_json::json!([]))
+            .unwrap()
+            .is_empty());
+    }
 }
@@ -0,0 +1,216 @@
+use anyhow::Result;
+use reqwest::{ResponseBuilderExt, Url};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Response headers worth keeping in a cassette: the ones this crate reads.
+const RECORDED_HEADERS: &[&str] = &["content-type", "etag", "last-modified", "location"];
+
+/// HTTP interactions recorded with `--record` and served back by `--replay`,
+/// so that an investigation can be reproduced without network access.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+    /// Which interactions have been served during replay.
+    #[serde(skip)]
+    played: Vec<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    pub method: String,
+    pub url: String,
+    pub status: u16,
+    /// Where redirects led, when it differs from `url`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub final_url: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    #[serde(flatten)]
+    pub body: Body,
+}
+
+/// A response body, kept as text when it is UTF-8 so cassettes stay
+/// readable and diffable.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Body {
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl Body {
+    fn new(bytes: Vec<u8>) -> Self {
+        match String::from_utf8(bytes) {
+            Ok(text) => Body::Text(text),
+            Err(e) => Body::Bytes(e.into_bytes()),
+        }
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        match self {
+            Body::Text(text) => text.into_bytes(),
+            Body::Bytes(bytes) => bytes,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    /// Issue requests and append each exchange to the cassette.
+    Record,
+    /// Answer requests from the cassette only, failing on anything it lacks.
+    Replay,
+}
+
+impl Cassette {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Could not read cassette {}: {}", path.display(), e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Invalid cassette {}: {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub(crate) fn push(
+        &mut self,
+        method: &str,
+        url: &str,
+        final_url: &str,
+        response: &http::Response<Vec<u8>>,
+    ) {
+        let headers = RECORDED_HEADERS
+            .iter()
+            .filter_map(|name| {
+                let value = response.headers().get(*name)?.to_str().ok()?;
+                Some((name.to_string(), value.to_string()))
+            })
+            .collect();
+        self.interactions.push(Interaction {
+            method: method.to_string(),
+            url: url.to_string(),
+            status: response.status().as_u16(),
+            final_url: (final_url != url).then(|| final_url.to_string()),
+            headers,
+            body: Body::new(response.body().clone()),
+        });
+    }
+
+    /// The recorded response to `method url`. Identical requests are
+    /// answered in recording order; once they are exhausted, the last one is
+    /// served again.
+    pub fn next_response(&mut self, method: &str, url: &str) -> Option<Interaction> {
+        self.played.resize(self.interactions.len(), false);
+        let matching: Vec<usize> = (0..self.interactions.len())
+            .filter(|&i| self.interactions[i].method == method && self.interactions[i].url == url)
+            .collect();
+        let index = matching
+            .iter()
+            .copied()
+            .find(|&i| !self.played[i])
+            .or(matching.last().copied())?;
+        self.played[index] = true;
+        Some(self.interactions[index].clone())
+    }
+}
+
+impl Interaction {
+    pub(crate) fn into_response(self) -> Result<reqwest::Response> {
+        let mut builder = http::Response::builder()
+            .status(self.status)
+            .url(Url::parse(self.final_url.as_deref().unwrap_or(&self.url))?);
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+        Ok(reqwest::Response::from(
+            builder.body(self.body.into_bytes())?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::SearchfoxClient;
+    use std::sync::{Arc, Mutex};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn identical_requests_replay_in_order() {
+        let interaction = |status, body: &str| Interaction {
+            method: "GET".into(),
+            url: "https://searchfox.org/a".into(),
+            status,
+            final_url: None,
+            headers: BTreeMap::new(),
+            body: Body::Text(body.into()),
+        };
+        let mut cassette = Cassette {
+            interactions: vec![interaction(500, "first"), interaction(200, "second")],
+            ..Default::default()
+        };
+        let status = |c: &mut Cassette| {
+            c.next_response("GET", "https://searchfox.org/a")
+                .unwrap()
+                .status
+        };
+        assert_eq!(status(&mut cassette), 500);
+        assert_eq!(status(&mut cassette), 200);
+        assert_eq!(status(&mut cassette), 200);
+        assert!(cassette
+            .next_response("HEAD", "https://searchfox.org/a")
+            .is_none());
+    }
+
+    #[test]
+    fn binary_bodies_round_trip() {
+        let text: Interaction = serde_json::from_str(
+            r#"{"method": "GET", "url": "https://searchfox.org/a", "status": 200, "text": "hi"}"#,
+        )
+        .unwrap();
+        assert!(matches!(text.body, Body::Text(ref t) if t == "hi"));
+        assert!(matches!(Body::new(vec![0xff, 0]), Body::Bytes(_)));
+    }
+
+    #[tokio::test]
+    async fn replays_recorded_requests_offline() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/firefox-main/source/some/file.js"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("etag", "\"v1\"")
+                    .set_body_string(
+                        r#"<html><body><code class="source-line">let x;</code></body></html>"#,
+                    ),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let cassette = Arc::new(Mutex::new(Cassette::default()));
+        let mut client =
+            SearchfoxClient::new_for_test("mozilla-central".into(), server.uri()).unwrap();
+        client.set_cassette(CassetteMode::Record, cassette.clone());
+        let recorded = client.get_file("some/file.js").await.unwrap();
+        assert_eq!(cassette.lock().unwrap().interactions.len(), 1);
+        assert_eq!(
+            cassette.lock().unwrap().interactions[0].headers["etag"],
+            "\"v1\""
+        );
+
+        // The server saw exactly one request: replaying does not reach it.
+        let mut client =
+            SearchfoxClient::new_for_test("mozilla-central".into(), server.uri()).unwrap();
+        client.set_cassette(CassetteMode::Replay, cassette);
+        assert_eq!(client.get_file("some/file.js").await.unwrap(), recorded);
+        let err = client.get_file("other/file.js").await.unwrap_err();
+        assert!(err.to_string().contains("No response recorded"));
+    }
+}
@@ -0,0 +1,207 @@
+use std::path::{Path, PathBuf};
+
+/// Sections of an INI-style file (`.hg/hgrc`, `.git/config`), as
+/// `(section, key, value)` triples. Section names keep their subsection,
+/// e.g. `remote "origin"`.
+fn ini_entries(text: &str) -> Vec<(String, String, String)> {
+    let mut section = String::new();
+    let mut entries = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with(['#', ';']) || line.is_empty() {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = name.trim().to_string();
+        } else if let Some((key, value)) = line.split_once('=') {
+            entries.push((
+                section.clone(),
+                key.trim().to_string(),
+                value.trim().to_string(),
+            ));
+        }
+    }
+    entries
+}
+
+fn ini_value<'a>(
+    entries: &'a [(String, String, String)],
+    section: &str,
+    key: &str,
+) -> Option<&'a str> {
+    entries
+        .iter()
+        .find(|(s, k, _)| s == section && k == key)
+        .map(|(_, _, v)| v.as_str())
+}
+
+/// Repository name of an hg.mozilla.org URL, e.g. `mozilla-esr128` for
+/// `https://hg.mozilla.org/releases/mozilla-esr128/`. Git-cinnabar remotes
+/// (`hg::https://...`) are accepted.
+pub fn repo_from_hg_url(url: &str) -> Option<String> {
+    let url = url.strip_prefix("hg::").unwrap_or(url);
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let (host, path) = rest.split_once('/')?;
+    if !host.ends_with("hg.mozilla.org") {
+        return None;
+    }
+    let name = path.trim_end_matches('/').rsplit('/').next()?;
+    let known = name.starts_with("mozilla-") || name.starts_with("comm-") || name == "autoland";
+    known.then(|| name.to_string())
+}
+
+/// Repository matching a branch of the Firefox git repository (or of the
+/// gecko-dev mirror): `main` is mozilla-central, `beta` mozilla-beta,
+/// `esr128` mozilla-esr128, and so on.
+pub fn repo_from_git_branch(branch: &str) -> Option<String> {
+    let branch = branch.strip_prefix("refs/heads/").unwrap_or(branch);
+    match branch {
+        "main" | "master" | "central" => Some("mozilla-central".to_string()),
+        "autoland" => Some("autoland".to_string()),
+        "beta" | "release" => Some(format!("mozilla-{branch}")),
+        _ if branch
+            .strip_prefix("esr")
+            .is_some_and(|v| !v.is_empty() && v.bytes().all(|b| b.is_ascii_digit())) =>
+        {
+            Some(format!("mozilla-{branch}"))
+        }
+        _ => None,
+    }
+}
+
+fn is_firefox_git_url(url: &str) -> bool {
+    let url = url.trim_end_matches('/').trim_end_matches(".git");
+    url.ends_with("mozilla-firefox/firefox") || url.ends_with("mozilla/gecko-dev")
+}
+
+/// Repository of a Mercurial checkout, from the default path of its hgrc.
+pub fn repo_from_hgrc(hgrc: &str) -> Option<String> {
+    let entries = ini_entries(hgrc);
+    repo_from_hg_url(ini_value(&entries, "paths", "default")?)
+}
+
+/// Repository of a git checkout on `head` (the content of `.git/HEAD`),
+/// from the remote and upstream branch of the current branch. Without an
+/// upstream, the local branch name and the `origin` remote are used.
+pub fn repo_from_git_config(config: &str, head: &str) -> Option<String> {
+    let entries = ini_entries(config);
+    let branch = head.trim().strip_prefix("ref: refs/heads/")?;
+    let section = format!("branch \"{branch}\"");
+    let remote = ini_value(&entries, &section, "remote").unwrap_or("origin");
+    let upstream = ini_value(&entries, &section, "merge").unwrap_or(branch);
+    let url = ini_value(&entries, &format!("remote \"{remote}\""), "url")?;
+    if let Some(repo) = repo_from_hg_url(url) {
+        return Some(repo);
+    }
+    if is_firefox_git_url(url) {
+        return repo_from_git_branch(upstream);
+    }
+    None
+}
+
+/// The `.git` directory of a checkout and the directory holding its
+/// config, which differ for worktrees.
+fn git_dirs(dot_git: &Path) -> Option<(PathBuf, PathBuf)> {
+    if dot_git.is_dir() {
+        return Some((dot_git.to_path_buf(), dot_git.to_path_buf()));
+    }
+    let text = std::fs::read_to_string(dot_git).ok()?;
+    let gitdir = text.trim().strip_prefix("gitdir:")?.trim();
+    let gitdir = dot_git.parent()?.join(gitdir);
+    let common = match std::fs::read_to_string(gitdir.join("commondir")) {
+        Ok(common) => gitdir.join(common.trim()),
+        Err(_) => gitdir.clone(),
+    };
+    Some((gitdir, common))
+}
+
+/// The repository of the Mozilla checkout containing `dir`, if any: the
+/// hg.mozilla.org repository it was cloned from, or for git checkouts the
+/// branch of the Firefox repository it tracks.
+pub fn detect_repository(dir: &Path) -> Option<String> {
+    for dir in dir.ancestors() {
+        let hgrc = dir.join(".hg").join("hgrc");
+        if hgrc.exists() {
+            return repo_from_hgrc(&std::fs::read_to_string(hgrc).ok()?);
+        }
+        let dot_git = dir.join(".git");
+        if dot_git.exists() {
+            let (gitdir, common) = git_dirs(&dot_git)?;
+            let head = std::fs::read_to_string(gitdir.join("HEAD")).ok()?;
+            let config = std::fs::read_to_string(common.join("config")).ok()?;
+            return repo_from_git_config(&config, &head);
+        }
+    }
+    None
+}
+
+/// The root of the hg or git checkout containing `dir`, if any.
+pub fn checkout_root(dir: &Path) -> Option<&Path> {
+    dir.ancestors()
+        .find(|dir| dir.join(".hg").exists() || dir.join(".git").exists())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_hg_repositories() {
+        assert_eq!(
+            repo_from_hgrc("[paths]\ndefault = https://hg.mozilla.org/mozilla-central/\n"),
+            Some("mozilla-central".to_string())
+        );
+        assert_eq!(
+            repo_from_hgrc(
+                "# comment\n[ui]\nusername = x\n[paths]\ndefault = https://hg.mozilla.org/releases/mozilla-esr128\n"
+            ),
+            Some("mozilla-esr128".to_string())
+        );
+        assert_eq!(
+            repo_from_hgrc("[paths]\ndefault = https://hg.mozilla.org/comm-central\n"),
+            Some("comm-central".to_string())
+        );
+        assert_eq!(
+            repo_from_hgrc("[paths]\ndefault = https://example.com/mozilla-central\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn detects_git_repositories() {
+        let config = r#"
+[core]
+	bare = false
+[remote "origin"]
+	url = https://github.com/mozilla-firefox/firefox.git
+	fetch = +refs/heads/*:refs/remotes/origin/*
+[remote "cinnabar"]
+	url = hg::https://hg.mozilla.org/releases/mozilla-beta
+[branch "main"]
+	remote = origin
+	merge = refs/heads/main
+[branch "bug-123"]
+	remote = origin
+	merge = refs/heads/esr140
+[branch "uplift"]
+	remote = cinnabar
+	merge = refs/heads/branches/default/tip
+"#;
+        let repo = |head: &str| repo_from_git_config(config, head);
+        assert_eq!(
+            repo("ref: refs/heads/main\n"),
+            Some("mozilla-central".into())
+        );
+        assert_eq!(
+            repo("ref: refs/heads/bug-123\n"),
+            Some("mozilla-esr140".into())
+        );
+        assert_eq!(
+            repo("ref: refs/heads/uplift\n"),
+            Some("mozilla-beta".into())
+        );
+        assert_eq!(repo("ref: refs/heads/beta\n"), Some("mozilla-beta".into()));
+        assert_eq!(repo("ref: refs/heads/feature\n"), None);
+        assert_eq!(repo("0123456789abcdef\n"), None);
+    }
+}
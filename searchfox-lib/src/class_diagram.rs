@@ -0,0 +1,260 @@
+use crate::call_graph::SymbolGraphCollection;
+use crate::client::SearchfoxClient;
+use anyhow::Result;
+use std::str::FromStr;
+
+/// How a class of a diagram relates to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelationKind {
+    /// Derives from it.
+    Inherits,
+    /// Has fields of its type.
+    Member,
+    /// Any other relation searchfox drew, e.g. through a method signature.
+    Uses,
+}
+
+/// An edge of a class diagram, between pretty class names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassRelation {
+    pub from: String,
+    pub to: String,
+    pub kind: RelationKind,
+    /// Names of the fields of `from` whose type is `to`, for members.
+    pub fields: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassDiagram {
+    pub class: String,
+    pub relations: Vec<ClassRelation>,
+}
+
+/// Output of --class-diagram.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DiagramFormat {
+    /// One relation per line.
+    #[default]
+    Text,
+    /// Graphviz, for `dot -Tsvg`.
+    Dot,
+    /// Mermaid class diagram, for markdown renderers.
+    Mermaid,
+}
+
+impl FromStr for DiagramFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(DiagramFormat::Text),
+            "dot" => Ok(DiagramFormat::Dot),
+            "mermaid" => Ok(DiagramFormat::Mermaid),
+            _ => anyhow::bail!(
+                "Unknown diagram format '{}' (expected text, dot or mermaid)",
+                s
+            ),
+        }
+    }
+}
+
+/// Whether `type_name` mentions the class `pretty`, qualified or not.
+fn type_mentions(type_name: &str, pretty: &str) -> bool {
+    let name = pretty.rsplit("::").next().unwrap_or(pretty);
+    type_name
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .any(|word| word == name)
+}
+
+/// The relations of a `class-diagram:` response, in the order of its edges.
+/// Edges are told apart with the metadata of their source class: its bases,
+/// then the types of its fields.
+pub fn parse_class_diagram(class_name: &str, graph: &SymbolGraphCollection) -> ClassDiagram {
+    let pretty = |symbol: &str| {
+        let pretty = graph.pretty(symbol);
+        pretty.strip_prefix("T_").unwrap_or(pretty).to_string()
+    };
+    let relations = graph
+        .edges()
+        .into_iter()
+        .map(|edge| {
+            let (from, to) = (pretty(&edge.from), pretty(&edge.to));
+            let meta = graph.jumprefs.get(&edge.from).map(|info| &info.meta);
+            let inherits = meta.is_some_and(|m| m.supers.iter().any(|s| s.sym == edge.to));
+            let fields: Vec<String> = meta
+                .map(|m| m.fields.as_slice())
+                .unwrap_or_default()
+                .iter()
+                .filter(|f| {
+                    f.type_name
+                        .as_deref()
+                        .is_some_and(|t| type_mentions(t, &to))
+                })
+                .filter_map(|f| f.pretty.as_deref())
+                .map(|f| f.rsplit("::").next().unwrap_or(f).to_string())
+                .collect();
+            let kind = if inherits {
+                RelationKind::Inherits
+            } else if !fields.is_empty() {
+                RelationKind::Member
+            } else {
+                RelationKind::Uses
+            };
+            ClassRelation {
+                from,
+                to,
+                kind,
+                fields,
+            }
+        })
+        .collect();
+    ClassDiagram {
+        class: class_name.to_string(),
+        relations,
+    }
+}
+
+/// A Mermaid class identifier for a pretty name, which may not contain `::`.
+fn mermaid_id(pretty: &str) -> String {
+    pretty
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// The diagram as text, or as Graphviz or Mermaid source.
+pub fn format_class_diagram(diagram: &ClassDiagram, format: DiagramFormat) -> String {
+    let mut output = String::new();
+    match format {
+        DiagramFormat::Text => {
+            if diagram.relations.is_empty() {
+                output.push_str(&format!("No relations found for {}\n", diagram.class));
+            }
+            for relation in &diagram.relations {
+                let line = match relation.kind {
+                    RelationKind::Inherits => format!("{} inherits {}", relation.from, relation.to),
+                    RelationKind::Member => format!(
+                        "{} has {} ({})",
+                        relation.from,
+                        relation.to,
+                        relation.fields.join(", ")
+                    ),
+                    RelationKind::Uses => format!("{} uses {}", relation.from, relation.to),
+                };
+                output.push_str(&line);
+                output.push('\n');
+            }
+        }
+        DiagramFormat::Dot => {
+            output.push_str(&format!("digraph \"{}\" {{\n", diagram.class));
+            output.push_str("  node [shape=box];\n");
+            for relation in &diagram.relations {
+                let attributes = match relation.kind {
+                    RelationKind::Inherits => "arrowhead=empty".to_string(),
+                    RelationKind::Member => {
+                        format!(
+                            "arrowhead=odiamond, label=\"{}\"",
+                            relation.fields.join(", ")
+                        )
+                    }
+                    RelationKind::Uses => "style=dashed".to_string(),
+                };
+                output.push_str(&format!(
+                    "  \"{}\" -> \"{}\" [{}];\n",
+                    relation.from, relation.to, attributes
+                ));
+            }
+            output.push_str("}\n");
+        }
+        DiagramFormat::Mermaid => {
+            output.push_str("classDiagram\n");
+            let mut classes: Vec<&str> = diagram
+                .relations
+                .iter()
+                .flat_map(|r| [r.from.as_str(), r.to.as_str()])
+                .collect();
+            classes.sort();
+            classes.dedup();
+            for class in classes {
+                output.push_str(&format!("  class {}[\"{}\"]\n", mermaid_id(class), class));
+            }
+            for relation in &diagram.relations {
+                let (from, to) = (mermaid_id(&relation.from), mermaid_id(&relation.to));
+                let line = match relation.kind {
+                    RelationKind::Inherits => format!("{to} <|-- {from}"),
+                    RelationKind::Member => {
+                        format!("{from} o-- {to} : {}", relation.fields.join(", "))
+                    }
+                    RelationKind::Uses => format!("{from} ..> {to}"),
+                };
+                output.push_str(&format!("  {line}\n"));
+            }
+        }
+    }
+    output
+}
+
+impl SearchfoxClient {
+    /// The classes related to `class_name` up to `depth` steps away, from
+    /// searchfox's `class-diagram:` query.
+    pub async fn class_diagram(&self, class_name: &str, depth: u32) -> Result<ClassDiagram> {
+        let query = format!("class-diagram:'{class_name}' depth:{depth} graph-format:json");
+        let graph = SymbolGraphCollection::from_json(&self.query_symbol_graph(&query).await?)?;
+        Ok(parse_class_diagram(class_name, &graph))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tells_relations_apart() {
+        let json = serde_json::json!({
+            "graphs": [{"edges": [
+                {"from": "T_mozilla::dom::GainNode", "to": "T_mozilla::dom::AudioNode"},
+                {"from": "T_mozilla::dom::AudioNode", "to": "T_mozilla::dom::AudioContext"},
+                {"from": "T_mozilla::dom::AudioNode", "to": "T_mozilla::dom::AudioNodeTrack"}
+            ]}],
+            "jumprefs": {
+                "T_mozilla::dom::GainNode": {
+                    "pretty": "mozilla::dom::GainNode",
+                    "meta": {"supers": [{"sym": "T_mozilla::dom::AudioNode"}]}
+                },
+                "T_mozilla::dom::AudioNode": {
+                    "pretty": "mozilla::dom::AudioNode",
+                    "meta": {"fields": [
+                        {"pretty": "mozilla::dom::AudioNode::mContext", "type": "RefPtr<AudioContext>"},
+                        {"pretty": "mozilla::dom::AudioNode::mState", "type": "AudioContextState"}
+                    ]}
+                },
+                "T_mozilla::dom::AudioContext": {"pretty": "mozilla::dom::AudioContext"}
+            }
+        });
+        let graph = SymbolGraphCollection::from_json(&json).unwrap();
+        let diagram = parse_class_diagram("mozilla::dom::GainNode", &graph);
+        assert_eq!(
+            format_class_diagram(&diagram, DiagramFormat::Text),
+            "mozilla::dom::GainNode inherits mozilla::dom::AudioNode
+mozilla::dom::AudioNode has mozilla::dom::AudioContext (mContext)
+mozilla::dom::AudioNode uses mozilla::dom::AudioNodeTrack
+"
+        );
+        assert_eq!(
+            format_class_diagram(&diagram, DiagramFormat::Dot),
+            "digraph \"mozilla::dom::GainNode\" {
+  node [shape=box];
+  \"mozilla::dom::GainNode\" -> \"mozilla::dom::AudioNode\" [arrowhead=empty];
+  \"mozilla::dom::AudioNode\" -> \"mozilla::dom::AudioContext\" [arrowhead=odiamond, label=\"mContext\"];
+  \"mozilla::dom::AudioNode\" -> \"mozilla::dom::AudioNodeTrack\" [style=dashed];
+}
+"
+        );
+        let mermaid = format_class_diagram(&diagram, DiagramFormat::Mermaid);
+        assert!(mermaid.starts_with("classDiagram\n"));
+        assert!(mermaid.contains("  class mozilla__dom__GainNode[\"mozilla::dom::GainNode\"]\n"));
+        assert!(mermaid.contains("  mozilla__dom__AudioNode <|-- mozilla__dom__GainNode\n"));
+        assert!(mermaid
+            .contains("  mozilla__dom__AudioNode o-- mozilla__dom__AudioContext : mContext\n"));
+    }
+}
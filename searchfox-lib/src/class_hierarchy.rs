@@ -0,0 +1,232 @@
+use crate::call_graph::{JumpRef, SymbolGraphCollection};
+use crate::client::SearchfoxClient;
+use anyhow::Result;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A class of an inheritance hierarchy, with the classes one step further
+/// from the queried class: its bases above it, its subclasses below it.
+/// Also used for the methods of an override hierarchy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HierarchyNode {
+    /// Symbol, as `T_mozilla::dom::AudioNode` for a class.
+    pub symbol: String,
+    pub pretty: String,
+    /// Definition, or declaration when there is none, as `path#line`.
+    pub location: Option<String>,
+    pub children: Vec<HierarchyNode>,
+}
+
+/// The bases and subclasses of a class, as far as the query's depth.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassHierarchy {
+    pub class: HierarchyNode,
+    pub superclasses: Vec<HierarchyNode>,
+    pub subclasses: Vec<HierarchyNode>,
+}
+
+/// Subclass and base class of an inheritance edge. Edges go from the
+/// subclass to its base, unless the `supers` of the target say otherwise.
+fn inheritance<'a>(
+    graph: &SymbolGraphCollection,
+    from: &'a str,
+    to: &'a str,
+) -> (&'a str, &'a str) {
+    let derives_from = |class: &str, base: &str| {
+        graph
+            .jumprefs
+            .get(class)
+            .is_some_and(|info| info.meta.supers.iter().any(|s| s.sym == base))
+    };
+    if derives_from(to, from) && !derives_from(from, to) {
+        (to, from)
+    } else {
+        (from, to)
+    }
+}
+
+/// The symbol of `class_name` in a hierarchy response: `T_<class_name>`, or
+/// a class whose pretty name is `class_name` or ends with `::<class_name>`.
+fn root_symbol<'a>(graph: &'a SymbolGraphCollection, class_name: &str) -> Option<&'a str> {
+    let symbol = format!("T_{class_name}");
+    if let Some((key, _)) = graph.jumprefs.get_key_value(&symbol) {
+        return Some(key);
+    }
+    let qualified = format!("::{class_name}");
+    let matching = |exact: bool| {
+        graph.jumprefs.iter().find_map(|(key, info)| {
+            let pretty = info.pretty()?;
+            let found = if exact {
+                pretty == class_name
+            } else {
+                pretty.ends_with(&qualified)
+            };
+            found.then_some(key.as_str())
+        })
+    };
+    matching(true).or_else(|| matching(false))
+}
+
+/// The node of `symbol`, with the nodes `links` leads to as children.
+pub(crate) fn hierarchy_node(
+    graph: &SymbolGraphCollection,
+    links: &BTreeMap<&str, BTreeSet<&str>>,
+    symbol: &str,
+    ancestors: &mut Vec<String>,
+) -> HierarchyNode {
+    ancestors.push(symbol.to_string());
+    let mut children = Vec::new();
+    for next in links.get(symbol).into_iter().flatten() {
+        // Guards against cycles in malformed responses.
+        if !ancestors.iter().any(|a| a == next) {
+            children.push(hierarchy_node(graph, links, next, ancestors));
+        }
+    }
+    ancestors.pop();
+    HierarchyNode {
+        symbol: symbol.to_string(),
+        pretty: graph.pretty(symbol).to_string(),
+        location: graph
+            .jumprefs
+            .get(symbol)
+            .and_then(JumpRef::location)
+            .map(String::from),
+        children,
+    }
+}
+
+/// The hierarchy of `class_name` in an `inheritance-diagram:` response, or
+/// None when the class is not part of it.
+pub fn parse_class_hierarchy(
+    class_name: &str,
+    graph: &SymbolGraphCollection,
+) -> Option<ClassHierarchy> {
+    let root = root_symbol(graph, class_name)?;
+    let mut bases: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    let mut derived: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for edge in graph.edges() {
+        let (subclass, base) = inheritance(graph, &edge.from, &edge.to);
+        bases.entry(subclass).or_default().insert(base);
+        derived.entry(base).or_default().insert(subclass);
+    }
+    // Bases known only from the class's own metadata, e.g. at depth 0.
+    if let Some(info) = graph.jumprefs.get(root) {
+        for base in &info.meta.supers {
+            bases.entry(root).or_default().insert(&base.sym);
+        }
+    }
+
+    let mut ancestors = Vec::new();
+    let mut class = hierarchy_node(graph, &BTreeMap::new(), root, &mut ancestors);
+    if class.pretty == root {
+        class.pretty = class_name.to_string();
+    }
+    let superclasses = hierarchy_node(graph, &bases, root, &mut ancestors).children;
+    let subclasses = hierarchy_node(graph, &derived, root, &mut ancestors).children;
+    Some(ClassHierarchy {
+        class,
+        superclasses,
+        subclasses,
+    })
+}
+
+/// Nodes one per line, indented by level, with their locations.
+pub(crate) fn format_nodes(nodes: &[HierarchyNode], indent: usize, output: &mut String) {
+    for node in nodes {
+        output.push_str(&"  ".repeat(indent));
+        output.push_str(&node.pretty);
+        if let Some(location) = &node.location {
+            output.push_str(&format!(" ({location})"));
+        }
+        output.push('\n');
+        format_nodes(&node.children, indent + 1, output);
+    }
+}
+
+/// The hierarchy as two indented trees under the class: its bases, each
+/// followed by its own bases, and its subclasses, each followed by its own
+/// subclasses.
+pub fn format_class_hierarchy(hierarchy: &ClassHierarchy) -> String {
+    let mut output = String::new();
+    format_nodes(std::slice::from_ref(&hierarchy.class), 0, &mut output);
+    for (title, nodes) in [
+        ("Superclasses", &hierarchy.superclasses),
+        ("Subclasses", &hierarchy.subclasses),
+    ] {
+        output.push_str(&format!("  {title}:\n"));
+        if nodes.is_empty() {
+            output.push_str("    (none)\n");
+        }
+        format_nodes(nodes, 2, &mut output);
+    }
+    output
+}
+
+impl SearchfoxClient {
+    /// The bases and subclasses of `class_name`, `depth` levels up and down,
+    /// from searchfox's `inheritance-diagram:` query.
+    pub async fn class_hierarchy(
+        &self,
+        class_name: &str,
+        depth: u32,
+    ) -> Result<Option<ClassHierarchy>> {
+        let query = format!("inheritance-diagram:'{class_name}' depth:{depth} graph-format:json");
+        let graph = SymbolGraphCollection::from_json(&self.query_symbol_graph(&query).await?)?;
+        Ok(parse_class_hierarchy(class_name, &graph))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_both_sides_of_the_hierarchy() {
+        let json = serde_json::json!({
+            "graphs": [{"edges": [
+                {"from": "T_mozilla::dom::AudioNode", "to": "T_mozilla::DOMEventTargetHelper"},
+                {"from": "T_mozilla::DOMEventTargetHelper", "to": "T_mozilla::dom::EventTarget"},
+                {"from": "T_mozilla::dom::AudioScheduledSourceNode", "to": "T_mozilla::dom::AudioNode"},
+                {"from": "T_mozilla::dom::AudioBufferSourceNode", "to": "T_mozilla::dom::AudioScheduledSourceNode"},
+                {"from": "T_mozilla::dom::GainNode", "to": "T_mozilla::dom::AudioNode"}
+            ]}],
+            "jumprefs": {
+                "T_mozilla::dom::AudioNode": {
+                    "pretty": "mozilla::dom::AudioNode",
+                    "jumps": {"def": "dom/media/webaudio/AudioNode.h#47"},
+                    "meta": {"supers": [{"sym": "T_mozilla::DOMEventTargetHelper"}]}
+                },
+                "T_mozilla::DOMEventTargetHelper": {
+                    "pretty": "mozilla::DOMEventTargetHelper",
+                    "jumps": {"def": "dom/events/DOMEventTargetHelper.h#31"}
+                },
+                "T_mozilla::dom::EventTarget": {"pretty": "mozilla::dom::EventTarget"},
+                "T_mozilla::dom::AudioScheduledSourceNode": {
+                    "pretty": "mozilla::dom::AudioScheduledSourceNode"
+                },
+                "T_mozilla::dom::AudioBufferSourceNode": {
+                    "pretty": "mozilla::dom::AudioBufferSourceNode"
+                },
+                "T_mozilla::dom::GainNode": {
+                    "pretty": "mozilla::dom::GainNode",
+                    "jumps": {"decl": "dom/media/webaudio/GainNode.h#19"}
+                }
+            }
+        });
+        let graph = SymbolGraphCollection::from_json(&json).unwrap();
+        let hierarchy = parse_class_hierarchy("AudioNode", &graph).unwrap();
+        assert_eq!(hierarchy.class.symbol, "T_mozilla::dom::AudioNode");
+        assert_eq!(
+            format_class_hierarchy(&hierarchy),
+            "mozilla::dom::AudioNode (dom/media/webaudio/AudioNode.h#47)
+  Superclasses:
+    mozilla::DOMEventTargetHelper (dom/events/DOMEventTargetHelper.h#31)
+      mozilla::dom::EventTarget
+  Subclasses:
+    mozilla::dom::AudioScheduledSourceNode
+      mozilla::dom::AudioBufferSourceNode
+    mozilla::dom::GainNode (dom/media/webaudio/GainNode.h#19)
+"
+        );
+        assert!(parse_class_hierarchy("AudioContext", &graph).is_none());
+    }
+}
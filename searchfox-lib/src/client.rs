@@ -1,53 +1,158 @@
+use crate::cassette::{Cassette, CassetteMode};
+use crate::config::NetworkConfig;
+use crate::error::RequestError;
+use crate::file_reader::{decode_text, FileBackend};
+use crate::progress::{ProgressEvent, ProgressSink};
+use crate::session::RecordedRequest;
 use crate::types::{RequestLog, ResponseLog};
 use anyhow::Result;
 use log::debug;
-use reqwest::{Client, Url};
+use reqwest::{Client, ResponseBuilderExt, Url};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
+/// The delay a `Retry-After` header asks for. Only the delay-seconds form
+/// is understood.
+fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let value = headers.get(reqwest::header::RETRY_AFTER)?.to_str().ok()?;
+    value.trim().parse().ok().map(Duration::from_secs)
+}
+
+/// A number in [0, 1), different for each call.
+fn jitter() -> f64 {
+    use std::hash::{BuildHasher, Hasher};
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    if let Ok(now) = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+        hasher.write_u128(now.as_nanos());
+    }
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Delay before retry number `attempt` (from 0): what the server asked for
+/// with Retry-After, or `backoff` doubled for each previous attempt, capped
+/// at `max_backoff`, of which up to half is taken off according to `jitter`
+/// so that concurrent requests do not retry in lockstep. None when the
+/// server asks to wait longer than `max_backoff`.
+fn retry_delay(
+    attempt: u32,
+    backoff: Duration,
+    max_backoff: Duration,
+    retry_after: Option<Duration>,
+    jitter: f64,
+) -> Option<Duration> {
+    if let Some(delay) = retry_after {
+        return (delay <= max_backoff).then_some(delay);
+    }
+    let exponential = backoff
+        .saturating_mul(1 << attempt.min(16))
+        .min(max_backoff);
+    Some(exponential.mul_f64(1.0 - jitter / 2.0))
+}
+
+/// Base URL of the public searchfox instance, used unless `--server` or the
+/// `server` key of the `[network]` table says otherwise.
+pub const DEFAULT_SERVER: &str = "https://searchfox.org";
+
 pub struct SearchfoxClient {
     client: Client,
     pub repo: String,
     pub log_requests: bool,
     pub(crate) base_url: String,
+    pub(crate) hg_base_url: String,
+    pub(crate) file_backend: FileBackend,
     request_counter: std::sync::atomic::AtomicUsize,
     cache: Option<std::sync::Mutex<rusqlite::Connection>>,
     cache_enabled: bool,
     force_refetch: bool,
+    session: Option<std::sync::Mutex<Vec<RecordedRequest>>>,
+    session_responses: bool,
+    progress: Option<Arc<dyn ProgressSink>>,
+    cassette: Option<(CassetteMode, Arc<Mutex<Cassette>>)>,
+    cache_ttl: u64,
+    retries: u32,
+    backoff: Duration,
+    max_backoff: Duration,
+    min_interval: Option<Duration>,
+    next_request: Mutex<Instant>,
 }
 
 impl SearchfoxClient {
     pub fn new(repo: String, log_requests: bool) -> Result<Self> {
-        let client = Self::create_tls13_client()?;
-        let cache = crate::cache::open().map(|conn| {
-            crate::cache::prune(&conn);
-            std::sync::Mutex::new(conn)
-        });
+        Self::with_network(repo, log_requests, &crate::config::global().network)
+    }
+
+    /// A client using `network` instead of the `[network]` table of the
+    /// user configuration.
+    pub fn with_network(repo: String, log_requests: bool, network: &NetworkConfig) -> Result<Self> {
+        let cache = crate::cache::cache_path_in(network.cache_dir.as_deref())
+            .and_then(|path| crate::cache::open(&path))
+            .inspect(crate::cache::prune);
+        Self::from_parts(
+            repo,
+            log_requests,
+            network,
+            cache,
+            network
+                .server
+                .as_deref()
+                .unwrap_or(DEFAULT_SERVER)
+                .trim_end_matches('/')
+                .to_string(),
+            "https://hg.mozilla.org".to_string(),
+        )
+    }
+
+    fn from_parts(
+        repo: String,
+        log_requests: bool,
+        network: &NetworkConfig,
+        cache: Option<rusqlite::Connection>,
+        base_url: String,
+        hg_base_url: String,
+    ) -> Result<Self> {
+        let client = Self::create_tls13_client(network)?;
+        let min_interval = network
+            .rate_limit
+            .filter(|rate| *rate > 0.0)
+            .map(|rate| config_duration("rate_limit", 1.0 / rate))
+            .transpose()?;
         Ok(Self {
             client,
             repo,
             log_requests,
-            base_url: "https://searchfox.org".to_string(),
+            base_url,
+            hg_base_url,
+            file_backend: FileBackend::default(),
             request_counter: std::sync::atomic::AtomicUsize::new(0),
-            cache,
+            cache: cache.map(std::sync::Mutex::new),
             cache_enabled: true,
             force_refetch: false,
+            session: None,
+            session_responses: false,
+            progress: None,
+            cassette: None,
+            cache_ttl: network.cache_ttl,
+            retries: network.retries,
+            backoff: config_duration("backoff", network.backoff.max(0.0))?,
+            max_backoff: config_duration("max_backoff", network.max_backoff.max(0.0))?,
+            min_interval,
+            next_request: Mutex::new(Instant::now()),
         })
     }
 
     #[cfg(test)]
     pub(crate) fn new_for_test(repo: String, base_url: String) -> Result<Self> {
-        let client = Self::create_tls13_client()?;
-        let conn = crate::cache::open_in_memory()?;
-        Ok(Self {
-            client,
+        Self::from_parts(
             repo,
-            log_requests: false,
+            false,
+            &NetworkConfig {
+                retries: 0,
+                ..NetworkConfig::default()
+            },
+            Some(crate::cache::open_in_memory()?),
+            base_url.clone(),
             base_url,
-            request_counter: std::sync::atomic::AtomicUsize::new(0),
-            cache: Some(std::sync::Mutex::new(conn)),
-            cache_enabled: true,
-            force_refetch: false,
-        })
+        )
     }
 
     #[cfg(test)]
@@ -65,20 +170,29 @@ impl SearchfoxClient {
         }
     }
 
-    fn create_tls13_client() -> Result<Client> {
-        Client::builder()
-            .user_agent(Self::get_user_agent())
+    fn create_tls13_client(network: &NetworkConfig) -> Result<Client> {
+        let mut builder = Client::builder()
+            .user_agent(Self::get_user_agent(network))
             .use_rustls_tls()
             .min_tls_version(reqwest::tls::Version::TLS_1_2)
             .max_tls_version(reqwest::tls::Version::TLS_1_3)
-            .timeout(Duration::from_secs(30))
+            .timeout(Duration::from_secs(network.timeout));
+        if let Some(ref proxy) = network.proxy {
+            builder = builder.proxy(
+                reqwest::Proxy::all(proxy)
+                    .map_err(|e| anyhow::anyhow!("Invalid proxy '{}': {}", proxy, e))?,
+            );
+        }
+        builder
             .build()
             .map_err(|e| anyhow::anyhow!("Failed to build TLS client with rustls: {}", e))
     }
 
-    fn get_user_agent() -> String {
+    fn get_user_agent(network: &NetworkConfig) -> String {
         let magic_word = std::env::var("SEARCHFOX_MAGIC_WORD")
-            .unwrap_or_else(|_| "sésame ouvre toi".to_string());
+            .ok()
+            .or_else(|| network.magic_word.clone())
+            .unwrap_or_else(|| "sésame ouvre toi".to_string());
         format!("searchfox-cli/{} ({})", crate::VERSION, magic_word)
     }
 
@@ -133,17 +247,15 @@ impl SearchfoxClient {
         }
 
         eprintln!(
-            "[PING] Testing network latency to searchfox.org (ICMP ping disabled, using HTTP HEAD)..."
+            "[PING] Testing network latency to {} (ICMP ping disabled, using HTTP HEAD)...",
+            self.base_url
         );
 
-        let ping_url = "https://searchfox.org/";
+        let ping_url = format!("{}/", self.base_url);
         let start = Instant::now();
 
         let response = self
-            .client
-            .head(ping_url)
-            .timeout(Duration::from_secs(10))
-            .send()
+            .send(self.client.head(&ping_url).timeout(Duration::from_secs(10)))
             .await?;
 
         let latency = start.elapsed();
@@ -161,34 +273,43 @@ impl SearchfoxClient {
     }
 
     pub async fn get(&self, url: Url) -> Result<reqwest::Response> {
+        self.emit_request(url.as_str());
         let request_log = self.log_request_start("GET", url.as_ref());
         let response = self
-            .client
-            .get(url.clone())
-            .header("Accept", "application/json")
-            .send()
+            .send(
+                self.client
+                    .get(url.clone())
+                    .header("Accept", "application/json"),
+            )
             .await?;
 
         if let Some(req_log) = request_log {
             self.log_request_end(req_log, response.status().as_u16(), 0);
         }
 
+        if self.session.is_some() {
+            return self.record_response(url.as_str(), response).await;
+        }
+
         Ok(response)
     }
 
     pub async fn get_raw(&self, url: &str) -> Result<String> {
+        self.emit_request(url);
         let request_log = self.log_request_start("GET", url);
-        let response = self.client.get(url).send().await?;
+        let response = self.send(self.client.get(url)).await?;
 
         if !response.status().is_success() {
+            self.record(url, response.status().as_u16(), None);
             if let Some(req_log) = request_log {
                 self.log_request_end(req_log, response.status().as_u16(), 0);
             }
-            anyhow::bail!("Request failed: {}", response.status());
+            return Err(RequestError::new(url, response.status()).into());
         }
 
-        let text = response.text().await?;
+        let text = decode_text(response.bytes().await?.to_vec(), url);
         let size = text.len();
+        self.record(url, 200, Some(&text));
 
         if let Some(req_log) = request_log {
             self.log_request_end(req_log, 200, size);
@@ -197,27 +318,101 @@ impl SearchfoxClient {
         Ok(text)
     }
 
+    /// Like `get_raw`, without decoding the body as text.
+    pub async fn get_raw_bytes(&self, url: &str) -> Result<Vec<u8>> {
+        self.emit_request(url);
+        let request_log = self.log_request_start("GET", url);
+        let response = self.send(self.client.get(url)).await?;
+        let status = response.status();
+
+        if !status.is_success() {
+            self.record(url, status.as_u16(), None);
+            if let Some(req_log) = request_log {
+                self.log_request_end(req_log, status.as_u16(), 0);
+            }
+            return Err(RequestError::new(url, status).into());
+        }
+
+        let bytes = response.bytes().await?.to_vec();
+        self.record(url, status.as_u16(), Some(&String::from_utf8_lossy(&bytes)));
+        if let Some(req_log) = request_log {
+            self.log_request_end(req_log, status.as_u16(), bytes.len());
+        }
+        Ok(bytes)
+    }
+
+    /// GET `url`, but stop reading the body once `count` occurrences of
+    /// `marker` have been received. Returns the bytes read and whether the
+    /// body was cut short.
+    pub async fn get_until(
+        &self,
+        url: &str,
+        marker: &[u8],
+        count: usize,
+    ) -> Result<(Vec<u8>, bool)> {
+        self.emit_request(url);
+        let request_log = self.log_request_start("GET", url);
+        let mut response = self.send(self.client.get(url)).await?;
+        let status = response.status();
+
+        if !status.is_success() {
+            self.record(url, status.as_u16(), None);
+            if let Some(req_log) = request_log {
+                self.log_request_end(req_log, status.as_u16(), 0);
+            }
+            return Err(RequestError::new(url, status).into());
+        }
+
+        let mut body = Vec::new();
+        let mut seen = 0;
+        // Markers can straddle chunks: every start position is scanned once.
+        let mut scan_from = 0;
+        let mut truncated = false;
+        while let Some(chunk) = response.chunk().await? {
+            body.extend_from_slice(&chunk);
+            if body.len() >= marker.len() {
+                let last_start = body.len() - marker.len();
+                seen += (scan_from..=last_start)
+                    .filter(|&i| body[i..].starts_with(marker))
+                    .count();
+                scan_from = last_start + 1;
+            }
+            if seen >= count {
+                truncated = true;
+                break;
+            }
+        }
+
+        let text = String::from_utf8_lossy(&body);
+        self.record(url, status.as_u16(), (!truncated).then_some(&*text));
+        if let Some(req_log) = request_log {
+            self.log_request_end(req_log, status.as_u16(), body.len());
+        }
+        Ok((body, truncated))
+    }
+
     pub async fn get_final_url(&self, url: &str) -> Result<String> {
         let url = reqwest::Url::parse(url).map_err(|e| anyhow::anyhow!("Invalid URL: {}", e))?;
-        let response = self.client.head(url).send().await?;
+        let response = self.send(self.client.head(url)).await?;
         Ok(response.url().as_str().to_string())
     }
 
     pub async fn get_html(&self, url: &str) -> Result<String> {
         debug!("Fetching HTML from: {}", url);
+        self.emit_request(url);
 
         let response = self
-            .client
-            .get(url)
-            .header("Accept", "text/html")
-            .send()
+            .send(self.client.get(url).header("Accept", "text/html"))
             .await?;
 
         if !response.status().is_success() {
-            anyhow::bail!("Request failed: {}", response.status());
+            self.record(url, response.status().as_u16(), None);
+            return Err(RequestError::new(url, response.status()).into());
         }
 
-        Ok(response.text().await?)
+        let text = response.text().await?;
+        self.record(url, 200, Some(&text));
+        Ok(text)
     }
 
     pub async fn get_html_with_meta(
@@ -227,6 +422,7 @@ impl SearchfoxClient {
         last_modified: Option<&str>,
     ) -> Result<Option<(String, Option<String>, Option<String>)>> {
         debug!("Fetching HTML from: {}", url);
+        self.emit_request(url);
 
         let mut request = self.client.get(url).header("Accept", "text/html");
         if let Some(etag) = etag {
@@ -236,15 +432,17 @@ impl SearchfoxClient {
             request = request.header("If-Modified-Since", lm);
         }
 
-        let response = request.send().await?;
+        let response = self.send(request).await?;
         let status = response.status();
 
         if status == reqwest::StatusCode::NOT_MODIFIED {
+            self.record(url, status.as_u16(), None);
             return Ok(None);
         }
 
         if !status.is_success() {
-            anyhow::bail!("Request failed: {}", status);
+            self.record(url, status.as_u16(), None);
+            return Err(RequestError::new(url, status).into());
         }
 
         let etag = response
@@ -259,9 +457,207 @@ impl SearchfoxClient {
             .map(String::from);
 
         let text = response.text().await?;
+        self.record(url, status.as_u16(), Some(&text));
         Ok(Some((text, etag, last_modified)))
     }
 
+    /// Send a request, or answer it from the cassette when replaying. When
+    /// recording, the response is buffered into the cassette and handed back
+    /// rebuilt.
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let request = request.build()?;
+        let Some((mode, ref cassette)) = self.cassette else {
+            return self.execute(request).await;
+        };
+        let method = request.method().to_string();
+        let url = request.url().to_string();
+        match mode {
+            CassetteMode::Replay => {
+                let interaction = cassette
+                    .lock()
+                    .map_err(|_| anyhow::anyhow!("Cassette lock poisoned"))?
+                    .next_response(&method, &url)
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "No response recorded for {} {} in the cassette",
+                            method,
+                            url
+                        )
+                    })?;
+                interaction.into_response()
+            }
+            CassetteMode::Record => {
+                let response = self.execute(request).await?;
+                let final_url = response.url().clone();
+                let mut builder = http::Response::builder()
+                    .status(response.status())
+                    .version(response.version())
+                    .url(final_url.clone());
+                if let Some(h) = builder.headers_mut() {
+                    *h = response.headers().clone();
+                }
+                let recorded = builder.body(response.bytes().await?.to_vec())?;
+                if let Ok(mut c) = cassette.lock() {
+                    c.push(&method, &url, final_url.as_str(), &recorded);
+                }
+                Ok(reqwest::Response::from(recorded))
+            }
+        }
+    }
+
+    /// Execute a request over the network, waiting for the rate limit and
+    /// retrying transient failures up to the configured number of times.
+    async fn execute(&self, mut request: reqwest::Request) -> Result<reqwest::Response> {
+        let mut attempt = 0;
+        loop {
+            self.throttle().await;
+            let retry = if attempt < self.retries {
+                request.try_clone()
+            } else {
+                None
+            };
+            let result = self.client.execute(request).await;
+            let (transient, retry_after) = match &result {
+                Ok(response) => {
+                    let status = response.status();
+                    (
+                        status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                            || status.is_server_error(),
+                        retry_after(response.headers()),
+                    )
+                }
+                Err(e) => (e.is_timeout() || e.is_connect(), None),
+            };
+            let delay = retry_delay(
+                attempt,
+                self.backoff,
+                self.max_backoff,
+                retry_after,
+                jitter(),
+            );
+            match (retry, delay) {
+                (Some(next), Some(delay)) if transient => {
+                    debug!(
+                        "Retrying {} in {}ms ({})",
+                        next.url(),
+                        delay.as_millis(),
+                        match &result {
+                            Ok(response) => response.status().to_string(),
+                            Err(e) => e.to_string(),
+                        }
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                    request = next;
+                }
+                _ => return Ok(result?),
+            }
+        }
+    }
+
+    /// Wait until the next request is allowed by `rate_limit`.
+    async fn throttle(&self) {
+        let Some(interval) = self.min_interval else {
+            return;
+        };
+        let slot = {
+            let Ok(mut next) = self.next_request.lock() else {
+                return;
+            };
+            let slot = (*next).max(Instant::now());
+            *next = slot + interval;
+            slot
+        };
+        tokio::time::sleep_until(slot.into()).await;
+    }
+
+    /// Record every exchange into `cassette`, or answer requests from it
+    /// without touching the network. Either way the local cache is bypassed,
+    /// so that a recording holds every request the command needs.
+    pub fn set_cassette(&mut self, mode: CassetteMode, cassette: Arc<Mutex<Cassette>>) {
+        self.cassette = Some((mode, cassette));
+        self.cache_enabled = false;
+    }
+
+    pub fn cassette(&self) -> Option<(CassetteMode, Arc<Mutex<Cassette>>)> {
+        self.cassette.clone()
+    }
+
+    /// Start recording every request issued through this client, for
+    /// `--save-session`. Response bodies are kept when `include_responses`
+    /// is set.
+    pub fn enable_session_recording(&mut self, include_responses: bool) {
+        self.session = Some(std::sync::Mutex::new(Vec::new()));
+        self.session_responses = include_responses;
+    }
+
+    /// Drain the requests recorded since the last call.
+    pub fn take_recorded_requests(&self) -> Vec<RecordedRequest> {
+        self.session
+            .as_ref()
+            .and_then(|m| m.lock().ok().map(|mut r| std::mem::take(&mut *r)))
+            .unwrap_or_default()
+    }
+
+    fn record(&self, url: &str, status: u16, body: Option<&str>) {
+        if let Some(ref m) = self.session {
+            if let Ok(mut requests) = m.lock() {
+                requests.push(RecordedRequest {
+                    url: url.to_string(),
+                    status,
+                    response: body.filter(|_| self.session_responses).map(String::from),
+                });
+            }
+        }
+    }
+
+    /// Record a response whose body has not been consumed yet. When bodies
+    /// are recorded, the response is buffered and handed back rebuilt.
+    async fn record_response(
+        &self,
+        url: &str,
+        response: reqwest::Response,
+    ) -> Result<reqwest::Response> {
+        let status = response.status();
+        if !self.session_responses {
+            self.record(url, status.as_u16(), None);
+            return Ok(response);
+        }
+
+        let version = response.version();
+        let headers = response.headers().clone();
+        let bytes = response.bytes().await?;
+        self.record(url, status.as_u16(), Some(&String::from_utf8_lossy(&bytes)));
+
+        let mut builder = http::Response::builder()
+            .status(status)
+            .version(version)
+            .url(Url::parse(url)?);
+        if let Some(h) = builder.headers_mut() {
+            *h = headers;
+        }
+        Ok(reqwest::Response::from(builder.body(bytes)?))
+    }
+
+    /// Receive progress events for long operations and for every request.
+    pub fn set_progress_sink(&mut self, sink: Arc<dyn ProgressSink>) {
+        self.progress = Some(sink);
+    }
+
+    pub(crate) fn emit(&self, event: ProgressEvent) {
+        if let Some(ref sink) = self.progress {
+            sink.on_event(&event);
+        }
+    }
+
+    fn emit_request(&self, url: &str) {
+        if self.progress.is_some() {
+            self.emit(ProgressEvent::Request {
+                url: url.to_string(),
+            });
+        }
+    }
+
     pub fn client(&self) -> &Client {
         &self.client
     }
@@ -270,14 +666,33 @@ impl SearchfoxClient {
         self.cache_enabled = enabled;
     }
 
+    /// Query the mozsearch instance at `url` instead of searchfox.org.
+    pub fn set_base_url(&mut self, url: &str) {
+        self.base_url = url.trim_end_matches('/').to_string();
+    }
+
+    /// Base URL of the mozsearch instance, without a trailing slash.
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
     pub fn set_force_refetch(&mut self, force_refetch: bool) {
         self.force_refetch = force_refetch;
     }
 
+    /// Choose where `get_file` fetches file contents from.
+    pub fn set_file_backend(&mut self, backend: FileBackend) {
+        self.file_backend = backend;
+    }
+
     pub(crate) fn force_refetch(&self) -> bool {
         self.force_refetch
     }
 
+    pub(crate) fn cache_ttl(&self) -> u64 {
+        self.cache_ttl
+    }
+
     pub(crate) fn cache_get(&self, url: &str) -> Option<crate::cache::CacheEntry> {
         if !self.cache_enabled || self.force_refetch {
             return None;
@@ -289,6 +704,22 @@ impl SearchfoxClient {
             .and_then(|c| crate::cache::get(&c, url))
     }
 
+    pub(crate) fn watch_get(&self, key: &str) -> Option<(String, u64)> {
+        self.cache
+            .as_ref()?
+            .lock()
+            .ok()
+            .and_then(|c| crate::cache::watch_get(&c, key))
+    }
+
+    pub(crate) fn watch_set(&self, key: &str, snapshot: &str) {
+        if let Some(ref m) = self.cache {
+            if let Ok(c) = m.lock() {
+                crate::cache::watch_set(&c, key, snapshot);
+            }
+        }
+    }
+
     pub(crate) fn cache_set(
         &self,
         url: &str,
@@ -307,10 +738,27 @@ impl SearchfoxClient {
     }
 }
 
+/// `secs` of the `[network]` setting `name` as a duration, an error when
+/// it has none, e.g. for `inf`.
+fn config_duration(name: &str, secs: f64) -> Result<Duration> {
+    Duration::try_from_secs_f64(secs)
+        .map_err(|_| anyhow::anyhow!("Invalid [network] config: {name} gives {secs} seconds"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn rejects_durations_out_of_range() {
+        assert_eq!(
+            config_duration("backoff", 0.5).unwrap(),
+            Duration::from_millis(500)
+        );
+        assert!(config_duration("backoff", f64::INFINITY).is_err());
+        assert!(config_duration("rate_limit", 1.0 / 1e-300).is_err());
+    }
+
     #[test]
     fn no_cache_disables_reads_and_writes() {
         let mut client =
@@ -350,4 +798,111 @@ mod tests {
             "v2"
         );
     }
+
+    #[tokio::test]
+    async fn retries_transient_failures() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/flaky"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/flaky"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+            .mount(&server)
+            .await;
+        let url = format!("{}/flaky", server.uri());
+
+        let client = |retries| {
+            let network = NetworkConfig {
+                retries,
+                ..NetworkConfig::default()
+            };
+            SearchfoxClient::from_parts(
+                "mozilla-central".into(),
+                false,
+                &network,
+                None,
+                server.uri(),
+                server.uri(),
+            )
+            .unwrap()
+        };
+        assert!(client(0).get_raw(&url).await.is_err());
+        assert_eq!(client(0).get_raw(&url).await.unwrap(), "ok");
+
+        server.reset().await;
+        Mock::given(method("GET"))
+            .and(path("/flaky"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/flaky"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+            .mount(&server)
+            .await;
+        assert_eq!(client(1).get_raw(&url).await.unwrap(), "ok");
+    }
+
+    #[test]
+    fn backs_off_exponentially_with_jitter() {
+        let ms = Duration::from_millis;
+        let delay = |attempt, retry_after, jitter| {
+            retry_delay(attempt, ms(500), ms(30_000), retry_after, jitter)
+        };
+        assert_eq!(delay(0, None, 0.0), Some(ms(500)));
+        assert_eq!(delay(2, None, 0.0), Some(ms(2000)));
+        assert_eq!(delay(2, None, 0.5), Some(ms(1500)));
+        assert_eq!(delay(10, None, 0.0), Some(ms(30_000)));
+        assert_eq!(delay(31, None, 0.0), Some(ms(30_000)));
+        assert_eq!(delay(0, Some(ms(7000)), 0.9), Some(ms(7000)));
+        assert_eq!(delay(0, Some(ms(60_000)), 0.0), None);
+        let j = jitter();
+        assert!((0.0..1.0).contains(&j));
+    }
+
+    #[tokio::test]
+    async fn honors_retry_after() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/busy"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/busy"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+            .mount(&server)
+            .await;
+        // Without Retry-After, the first retry would wait a minute.
+        let network = NetworkConfig {
+            retries: 1,
+            backoff: 60.0,
+            max_backoff: 60.0,
+            ..NetworkConfig::default()
+        };
+        let client = SearchfoxClient::from_parts(
+            "mozilla-central".into(),
+            false,
+            &network,
+            None,
+            server.uri(),
+            server.uri(),
+        )
+        .unwrap();
+        let url = format!("{}/busy", server.uri());
+        let body = tokio::time::timeout(Duration::from_secs(10), client.get_raw(&url)).await;
+        assert_eq!(body.unwrap().unwrap(), "ok");
+    }
 }
@@ -0,0 +1,226 @@
+use crate::client::SearchfoxClient;
+use crate::search::SearchOptions;
+use crate::types::{File, SearchfoxResponse};
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+/// An indexed identifier matching a completion prefix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    pub name: String,
+    /// Kind of symbol guessed from its definition line, e.g. "class" or
+    /// "function", or "symbol" when unknown.
+    pub kind: &'static str,
+    /// Definition site, or first declaration or use when there is none.
+    pub path: String,
+    pub line: usize,
+}
+
+/// Guess the kind of a symbol from the source line defining it.
+pub fn symbol_kind(line: &str) -> &'static str {
+    let line = line.trim_start();
+    let words: Vec<&str> = line
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|w| !w.is_empty())
+        .collect();
+    let has = |word: &str| words.contains(&word);
+    if line.starts_with("#define") {
+        "macro"
+    } else if has("enum") {
+        "enum"
+    } else if has("class") || has("interface") {
+        "class"
+    } else if has("struct") {
+        "struct"
+    } else if has("union") {
+        "union"
+    } else if has("namespace") || has("mod") {
+        "namespace"
+    } else if has("typedef") || has("using") || has("type") {
+        "type"
+    } else if line.contains('(') {
+        "function"
+    } else {
+        "variable"
+    }
+}
+
+/// Whether a component of the qualified `name` starts with `prefix`, ignoring
+/// case, as searchfox matches identifiers.
+fn matches_prefix(name: &str, prefix: &str) -> bool {
+    let prefix = prefix.to_lowercase();
+    let name = name.to_lowercase();
+    name.starts_with(&prefix)
+        || name
+            .match_indices("::")
+            .any(|(i, _)| name[i + 2..].starts_with(&prefix))
+}
+
+/// Identifiers starting with `prefix` in the identifier results of a search,
+/// sorted by name.
+pub fn collect_completions(
+    json: &SearchfoxResponse,
+    prefix: &str,
+    options: &SearchOptions,
+) -> Vec<Completion> {
+    // name -> (is a definition, completion)
+    let mut found: BTreeMap<String, (bool, Completion)> = BTreeMap::new();
+    let mut categories: Vec<_> = json.iter().collect();
+    categories.sort_by_key(|(category, _)| *category);
+    for (category, value) in categories {
+        if category.starts_with('*') || !options.category_filter.should_include(category) {
+            continue;
+        }
+        let Some(obj) = value.as_object() else {
+            continue;
+        };
+        for (label, file_list) in obj {
+            let Some((kind_label, name)) = label
+                .strip_suffix(')')
+                .and_then(|rest| rest.split_once(" ("))
+            else {
+                continue;
+            };
+            if !matches_prefix(name, prefix) {
+                continue;
+            }
+            let is_definition = kind_label == "Definitions";
+            if found
+                .get(name)
+                .is_some_and(|(def, _)| *def || !is_definition)
+            {
+                continue;
+            }
+            let site = file_list
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|file| serde_json::from_value::<File>(file.clone()).ok())
+                .filter(|file| options.matches_language_filter(&file.path))
+                .find_map(|file| {
+                    let line = file.lines.first()?;
+                    Some((file.path.clone(), line.lno, symbol_kind(&line.line)))
+                });
+            let Some((path, line, definition_kind)) = site else {
+                continue;
+            };
+            let kind = if is_definition {
+                definition_kind
+            } else {
+                "symbol"
+            };
+            found.insert(
+                name.to_string(),
+                (
+                    is_definition,
+                    Completion {
+                        name: name.to_string(),
+                        kind,
+                        path,
+                        line,
+                    },
+                ),
+            );
+        }
+    }
+    found.into_values().map(|(_, c)| c).collect()
+}
+
+impl SearchfoxClient {
+    /// List indexed identifiers starting with `prefix`, like the website's
+    /// autocomplete. `options.path` narrows the search.
+    pub async fn complete(&self, prefix: &str, options: &SearchOptions) -> Result<Vec<Completion>> {
+        let prefix_options = SearchOptions {
+            query: Some(prefix.to_string()),
+            symbol: None,
+            id: None,
+            context: None,
+            ..options.clone()
+        };
+        let json = self.search_response(&prefix_options).await?;
+        Ok(collect_completions(&json, prefix, &prefix_options))
+    }
+}
+
+/// One completion per line, as `name<TAB>kind<TAB>path:line`, for shell
+/// completion scripts and other tools.
+pub fn format_completions(completions: &[Completion]) -> String {
+    completions
+        .iter()
+        .map(|c| format!("{}\t{}\t{}:{}\n", c.name, c.kind, c.path, c.line))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guesses_symbol_kinds() {
+        assert_eq!(
+            symbol_kind("class AudioContext final : public DOMEventTargetHelper"),
+            "class"
+        );
+        assert_eq!(symbol_kind("struct AudioChunk {"), "struct");
+        assert_eq!(
+            symbol_kind("enum class AudioContextState : uint8_t {"),
+            "enum"
+        );
+        assert_eq!(symbol_kind("void AudioContext::Shutdown() {"), "function");
+        assert_eq!(symbol_kind("#define AUDIO_BUFFER_SIZE 128"), "macro");
+        assert_eq!(symbol_kind("  uint32_t mSampleRate;"), "variable");
+    }
+
+    #[test]
+    fn collects_prefix_matches() {
+        let json: SearchfoxResponse = serde_json::from_str(
+            r#"{
+                "*title*": "Audio",
+                "normal": {
+                    "Uses (mozilla::dom::AudioContext)": [
+                        {"path": "dom/media/webaudio/AudioNode.cpp", "lines": [{"lno": 3, "line": "AudioContext* c;"}]}
+                    ],
+                    "Definitions (mozilla::dom::AudioContext)": [
+                        {"path": "dom/media/webaudio/AudioContext.h", "lines": [{"lno": 120, "line": "class AudioContext final"}]}
+                    ],
+                    "Declarations (mozilla::AudioStream)": [
+                        {"path": "dom/media/AudioStream.h", "lines": [{"lno": 9, "line": "class AudioStream;"}]}
+                    ],
+                    "Definitions (mozilla::MediaDecoder)": [
+                        {"path": "dom/media/MediaDecoder.h", "lines": [{"lno": 1, "line": "class MediaDecoder"}]}
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+        let completions = collect_completions(&json, "audio", &SearchOptions::default());
+        assert_eq!(
+            completions,
+            vec![
+                Completion {
+                    name: "mozilla::AudioStream".into(),
+                    kind: "symbol",
+                    path: "dom/media/AudioStream.h".into(),
+                    line: 9,
+                },
+                Completion {
+                    name: "mozilla::dom::AudioContext".into(),
+                    kind: "class",
+                    path: "dom/media/webaudio/AudioContext.h".into(),
+                    line: 120,
+                },
+            ]
+        );
+
+        // A qualified prefix narrows to a namespace, from any of its components.
+        let names = |prefix| {
+            collect_completions(&json, prefix, &SearchOptions::default())
+                .into_iter()
+                .map(|c| c.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("mozilla::dom::Audio"), ["mozilla::dom::AudioContext"]);
+        assert_eq!(names("dom::audio"), ["mozilla::dom::AudioContext"]);
+        assert!(names("mozilla::Dom::Stream").is_empty());
+    }
+}
@@ -0,0 +1,205 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// User configuration, read from `config.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Per-repository overrides of the built-in repository table, keyed by
+    /// repository name as passed to `--repo`.
+    pub repos: BTreeMap<String, RepoConfig>,
+    pub network: NetworkConfig,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RepoConfig {
+    /// Name of the tree in searchfox URLs, e.g. `firefox-esr140`.
+    pub searchfox: Option<String>,
+    /// Path of the repository on hg.mozilla.org, e.g. `releases/mozilla-esr140`.
+    pub hg: Option<String>,
+    /// URL of the raw files of the repository, where `{path}` stands for
+    /// the path of a file and `{rev}` for the revision, `HEAD` unless one is
+    /// asked for, e.g.
+    /// `https://raw.githubusercontent.com/mozilla/glean/{rev}/{path}`.
+    /// Takes precedence over `hg`.
+    pub raw: Option<String>,
+}
+
+/// How `SearchfoxClient` talks to the network, from the `[network]` table.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkConfig {
+    /// Timeout of a whole request, in seconds.
+    pub timeout: u64,
+    /// How many times a request failing with a connection error, a timeout,
+    /// 429 or a 5xx status is retried, with exponential backoff.
+    pub retries: u32,
+    /// Delay before the first retry, in seconds, doubled for each next one.
+    pub backoff: f64,
+    /// Longest delay between retries, in seconds. A server asking, with
+    /// Retry-After, to wait longer than this is not retried.
+    pub max_backoff: f64,
+    /// Proxy for every request, e.g. `http://proxy.example.com:3128`.
+    pub proxy: Option<String>,
+    /// Maximum number of requests per second.
+    pub rate_limit: Option<f64>,
+    /// Directory holding the cache database, instead of the XDG cache
+    /// directory.
+    pub cache_dir: Option<PathBuf>,
+    /// How long cached file contents are used without revalidation, in
+    /// seconds.
+    pub cache_ttl: u64,
+    /// Magic word sent in the user agent. `SEARCHFOX_MAGIC_WORD` takes
+    /// precedence.
+    pub magic_word: Option<String>,
+    /// Base URL of the mozsearch instance to query, for self-hosted
+    /// deployments, e.g. `https://searchfox.example.com`.
+    pub server: Option<String>,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            timeout: 30,
+            retries: 3,
+            backoff: 0.5,
+            max_backoff: 30.0,
+            proxy: None,
+            rate_limit: None,
+            cache_dir: None,
+            cache_ttl: crate::cache::FRESH_SECS,
+            magic_word: None,
+            server: None,
+        }
+    }
+}
+
+impl Config {
+    pub fn parse(text: &str) -> Result<Self> {
+        Ok(toml::from_str(text)?)
+    }
+
+    /// Read the configuration at `path`. A missing file is an empty
+    /// configuration.
+    pub fn load(path: &Path) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => {
+                Self::parse(&text).with_context(|| format!("Invalid config {}", path.display()))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Could not read {}", path.display())),
+        }
+    }
+
+    pub fn repo(&self, repo: &str) -> Option<&RepoConfig> {
+        self.repos.get(repo)
+    }
+}
+
+pub fn config_path() -> Option<PathBuf> {
+    let base = if let Ok(xdg) = std::env::var("XDG_CONFIG_HOME") {
+        PathBuf::from(xdg)
+    } else {
+        PathBuf::from(std::env::var("HOME").ok()?).join(".config")
+    };
+    Some(base.join("searchfox-cli").join("config.toml"))
+}
+
+/// The user configuration, loaded on first use. An unreadable or invalid
+/// file is reported once and otherwise ignored.
+pub fn global() -> &'static Config {
+    static CONFIG: OnceLock<Config> = OnceLock::new();
+    CONFIG.get_or_init(|| {
+        let Some(path) = config_path() else {
+            return Config::default();
+        };
+        Config::load(&path).unwrap_or_else(|e| {
+            log::warn!("{:#}", e);
+            Config::default()
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_repo_overrides() {
+        let config = Config::parse(
+            r#"
+            [repos.mozilla-esr153]
+            searchfox = "firefox-esr153"
+            hg = "releases/mozilla-esr153"
+
+            [repos.mozilla-beta]
+            searchfox = "beta-tree"
+            "#,
+        )
+        .unwrap();
+        let esr = config.repo("mozilla-esr153").unwrap();
+        assert_eq!(esr.searchfox.as_deref(), Some("firefox-esr153"));
+        assert_eq!(esr.hg.as_deref(), Some("releases/mozilla-esr153"));
+        assert!(config.repo("mozilla-beta").unwrap().hg.is_none());
+        assert!(esr.raw.is_none());
+        assert!(config.repo("mozilla-central").is_none());
+    }
+
+    #[test]
+    fn parses_network_settings() {
+        let config = Config::parse(
+            r#"
+            [network]
+            timeout = 10
+            retries = 3
+            backoff = 1.5
+            proxy = "http://proxy.example.com:3128"
+            rate_limit = 2.5
+            cache_dir = "/tmp/searchfox-cache"
+            server = "https://searchfox.example.com"
+            "#,
+        )
+        .unwrap();
+        let network = &config.network;
+        assert_eq!(network.timeout, 10);
+        assert_eq!(network.retries, 3);
+        assert_eq!(network.backoff, 1.5);
+        assert_eq!(network.max_backoff, 30.0);
+        assert_eq!(
+            network.proxy.as_deref(),
+            Some("http://proxy.example.com:3128")
+        );
+        assert_eq!(network.rate_limit, Some(2.5));
+        assert_eq!(
+            network.cache_dir.as_deref(),
+            Some(Path::new("/tmp/searchfox-cache"))
+        );
+        assert_eq!(network.cache_ttl, crate::cache::FRESH_SECS);
+        assert!(network.magic_word.is_none());
+        assert_eq!(
+            network.server.as_deref(),
+            Some("https://searchfox.example.com")
+        );
+        assert!(Config::parse(
+            "[network]
+retry = 1
+"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        assert!(Config::parse("[repos.foo]\nbranch = \"main\"\n").is_err());
+    }
+
+    #[test]
+    fn missing_file_is_empty() {
+        let config = Config::load(Path::new("/nonexistent/searchfox-cli/config.toml")).unwrap();
+        assert!(config.repos.is_empty());
+    }
+}
@@ -0,0 +1,368 @@
+use crate::client::SearchfoxClient;
+use crate::search::SearchOptions;
+use crate::types::{File, SearchfoxResponse};
+use anyhow::Result;
+use regex::Regex;
+
+/// Where and how an XPCOM contract ID is registered.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Registration {
+    /// "components.conf", "static module" or "chrome manifest".
+    pub mechanism: &'static str,
+    pub path: String,
+    pub line: usize,
+    /// Implementing class, or JS module for JS components.
+    pub class: Option<String>,
+    pub constructor: Option<String>,
+    pub cid: Option<String>,
+    /// Headers declaring the class, as listed in components.conf.
+    pub headers: Vec<String>,
+}
+
+/// The Python dict literal (`{ ... }`) enclosing `line` (1-based) of a
+/// components.conf file. Braces in strings, like those of CIDs, and in
+/// comments are skipped.
+pub fn enclosing_dict(source: &str, line: usize) -> Option<&str> {
+    let target: usize = source
+        .split_inclusive('\n')
+        .take(line.checked_sub(1)?)
+        .map(str::len)
+        .sum();
+    let mut stack = Vec::new();
+    let mut quote = None;
+    let mut comment = false;
+    let mut open = None;
+    for (i, c) in source.char_indices() {
+        if open.is_none() && i >= target {
+            open = Some(*stack.last()?);
+        }
+        if comment {
+            comment = c != '\n';
+            continue;
+        }
+        if let Some(q) = quote {
+            if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '\'' | '"' => quote = Some(c),
+            '#' => comment = true,
+            '{' => stack.push(i),
+            '}' => {
+                let start = stack.pop()?;
+                if open == Some(start) {
+                    return Some(&source[start..=i]);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The strings a components.conf dict maps `key` to: one for a string
+/// value, each element for a list.
+fn dict_strings(dict: &str, key: &str) -> Vec<String> {
+    let re = Regex::new(&format!(
+        r#"['"]{}['"]\s*:\s*(\[[^\]]*\]|'[^']*'|"[^"]*")"#,
+        regex::escape(key)
+    ))
+    .expect("valid regex");
+    let string_re = Regex::new(r#"'([^']*)'|"([^"]*)""#).expect("valid regex");
+    let Some(value) = re.captures(dict).and_then(|c| c.get(1)) else {
+        return Vec::new();
+    };
+    string_re
+        .captures_iter(value.as_str())
+        .filter_map(|c| c.get(1).or_else(|| c.get(2)))
+        .map(|m| m.as_str().to_string())
+        .collect()
+}
+
+/// Registration described by a components.conf entry.
+pub fn parse_components_conf_entry(dict: &str) -> Registration {
+    let first = |key: &str| dict_strings(dict, key).into_iter().next();
+    Registration {
+        mechanism: "components.conf",
+        class: first("type")
+            .or_else(|| first("esModule"))
+            .or_else(|| first("jsm")),
+        constructor: first("constructor"),
+        cid: first("cid"),
+        headers: dict_strings(dict, "headers"),
+        ..Default::default()
+    }
+}
+
+/// Name of the macro in `#define NAME "contract"`.
+fn define_name(line: &str) -> Option<&str> {
+    let rest = line.trim_start().strip_prefix('#')?.trim_start();
+    let rest = rest.strip_prefix("define")?;
+    rest.split_whitespace().next()
+}
+
+/// CID variable of a static module table entry, e.g. `kNS_FOO_CID` in
+/// `{ NS_FOO_CONTRACTID, &kNS_FOO_CID },`.
+fn table_cid(line: &str) -> Option<&str> {
+    let rest = &line[line.find('&')? + 1..];
+    let end = rest
+        .find(|c: char| !c.is_alphanumeric() && c != '_')
+        .unwrap_or(rest.len());
+    (end > 0).then(|| &rest[..end])
+}
+
+/// Constructor of a static module CID entry such as
+/// `{ &kNS_FOO_CID, false, nullptr, nsFooConstructor },`.
+fn table_constructor(line: &str) -> Option<&str> {
+    let inner = line.trim().trim_end_matches(',').trim_end_matches('}');
+    let last = inner.rsplit(',').next()?.trim();
+    (!last.is_empty()
+        && last != "nullptr"
+        && last
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == ':'))
+    .then_some(last)
+}
+
+/// CID of a `contract @mozilla.org/foo;1 {cid}` chrome manifest line.
+fn manifest_cid(line: &str) -> Option<&str> {
+    let mut words = line.split_whitespace();
+    (words.next()? == "contract").then_some(())?;
+    words.nth(1)
+}
+
+/// Path, line number and text of every line matched by a search.
+pub(crate) fn lines_of(
+    json: &SearchfoxResponse,
+    options: &SearchOptions,
+) -> Vec<(String, usize, String)> {
+    let mut found = Vec::new();
+    let mut add = |file: &serde_json::Value| {
+        let Ok(file) = serde_json::from_value::<File>(file.clone()) else {
+            return;
+        };
+        if options.matches_language_filter(&file.path) {
+            found.extend(
+                file.lines
+                    .into_iter()
+                    .map(|l| (file.path.clone(), l.lno, l.line)),
+            );
+        }
+    };
+    for (category, value) in json {
+        if category.starts_with('*') || !options.category_filter.should_include(category) {
+            continue;
+        }
+        if let Some(file_list) = value.as_array() {
+            file_list.iter().for_each(&mut add);
+        } else if let Some(obj) = value.as_object() {
+            for file_list in obj.values() {
+                file_list
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .for_each(&mut add);
+            }
+        }
+    }
+    found.sort();
+    found.dedup();
+    found
+}
+
+impl SearchfoxClient {
+    /// Lines containing `text`, case-sensitively, within the path, language
+    /// and category filters of `options`.
+    pub(crate) async fn text_lines(
+        &self,
+        text: &str,
+        options: &SearchOptions,
+    ) -> Result<Vec<(String, usize, String)>> {
+        let search = SearchOptions {
+            query: Some(text.to_string()),
+            symbol: None,
+            id: None,
+            regexp: false,
+            case: true,
+            context: None,
+            ..options.clone()
+        };
+        let json = self.search_response(&search).await?;
+        Ok(lines_of(&json, &search))
+    }
+
+    /// Find where contract ID `contract` is registered, following the three
+    /// registration mechanisms: components.conf entries, static module
+    /// tables (`#define ..._CONTRACTID` mapped to a CID and a constructor),
+    /// and `contract` lines of chrome manifests.
+    pub async fn find_contract(
+        &self,
+        contract: &str,
+        options: &SearchOptions,
+    ) -> Result<Vec<Registration>> {
+        let mut registrations = Vec::new();
+        for (path, line, text) in self.text_lines(contract, options).await? {
+            if path.ends_with("components.conf") {
+                let source = self.get_file(&path).await?;
+                if let Some(dict) = enclosing_dict(&source, line) {
+                    registrations.push(Registration {
+                        path,
+                        line,
+                        ..parse_components_conf_entry(dict)
+                    });
+                }
+            } else if let Some(name) = define_name(&text) {
+                let mut registration = Registration {
+                    mechanism: "static module",
+                    path,
+                    line,
+                    class: Some(name.to_string()),
+                    ..Default::default()
+                };
+                let table_entry =
+                    self.text_lines(name, options)
+                        .await?
+                        .into_iter()
+                        .find_map(|(_, _, text)| {
+                            text.contains('{')
+                                .then(|| table_cid(&text))?
+                                .map(String::from)
+                        });
+                if let Some(cid) = table_entry {
+                    registration.constructor = self
+                        .text_lines(&format!("&{cid}"), options)
+                        .await?
+                        .into_iter()
+                        .find_map(|(_, _, text)| table_constructor(&text).map(String::from));
+                    registration.cid = Some(cid);
+                }
+                registrations.push(registration);
+            } else if let Some(cid) = manifest_cid(&text) {
+                let component = self
+                    .text_lines(&format!("component {cid}"), options)
+                    .await?
+                    .into_iter()
+                    .find_map(|(_, _, text)| text.split_whitespace().nth(2).map(String::from));
+                registrations.push(Registration {
+                    mechanism: "chrome manifest",
+                    path,
+                    line,
+                    class: component,
+                    cid: Some(cid.to_string()),
+                    ..Default::default()
+                });
+            }
+        }
+        Ok(registrations)
+    }
+}
+
+pub fn format_registrations(contract: &str, registrations: &[Registration]) -> String {
+    let mut output = format!("# Contract {}\n\n", contract);
+    if registrations.is_empty() {
+        output.push_str("No registration found.\n");
+        return output;
+    }
+    for r in registrations {
+        output.push_str(&format!("## {}: {}:{}\n", r.mechanism, r.path, r.line));
+        if let Some(class) = &r.class {
+            let label = match r.mechanism {
+                "static module" => "Defined as",
+                "chrome manifest" => "Component",
+                _ => "Class",
+            };
+            output.push_str(&format!("- {}: {}\n", label, class));
+        }
+        match (&r.constructor, r.mechanism) {
+            (Some(constructor), _) => output.push_str(&format!("- Constructor: {}\n", constructor)),
+            (None, "components.conf") if r.class.is_some() => {
+                output.push_str("- Constructor: default constructor of the class\n")
+            }
+            _ => {}
+        }
+        if !r.headers.is_empty() {
+            output.push_str(&format!("- Headers: {}\n", r.headers.join(", ")));
+        }
+        if let Some(cid) = &r.cid {
+            output.push_str(&format!("- CID: {}\n", cid));
+        }
+        output.push('\n');
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMPONENTS_CONF: &str = r#"
+Classes = [
+    {
+        'cid': '{4f47e42e-4d23-4dd3-bfda-eb29255e9ea3}',
+        'contract_ids': ['@mozilla.org/network/protocol;1?name=http'],
+        'singleton': True,
+        # Registered early {not a brace}
+        'type': 'mozilla::net::nsHttpHandler',
+        'headers': ['/netwerk/protocol/http/nsHttpHandler.h'],
+        'constructor': 'mozilla::net::nsHttpHandler::GetInstance',
+    },
+    {
+        'cid': '{dccbe7e4-7750-466b-a557-5ea36c8ff24e}',
+        'contract_ids': ['@mozilla.org/network/protocol;1?name=https'],
+        'type': 'mozilla::net::nsHttpsHandler',
+    },
+]
+"#;
+
+    #[test]
+    fn parses_components_conf_entries() {
+        let dict = enclosing_dict(COMPONENTS_CONF, 5).unwrap();
+        let registration = parse_components_conf_entry(dict);
+        assert_eq!(
+            registration.class.as_deref(),
+            Some("mozilla::net::nsHttpHandler")
+        );
+        assert_eq!(
+            registration.constructor.as_deref(),
+            Some("mozilla::net::nsHttpHandler::GetInstance")
+        );
+        assert_eq!(
+            registration.cid.as_deref(),
+            Some("{4f47e42e-4d23-4dd3-bfda-eb29255e9ea3}")
+        );
+        assert_eq!(
+            registration.headers,
+            vec!["/netwerk/protocol/http/nsHttpHandler.h"]
+        );
+
+        let other = parse_components_conf_entry(enclosing_dict(COMPONENTS_CONF, 14).unwrap());
+        assert_eq!(other.class.as_deref(), Some("mozilla::net::nsHttpsHandler"));
+        assert!(other.constructor.is_none());
+    }
+
+    #[test]
+    fn parses_static_and_manifest_registrations() {
+        assert_eq!(
+            define_name(r#"#define NS_FOO_CONTRACTID "@mozilla.org/foo;1""#),
+            Some("NS_FOO_CONTRACTID")
+        );
+        assert_eq!(
+            table_cid("  { NS_FOO_CONTRACTID, &kNS_FOO_CID },"),
+            Some("kNS_FOO_CID")
+        );
+        assert_eq!(
+            table_constructor("  { &kNS_FOO_CID, false, nullptr, nsFooConstructor },"),
+            Some("nsFooConstructor")
+        );
+        assert_eq!(
+            table_constructor("  { NS_FOO_CONTRACTID, &kNS_FOO_CID },"),
+            None
+        );
+        assert_eq!(
+            manifest_cid("contract @mozilla.org/foo;1 {1b8f1bf2-1f8e-4b6c-a1f0-2d3c4e5f6a7b}"),
+            Some("{1b8f1bf2-1f8e-4b6c-a1f0-2d3c4e5f6a7b}")
+        );
+    }
+}
@@ -0,0 +1,181 @@
+use anyhow::{bail, Result};
+
+/// Characters an operator name can be made of, as in `operator<<=`.
+const OPERATOR_CHARS: &str = "()[]<>=!+-*/%&|^~,";
+
+/// Qualifiers that can follow the argument list of a frame.
+const TRAILING_QUALIFIERS: &[&str] = &["const", "volatile", "&", "&&", "noexcept"];
+
+/// Remove `(...)` and `<...>` groups (arguments, template arguments,
+/// `(anonymous namespace)`), keeping operator names such as `operator()`
+/// and `operator<`.
+fn strip_groups(frame: &str) -> String {
+    let chars: Vec<char> = frame.chars().collect();
+    let mut output = String::new();
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if depth == 0 && output.ends_with("operator") {
+            if chars[i..].starts_with(&['(', ')']) {
+                output.push_str("()");
+                i += 2;
+                continue;
+            }
+            let start = i;
+            while i < chars.len() && chars[i] != '(' && OPERATOR_CHARS.contains(chars[i]) {
+                i += 1;
+            }
+            if i > start {
+                output.extend(&chars[start..i]);
+                continue;
+            }
+        }
+        match c {
+            '(' | '<' => depth += 1,
+            ')' | '>' if depth > 0 => depth -= 1,
+            _ if depth == 0 => output.push(c),
+            _ => {}
+        }
+        i += 1;
+    }
+    output
+}
+
+/// Whether a `::` component of a frame names something searchfox indexes,
+/// rather than a lambda or closure (`$_0`, `{lambda()#1}`, `{{closure}}`)
+/// or a Rust symbol hash (`h0123456789abcdef`).
+fn is_named_component(component: &str) -> bool {
+    let is_hash = component.len() == 17
+        && component.starts_with('h')
+        && component[1..].chars().all(|c| c.is_ascii_hexdigit());
+    !component.is_empty()
+        && !component.starts_with(['$', '{', '\''])
+        && !component.starts_with("lambda_")
+        && !is_hash
+}
+
+/// Turn a Socorro-style crash frame into a qualified name: drop the module
+/// prefix (`xul.dll!`), the source location and offset suffixes, virtual
+/// thunk wrappers, argument lists, template arguments, anonymous
+/// namespaces, lambdas and Rust symbol hashes.
+pub fn normalize_frame(frame: &str) -> Result<String> {
+    let mut frame = frame.trim();
+    if let Some((module, rest)) = frame.split_once('!') {
+        if !module.contains([':', '(', '<', ' ']) && !rest.starts_with('=') {
+            frame = rest;
+        }
+    }
+    for suffix in [" [", " + 0x"] {
+        if let Some(pos) = frame.find(suffix) {
+            frame = &frame[..pos];
+        }
+    }
+    if frame.starts_with("0x") || frame.contains("@0x") {
+        bail!(
+            "'{}' is an address, not a symbol: the module had no symbols in the crash report",
+            frame
+        );
+    }
+    // {virtual override thunk({offset(-8)}, mozilla::Foo::Bar)}
+    if let Some(inner) = frame.strip_prefix('{').and_then(|f| f.strip_suffix('}')) {
+        if inner.contains("thunk") {
+            if let Some(target) = inner.rsplit(", ").next() {
+                frame = target.trim_end_matches(')');
+            }
+        }
+    }
+
+    let stripped = strip_groups(frame);
+    let name = stripped
+        .split_whitespace()
+        .rfind(|word| !TRAILING_QUALIFIERS.contains(word))
+        .unwrap_or_default();
+    let components: Vec<&str> = name.split("::").filter(|c| is_named_component(c)).collect();
+    if components.is_empty() {
+        bail!("Could not find a symbol name in frame '{}'", frame);
+    }
+    Ok(components.join("::"))
+}
+
+/// Names to look a normalized frame up with: the full qualified name, then
+/// shorter suffixes down to `Class::Method`, for frames whose namespaces
+/// searchfox qualifies differently.
+pub fn frame_candidates(symbol: &str) -> Vec<String> {
+    let components: Vec<&str> = symbol.split("::").collect();
+    let shortest = components.len().min(2);
+    (0..=components.len() - shortest)
+        .map(|start| components[start..].join("::"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_cpp_frames() {
+        for (frame, expected) in [
+            (
+                "mozilla::dom::AudioContext::CreateGain(mozilla::ErrorResult&)",
+                "mozilla::dom::AudioContext::CreateGain",
+            ),
+            (
+                "xul.dll!nsTArray_Impl<RefPtr<nsIRunnable>, nsTArrayInfallibleAllocator>::AppendElement<nsIRunnable*>(nsIRunnable*&&) [nsTArray.h:abcdef : 2570 + 0x5]",
+                "nsTArray_Impl::AppendElement",
+            ),
+            (
+                "static void mozilla::(anonymous namespace)::Helper::Run() const",
+                "mozilla::Helper::Run",
+            ),
+            (
+                "mozilla::MediaDecoder::Shutdown()::$_0::operator()() const",
+                "mozilla::MediaDecoder::Shutdown::operator()",
+            ),
+            (
+                "{virtual override thunk({offset(-8)}, mozilla::net::HttpChannelChild::Release())}",
+                "mozilla::net::HttpChannelChild::Release",
+            ),
+            ("nsCOMPtr<nsIFoo>::operator->() const", "nsCOMPtr::operator->"),
+        ] {
+            assert_eq!(normalize_frame(frame).unwrap(), expected, "{frame}");
+        }
+    }
+
+    #[test]
+    fn normalizes_rust_frames() {
+        assert_eq!(
+            normalize_frame("core::ptr::drop_in_place<style::gecko::data::PerDocumentStyleData>")
+                .unwrap(),
+            "core::ptr::drop_in_place"
+        );
+        assert_eq!(
+            normalize_frame("neqo_transport::connection::Connection::process::h0123456789abcdef")
+                .unwrap(),
+            "neqo_transport::connection::Connection::process"
+        );
+        assert_eq!(
+            normalize_frame("<audioipc::Foo as core::ops::Drop>::drop::{{closure}}").unwrap(),
+            "drop"
+        );
+    }
+
+    #[test]
+    fn rejects_unsymbolicated_frames() {
+        assert!(normalize_frame("libxul.so@0x1234abcd").is_err());
+        assert!(normalize_frame("0x7ffe1234").is_err());
+    }
+
+    #[test]
+    fn candidates_shorten_to_class_and_method() {
+        assert_eq!(
+            frame_candidates("mozilla::dom::AudioContext::CreateGain"),
+            vec![
+                "mozilla::dom::AudioContext::CreateGain",
+                "dom::AudioContext::CreateGain",
+                "AudioContext::CreateGain",
+            ]
+        );
+        assert_eq!(frame_candidates("NS_InitXPCOM"), vec!["NS_InitXPCOM"]);
+    }
+}
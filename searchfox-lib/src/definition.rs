@@ -1,10 +1,38 @@
 use crate::client::SearchfoxClient;
-use crate::search::SearchOptions;
+use crate::progress::ProgressEvent;
+use crate::search::{declaration_candidates, definition_candidates, SearchOptions};
 use crate::utils::{
     extract_complete_method, find_symbol_in_local_content, is_mozilla_repository, read_local_file,
 };
 use anyhow::Result;
 use log::{debug, error};
+use regex::Regex;
+
+/// Lines fetched past a definition's first line before looking for its end.
+const HEAD_MARGIN: usize = 300;
+
+/// Lines a declaration may span, e.g. with long lists of default arguments.
+const MAX_DECLARATION_LINES: usize = 20;
+
+/// One place where a symbol is defined.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Definition {
+    pub path: String,
+    pub line: usize,
+    /// The first line of the definition, trimmed.
+    pub signature: String,
+    /// Numbered source of the definition, as returned by
+    /// `get_definition_context`, when it was asked for.
+    pub source: Option<String>,
+}
+
+/// Whether the method starting at `line_number` is still open on the last
+/// line of `content`.
+fn method_reaches_end(content: &str, line_number: usize) -> bool {
+    let lines: Vec<&str> = content.lines().collect();
+    let (_, method_lines) = extract_complete_method(&lines, line_number);
+    line_number + method_lines.len() > lines.len()
+}
 
 impl SearchfoxClient {
     pub async fn get_definition_context(
@@ -71,7 +99,17 @@ impl SearchfoxClient {
             }
         }
 
-        let file_content = self.get_file(file_path).await?;
+        // Only download the file up to a little past the definition, unless
+        // the definition turns out to run past that.
+        let head = self
+            .get_file_head(file_path, line_number + context_lines.max(HEAD_MARGIN))
+            .await?;
+        let file_content = if head.truncated && method_reaches_end(&head.content, line_number) {
+            debug!("Definition in {} runs past the fetched lines", file_path);
+            self.get_file(file_path).await?
+        } else {
+            head.content
+        };
         let lines: Vec<&str> = file_content.lines().collect();
 
         let (_, method_lines) = extract_complete_method(&lines, line_number);
@@ -103,12 +141,14 @@ impl SearchfoxClient {
         Ok(result)
     }
 
-    pub async fn find_and_display_definition(
+    /// Each definition of `symbol`: its path, line, and numbered source, as
+    /// returned by `get_definition_context`.
+    pub async fn find_definitions(
         &self,
         symbol: &str,
         path_filter: Option<&str>,
         options: &SearchOptions,
-    ) -> Result<String> {
+    ) -> Result<Vec<(String, usize, String)>> {
         debug!("Finding potential definition locations...");
         let file_locations = self
             .find_symbol_locations(symbol, path_filter, options)
@@ -116,7 +156,7 @@ impl SearchfoxClient {
 
         if file_locations.is_empty() {
             error!("No potential definitions found for '{symbol}'");
-            return Ok(String::new());
+            return Ok(Vec::new());
         }
 
         debug!(
@@ -131,31 +171,313 @@ impl SearchfoxClient {
             class_name == method_part
         });
 
+        let operation = format!("define {symbol}");
+        let total = file_locations.len();
+        self.emit(ProgressEvent::Started {
+            operation: operation.clone(),
+            total,
+        });
+
         let mut results = Vec::new();
-        for (file_path, line_number) in &file_locations {
+        for (completed, (file_path, line_number)) in (1..).zip(file_locations) {
             let context_lines = if is_ctor { 2 } else { 10 };
             match self
-                .get_definition_context(file_path, *line_number, context_lines, Some(symbol))
+                .get_definition_context(&file_path, line_number, context_lines, Some(symbol))
                 .await
             {
                 Ok(context) => {
                     if !context.is_empty() {
-                        results.push(context);
+                        results.push((file_path, line_number, context));
                     }
                 }
                 Err(e) => {
                     error!("Could not fetch context: {e}");
                 }
             }
+            self.emit(ProgressEvent::Advanced {
+                operation: operation.clone(),
+                completed,
+                total,
+            });
         }
+        self.emit(ProgressEvent::Finished { operation });
 
         if results.is_empty() {
             error!("No definition found for symbol '{symbol}'");
-            Ok(String::new())
-        } else if results.len() == 1 {
-            Ok(results[0].clone())
-        } else {
-            Ok(results.join("\n\n"))
         }
+        Ok(results)
+    }
+
+    /// Every definition of `symbol`, for overloaded or multiply-defined
+    /// symbols, where `find_definitions` keeps the first one. With
+    /// `with_source`, the source of each one is fetched too.
+    pub async fn find_all_definitions(
+        &self,
+        symbol: &str,
+        path_filter: Option<&str>,
+        options: &SearchOptions,
+        with_source: bool,
+    ) -> Result<Vec<Definition>> {
+        let json = self.definition_response(symbol, path_filter).await?;
+        let mut definitions: Vec<Definition> = definition_candidates(&json, symbol, options)
+            .into_iter()
+            .map(|(path, line, signature)| Definition {
+                path,
+                line,
+                signature,
+                source: None,
+            })
+            .collect();
+        if definitions.is_empty() {
+            debug!("No definition results for '{symbol}', falling back to a single location");
+            definitions = self
+                .find_symbol_locations(symbol, path_filter, options)
+                .await?
+                .into_iter()
+                .map(|(path, line)| Definition {
+                    path,
+                    line,
+                    signature: String::new(),
+                    source: None,
+                })
+                .collect();
+        }
+        if !with_source || definitions.is_empty() {
+            return Ok(definitions);
+        }
+
+        let operation = format!("define {symbol}");
+        let total = definitions.len();
+        self.emit(ProgressEvent::Started {
+            operation: operation.clone(),
+            total,
+        });
+        for (completed, definition) in (1..).zip(definitions.iter_mut()) {
+            match self
+                .get_definition_context(&definition.path, definition.line, 10, Some(symbol))
+                .await
+            {
+                Ok(source) => {
+                    if definition.signature.is_empty() {
+                        definition.signature = parse_definition_lines(&source)
+                            .into_iter()
+                            .find(|(number, _)| *number == definition.line)
+                            .map(|(_, text)| text.trim().to_string())
+                            .unwrap_or_default();
+                    }
+                    definition.source = Some(source);
+                }
+                Err(e) => {
+                    error!("Could not fetch context: {e}");
+                }
+            }
+            self.emit(ProgressEvent::Advanced {
+                operation: operation.clone(),
+                completed,
+                total,
+            });
+        }
+        self.emit(ProgressEvent::Finished { operation });
+        Ok(definitions)
+    }
+
+    /// Each declaration of `symbol`, with its doc comment and the line
+    /// opening its class, as numbered source in `source`. Symbols without
+    /// a separate declaration, such as inline methods, give their
+    /// definitions instead.
+    pub async fn find_declarations(
+        &self,
+        symbol: &str,
+        path_filter: Option<&str>,
+        options: &SearchOptions,
+    ) -> Result<Vec<Definition>> {
+        let json = self.definition_response(symbol, path_filter).await?;
+        let mut candidates = declaration_candidates(&json, symbol, options);
+        if candidates.is_empty() {
+            debug!("No declaration results for '{symbol}', using its definitions");
+            candidates = definition_candidates(&json, symbol, options);
+        }
+        let class_name = symbol.rsplit("::").nth(1);
+
+        let operation = format!("declare {symbol}");
+        let total = candidates.len();
+        self.emit(ProgressEvent::Started {
+            operation: operation.clone(),
+            total,
+        });
+        let mut declarations = Vec::new();
+        for (completed, (path, line, signature)) in (1..).zip(candidates) {
+            match self.get_file_head(&path, line + HEAD_MARGIN).await {
+                Ok(head) => {
+                    let lines: Vec<&str> = head.content.lines().collect();
+                    let source = declaration_context(&lines, line, class_name);
+                    declarations.push(Definition {
+                        path,
+                        line,
+                        signature,
+                        source: Some(source),
+                    });
+                }
+                Err(e) => {
+                    error!("Could not fetch context: {e}");
+                }
+            }
+            self.emit(ProgressEvent::Advanced {
+                operation: operation.clone(),
+                completed,
+                total,
+            });
+        }
+        self.emit(ProgressEvent::Finished { operation });
+        Ok(declarations)
+    }
+
+    pub async fn find_and_display_definition(
+        &self,
+        symbol: &str,
+        path_filter: Option<&str>,
+        options: &SearchOptions,
+    ) -> Result<String> {
+        let definitions = self.find_definitions(symbol, path_filter, options).await?;
+        let contexts: Vec<String> = definitions
+            .into_iter()
+            .map(|(_, _, context)| context)
+            .collect();
+        Ok(contexts.join("\n\n"))
+    }
+}
+
+/// Line numbers and text of definition source as returned by
+/// `get_definition_context`, where lines look like `>>>  469: code`.
+pub fn parse_definition_lines(context: &str) -> Vec<(usize, String)> {
+    context
+        .lines()
+        .filter_map(|line| {
+            let (number, text) = line.get(3..)?.split_once(':')?;
+            let number = number.trim().parse().ok()?;
+            Some((number, text.strip_prefix(' ').unwrap_or(text).to_string()))
+        })
+        .collect()
+}
+
+/// Whether a line is part of the comment or template header preceding a
+/// declaration.
+fn is_declaration_preamble(line: &str) -> bool {
+    let line = line.trim();
+    line.starts_with("//")
+        || line.starts_with("/*")
+        || line.starts_with('*')
+        || line.ends_with("*/")
+        || line.starts_with("template")
+}
+
+/// Where the declaration starting at index `start` of `lines` ends: at its
+/// `;`, or at the closing brace of an inline definition.
+fn declaration_end(lines: &[&str], start: usize) -> usize {
+    let last = (start + MAX_DECLARATION_LINES).min(lines.len() - 1);
+    let Some(end) = (start..=last).find(|&i| lines[i].contains(';') || lines[i].contains('{'))
+    else {
+        return start;
+    };
+    if !lines[end].contains('{') {
+        return end;
+    }
+    let mut depth = 0i32;
+    for (i, line) in lines.iter().enumerate().skip(end).take(HEAD_MARGIN) {
+        depth += line.matches('{').count() as i32 - line.matches('}').count() as i32;
+        if depth <= 0 {
+            return i;
+        }
+    }
+    end
+}
+
+/// The declaration at `line_number` of `lines`, numbered like
+/// `get_definition_context` output: its doc comment, the declaration through
+/// its `;` (or inline body), and when `class_name` is given, the line opening
+/// that class, separated by `...` when they are apart.
+pub fn declaration_context(lines: &[&str], line_number: usize, class_name: Option<&str>) -> String {
+    let index = line_number.saturating_sub(1);
+    if index >= lines.len() {
+        return String::new();
+    }
+    let end = declaration_end(lines, index);
+    let mut start = index;
+    while start > 0 && is_declaration_preamble(lines[start - 1]) {
+        start -= 1;
+    }
+
+    let class_line = class_name.and_then(|class| {
+        let pattern = format!(
+            r"^\s*(?:class|struct)\s[^;]*\b{}\b[^;]*$",
+            regex::escape(class)
+        );
+        let re = Regex::new(&pattern).ok()?;
+        (0..start).rev().find(|&i| re.is_match(lines[i]))
+    });
+
+    let mut result = String::new();
+    if let Some(i) = class_line {
+        result.push_str(&format!("    {:4}: {}\n", i + 1, lines[i]));
+        if i + 1 < start {
+            result.push_str("          ...\n");
+        }
+    }
+    for (i, line) in lines.iter().enumerate().take(end + 1).skip(start) {
+        let marker = if i == index { ">>>" } else { "   " };
+        result.push_str(&format!("{marker} {:4}: {line}\n", i + 1));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shows_declarations_with_their_class() {
+        let header = "\
+class AudioNodeTrack;
+
+class AudioNode : public DOMEventTargetHelper {
+ public:
+  AudioNode(AudioContext* aContext);
+
+  /**
+   * Connect to another node.
+   */
+  AudioNode* Connect(AudioNode& aDestination, uint32_t aOutput = 0,
+                     uint32_t aInput = 0);
+  uint16_t Id() const { return mId; }
+};";
+        let lines: Vec<&str> = header.lines().collect();
+        assert_eq!(
+            declaration_context(&lines, 10, Some("AudioNode")),
+            "       3: class AudioNode : public DOMEventTargetHelper {
+          ...
+       7:   /**
+       8:    * Connect to another node.
+       9:    */
+>>>   10:   AudioNode* Connect(AudioNode& aDestination, uint32_t aOutput = 0,
+      11:                      uint32_t aInput = 0);
+"
+        );
+        assert_eq!(
+            declaration_context(&lines, 12, None),
+            ">>>   12:   uint16_t Id() const { return mId; }\n"
+        );
+    }
+
+    #[test]
+    fn parses_definition_lines() {
+        let context = ">>>  469: void AudioContext::Close() {\n     470: }\n     471: \n";
+        assert_eq!(
+            parse_definition_lines(context),
+            vec![
+                (469, "void AudioContext::Close() {".to_string()),
+                (470, "}".to_string()),
+                (471, String::new()),
+            ]
+        );
     }
 }
@@ -0,0 +1,57 @@
+use cpp_demangle::{DemangleOptions, Symbol};
+use regex::{Captures, Regex};
+use std::borrow::Cow;
+
+/// The C++ signature of an Itanium-mangled symbol such as
+/// `_ZN7mozilla3dom12AudioContext10CreateGainERNS_11ErrorResultE`, None for
+/// anything else: C functions, JS and Rust symbols, searchfox's `T_` names.
+pub fn demangle(symbol: &str) -> Option<String> {
+    if !symbol.starts_with("_Z") {
+        return None;
+    }
+    Symbol::new(symbol.as_bytes())
+        .ok()?
+        .demangle(&DemangleOptions::default().no_return_type())
+        .ok()
+}
+
+/// `text` with every mangled C++ symbol it contains replaced by its
+/// signature. Symbols that do not demangle are left as they are.
+pub fn demangle_text(text: &str) -> Cow<'_, str> {
+    if !text.contains("_Z") {
+        return Cow::Borrowed(text);
+    }
+    let re = Regex::new(r"\b_Z[A-Za-z0-9_]+").expect("valid regex");
+    re.replace_all(text, |captures: &Captures| {
+        let symbol = &captures[0];
+        demangle(symbol).unwrap_or_else(|| symbol.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demangles_cpp_symbols_only() {
+        assert_eq!(
+            demangle("_ZN7mozilla3dom12AudioContext10CreateGainERNS_11ErrorResultE").as_deref(),
+            Some("mozilla::dom::AudioContext::CreateGain(mozilla::ErrorResult&)")
+        );
+        assert_eq!(demangle("PR_Sleep"), None);
+        assert_eq!(demangle("T_mozilla::dom::AudioContext"), None);
+        assert_eq!(demangle("_ZNotMangled"), None);
+
+        assert_eq!(
+            demangle_text(
+                "- Shutdown (`_ZN7mozilla11AudioStream8ShutdownENS0_13ShutdownCauseE`, dom/media/AudioStream.cpp#300)"
+            ),
+            "- Shutdown (`mozilla::AudioStream::Shutdown(mozilla::AudioStream::ShutdownCause)`, dom/media/AudioStream.cpp#300)"
+        );
+        assert_eq!(
+            demangle_text("  - From: `_ZChildDoFoo`"),
+            "  - From: `_ZChildDoFoo`"
+        );
+        assert!(matches!(demangle_text("no symbols"), Cow::Borrowed(_)));
+    }
+}
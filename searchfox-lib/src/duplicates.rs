@@ -0,0 +1,188 @@
+use crate::client::SearchfoxClient;
+use crate::search::SearchOptions;
+use crate::types::{File, SearchfoxResponse};
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+/// A distinct definition of an identifier, e.g. `mozilla::AudioSink::Shutdown`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateDefinition {
+    pub qualified_name: String,
+    /// `(path, line number)` of each definition site.
+    pub locations: Vec<(String, usize)>,
+}
+
+/// Split a qualified name into its enclosing scope and last component.
+/// C++ (`a::B::f`) and JS (`B#f`, `B.f`) separators are recognized.
+fn split_scope(qualified: &str) -> (&str, &str) {
+    ["::", "#", "."]
+        .iter()
+        .filter_map(|sep| qualified.rfind(sep).map(|pos| (pos, sep.len())))
+        .max_by_key(|(pos, _)| *pos)
+        .map(|(pos, len)| (&qualified[..pos], &qualified[pos + len..]))
+        .unwrap_or(("", qualified))
+}
+
+/// Group the definitions of `name` found in an identifier search by scope.
+/// Only symbols whose last component is exactly `name` are kept, so searching
+/// `Shutdown` does not also list `ShutdownThreads`.
+pub fn collect_duplicates(
+    json: &SearchfoxResponse,
+    name: &str,
+    options: &SearchOptions,
+) -> BTreeMap<String, Vec<DuplicateDefinition>> {
+    let mut definitions: BTreeMap<String, Vec<(String, usize)>> = BTreeMap::new();
+    for (category, value) in json {
+        if category.starts_with('*') || !options.category_filter.should_include(category) {
+            continue;
+        }
+        let Some(obj) = value.as_object() else {
+            continue;
+        };
+        for (label, file_list) in obj {
+            let Some(qualified) = label
+                .strip_prefix("Definitions (")
+                .and_then(|rest| rest.strip_suffix(')'))
+            else {
+                continue;
+            };
+            if split_scope(qualified).1 != name {
+                continue;
+            }
+            for file in file_list.as_array().into_iter().flatten() {
+                let Ok(file) = serde_json::from_value::<File>(file.clone()) else {
+                    continue;
+                };
+                if !options.matches_language_filter(&file.path) {
+                    continue;
+                }
+                let locations = definitions.entry(qualified.to_string()).or_default();
+                for line in &file.lines {
+                    locations.push((file.path.clone(), line.lno));
+                }
+            }
+        }
+    }
+
+    let mut by_scope: BTreeMap<String, Vec<DuplicateDefinition>> = BTreeMap::new();
+    for (qualified_name, mut locations) in definitions {
+        if locations.is_empty() {
+            continue;
+        }
+        locations.sort();
+        locations.dedup();
+        let scope = split_scope(&qualified_name).0.to_string();
+        by_scope
+            .entry(scope)
+            .or_default()
+            .push(DuplicateDefinition {
+                qualified_name,
+                locations,
+            });
+    }
+    by_scope
+}
+
+impl SearchfoxClient {
+    /// List every distinct definition sharing the identifier `name`, grouped
+    /// by enclosing namespace or class. `options.path` narrows the search.
+    pub async fn find_duplicates(
+        &self,
+        name: &str,
+        options: &SearchOptions,
+    ) -> Result<BTreeMap<String, Vec<DuplicateDefinition>>> {
+        let id_options = SearchOptions {
+            query: None,
+            symbol: None,
+            id: Some(name.to_string()),
+            ..options.clone()
+        };
+        let json = self.search_response(&id_options).await?;
+        Ok(collect_duplicates(&json, name, &id_options))
+    }
+}
+
+pub fn format_duplicates(
+    name: &str,
+    by_scope: &BTreeMap<String, Vec<DuplicateDefinition>>,
+) -> String {
+    let count: usize = by_scope.values().map(Vec::len).sum();
+    let mut output = format!("# Definitions of {}\n\n", name);
+    if count == 0 {
+        output.push_str("No definitions found.\n");
+        return output;
+    }
+    output.push_str(&format!(
+        "{} distinct definition{} in {} scope{}\n",
+        count,
+        if count == 1 { "" } else { "s" },
+        by_scope.len(),
+        if by_scope.len() == 1 { "" } else { "s" }
+    ));
+    for (scope, definitions) in by_scope {
+        let scope = if scope.is_empty() { "(global)" } else { scope };
+        output.push_str(&format!("\n## {}\n\n", scope));
+        for definition in definitions {
+            output.push_str(&format!("- {}\n", definition.qualified_name));
+            for (path, line) in &definition.locations {
+                output.push_str(&format!("  - {}:{}\n", path, line));
+            }
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_scopes() {
+        assert_eq!(
+            split_scope("mozilla::AudioSink::Shutdown"),
+            ("mozilla::AudioSink", "Shutdown")
+        );
+        assert_eq!(split_scope("Shutdown"), ("", "Shutdown"));
+        assert_eq!(
+            split_scope("PeerConnection#close"),
+            ("PeerConnection", "close")
+        );
+    }
+
+    #[test]
+    fn groups_exact_matches_by_scope() {
+        let json: SearchfoxResponse = serde_json::from_str(
+            r#"{
+                "*timedout*": false,
+                "normal": {
+                    "Definitions (mozilla::AudioSink::Shutdown)": [
+                        {"path": "dom/media/mediasink/AudioSink.cpp", "lines": [{"lno": 80, "line": "void AudioSink::Shutdown() {"}]}
+                    ],
+                    "Definitions (mozilla::VideoSink::Shutdown)": [
+                        {"path": "dom/media/mediasink/VideoSink.cpp", "lines": [{"lno": 120, "line": "void VideoSink::Shutdown() {"}]}
+                    ],
+                    "Definitions (mozilla::ShutdownThreads)": [
+                        {"path": "xpcom/Threads.cpp", "lines": [{"lno": 5, "line": "void ShutdownThreads() {"}]}
+                    ],
+                    "Uses (mozilla::AudioSink::Shutdown)": [
+                        {"path": "dom/media/MediaDecoder.cpp", "lines": [{"lno": 9, "line": "mSink->Shutdown();"}]}
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+        let by_scope = collect_duplicates(&json, "Shutdown", &SearchOptions::default());
+        assert_eq!(
+            by_scope.keys().collect::<Vec<_>>(),
+            vec!["mozilla::AudioSink", "mozilla::VideoSink"]
+        );
+        assert_eq!(
+            by_scope["mozilla::AudioSink"][0].locations,
+            vec![("dom/media/mediasink/AudioSink.cpp".to_string(), 80)]
+        );
+
+        let text = format_duplicates("Shutdown", &by_scope);
+        assert!(text.contains("2 distinct definitions in 2 scopes"));
+        assert!(text.contains("## mozilla::VideoSink"));
+    }
+}
@@ -0,0 +1,185 @@
+use reqwest::StatusCode;
+use serde::Serialize;
+use std::fmt;
+
+/// A request answered with a non-success HTTP status.
+#[derive(Debug)]
+pub struct RequestError {
+    pub url: String,
+    pub status: StatusCode,
+}
+
+impl RequestError {
+    pub fn new(url: impl Into<String>, status: StatusCode) -> Self {
+        Self {
+            url: url.into(),
+            status,
+        }
+    }
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Request failed: {}", self.status)
+    }
+}
+
+impl std::error::Error for RequestError {}
+
+/// A file that is not text, refused where text is expected.
+#[derive(Debug)]
+pub struct BinaryFileError {
+    pub path: String,
+}
+
+impl fmt::Display for BinaryFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is a binary file", self.path)
+    }
+}
+
+impl std::error::Error for BinaryFileError {}
+
+/// A query that ran fine but matched nothing, which the CLI exits with
+/// status 1 for, like grep.
+#[derive(Debug, Default)]
+pub struct NoMatchesError {
+    /// What to tell the user, None when the output already says it.
+    pub message: Option<String>,
+}
+
+impl NoMatchesError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: Some(message.into()),
+        }
+    }
+}
+
+impl fmt::Display for NoMatchesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message.as_deref().unwrap_or("No matches"))
+    }
+}
+
+impl std::error::Error for NoMatchesError {}
+
+/// Structured description of an error, for tools wrapping the CLI.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct ErrorReport {
+    /// One of `not_found`, `rate_limited`, `client_error`, `server_error`,
+    /// `network`, `binary_file`, `no_matches` or `other`.
+    pub kind: &'static str,
+    pub message: String,
+    pub url: Option<String>,
+    pub status: Option<u16>,
+}
+
+fn status_kind(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::NOT_FOUND => "not_found",
+        StatusCode::TOO_MANY_REQUESTS => "rate_limited",
+        s if s.is_server_error() => "server_error",
+        _ => "client_error",
+    }
+}
+
+pub fn error_report(e: &anyhow::Error) -> ErrorReport {
+    let message = format!("{:#}", e);
+    for cause in e.chain() {
+        if let Some(re) = cause.downcast_ref::<RequestError>() {
+            return ErrorReport {
+                kind: status_kind(re.status),
+                message,
+                url: Some(re.url.clone()),
+                status: Some(re.status.as_u16()),
+            };
+        }
+        if cause.is::<BinaryFileError>() {
+            return ErrorReport {
+                kind: "binary_file",
+                message,
+                url: None,
+                status: None,
+            };
+        }
+        if cause.is::<NoMatchesError>() {
+            return ErrorReport {
+                kind: "no_matches",
+                message,
+                url: None,
+                status: None,
+            };
+        }
+        if let Some(re) = cause.downcast_ref::<reqwest::Error>() {
+            return ErrorReport {
+                kind: re.status().map_or("network", status_kind),
+                message,
+                url: re.url().map(|u| u.to_string()),
+                status: re.status().map(|s| s.as_u16()),
+            };
+        }
+    }
+    ErrorReport {
+        kind: "other",
+        message,
+        url: None,
+        status: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_request_errors() {
+        let e: anyhow::Error = RequestError::new(
+            "https://searchfox.org/mozilla-central/search?q=x",
+            StatusCode::TOO_MANY_REQUESTS,
+        )
+        .into();
+        assert_eq!(e.to_string(), "Request failed: 429 Too Many Requests");
+        let report = error_report(&e.context("Search failed"));
+        assert_eq!(report.kind, "rate_limited");
+        assert_eq!(report.status, Some(429));
+        assert_eq!(
+            report.url.as_deref(),
+            Some("https://searchfox.org/mozilla-central/search?q=x")
+        );
+        assert_eq!(
+            report.message,
+            "Search failed: Request failed: 429 Too Many Requests"
+        );
+    }
+
+    #[test]
+    fn reports_binary_files() {
+        let e: anyhow::Error = BinaryFileError {
+            path: "browser/branding/official/firefox.icns".into(),
+        }
+        .into();
+        let report = error_report(&e);
+        assert_eq!(report.kind, "binary_file");
+        assert_eq!(
+            report.message,
+            "browser/branding/official/firefox.icns is a binary file"
+        );
+    }
+
+    #[test]
+    fn reports_queries_matching_nothing() {
+        let e: anyhow::Error = NoMatchesError::new("No uses found for Foo").into();
+        let report = error_report(&e);
+        assert_eq!(report.kind, "no_matches");
+        assert_eq!(report.message, "No uses found for Foo");
+        assert_eq!(NoMatchesError::default().to_string(), "No matches");
+    }
+
+    #[test]
+    fn other_errors_have_no_status() {
+        let report = error_report(&anyhow::anyhow!("Invalid line number: 'x'"));
+        assert_eq!(report.kind, "other");
+        assert!(report.url.is_none() && report.status.is_none());
+    }
+}
@@ -0,0 +1,241 @@
+use crate::client::SearchfoxClient;
+use crate::nesting::EnclosingBlock;
+use crate::search::SearchOptions;
+use crate::types::{File, SearchfoxResponse};
+use crate::utils::path_category;
+use anyhow::Result;
+use futures::stream::{self, StreamExt};
+use std::collections::HashMap;
+
+const CONTEXT_LINES: usize = 3;
+const CONCURRENT_FETCHES: usize = 8;
+
+/// A call site of a symbol, shown as a usage example.
+#[derive(Debug, Clone)]
+pub struct UsageExample {
+    pub path: String,
+    pub line: usize,
+    pub is_test: bool,
+    /// Function containing the call site, when known.
+    pub block: Option<EnclosingBlock>,
+    /// `(line number, text)` of the lines around the call site.
+    pub context: Vec<(usize, String)>,
+}
+
+impl UsageExample {
+    fn block_len(&self) -> usize {
+        self.block
+            .as_ref()
+            .map_or(usize::MAX, |b| b.end.saturating_sub(b.start) + 1)
+    }
+}
+
+/// Call sites of a symbol in an identifier search.
+pub fn collect_call_sites(json: &SearchfoxResponse, options: &SearchOptions) -> Vec<UsageExample> {
+    let mut sites = Vec::new();
+    for (category, value) in json {
+        if category.starts_with('*') || !options.category_filter.should_include(category) {
+            continue;
+        }
+        let Some(obj) = value.as_object() else {
+            continue;
+        };
+        for (label, file_list) in obj {
+            if !label.starts_with("Uses") {
+                continue;
+            }
+            for file in file_list.as_array().into_iter().flatten() {
+                let Ok(file) = serde_json::from_value::<File>(file.clone()) else {
+                    continue;
+                };
+                if !options.matches_language_filter(&file.path) {
+                    continue;
+                }
+                let is_test = category == "test" || path_category(&file.path) == "test";
+                for line in &file.lines {
+                    sites.push(UsageExample {
+                        path: file.path.clone(),
+                        line: line.lno,
+                        is_test,
+                        block: None,
+                        context: Vec::new(),
+                    });
+                }
+            }
+        }
+    }
+    sites
+}
+
+/// Order call sites from most to least useful as examples: non-test code
+/// first, then spreading picks across directories, then shorter enclosing
+/// functions.
+pub fn rank_examples(mut sites: Vec<UsageExample>) -> Vec<UsageExample> {
+    sites.sort_by(|a, b| {
+        a.is_test
+            .cmp(&b.is_test)
+            .then(a.block_len().cmp(&b.block_len()))
+            .then(a.path.cmp(&b.path))
+            .then(a.line.cmp(&b.line))
+    });
+
+    // Rank of each site among those of its directory, so that the n-th site
+    // of a directory only comes after every directory has had n picks.
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut ranked: Vec<(usize, UsageExample)> = sites
+        .into_iter()
+        .map(|site| {
+            let dir = site
+                .path
+                .rsplit_once('/')
+                .map(|(dir, _)| dir)
+                .unwrap_or(".");
+            let occurrence = seen.entry(dir.to_string()).or_default();
+            *occurrence += 1;
+            (*occurrence, site)
+        })
+        .collect();
+    ranked.sort_by_key(|(occurrence, site)| (site.is_test, *occurrence));
+    ranked.into_iter().map(|(_, site)| site).collect()
+}
+
+impl SearchfoxClient {
+    /// Pick up to `count` call sites of `symbol` worth reading to learn how
+    /// it is used, each with a few lines of surrounding context.
+    pub async fn usage_examples(
+        &self,
+        symbol: &str,
+        count: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<UsageExample>> {
+        let id_options = SearchOptions {
+            query: None,
+            symbol: None,
+            id: Some(symbol.to_string()),
+            ..options.clone()
+        };
+        let json = self.search_response(&id_options).await?;
+
+        // Only look up the enclosing function of the best candidates.
+        let candidates: Vec<UsageExample> = rank_examples(collect_call_sites(&json, &id_options))
+            .into_iter()
+            .take(count * 3)
+            .collect();
+        let candidates: Vec<UsageExample> = stream::iter(candidates)
+            .map(|mut site| async move {
+                site.block = self
+                    .get_enclosing_block(&site.path, site.line)
+                    .await
+                    .ok()
+                    .flatten();
+                site
+            })
+            .buffered(CONCURRENT_FETCHES)
+            .collect()
+            .await;
+
+        let mut examples: Vec<UsageExample> =
+            rank_examples(candidates).into_iter().take(count).collect();
+        for example in &mut examples {
+            let content = self.get_file(&example.path).await?;
+            let start = example.line.saturating_sub(CONTEXT_LINES).max(1);
+            example.context = content
+                .lines()
+                .enumerate()
+                .map(|(i, line)| (i + 1, line.to_string()))
+                .skip(start - 1)
+                .take(example.line + CONTEXT_LINES + 1 - start)
+                .collect();
+        }
+        Ok(examples)
+    }
+}
+
+pub fn format_usage_examples(symbol: &str, examples: &[UsageExample]) -> String {
+    let mut output = format!("# Usage examples of {}\n\n", symbol);
+    if examples.is_empty() {
+        output.push_str("No call sites found.\n");
+        return output;
+    }
+    for example in examples {
+        output.push_str(&format!("## {}:{}", example.path, example.line));
+        if let Some(ref block) = example.block {
+            output.push_str(&format!(
+                " in {} ({} lines)",
+                block.sym,
+                example.block_len()
+            ));
+        }
+        output.push_str("\n\n```\n");
+        for (line_number, line) in &example.context {
+            let marker = if *line_number == example.line {
+                ">>>"
+            } else {
+                "   "
+            };
+            output.push_str(&format!("{marker} {line_number:4}: {line}\n"));
+        }
+        output.push_str("```\n\n");
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn site(path: &str, is_test: bool, len: Option<usize>) -> UsageExample {
+        UsageExample {
+            path: path.into(),
+            line: 10,
+            is_test,
+            block: len.map(|len| EnclosingBlock {
+                sym: "f".into(),
+                start: 1,
+                end: len,
+            }),
+            context: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn prefers_non_test_diverse_and_short() {
+        let ranked = rank_examples(vec![
+            site("dom/media/test/TestA.cpp", true, Some(3)),
+            site("dom/media/A.cpp", false, Some(200)),
+            site("dom/media/B.cpp", false, Some(10)),
+            site("dom/webaudio/C.cpp", false, Some(50)),
+        ]);
+        let paths: Vec<&str> = ranked.iter().map(|s| s.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "dom/media/B.cpp",
+                "dom/webaudio/C.cpp",
+                "dom/media/A.cpp",
+                "dom/media/test/TestA.cpp",
+            ]
+        );
+    }
+
+    #[test]
+    fn collects_uses_only() {
+        let json: SearchfoxResponse = serde_json::from_str(
+            r#"{
+                "normal": {
+                    "Definitions (Foo::Bar)": [
+                        {"path": "a/Foo.cpp", "lines": [{"lno": 1, "line": "void Foo::Bar() {"}]}
+                    ],
+                    "Uses (Foo::Bar)": [
+                        {"path": "b/Baz.cpp", "lines": [{"lno": 7, "line": "foo->Bar();"}]}
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+        let sites = collect_call_sites(&json, &SearchOptions::default());
+        assert_eq!(sites.len(), 1);
+        assert_eq!((sites[0].path.as_str(), sites[0].line), ("b/Baz.cpp", 7));
+        assert!(!sites[0].is_test);
+    }
+}
@@ -1,13 +1,21 @@
 use crate::client::SearchfoxClient;
+use crate::error::RequestError;
+use crate::style::color_table_header;
 use crate::types::SearchfoxResponse;
+use crate::wrap::terminal_width;
 use anyhow::Result;
+use futures::stream::{self, StreamExt};
 use reqwest::Url;
 use serde_json;
+use std::collections::HashMap;
+use std::str::FromStr;
 use tabled::{
-    settings::{object::Rows, Color, Modify, Style},
+    settings::{Color, Style},
     Table, Tabled,
 };
 
+const CONCURRENT_LOOKUPS: usize = 8;
+
 pub struct FieldLayoutQuery {
     pub class_name: String,
 }
@@ -22,8 +30,15 @@ struct BaseClass {
 
 #[derive(Tabled)]
 struct Field {
-    offset: u64,
-    size: u64,
+    offset: String,
+    size: String,
+    #[tabled(rename = "type")]
+    field_type: String,
+    name: String,
+}
+
+#[derive(Tabled)]
+struct FieldType {
     #[tabled(rename = "type")]
     field_type: String,
     name: String,
@@ -98,167 +113,808 @@ fn wrap_cpp_type(type_str: &str, max_width: usize) -> String {
     result
 }
 
+/// Whether a field-layout query response has layout data for `class_name`.
+pub fn has_field_layout(class_name: &str, json: &serde_json::Value) -> bool {
+    symbol_info(class_name, json).is_some()
+}
+
+/// Layout data for `class_name`: the C++ type symbol `T_<class_name>`, or
+/// else a symbol whose pretty name is `class_name`, as for Rust types.
+fn symbol_info<'a>(class_name: &str, json: &'a serde_json::Value) -> Option<&'a serde_json::Value> {
+    let symbol_key = format!("T_{}", class_name);
+    let jumprefs: Vec<&serde_json::Map<String, serde_json::Value>> = json
+        .get("SymbolTreeTableList")
+        .and_then(|v| v.get("tables"))
+        .and_then(|v| v.as_array())?
+        .iter()
+        .filter_map(|table| table.get("jumprefs").and_then(|v| v.as_object()))
+        .collect();
+    jumprefs
+        .iter()
+        .find_map(|jumprefs| jumprefs.get(&symbol_key))
+        .or_else(|| {
+            jumprefs.iter().flat_map(|j| j.values()).find(|info| {
+                info.get("pretty").and_then(|v| v.as_str()) == Some(class_name)
+                    && info.get("meta").is_some()
+            })
+        })
+}
+
+/// A base class within a class layout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutBase {
+    pub offset: u64,
+    pub size: u64,
+    pub type_name: String,
+}
+
+/// A field within a class layout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutField {
+    pub offset: u64,
+    pub size: u64,
+    pub type_name: String,
+    pub name: String,
+    /// Bit range of a bitfield, within the storage unit at `offset`.
+    pub bits: Option<BitRange>,
+    /// Layout of the field's own type, when expanded with
+    /// `SearchfoxClient::expand_field_layouts`.
+    pub expanded: Option<Box<ClassLayout>>,
+}
+
+/// Position of a bitfield, in bits from the start of its storage unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitRange {
+    pub begin: u64,
+    pub width: u64,
+}
+
+impl LayoutField {
+    /// Bytes the field occupies from its offset: bitfields are reported with
+    /// a size of 0, but use the bytes holding their bits.
+    fn occupied_bytes(&self) -> u64 {
+        match self.bits {
+            Some(bits) if self.size == 0 => (bits.begin + bits.width).div_ceil(8),
+            _ => self.size,
+        }
+    }
+}
+
+/// Memory layout of a class, as indexed by searchfox.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClassLayout {
+    pub size: Option<u64>,
+    pub alignment: Option<u64>,
+    pub bases: Vec<LayoutBase>,
+    pub fields: Vec<LayoutField>,
+    /// Whether members come with offsets and sizes. Rust types may only
+    /// have their field names and types indexed.
+    pub offsets_known: bool,
+}
+
+fn u64_field(obj: &serde_json::Value, key: &str) -> Option<u64> {
+    obj.get(key).and_then(|v| v.as_u64())
+}
+
+/// Extract the layout of `class_name` from a field-layout query response.
+/// Classes with several variants (e.g. per platform) use the first one.
+pub fn parse_field_layout(class_name: &str, json: &serde_json::Value) -> Option<ClassLayout> {
+    let symbol_info = symbol_info(class_name, json)?;
+    let meta = match symbol_info
+        .get("meta")
+        .and_then(|m| m.get("variants"))
+        .and_then(|v| v.as_array())
+    {
+        Some(variants) => variants.first(),
+        None => symbol_info.get("meta"),
+    };
+    let Some(meta) = meta else {
+        return Some(ClassLayout::default());
+    };
+
+    let bases = meta
+        .get("supers")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter(|base| base.is_object())
+        .map(|base| {
+            let sym = base
+                .get("sym")
+                .and_then(|v| v.as_str())
+                .unwrap_or("unknown");
+            LayoutBase {
+                offset: u64_field(base, "offsetBytes").unwrap_or(0),
+                size: u64_field(base, "sizeBytes").unwrap_or(0),
+                type_name: sym.strip_prefix("T_").unwrap_or(sym).to_string(),
+            }
+        })
+        .collect();
+
+    let fields = meta
+        .get("fields")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter(|field| field.is_object())
+        .map(|field| LayoutField {
+            offset: u64_field(field, "offsetBytes").unwrap_or(0),
+            size: u64_field(field, "sizeBytes").unwrap_or(0),
+            type_name: field
+                .get("type")
+                .and_then(|v| v.as_str())
+                .unwrap_or("unknown")
+                .to_string(),
+            name: field
+                .get("pretty")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.split("::").last())
+                .unwrap_or("unnamed")
+                .to_string(),
+            bits: field.get("bitPositions").and_then(|bits| {
+                Some(BitRange {
+                    begin: u64_field(bits, "begin")?,
+                    width: u64_field(bits, "width")?,
+                })
+            }),
+            expanded: None,
+        })
+        .collect();
+
+    let members = ["supers", "fields"].map(|key| meta.get(key).and_then(|v| v.as_array()));
+    let offsets_known = members
+        .iter()
+        .flatten()
+        .flat_map(|list| list.iter())
+        .any(|member| member.get("offsetBytes").is_some());
+
+    Some(ClassLayout {
+        size: u64_field(meta, "sizeBytes"),
+        alignment: u64_field(meta, "alignmentBytes"),
+        bases,
+        fields,
+        offsets_known,
+    })
+}
+
+/// Unused bytes between two members of a class, or at its end.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaddingHole {
+    pub offset: u64,
+    pub size: u64,
+    /// Member the hole follows, `None` for a hole before the first member.
+    pub after: Option<String>,
+    /// Whether this is the padding at the end of the class.
+    pub tail: bool,
+}
+
+/// Where the members of a class start: 0, or the offset of the first field
+/// of a class without bases whose first field is further, the bytes before
+/// it being the vtable pointer of a polymorphic class.
+fn members_start(layout: &ClassLayout) -> u64 {
+    if !layout.bases.is_empty() {
+        return 0;
+    }
+    layout.fields.iter().map(|f| f.offset).min().unwrap_or(0)
+}
+
+/// Alignment holes in a layout, tail padding included. Bitfields occupy the
+/// bytes holding their bits; the space following a zero-sized field whose
+/// bits are not described is not reported, as it may well be in use.
+pub fn padding_holes(layout: &ClassLayout) -> Vec<PaddingHole> {
+    if !layout.offsets_known {
+        return Vec::new();
+    }
+    let mut members: Vec<(u64, u64, &str, bool)> = layout
+        .bases
+        .iter()
+        .map(|b| (b.offset, b.size, b.type_name.as_str(), false))
+        .chain(layout.fields.iter().map(|f| {
+            let size = f.occupied_bytes();
+            (f.offset, size, f.name.as_str(), size == 0)
+        }))
+        .collect();
+    members.sort_by_key(|&(offset, ..)| offset);
+
+    let mut holes = Vec::new();
+    let mut end = members_start(layout);
+    let mut after: Option<&str> = None;
+    let mut unknown_bits = false;
+    for (offset, size, name, is_bitfield) in members {
+        if offset > end && !unknown_bits {
+            holes.push(PaddingHole {
+                offset: end,
+                size: offset - end,
+                after: after.map(String::from),
+                tail: false,
+            });
+        }
+        unknown_bits = is_bitfield || (unknown_bits && offset <= end);
+        if offset + size >= end {
+            end = offset + size;
+            after = Some(name);
+        }
+    }
+    if let Some(size) = layout.size {
+        if size > end && !unknown_bits {
+            holes.push(PaddingHole {
+                offset: end,
+                size: size - end,
+                after: after.map(String::from),
+                tail: true,
+            });
+        }
+    }
+    holes
+}
+
+fn align_up(value: u64, alignment: u64) -> u64 {
+    value.div_ceil(alignment.max(1)) * alignment.max(1)
+}
+
+/// Alignment a field of `size` bytes most likely has: the largest power of
+/// two dividing its size, capped by the alignment of the class.
+fn likely_alignment(size: u64, class_alignment: u64) -> u64 {
+    if size == 0 {
+        return 1;
+    }
+    (1 << size.trailing_zeros()).min(class_alignment)
+}
+
+/// A field order that wastes less space than the current one, with the
+/// resulting class size. Fields are sorted by decreasing alignment after the
+/// base classes, which keep their place. Returns `None` when the layout
+/// cannot be improved or contains bitfields, whose packing is unknown.
+pub fn suggest_field_order(layout: &ClassLayout) -> Option<(Vec<&LayoutField>, u64)> {
+    let size = layout.size.filter(|_| layout.offsets_known)?;
+    let class_alignment = layout.alignment.unwrap_or(8).max(1);
+    if layout.fields.is_empty() || layout.fields.iter().any(|f| f.size == 0) {
+        return None;
+    }
+
+    let mut order: Vec<&LayoutField> = layout.fields.iter().collect();
+    order.sort_by_key(|f| std::cmp::Reverse(likely_alignment(f.size, class_alignment)));
+
+    let mut end = layout
+        .bases
+        .iter()
+        .map(|b| b.offset + b.size)
+        .max()
+        .unwrap_or_else(|| members_start(layout));
+    for field in &order {
+        end = align_up(end, likely_alignment(field.size, class_alignment)) + field.size;
+    }
+    let new_size = align_up(end, class_alignment);
+    (new_size < size).then_some((order, new_size))
+}
+
+fn format_padding(layout: &ClassLayout) -> String {
+    let holes = padding_holes(layout);
+    if holes.is_empty() {
+        return String::from("Padding: none\n");
+    }
+
+    let wasted: u64 = holes.iter().map(|h| h.size).sum();
+    let mut output = format!(
+        "Padding: {} byte{} wasted in {} hole{}\n",
+        wasted,
+        if wasted == 1 { "" } else { "s" },
+        holes.len(),
+        if holes.len() == 1 { "" } else { "s" }
+    );
+    for hole in &holes {
+        let position = match (&hole.after, hole.tail) {
+            (_, true) => "of tail padding".to_string(),
+            (Some(after), false) => format!("after {}", after),
+            (None, false) => "before the first member".to_string(),
+        };
+        output.push_str(&format!(
+            "  {} byte{} {} (offset {})\n",
+            hole.size,
+            if hole.size == 1 { "" } else { "s" },
+            position,
+            hole.offset
+        ));
+    }
+
+    if let (Some((order, new_size)), Some(size)) = (suggest_field_order(layout), layout.size) {
+        output.push_str(&format!(
+            "\nReordering fields by decreasing alignment would make it {} bytes (saves {}):\n",
+            new_size,
+            size - new_size
+        ));
+        for field in order {
+            output.push_str(&format!("  {} ({} bytes)\n", field.name, field.size));
+        }
+    }
+    output
+}
+
 pub fn format_field_layout(class_name: &str, json: &serde_json::Value) -> String {
+    match parse_field_layout(class_name, json) {
+        Some(layout) => format_class_layout(class_name, &layout),
+        None => format!(
+            "Field Layout: {}\n\nNo field layout information found.\n\
+             This feature works with C++ and Rust classes and structs indexed by searchfox.\n",
+            class_name
+        ),
+    }
+}
+
+/// Rows of the fields table: the fields of `layout`, each followed by the
+/// fields of its expanded type, indented, at offsets within the outer class.
+fn field_rows(
+    layout: &ClassLayout,
+    base_offset: u64,
+    depth: usize,
+    type_width: usize,
+) -> Vec<Field> {
+    let mut rows = Vec::new();
+    for field in &layout.fields {
+        let indent = if depth == 0 {
+            String::new()
+        } else {
+            format!("{}└ ", "  ".repeat(depth - 1))
+        };
+        let (offset, size) = match field.bits {
+            Some(bits) => (
+                format!("{} bit {}", base_offset + field.offset, bits.begin),
+                format!(
+                    "{} bit{}",
+                    bits.width,
+                    if bits.width == 1 { "" } else { "s" }
+                ),
+            ),
+            None => (
+                (base_offset + field.offset).to_string(),
+                field.size.to_string(),
+            ),
+        };
+        rows.push(Field {
+            offset,
+            size,
+            field_type: wrap_cpp_type(&field.type_name, type_width),
+            name: format!("{}{}", indent, field.name),
+        });
+        if let Some(ref nested) = field.expanded {
+            rows.extend(field_rows(
+                nested,
+                base_offset + field.offset,
+                depth + 1,
+                type_width,
+            ));
+        }
+    }
+    rows
+}
+
+pub fn format_class_layout(class_name: &str, layout: &ClassLayout) -> String {
     let mut output = String::new();
     output.push_str(&format!("Field Layout: {}\n\n", class_name));
 
-    let terminal_width = terminal_size::terminal_size()
-        .map(|(w, _)| w.0 as usize)
-        .unwrap_or(100);
-
+    let terminal_width = terminal_width().unwrap_or(100);
     let type_col_max_width = (terminal_width.saturating_sub(40)).clamp(30, 60);
 
-    let symbol_key = format!("T_{}", class_name);
+    if let Some(size) = layout.size {
+        output.push_str(&format!("Size: {} bytes", size));
+    }
+    if let Some(alignment) = layout.alignment {
+        output.push_str(&format!(", Alignment: {} bytes\n\n", alignment));
+    } else {
+        output.push_str("\n\n");
+    }
 
-    let mut found = false;
+    if !layout.bases.is_empty() {
+        let base_classes: Vec<BaseClass> = layout
+            .bases
+            .iter()
+            .map(|base| BaseClass {
+                offset: base.offset,
+                size: base.size,
+                base_type: wrap_cpp_type(&base.type_name, type_col_max_width),
+            })
+            .collect();
 
-    if let Some(tables) = json
-        .get("SymbolTreeTableList")
-        .and_then(|v| v.get("tables"))
-        .and_then(|v| v.as_array())
+        let mut table = Table::new(&base_classes);
+        table.with(Style::rounded());
+        color_table_header(&mut table, Color::FG_GREEN);
+
+        output.push_str("Base Classes:\n");
+        output.push_str(&format!("{}\n\n", table));
+    }
+
+    if !layout.fields.is_empty() && !layout.offsets_known {
+        let field_list: Vec<FieldType> = layout
+            .fields
+            .iter()
+            .map(|field| FieldType {
+                field_type: wrap_cpp_type(&field.type_name, type_col_max_width),
+                name: field.name.clone(),
+            })
+            .collect();
+
+        let mut table = Table::new(&field_list);
+        table.with(Style::rounded());
+        color_table_header(&mut table, Color::FG_CYAN);
+
+        output.push_str("Fields (offsets and sizes not indexed for this type):\n");
+        output.push_str(&format!("{}\n", table));
+        return output;
+    }
+
+    if !layout.fields.is_empty() {
+        let field_list = field_rows(layout, 0, 0, type_col_max_width);
+
+        let mut table = Table::new(&field_list);
+        table.with(Style::rounded());
+        color_table_header(&mut table, Color::FG_CYAN);
+
+        output.push_str("Fields:\n");
+        output.push_str(&format!("{}\n", table));
+    }
+
+    if layout.size.is_some() && (!layout.bases.is_empty() || !layout.fields.is_empty()) {
+        output.push('\n');
+        output.push_str(&format_padding(layout));
+    }
+
+    output
+}
+
+const PRIMITIVE_TYPES: &[&str] = &[
+    "bool",
+    "char",
+    "signed char",
+    "unsigned char",
+    "short",
+    "unsigned short",
+    "int",
+    "unsigned int",
+    "long",
+    "unsigned long",
+    "long long",
+    "unsigned long long",
+    "float",
+    "double",
+    "long double",
+    "char16_t",
+    "char32_t",
+    "wchar_t",
+];
+
+/// Names under which the layout of a field's type may be indexed: the type
+/// itself, then for templates the template name. `None` for pointers,
+/// references, arrays and builtin types, which have no layout of their own.
+pub fn layout_class_names(type_name: &str) -> Option<Vec<String>> {
+    let mut name = type_name.trim();
+    for prefix in ["const ", "volatile ", "struct ", "class ", "union "] {
+        name = name.strip_prefix(prefix).unwrap_or(name);
+    }
+    let name = name.trim_end_matches(" const").trim();
+    if name.is_empty()
+        || name.ends_with('*')
+        || name.ends_with('&')
+        || name.contains('[')
+        || name.contains('(')
+        || PRIMITIVE_TYPES.contains(&name)
+        || (name.ends_with("_t") && !name.contains("::") && !name.contains('<'))
     {
-        for table in tables {
-            if let Some(jumprefs) = table.get("jumprefs").and_then(|v| v.as_object()) {
-                if let Some(symbol_info) = jumprefs.get(&symbol_key) {
-                    found = true;
-
-                    let meta = if let Some(variants) = symbol_info
-                        .get("meta")
-                        .and_then(|m| m.get("variants"))
-                        .and_then(|v| v.as_array())
-                    {
-                        variants.first()
-                    } else {
-                        symbol_info.get("meta")
-                    };
-
-                    if let Some(meta_obj) = meta {
-                        if let Some(size) = meta_obj.get("sizeBytes").and_then(|v| v.as_u64()) {
-                            output.push_str(&format!("Size: {} bytes", size));
-                        }
+        return None;
+    }
+    let mut names = vec![name.to_string()];
+    if let Some((template, _)) = name.split_once('<') {
+        names.push(template.trim().to_string());
+    }
+    Some(names)
+}
 
-                        if let Some(alignment) =
-                            meta_obj.get("alignmentBytes").and_then(|v| v.as_u64())
-                        {
-                            output.push_str(&format!(", Alignment: {} bytes\n\n", alignment));
-                        } else {
-                            output.push_str("\n\n");
-                        }
+fn visit_fields_at_depth(
+    layout: &mut ClassLayout,
+    depth: usize,
+    visit: &mut impl FnMut(&mut LayoutField),
+) {
+    for field in &mut layout.fields {
+        if depth == 0 {
+            visit(field);
+        } else if let Some(ref mut nested) = field.expanded {
+            visit_fields_at_depth(nested, depth - 1, visit);
+        }
+    }
+}
 
-                        if let Some(supers) = meta_obj.get("supers").and_then(|v| v.as_array()) {
-                            if !supers.is_empty() {
-                                let mut base_classes = Vec::new();
-
-                                for base in supers {
-                                    if let Some(base_obj) = base.as_object() {
-                                        let offset = base_obj
-                                            .get("offsetBytes")
-                                            .and_then(|v| v.as_u64())
-                                            .unwrap_or(0);
-                                        let size = base_obj
-                                            .get("sizeBytes")
-                                            .and_then(|v| v.as_u64())
-                                            .unwrap_or(0);
-                                        let base_sym = base_obj
-                                            .get("sym")
-                                            .and_then(|v| v.as_str())
-                                            .unwrap_or("unknown");
-                                        let base_type =
-                                            base_sym.strip_prefix("T_").unwrap_or(base_sym);
-                                        let wrapped_type =
-                                            wrap_cpp_type(base_type, type_col_max_width);
-
-                                        base_classes.push(BaseClass {
-                                            offset,
-                                            size,
-                                            base_type: wrapped_type,
-                                        });
-                                    }
-                                }
+/// Order of the rows of the fields table.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LayoutSort {
+    #[default]
+    Offset,
+    /// Largest first.
+    Size,
+    Name,
+}
 
-                                let mut table = Table::new(&base_classes);
-                                table
-                                    .with(Style::rounded())
-                                    .with(Modify::new(Rows::first()).with(Color::FG_GREEN));
+impl FromStr for LayoutSort {
+    type Err = anyhow::Error;
 
-                                output.push_str("Base Classes:\n");
-                                output.push_str(&format!("{}\n\n", table));
-                            }
-                        }
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "offset" => Ok(LayoutSort::Offset),
+            "size" => Ok(LayoutSort::Size),
+            "name" => Ok(LayoutSort::Name),
+            _ => anyhow::bail!("Unknown sort order '{}' (expected size, offset or name)", s),
+        }
+    }
+}
 
-                        if let Some(fields) = meta_obj.get("fields").and_then(|v| v.as_array()) {
-                            if !fields.is_empty() {
-                                let mut field_list = Vec::new();
-
-                                for field in fields {
-                                    if let Some(field_obj) = field.as_object() {
-                                        let offset = field_obj
-                                            .get("offsetBytes")
-                                            .and_then(|v| v.as_u64())
-                                            .unwrap_or(0);
-                                        let size = field_obj
-                                            .get("sizeBytes")
-                                            .and_then(|v| v.as_u64())
-                                            .unwrap_or(0);
-                                        let field_type = field_obj
-                                            .get("type")
-                                            .and_then(|v| v.as_str())
-                                            .unwrap_or("unknown");
-                                        let name = field_obj
-                                            .get("pretty")
-                                            .and_then(|v| v.as_str())
-                                            .and_then(|s| s.split("::").last())
-                                            .unwrap_or("unnamed");
-                                        let wrapped_type =
-                                            wrap_cpp_type(field_type, type_col_max_width);
-
-                                        field_list.push(Field {
-                                            offset,
-                                            size,
-                                            field_type: wrapped_type,
-                                            name: name.to_string(),
-                                        });
-                                    }
-                                }
+/// Sort the fields of `layout`, and of the layouts expanded in it.
+pub fn sort_fields(layout: &mut ClassLayout, by: LayoutSort) {
+    let position = |f: &LayoutField| (f.offset, f.bits.map_or(0, |b| b.begin));
+    match by {
+        LayoutSort::Offset => layout.fields.sort_by_key(position),
+        LayoutSort::Size => layout.fields.sort_by(|a, b| {
+            b.occupied_bytes()
+                .cmp(&a.occupied_bytes())
+                .then(position(a).cmp(&position(b)))
+        }),
+        LayoutSort::Name => layout.fields.sort_by(|a, b| a.name.cmp(&b.name)),
+    }
+    for field in &mut layout.fields {
+        if let Some(ref mut nested) = field.expanded {
+            sort_fields(nested, by);
+        }
+    }
+}
 
-                                let mut table = Table::new(&field_list);
-                                table
-                                    .with(Style::rounded())
-                                    .with(Modify::new(Rows::first()).with(Color::FG_CYAN));
+/// A field present in either of two compared layouts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldDiff {
+    pub name: String,
+    pub old: Option<LayoutField>,
+    pub new: Option<LayoutField>,
+}
 
-                                output.push_str("Fields:\n");
-                                output.push_str(&format!("{}\n", table));
-                            }
-                        }
-                    }
-                    break;
-                }
-            }
+impl FieldDiff {
+    /// Comma-separated list of what changed, empty when nothing did.
+    pub fn change(&self) -> String {
+        let (old, new) = match (&self.old, &self.new) {
+            (None, _) => return "added".to_string(),
+            (_, None) => return "removed".to_string(),
+            (Some(old), Some(new)) => (old, new),
+        };
+        let mut changes = Vec::new();
+        if old.offset != new.offset {
+            changes.push("moved");
+        }
+        if old.size != new.size {
+            changes.push("resized");
+        }
+        if old.type_name != new.type_name {
+            changes.push("retyped");
+        }
+        changes.join(", ")
+    }
+}
+
+/// Align the fields of two layouts by name, in the order of the new layout
+/// with removed fields at the end.
+pub fn diff_layouts(old: &ClassLayout, new: &ClassLayout) -> Vec<FieldDiff> {
+    let mut diffs: Vec<FieldDiff> = new
+        .fields
+        .iter()
+        .map(|field| FieldDiff {
+            name: field.name.clone(),
+            old: old.fields.iter().find(|f| f.name == field.name).cloned(),
+            new: Some(field.clone()),
+        })
+        .collect();
+    diffs.extend(
+        old.fields
+            .iter()
+            .filter(|field| !new.fields.iter().any(|f| f.name == field.name))
+            .map(|field| FieldDiff {
+                name: field.name.clone(),
+                old: Some(field.clone()),
+                new: None,
+            }),
+    );
+    diffs
+}
+
+#[derive(Tabled)]
+struct FieldChangeRow {
+    change: String,
+    name: String,
+    offset: String,
+    size: String,
+    #[tabled(rename = "type")]
+    field_type: String,
+}
+
+fn before_after<T: PartialEq + std::fmt::Display>(old: Option<T>, new: Option<T>) -> String {
+    match (old, new) {
+        (Some(old), Some(new)) if old == new => new.to_string(),
+        (Some(old), Some(new)) => format!("{} -> {}", old, new),
+        (Some(old), None) => old.to_string(),
+        (None, Some(new)) => new.to_string(),
+        (None, None) => String::new(),
+    }
+}
+
+/// What got worse from `old` to `new`: a larger size, alignment or padding,
+/// and fields that grew.
+pub fn layout_regressions(old: &ClassLayout, new: &ClassLayout) -> Vec<String> {
+    let mut regressions = Vec::new();
+    let grew = |what: &str, old: Option<u64>, new: Option<u64>| match (old, new) {
+        (Some(old), Some(new)) if new > old => Some(format!(
+            "{} grew by {} byte{} ({} -> {})",
+            what,
+            new - old,
+            if new - old == 1 { "" } else { "s" },
+            old,
+            new
+        )),
+        _ => None,
+    };
+    let wasted = |layout: &ClassLayout| padding_holes(layout).iter().map(|h| h.size).sum::<u64>();
+    regressions.extend(grew("size", old.size, new.size));
+    regressions.extend(grew("alignment", old.alignment, new.alignment));
+    regressions.extend(grew("padding", Some(wasted(old)), Some(wasted(new))));
+    for diff in diff_layouts(old, new) {
+        if let (Some(old), Some(new)) = (&diff.old, &diff.new) {
+            regressions.extend(grew(&diff.name, Some(old.size), Some(new.size)));
         }
     }
+    regressions
+}
+
+/// Markdown comparison of two class layouts, showing size changes and the
+/// fields that were added, removed, moved, resized or retyped.
+pub fn format_layout_diff(
+    old_label: &str,
+    old: &ClassLayout,
+    new_label: &str,
+    new: &ClassLayout,
+) -> String {
+    let mut output = format!("Field Layout Diff: {} -> {}\n\n", old_label, new_label);
 
-    if !found {
-        output.push_str("No field layout information found.\n");
-        output.push_str("This feature only works with C++ classes and structs.\n");
+    if let (Some(old_size), Some(new_size)) = (old.size, new.size) {
+        output.push_str(&format!(
+            "Size: {} ({:+} bytes)\n",
+            before_after(Some(old_size), Some(new_size)),
+            new_size as i64 - old_size as i64
+        ));
     }
+    if old.alignment.is_some() || new.alignment.is_some() {
+        output.push_str(&format!(
+            "Alignment: {}\n",
+            before_after(old.alignment, new.alignment)
+        ));
+    }
+    let wasted = |layout: &ClassLayout| padding_holes(layout).iter().map(|h| h.size).sum::<u64>();
+    output.push_str(&format!(
+        "Padding: {} bytes\n\n",
+        before_after(Some(wasted(old)), Some(wasted(new)))
+    ));
 
+    let regressions = layout_regressions(old, new);
+    if !regressions.is_empty() {
+        output.push_str("Regressions:\n");
+        for regression in &regressions {
+            output.push_str(&format!("  - {}\n", regression));
+        }
+        output.push('\n');
+    }
+
+    for base in &new.bases {
+        if !old.bases.iter().any(|b| b.type_name == base.type_name) {
+            output.push_str(&format!("Base class added: {}\n", base.type_name));
+        }
+    }
+    for base in &old.bases {
+        if !new.bases.iter().any(|b| b.type_name == base.type_name) {
+            output.push_str(&format!("Base class removed: {}\n", base.type_name));
+        }
+    }
+
+    let terminal_width = terminal_width().unwrap_or(100);
+    let type_col_max_width = (terminal_width.saturating_sub(60)).clamp(30, 60);
+    let diffs = diff_layouts(old, new);
+    let rows: Vec<FieldChangeRow> = diffs
+        .iter()
+        .filter_map(|diff| {
+            let change = diff.change();
+            if change.is_empty() {
+                return None;
+            }
+            let field = diff.new.as_ref().or(diff.old.as_ref())?;
+            Some(FieldChangeRow {
+                change,
+                name: diff.name.clone(),
+                offset: before_after(
+                    diff.old.as_ref().map(|f| f.offset),
+                    diff.new.as_ref().map(|f| f.offset),
+                ),
+                size: before_after(
+                    diff.old.as_ref().map(|f| f.size),
+                    diff.new.as_ref().map(|f| f.size),
+                ),
+                field_type: wrap_cpp_type(&field.type_name, type_col_max_width),
+            })
+        })
+        .collect();
+
+    if rows.is_empty() {
+        output.push_str("\nNo field changes.\n");
+        return output;
+    }
+    let unchanged = diffs.len() - rows.len();
+    let mut table = Table::new(&rows);
+    table.with(Style::rounded());
+    color_table_header(&mut table, Color::FG_CYAN);
+    output.push_str(&format!("\nFields:\n{}\n", table));
+    if unchanged > 0 {
+        output.push_str(&format!("{} unchanged field(s) not shown\n", unchanged));
+    }
     output
 }
 
 impl SearchfoxClient {
+    /// Layout of `class_name`, or `None` when searchfox has none for it.
+    pub async fn class_layout(&self, class_name: &str) -> Result<Option<ClassLayout>> {
+        let query = FieldLayoutQuery {
+            class_name: class_name.to_string(),
+        };
+        let json = self.search_field_layout(&query).await?;
+        Ok(parse_field_layout(class_name, &json))
+    }
+
+    /// Inline the layouts of field types that are classes, `depth` levels
+    /// deep. Each type is looked up once, however many fields have it.
+    pub async fn expand_field_layouts(&self, layout: &mut ClassLayout, depth: usize) {
+        let mut known: HashMap<String, Option<ClassLayout>> = HashMap::new();
+        for level in 0..depth {
+            let mut wanted = Vec::new();
+            visit_fields_at_depth(layout, level, &mut |field| {
+                if !known.contains_key(&field.type_name) && !wanted.contains(&field.type_name) {
+                    wanted.push(field.type_name.clone());
+                }
+            });
+            let fetched: Vec<(String, Option<ClassLayout>)> = stream::iter(wanted)
+                .map(|type_name| async move {
+                    let mut found = None;
+                    for name in layout_class_names(&type_name).unwrap_or_default() {
+                        if let Ok(Some(layout)) = self.class_layout(&name).await {
+                            found = Some(layout);
+                            break;
+                        }
+                    }
+                    (type_name, found)
+                })
+                .buffered(CONCURRENT_LOOKUPS)
+                .collect()
+                .await;
+            known.extend(fetched);
+
+            visit_fields_at_depth(layout, level, &mut |field| {
+                field.expanded = known
+                    .get(&field.type_name)
+                    .cloned()
+                    .flatten()
+                    .filter(|l| !l.fields.is_empty())
+                    .map(Box::new);
+            });
+        }
+    }
+
     pub async fn search_field_layout(&self, query: &FieldLayoutQuery) -> Result<serde_json::Value> {
         let query_string = format!("field-layout:'{}'", query.class_name);
 
-        let mut url = Url::parse(&format!(
-            "https://searchfox.org/{}/query/default",
-            self.repo
-        ))?;
+        let mut url = Url::parse(&format!("{}/{}/query/default", self.base_url, self.repo))?;
         url.query_pairs_mut().append_pair("q", &query_string);
 
         let response = self.get(url).await?;
 
         if !response.status().is_success() {
-            anyhow::bail!("Request failed: {}", response.status());
+            return Err(RequestError::new(response.url().as_str(), response.status()).into());
         }
 
         let response_text = response.text().await?;
@@ -291,3 +947,264 @@ impl SearchfoxClient {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(offset: u64, size: u64, name: &str) -> LayoutField {
+        LayoutField {
+            offset,
+            size,
+            type_name: "int".into(),
+            name: name.into(),
+            bits: None,
+            expanded: None,
+        }
+    }
+
+    fn layout(size: u64, fields: Vec<LayoutField>) -> ClassLayout {
+        ClassLayout {
+            size: Some(size),
+            alignment: Some(8),
+            bases: Vec::new(),
+            fields,
+            offsets_known: true,
+        }
+    }
+
+    #[test]
+    fn parses_searchfox_layout() {
+        let json = serde_json::json!({
+            "SymbolTreeTableList": {"tables": [{"jumprefs": {"T_Foo": {"meta": {
+                "sizeBytes": 16,
+                "alignmentBytes": 8,
+                "supers": [{"sym": "T_Base", "offsetBytes": 0, "sizeBytes": 8}],
+                "fields": [{"pretty": "Foo::mBar", "type": "uint32_t", "offsetBytes": 8, "sizeBytes": 4}]
+            }}}}]}
+        });
+        let layout = parse_field_layout("Foo", &json).unwrap();
+        assert_eq!(layout.size, Some(16));
+        assert_eq!(layout.bases[0].type_name, "Base");
+        assert_eq!(
+            layout.fields,
+            vec![LayoutField {
+                offset: 8,
+                size: 4,
+                type_name: "uint32_t".into(),
+                name: "mBar".into(),
+                bits: None,
+                expanded: None,
+            }]
+        );
+        assert!(parse_field_layout("Other", &json).is_none());
+    }
+
+    #[test]
+    fn rust_types_are_found_by_pretty_name() {
+        let json = serde_json::json!({
+            "SymbolTreeTableList": {"tables": [{"jumprefs": {"S_rust_style_Foo": {
+                "pretty": "style::values::Foo",
+                "meta": {
+                    "sizeBytes": 24,
+                    "fields": [{"pretty": "style::values::Foo::bar", "type": "Vec<u8>"}]
+                }
+            }}}]}
+        });
+        let layout = parse_field_layout("style::values::Foo", &json).unwrap();
+        assert_eq!(layout.size, Some(24));
+        assert!(!layout.offsets_known);
+        let output = format_class_layout("style::values::Foo", &layout);
+        assert!(output.contains("offsets and sizes not indexed"));
+        assert!(output.contains("bar"));
+        assert!(!output.contains("Padding"));
+    }
+
+    #[test]
+    fn finds_holes_and_tail_padding() {
+        // bool, pointer, bool: 7 bytes of hole and 7 bytes of tail padding.
+        let layout = layout(
+            24,
+            vec![field(0, 1, "mA"), field(8, 8, "mPtr"), field(16, 1, "mB")],
+        );
+        assert_eq!(
+            padding_holes(&layout),
+            vec![
+                PaddingHole {
+                    offset: 1,
+                    size: 7,
+                    after: Some("mA".into()),
+                    tail: false,
+                },
+                PaddingHole {
+                    offset: 17,
+                    size: 7,
+                    after: Some("mB".into()),
+                    tail: true,
+                },
+            ]
+        );
+
+        let (order, new_size) = suggest_field_order(&layout).unwrap();
+        let names: Vec<&str> = order.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["mPtr", "mA", "mB"]);
+        assert_eq!(new_size, 16);
+    }
+
+    #[test]
+    fn leading_gap_without_bases_is_the_vtable_pointer() {
+        let layout = layout(24, vec![field(8, 4, "mCount"), field(16, 8, "mPtr")]);
+        assert_eq!(
+            padding_holes(&layout),
+            vec![PaddingHole {
+                offset: 12,
+                size: 4,
+                after: Some("mCount".into()),
+                tail: false,
+            }]
+        );
+        assert!(suggest_field_order(&layout).is_none());
+    }
+
+    #[test]
+    fn ignores_space_after_bitfields() {
+        let layout = layout(16, vec![field(0, 0, "mFlag"), field(8, 8, "mPtr")]);
+        assert!(padding_holes(&layout).is_empty());
+        assert!(suggest_field_order(&layout).is_none());
+    }
+
+    #[test]
+    fn diffs_fields_by_name() {
+        let old = layout(
+            16,
+            vec![
+                field(0, 8, "mPtr"),
+                field(8, 4, "mA"),
+                field(12, 4, "mGone"),
+            ],
+        );
+        let new = layout(
+            24,
+            vec![field(0, 8, "mPtr"), field(8, 4, "mNew"), field(16, 8, "mA")],
+        );
+        let changes: Vec<(String, String)> = diff_layouts(&old, &new)
+            .iter()
+            .map(|d| (d.name.clone(), d.change()))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                ("mPtr".to_string(), String::new()),
+                ("mNew".to_string(), "added".to_string()),
+                ("mA".to_string(), "moved, resized".to_string()),
+                ("mGone".to_string(), "removed".to_string()),
+            ]
+        );
+        let output = format_layout_diff("Old", &old, "New", &new);
+        assert!(output.contains("Size: 16 -> 24 (+8 bytes)"));
+        assert!(output.contains("1 unchanged field(s) not shown"));
+        assert_eq!(
+            layout_regressions(&old, &new),
+            vec![
+                "size grew by 8 bytes (16 -> 24)".to_string(),
+                "padding grew by 4 bytes (0 -> 4)".to_string(),
+                "mA grew by 4 bytes (4 -> 8)".to_string(),
+            ]
+        );
+        assert!(output.contains("Regressions:\n  - size grew by 8 bytes (16 -> 24)\n"));
+        assert!(layout_regressions(&new, &old).is_empty());
+    }
+
+    #[test]
+    fn bitfields_show_bits_and_fill_their_bytes() {
+        let json = serde_json::json!({
+            "SymbolTreeTableList": {"tables": [{"jumprefs": {"T_Foo": {"meta": {
+                "sizeBytes": 8,
+                "alignmentBytes": 4,
+                "fields": [
+                    {"pretty": "Foo::mA", "type": "bool", "offsetBytes": 0, "sizeBytes": 0,
+                     "bitPositions": {"begin": 0, "width": 1}},
+                    {"pretty": "Foo::mB", "type": "uint8_t", "offsetBytes": 0, "sizeBytes": 0,
+                     "bitPositions": {"begin": 1, "width": 3}},
+                    {"pretty": "Foo::mC", "type": "uint32_t", "offsetBytes": 4, "sizeBytes": 4}
+                ]
+            }}}}]}
+        });
+        let layout = parse_field_layout("Foo", &json).unwrap();
+        assert_eq!(layout.fields[1].bits, Some(BitRange { begin: 1, width: 3 }));
+        let holes = padding_holes(&layout);
+        assert_eq!((holes[0].offset, holes[0].size), (1, 3));
+        let rows = field_rows(&layout, 0, 0, 60);
+        assert_eq!(
+            (rows[1].offset.as_str(), rows[1].size.as_str()),
+            ("0 bit 1", "3 bits")
+        );
+    }
+
+    #[test]
+    fn sorts_fields() {
+        let mut l = layout(
+            24,
+            vec![field(0, 4, "mZ"), field(8, 8, "mA"), field(16, 1, "mM")],
+        );
+        sort_fields(&mut l, LayoutSort::Size);
+        let names: Vec<&str> = l.fields.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["mA", "mZ", "mM"]);
+        sort_fields(&mut l, LayoutSort::Name);
+        let names: Vec<&str> = l.fields.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["mA", "mM", "mZ"]);
+        sort_fields(&mut l, LayoutSort::Offset);
+        assert_eq!(l.fields[0].name, "mZ");
+        assert_eq!("size".parse::<LayoutSort>().unwrap(), LayoutSort::Size);
+        assert!("width".parse::<LayoutSort>().is_err());
+    }
+
+    #[test]
+    fn finds_layout_names_of_field_types() {
+        assert_eq!(
+            layout_class_names("const mozilla::Mutex"),
+            Some(vec!["mozilla::Mutex".to_string()])
+        );
+        assert_eq!(
+            layout_class_names("nsTArray<float>"),
+            Some(vec!["nsTArray<float>".to_string(), "nsTArray".to_string()])
+        );
+        assert_eq!(layout_class_names("mozilla::dom::AudioNode *"), None);
+        assert_eq!(layout_class_names("uint32_t"), None);
+        assert_eq!(layout_class_names("unsigned int"), None);
+        assert_eq!(layout_class_names("float[128]"), None);
+    }
+
+    #[test]
+    fn expanded_fields_are_indented_at_absolute_offsets() {
+        let mut outer = layout(24, vec![field(0, 8, "mPtr"), field(8, 16, "mInner")]);
+        outer.fields[1].expanded = Some(Box::new(layout(
+            16,
+            vec![field(0, 8, "mA"), field(8, 8, "mB")],
+        )));
+        let rows = field_rows(&outer, 0, 0, 60);
+        let rows: Vec<(&str, &str)> = rows
+            .iter()
+            .map(|r| (r.offset.as_str(), r.name.as_str()))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("0", "mPtr"),
+                ("8", "mInner"),
+                ("8", "└ mA"),
+                ("16", "└ mB")
+            ]
+        );
+    }
+
+    #[test]
+    fn packed_layout_has_no_suggestion() {
+        let layout = layout(
+            16,
+            vec![field(0, 8, "mPtr"), field(8, 4, "mA"), field(12, 4, "mB")],
+        );
+        assert!(padding_holes(&layout).is_empty());
+        assert!(suggest_field_order(&layout).is_none());
+    }
+}
@@ -1,22 +1,124 @@
 use crate::client::SearchfoxClient;
-use crate::utils::searchfox_url_repo;
+use crate::config::{self, Config};
+use crate::error::{BinaryFileError, RequestError};
+use crate::progress::ProgressEvent;
+use crate::utils::{repo_hg_path, searchfox_url_repo};
 use anyhow::Result;
-use log::debug;
+use futures::stream::{self, StreamExt};
+use log::{debug, warn};
 use scraper::{Html, Selector};
+use std::str::FromStr;
+
+/// Where file contents are fetched from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FileBackend {
+    /// Text extracted from searchfox's rendered source pages.
+    #[default]
+    Searchfox,
+    /// Raw files from hg.mozilla.org, or from the `raw` URL configured for
+    /// the repository. Generated files, which only exist on searchfox, and
+    /// files of repositories without a known mirror are still fetched from
+    /// there.
+    Hg,
+}
+
+impl FromStr for FileBackend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "searchfox" => Ok(FileBackend::Searchfox),
+            "hg" => Ok(FileBackend::Hg),
+            _ => anyhow::bail!("Unknown file backend '{}' (expected searchfox or hg)", s),
+        }
+    }
+}
+
+/// Files fetched at the same time by `get_files`.
+const CONCURRENT_FETCHES: usize = 8;
+
+/// Each line of a searchfox source page starts with this.
+const SOURCE_LINE_MARKER: &[u8] = b"class=\"source-line\"";
+
+/// The beginning of a file, see `SearchfoxClient::get_file_head`.
+#[derive(Debug, Clone)]
+pub struct FileHead {
+    pub content: String,
+    /// Whether the file goes on past `content`.
+    pub truncated: bool,
+}
+
+/// URL of the raw file at `path` of `repo`, at `revision` or else the tip:
+/// from the `raw` template of its configuration, or else on hg.mozilla.org
+/// (`hg_base_url`). None when the repository has no known mirror, or when a
+/// revision is asked for and the template has no `{rev}`.
+fn repo_raw_url(
+    config: &Config,
+    hg_base_url: &str,
+    repo: &str,
+    path: &str,
+    revision: Option<&str>,
+) -> Option<String> {
+    if let Some(template) = config.repo(repo).and_then(|r| r.raw.as_deref()) {
+        if revision.is_some() && !template.contains("{rev}") {
+            return None;
+        }
+        return Some(
+            template
+                .replace("{rev}", revision.unwrap_or("HEAD"))
+                .replace("{path}", path),
+        );
+    }
+    let hg_repo = repo_hg_path(config, repo)?;
+    let revision = revision.unwrap_or("tip");
+    Some(format!(
+        "{hg_base_url}/{hg_repo}/raw-file/{revision}/{path}"
+    ))
+}
 
 impl SearchfoxClient {
-    pub async fn get_file(&self, path: &str) -> Result<String> {
-        let url = format!(
+    /// Where the hg backend fetches `path` from, or None when the file is to
+    /// be read from searchfox: with the searchfox backend, for generated
+    /// files, and for repositories without a known mirror.
+    fn raw_url(&self, path: &str, revision: Option<&str>) -> Option<String> {
+        if self.file_backend != FileBackend::Hg || path.starts_with("__GENERATED__/") {
+            return None;
+        }
+        let url = repo_raw_url(
+            config::global(),
+            &self.hg_base_url,
+            &self.repo,
+            path,
+            revision,
+        );
+        if url.is_none() {
+            debug!(
+                "No raw file mirror known for {}, using searchfox",
+                self.repo
+            );
+        }
+        url
+    }
+
+    fn source_url(&self, path: &str) -> String {
+        format!(
             "{}/{}/source/{}",
             self.base_url,
             searchfox_url_repo(&self.repo),
             path
-        );
+        )
+    }
+
+    pub async fn get_file(&self, path: &str) -> Result<String> {
+        if let Some(url) = self.raw_url(path, None) {
+            return self.get_raw_file(path, &url).await;
+        }
 
+        let url = self.source_url(path);
         let cache_key = format!("source:{url}");
 
         if let Some(entry) = self.cache_get(&cache_key) {
-            if entry.is_fresh() {
+            if entry.is_fresh_within(self.cache_ttl()) {
                 debug!("Cache hit (fresh) for: {}", url);
                 return Ok(entry.content);
             }
@@ -37,7 +139,10 @@ impl SearchfoxClient {
                     return Ok(entry.content);
                 }
                 Some((html, etag, last_modified)) => {
-                    let content = parse_source_lines(&html, &url)?;
+                    let content = match parse_source_lines(&html, &url) {
+                        Ok(content) => content,
+                        Err(e) => return Err(self.explain_missing_lines(path, e).await),
+                    };
                     self.cache_set(
                         &cache_key,
                         &content,
@@ -58,7 +163,10 @@ impl SearchfoxClient {
             .await?
             .ok_or_else(|| anyhow::anyhow!("Unexpected 304 on cold cache miss"))?;
 
-        let content = parse_source_lines(&html, &url)?;
+        let content = match parse_source_lines(&html, &url) {
+            Ok(content) => content,
+            Err(e) => return Err(self.explain_missing_lines(path, e).await),
+        };
         self.cache_set(
             &cache_key,
             &content,
@@ -69,7 +177,173 @@ impl SearchfoxClient {
         Ok(content)
     }
 
+    /// Searchfox renders no source lines for binary files: check the raw
+    /// file to report that rather than a parsing failure.
+    async fn explain_missing_lines(&self, path: &str, e: anyhow::Error) -> anyhow::Error {
+        match self.get_file_bytes(path).await {
+            Ok(bytes) if is_binary(&bytes) => BinaryFileError {
+                path: path.to_string(),
+            }
+            .into(),
+            _ => e,
+        }
+    }
+
+    /// Raw contents of a file, binary files included.
+    pub async fn get_file_bytes(&self, path: &str) -> Result<Vec<u8>> {
+        let url = self.raw_url(path, None).unwrap_or_else(|| {
+            format!(
+                "{}/{}/raw/{}",
+                self.base_url,
+                searchfox_url_repo(&self.repo),
+                path
+            )
+        });
+        self.get_raw_bytes(&url).await
+    }
+
+    async fn get_raw_file(&self, path: &str, url: &str) -> Result<String> {
+        let cache_key = format!("hg:{url}");
+
+        if let Some(entry) = self.cache_get(&cache_key) {
+            if entry.is_fresh_within(self.cache_ttl()) {
+                debug!("Cache hit (fresh) for: {}", url);
+                return Ok(entry.content);
+            }
+        }
+
+        let bytes = self.get_raw_bytes(url).await?;
+        if is_binary(&bytes) {
+            return Err(BinaryFileError {
+                path: path.to_string(),
+            }
+            .into());
+        }
+        let content = decode_text(bytes, url);
+        self.cache_set(&cache_key, &content, None, None);
+        Ok(content)
+    }
+
+    /// Fetch at least the first `lines` lines of a file, without
+    /// downloading the rest of it: the download stops as soon as enough lines
+    /// have arrived, which matters for multi-megabyte generated files. A
+    /// cached copy of the whole file is used when fresh.
+    pub async fn get_file_head(&self, path: &str, lines: usize) -> Result<FileHead> {
+        let raw_url = self.raw_url(path, None);
+        let hg = raw_url.is_some();
+        let url = raw_url.unwrap_or_else(|| self.source_url(path));
+        let cache_key = if hg {
+            format!("hg:{url}")
+        } else {
+            format!("source:{url}")
+        };
+
+        if let Some(entry) = self.cache_get(&cache_key) {
+            if entry.is_fresh_within(self.cache_ttl()) {
+                debug!("Cache hit (fresh) for: {}", url);
+                return Ok(FileHead {
+                    content: entry.content,
+                    truncated: false,
+                });
+            }
+        }
+
+        let (content, truncated) = if hg {
+            let (mut body, truncated) = self.get_until(&url, b"\n", lines).await?;
+            if truncated {
+                let end = body
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &b)| b == b'\n')
+                    .nth(lines.saturating_sub(1))
+                    .map_or(body.len(), |(i, _)| i + 1);
+                body.truncate(end);
+            }
+            (String::from_utf8_lossy(&body).into_owned(), truncated)
+        } else {
+            // One more line than needed, so that the last one is complete.
+            let (body, truncated) = self.get_until(&url, SOURCE_LINE_MARKER, lines + 1).await?;
+            let mut source_lines = source_lines(&String::from_utf8_lossy(&body), &url)?;
+            if truncated {
+                source_lines.truncate(lines);
+            }
+            (source_lines.concat(), truncated)
+        };
+
+        if !truncated {
+            self.cache_set(&cache_key, &content, None, None);
+        }
+        Ok(FileHead { content, truncated })
+    }
+
+    /// The contents of `paths`, at `revision` or else the latest indexed
+    /// one, fetched concurrently and returned in the order of `paths`. A file
+    /// that could not be fetched comes with its error instead.
+    pub async fn get_files(
+        &self,
+        paths: &[String],
+        revision: Option<&str>,
+    ) -> Vec<(String, Result<String>)> {
+        let operation = "fetch files".to_string();
+        let total = paths.len();
+        self.emit(ProgressEvent::Started {
+            operation: operation.clone(),
+            total,
+        });
+        let mut fetches = stream::iter(paths)
+            .map(|path| async move {
+                let content = match revision {
+                    Some(revision) => self.get_file_at_revision(path, revision).await,
+                    None => self.get_file(path).await,
+                };
+                (path.clone(), content)
+            })
+            .buffered(CONCURRENT_FETCHES);
+        let mut files = Vec::with_capacity(total);
+        while let Some(file) = fetches.next().await {
+            files.push(file);
+            self.emit(ProgressEvent::Advanced {
+                operation: operation.clone(),
+                completed: files.len(),
+                total,
+            });
+        }
+        self.emit(ProgressEvent::Finished { operation });
+        files
+    }
+
+    /// Enough of a file to show the line ranges of `lines`: when none of
+    /// them is open-ended, only the beginning of the file up to the last
+    /// line asked for is downloaded, otherwise the whole file.
+    pub async fn get_file_covering(&self, path: &str, lines: Option<&str>) -> Result<String> {
+        let Some(end) = lines.and_then(line_ranges_end) else {
+            return self.get_file(path).await;
+        };
+        match self.get_file_head(path, end).await {
+            Ok(head) => Ok(head.content),
+            Err(e) if e.is::<RequestError>() => Err(e),
+            // E.g. a binary file, which get_file tells apart.
+            Err(_) => self.get_file(path).await,
+        }
+    }
+
+    /// The numbered lines of `path` in the ranges of `lines`, such as
+    /// `200-260` or `10,40-50`, downloading no more of the file than needed.
+    pub async fn get_file_lines(&self, path: &str, lines: &str) -> Result<Vec<(usize, String)>> {
+        let content = self.get_file_covering(path, Some(lines)).await?;
+        Ok(select_lines(&content, Some(lines))?
+            .into_iter()
+            .map(|(number, line)| (number, line.to_string()))
+            .collect())
+    }
+
+    /// The file as it was at `revision`: a searchfox permalink hash, or with
+    /// the hg backend, a revision of the mirror the file is fetched from.
     pub async fn get_file_at_revision(&self, path: &str, revision: &str) -> Result<String> {
+        if let Some(url) = self.raw_url(path, Some(revision)) {
+            return self.get_raw_file(path, &url).await;
+        }
+
         let url = format!(
             "{}/{}/rev/{}/{}",
             self.base_url,
@@ -81,13 +355,16 @@ impl SearchfoxClient {
         let cache_key = format!("source-rev:{revision}:{url}");
 
         if let Some(entry) = self.cache_get(&cache_key) {
-            if entry.is_fresh() {
+            if entry.is_fresh_within(self.cache_ttl()) {
                 debug!("Cache hit (fresh) for: {}", url);
                 return Ok(entry.content);
             }
         }
 
         let response = self.get(url.parse()?).await?;
+        if !response.status().is_success() {
+            return Err(RequestError::new(response.url().as_str(), response.status()).into());
+        }
         let html = response.text().await?;
         let content = parse_source_lines(&html, &url)?;
         // Revision-pinned URLs are immutable — cache indefinitely.
@@ -97,7 +374,167 @@ impl SearchfoxClient {
     }
 }
 
+/// Parse line range string (e.g., "10-20", "10", "10-", "-20")
+/// Returns (start_line, end_line) inclusive
+pub fn parse_line_range(range: &str, total_lines: usize) -> Result<(usize, usize)> {
+    let range = range.trim();
+
+    if range.contains('-') {
+        let parts: Vec<&str> = range.split('-').collect();
+        if parts.len() != 2 {
+            anyhow::bail!(
+                "Invalid line range format: '{}'. Expected formats: 10-20, 10, 10-, -20",
+                range
+            );
+        }
+
+        let start = if parts[0].is_empty() {
+            1
+        } else {
+            parts[0]
+                .parse::<usize>()
+                .map_err(|_| anyhow::anyhow!("Invalid start line number: '{}'", parts[0]))?
+        };
+
+        let end = if parts[1].is_empty() {
+            total_lines
+        } else {
+            parts[1]
+                .parse::<usize>()
+                .map_err(|_| anyhow::anyhow!("Invalid end line number: '{}'", parts[1]))?
+        };
+
+        if start < 1 {
+            anyhow::bail!("Start line must be >= 1");
+        }
+        if end > total_lines {
+            anyhow::bail!("End line {} exceeds file length {}", end, total_lines);
+        }
+        if start > end {
+            anyhow::bail!("Start line {} is greater than end line {}", start, end);
+        }
+
+        Ok((start, end))
+    } else {
+        // Single line number
+        let line_num = range
+            .parse::<usize>()
+            .map_err(|_| anyhow::anyhow!("Invalid line number: '{}'", range))?;
+
+        if line_num < 1 {
+            anyhow::bail!("Line number must be >= 1");
+        }
+        if line_num > total_lines {
+            anyhow::bail!("Line {} exceeds file length {}", line_num, total_lines);
+        }
+
+        Ok((line_num, line_num))
+    }
+}
+
+/// Comma-separated line ranges such as `100,120-140`, each in a format
+/// accepted by `parse_line_range`, sorted and with overlaps merged.
+pub fn parse_line_ranges(spec: &str, total_lines: usize) -> Result<Vec<(usize, usize)>> {
+    let mut ranges = spec
+        .split(',')
+        .map(|range| parse_line_range(range, total_lines))
+        .collect::<Result<Vec<_>>>()?;
+    ranges.sort();
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 + 1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    Ok(merged)
+}
+
+/// The numbered lines of `content` in the ranges of `ranges` (see
+/// `parse_line_ranges`), or all of them.
+pub fn select_lines<'a>(content: &'a str, ranges: Option<&str>) -> Result<Vec<(usize, &'a str)>> {
+    let ranges = match ranges {
+        Some(spec) => parse_line_ranges(spec, content.lines().count())?,
+        None => vec![(1, usize::MAX)],
+    };
+    Ok(content
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| (idx + 1, line))
+        .filter(|(n, _)| ranges.iter().any(|(start, end)| n >= start && n <= end))
+        .collect())
+}
+
+/// The last line the ranges of `spec` ask for, or None when one of them
+/// runs to the end of the file or cannot be parsed.
+pub fn line_ranges_end(spec: &str) -> Option<usize> {
+    spec.split(',')
+        .map(|range| {
+            let end = range.trim().rsplit('-').next()?;
+            end.parse::<usize>().ok()
+        })
+        .try_fold(0, |last, end| Some(last.max(end?)))
+}
+
+/// How much of a file is looked at to decide whether it is binary.
+const SNIFF_LEN: usize = 8000;
+
+/// Signatures of binary formats found in the tree that could otherwise pass
+/// for text.
+const BINARY_SIGNATURES: &[&[u8]] = &[
+    b"\x89PNG",
+    b"GIF8",
+    b"\xff\xd8\xff",
+    b"icns",
+    b"\0asm",
+    b"%PDF",
+    b"PK\x03\x04",
+    b"\x1f\x8b",
+    b"wOFF",
+    b"wOF2",
+];
+
+/// Whether `bytes` look like the contents of a binary file rather than text:
+/// a known binary signature, a NUL byte, or mostly control characters near
+/// the start.
+pub fn is_binary(bytes: &[u8]) -> bool {
+    if BINARY_SIGNATURES.iter().any(|sig| bytes.starts_with(sig)) {
+        return true;
+    }
+    let head = &bytes[..bytes.len().min(SNIFF_LEN)];
+    if head.contains(&0) {
+        return true;
+    }
+    let control = head
+        .iter()
+        .filter(|&&b| b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b))
+        .count();
+    control * 10 > head.len()
+}
+
+/// Decode file contents as UTF-8. Invalid sequences, e.g. in the Latin-1
+/// files still found in the tree, are replaced rather than failing the
+/// whole fetch, with a warning naming `source`.
+pub fn decode_text(bytes: Vec<u8>, source: &str) -> String {
+    match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) => {
+            let offset = e.utf8_error().valid_up_to();
+            warn!(
+                "{} is not valid UTF-8 (first invalid byte at offset {}), invalid bytes replaced",
+                source, offset
+            );
+            String::from_utf8_lossy(e.as_bytes()).into_owned()
+        }
+    }
+}
+
 fn parse_source_lines(html: &str, url: &str) -> Result<String> {
+    Ok(source_lines(html, url)?.concat())
+}
+
+/// Text of each line of a searchfox source page, newline included.
+fn source_lines(html: &str, url: &str) -> Result<Vec<String>> {
     let document = Html::parse_document(html);
     let selector = Selector::parse("code.source-line").expect("valid selector");
     let lines: Vec<String> = document
@@ -107,7 +544,7 @@ fn parse_source_lines(html: &str, url: &str) -> Result<String> {
     if lines.is_empty() {
         anyhow::bail!("Could not find file content at {}", url);
     }
-    Ok(lines.join(""))
+    Ok(lines)
 }
 
 #[cfg(test)]
@@ -242,6 +679,40 @@ mod tests {
         assert!(content.contains("99"));
     }
 
+    #[tokio::test]
+    async fn revision_fetch_uses_rev_url() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/firefox-main/rev/abc123/some/file.js"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(HTML_V1))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = SearchfoxClient::new_for_test("mozilla-central".into(), server.uri()).unwrap();
+        let content = client
+            .get_file_at_revision("some/file.js", "abc123")
+            .await
+            .unwrap();
+        assert!(content.contains("42"));
+    }
+
+    #[tokio::test]
+    async fn revision_fetch_unknown_revision_returns_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/firefox-main/rev/deadbeef/some/file.js"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+
+        let client = SearchfoxClient::new_for_test("mozilla-central".into(), server.uri()).unwrap();
+        assert!(client
+            .get_file_at_revision("some/file.js", "deadbeef")
+            .await
+            .is_err());
+    }
+
     #[tokio::test]
     async fn stale_hit_200_fetches_and_reparses() {
         let server = MockServer::start().await;
@@ -266,4 +737,266 @@ mod tests {
         assert!(content.contains("99"));
         assert!(!content.contains("42"));
     }
+
+    #[tokio::test]
+    async fn hg_backend_fetches_raw_files() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/releases/mozilla-esr128/raw-file/tip/some/file.js"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("let x = 1;\n"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut client =
+            SearchfoxClient::new_for_test("mozilla-esr128".into(), server.uri()).unwrap();
+        client.set_file_backend(FileBackend::Hg);
+        assert_eq!(
+            client.get_file("some/file.js").await.unwrap(),
+            "let x = 1;\n"
+        );
+        // Served from the cache the second time.
+        assert_eq!(
+            client.get_file("some/file.js").await.unwrap(),
+            "let x = 1;\n"
+        );
+    }
+
+    #[test]
+    fn raw_urls_follow_the_configuration() {
+        let config = Config::parse(
+            r#"
+            [repos.glean]
+            raw = "https://raw.githubusercontent.com/mozilla/glean/main/{path}"
+            "#,
+        )
+        .unwrap();
+        let hg = "https://hg.mozilla.org";
+        assert_eq!(
+            repo_raw_url(&config, hg, "glean", "glean-core/src/lib.rs", None).as_deref(),
+            Some("https://raw.githubusercontent.com/mozilla/glean/main/glean-core/src/lib.rs")
+        );
+        // The template cannot point at another revision.
+        assert!(repo_raw_url(&config, hg, "glean", "a.rs", Some("0123abcd")).is_none());
+        assert_eq!(
+            repo_raw_url(&config, hg, "mozilla-beta", "dom/a.cpp", None).as_deref(),
+            Some("https://hg.mozilla.org/releases/mozilla-beta/raw-file/tip/dom/a.cpp")
+        );
+        assert_eq!(
+            repo_raw_url(
+                &config,
+                hg,
+                "mozilla-central",
+                "dom/a.cpp",
+                Some("0123abcd")
+            )
+            .as_deref(),
+            Some("https://hg.mozilla.org/mozilla-central/raw-file/0123abcd/dom/a.cpp")
+        );
+        assert!(repo_raw_url(&config, hg, "mozilla-mobile", "a.kt", None).is_none());
+
+        let pinned = Config::parse(
+            r#"
+            [repos.glean]
+            raw = "https://raw.githubusercontent.com/mozilla/glean/{rev}/{path}"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            repo_raw_url(&pinned, hg, "glean", "a.rs", None).as_deref(),
+            Some("https://raw.githubusercontent.com/mozilla/glean/HEAD/a.rs")
+        );
+        assert_eq!(
+            repo_raw_url(&pinned, hg, "glean", "a.rs", Some("0123abcd")).as_deref(),
+            Some("https://raw.githubusercontent.com/mozilla/glean/0123abcd/a.rs")
+        );
+    }
+
+    #[tokio::test]
+    async fn hg_backend_without_mirror_reads_searchfox() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/l10n/source/fr/toolkit/main.ftl"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(HTML_V1))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut client = SearchfoxClient::new_for_test("l10n".into(), server.uri()).unwrap();
+        client.set_file_backend(FileBackend::Hg);
+        let content = client.get_file("fr/toolkit/main.ftl").await.unwrap();
+        assert!(content.contains("42"));
+    }
+
+    #[test]
+    fn sniffs_binary_content() {
+        assert!(is_binary(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"));
+        assert!(is_binary(b"\0asm\x01\0\0\0"));
+        assert!(is_binary(b"icns\0\x01\x02\x03"));
+        assert!(is_binary(&[0x01, 0x02, 0x03, 0x04, b'a', 0x05]));
+        assert!(!is_binary(b"#include \"AudioStream.h\"\n\tint x;\n"));
+        assert!(!is_binary("// Données audio\n".as_bytes()));
+        assert!(!is_binary(b""));
+    }
+
+    #[test]
+    fn decodes_invalid_utf8_lossily() {
+        assert_eq!(decode_text(b"caf\xc3\xa9".to_vec(), "a.txt"), "café");
+        // Latin-1 "é".
+        assert_eq!(
+            decode_text(b"caf\xe9 au lait".to_vec(), "a.txt"),
+            "caf\u{fffd} au lait"
+        );
+    }
+
+    #[tokio::test]
+    async fn binary_page_is_reported_as_binary() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/firefox-main/source/browser/icon.png"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<html><img></html>"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/firefox-main/raw/browser/icon.png"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"\x89PNG\r\n\x1a\n".to_vec()))
+            .mount(&server)
+            .await;
+
+        let client = SearchfoxClient::new_for_test("mozilla-central".into(), server.uri()).unwrap();
+        let e = client.get_file("browser/icon.png").await.unwrap_err();
+        assert!(e.is::<BinaryFileError>());
+        assert_eq!(
+            client.get_file_bytes("browser/icon.png").await.unwrap(),
+            b"\x89PNG\r\n\x1a\n"
+        );
+    }
+
+    fn numbered_page(count: usize) -> String {
+        let lines: String = (1..=count)
+            .map(|i| format!("<code class=\"source-line\">line {i}\n</code>\n"))
+            .collect();
+        format!("<html><body>{lines}</body></html>")
+    }
+
+    #[tokio::test]
+    async fn file_head_stops_after_requested_lines() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/firefox-main/source/big/Bindings.cpp"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(numbered_page(5000)))
+            .mount(&server)
+            .await;
+
+        let client = SearchfoxClient::new_for_test("mozilla-central".into(), server.uri()).unwrap();
+        let head = client.get_file_head("big/Bindings.cpp", 10).await.unwrap();
+        assert!(head.truncated);
+        assert_eq!(head.content.lines().count(), 10);
+        assert_eq!(head.content.lines().last(), Some("line 10"));
+    }
+
+    #[test]
+    fn line_ranges_are_merged() {
+        assert_eq!(
+            parse_line_ranges("120-140,100,130-150", 200).unwrap(),
+            vec![(100, 100), (120, 150)]
+        );
+        assert_eq!(parse_line_ranges("-2,3", 10).unwrap(), vec![(1, 3)]);
+        assert!(parse_line_ranges("5,300", 200).is_err());
+        let content = "a\nb\nc\nd\n";
+        assert_eq!(
+            select_lines(content, Some("1,3-")).unwrap(),
+            vec![(1, "a"), (3, "c"), (4, "d")]
+        );
+    }
+
+    #[test]
+    fn bounded_ranges_have_an_end() {
+        assert_eq!(line_ranges_end("200-260"), Some(260));
+        assert_eq!(line_ranges_end("10,40-50, 7"), Some(50));
+        assert_eq!(line_ranges_end("-20"), Some(20));
+        assert_eq!(line_ranges_end("200-"), None);
+        assert_eq!(line_ranges_end("10,x"), None);
+    }
+
+    #[tokio::test]
+    async fn file_lines_read_only_the_head() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/firefox-main/source/big/Bindings.cpp"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(numbered_page(5000)))
+            .mount(&server)
+            .await;
+
+        let client = SearchfoxClient::new_for_test("mozilla-central".into(), server.uri()).unwrap();
+        let lines = client
+            .get_file_lines("big/Bindings.cpp", "3-4,10")
+            .await
+            .unwrap();
+        assert_eq!(
+            lines,
+            vec![
+                (3, "line 3".to_string()),
+                (4, "line 4".to_string()),
+                (10, "line 10".to_string())
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn fetches_files_concurrently_in_order() {
+        let server = MockServer::start().await;
+        for (name, count) in [("a.cpp", 2), ("b.cpp", 1)] {
+            Mock::given(method("GET"))
+                .and(path(format!("/firefox-main/source/{name}")))
+                .respond_with(ResponseTemplate::new(200).set_body_string(numbered_page(count)))
+                .mount(&server)
+                .await;
+        }
+
+        let client = SearchfoxClient::new_for_test("mozilla-central".into(), server.uri()).unwrap();
+        let paths = ["b.cpp", "missing.cpp", "a.cpp"].map(String::from);
+        let files = client.get_files(&paths, None).await;
+        let names: Vec<&str> = files.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(names, ["b.cpp", "missing.cpp", "a.cpp"]);
+        assert_eq!(files[0].1.as_ref().unwrap(), "line 1\n");
+        assert!(files[1].1.is_err());
+        assert_eq!(files[2].1.as_ref().unwrap(), "line 1\nline 2\n");
+    }
+
+    #[tokio::test]
+    async fn file_head_of_short_file_is_whole_file() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/firefox-main/source/small.cpp"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(numbered_page(3)))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = SearchfoxClient::new_for_test("mozilla-central".into(), server.uri()).unwrap();
+        let head = client.get_file_head("small.cpp", 10).await.unwrap();
+        assert!(!head.truncated);
+        assert_eq!(head.content, "line 1\nline 2\nline 3\n");
+        // Complete files are cached for later full reads.
+        assert_eq!(client.get_file("small.cpp").await.unwrap(), head.content);
+    }
+
+    #[tokio::test]
+    async fn file_head_from_hg_cuts_at_line_boundary() {
+        let server = MockServer::start().await;
+        let body: String = (1..=1000).map(|i| format!("line {i}\n")).collect();
+        Mock::given(method("GET"))
+            .and(path("/mozilla-central/raw-file/tip/some/file.js"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .mount(&server)
+            .await;
+
+        let mut client =
+            SearchfoxClient::new_for_test("mozilla-central".into(), server.uri()).unwrap();
+        client.set_file_backend(FileBackend::Hg);
+        let head = client.get_file_head("some/file.js", 2).await.unwrap();
+        assert!(head.truncated);
+        assert_eq!(head.content, "line 1\nline 2\n");
+    }
 }
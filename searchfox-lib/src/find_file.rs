@@ -0,0 +1,187 @@
+use crate::client::SearchfoxClient;
+use crate::search::SearchOptions;
+use crate::utils::edit_distance;
+use anyhow::Result;
+use futures::future::join_all;
+use log::debug;
+
+/// Path searches run to gather candidates for a fuzzy name.
+const MAX_FRAGMENTS: usize = 3;
+
+/// Pieces of `name` likely to be spelled right, to gather candidate paths
+/// with: its words, split at punctuation and lower-to-upper case changes,
+/// and the first half of the long ones, longest first.
+pub fn name_fragments(name: &str) -> Vec<String> {
+    let mut words: Vec<String> = Vec::new();
+    let mut word = String::new();
+    let mut previous: Option<char> = None;
+    for c in name.chars() {
+        let boundary = !c.is_alphanumeric()
+            || (c.is_uppercase() && previous.is_some_and(|p| p.is_lowercase()));
+        if boundary && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        if c.is_alphanumeric() {
+            word.push(c);
+        }
+        previous = Some(c);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    let mut fragments: Vec<String> = Vec::new();
+    for word in &words {
+        let chars: Vec<char> = word.chars().collect();
+        fragments.push(word.clone());
+        if chars.len() > 6 {
+            fragments.push(chars[..chars.len() / 2].iter().collect());
+        }
+    }
+    fragments.retain(|f| f.chars().count() >= 3);
+    fragments.sort_by_key(|f| std::cmp::Reverse(f.chars().count()));
+    fragments.dedup();
+    fragments.truncate(MAX_FRAGMENTS);
+    fragments
+}
+
+/// Whether `c` starts a word of `text` at `index`: after a separator, or
+/// at a lower-to-upper case change.
+fn starts_word(text: &[char], index: usize) -> bool {
+    index == 0
+        || matches!(text[index - 1], '/' | '_' | '-' | '.')
+        || (text[index].is_uppercase() && text[index - 1].is_lowercase())
+}
+
+/// How well the characters of `query` appear in order in `text`, ignoring
+/// case: a point per character, more for runs and for word starts. None
+/// when they do not all appear.
+fn subsequence_score(query: &[char], text: &[char]) -> Option<i64> {
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+    for q in query {
+        let found = (next..text.len()).find(|&i| text[i].to_lowercase().eq(q.to_lowercase()))?;
+        score += 1;
+        if previous.is_some_and(|p| p + 1 == found) {
+            score += 5;
+        }
+        if starts_word(text, found) {
+            score += 3;
+        }
+        previous = Some(found);
+        next = found + 1;
+    }
+    Some(score)
+}
+
+/// Score of `path` for the half-remembered file name `query`, higher being
+/// better: the query's characters found in order in the file name, then in
+/// the whole path, then a file name a few typos away. None when the path
+/// does not match at all.
+pub fn fuzzy_score(query: &str, path: &str) -> Option<i64> {
+    let basename = path.rsplit('/').next().unwrap_or(path);
+    let query_chars: Vec<char> = query.chars().collect();
+    let name_chars: Vec<char> = basename.chars().collect();
+    let extra = name_chars.len().abs_diff(query_chars.len()) as i64;
+    if let Some(score) = subsequence_score(&query_chars, &name_chars) {
+        return Some(1000 + 10 * score - extra);
+    }
+    let path_chars: Vec<char> = path.chars().collect();
+    if let Some(score) = subsequence_score(&query_chars, &path_chars) {
+        return Some(500 + 10 * score - path_chars.len() as i64 / 8);
+    }
+    let distance = edit_distance(&query.to_lowercase(), &basename.to_lowercase());
+    (distance <= (query_chars.len() / 3).max(1)).then(|| 200 - 20 * distance as i64 - extra)
+}
+
+/// The paths matching `query`, best first, then shorter, then in
+/// alphabetical order.
+pub fn rank_paths(query: &str, paths: &[String]) -> Vec<String> {
+    let mut scored: Vec<(i64, &String)> = paths
+        .iter()
+        .filter_map(|path| Some((fuzzy_score(query, path)?, path)))
+        .collect();
+    scored.sort_by(|a, b| {
+        b.0.cmp(&a.0)
+            .then(a.1.len().cmp(&b.1.len()))
+            .then(a.1.cmp(b.1))
+    });
+    scored.dedup_by(|a, b| a.1 == b.1);
+    scored.into_iter().map(|(_, path)| path.clone()).collect()
+}
+
+impl SearchfoxClient {
+    /// Files whose name looks like `query`, best first, for when the name
+    /// is only half remembered. Candidates come from path searches for the
+    /// fragments of `query`; language and category filters apply.
+    pub async fn find_files(&self, query: &str, options: &SearchOptions) -> Result<Vec<String>> {
+        let fragments = name_fragments(query);
+        if fragments.is_empty() {
+            anyhow::bail!("'{}' is too short to look for files with", query);
+        }
+        let searches = fragments.iter().map(|fragment| {
+            let listing = SearchOptions {
+                query: None,
+                path: Some(fragment.clone()),
+                extra_paths: Vec::new(),
+                regexp: false,
+                limit: usize::MAX,
+                offset: 0,
+                context: None,
+                symbol: None,
+                id: None,
+                filter: None,
+                ..options.clone()
+            };
+            async move { self.search(&listing).await }
+        });
+        let mut candidates = Vec::new();
+        for (fragment, results) in fragments.iter().zip(join_all(searches).await) {
+            match results {
+                Ok(results) => candidates.extend(results.into_iter().map(|r| r.path)),
+                Err(e) => debug!("Path search for {fragment} failed: {e}"),
+            }
+        }
+        candidates.sort();
+        candidates.dedup();
+        Ok(rank_paths(query, &candidates)
+            .into_iter()
+            .skip(options.offset)
+            .take(options.limit)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fragments_are_words_and_prefixes() {
+        assert_eq!(name_fragments("AudioStrm.h"), ["Audio", "Strm"]);
+        assert_eq!(name_fragments("audiostrm"), ["audiostrm", "audi"]);
+        assert!(name_fragments("a.h").is_empty());
+    }
+
+    #[test]
+    fn ranks_like_an_editor() {
+        let paths: Vec<String> = [
+            "dom/media/AudioStream.cpp",
+            "dom/media/AudioStream.h",
+            "dom/media/AudioSegment.h",
+            "dom/media/webaudio/AudioNodeTrack.h",
+            "media/libcubeb/src/cubeb_audiounit.cpp",
+            "dom/media/test/crashtests/audiostream.html",
+        ]
+        .map(String::from)
+        .to_vec();
+        let ranked = rank_paths("AudioStrm.h", &paths);
+        assert_eq!(ranked[0], "dom/media/AudioStream.h");
+        assert!(!ranked.contains(&"dom/media/AudioSegment.h".to_string()));
+
+        // A typo the subsequence match cannot bridge.
+        let ranked = rank_paths("AudoiStream.h", &paths);
+        assert_eq!(ranked[0], "dom/media/AudioStream.h");
+    }
+}
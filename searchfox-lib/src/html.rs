@@ -0,0 +1,407 @@
+use crate::utils::language_id;
+use regex::{Captures, Regex};
+
+/// A collapsible section of an HTML report, usually the hits in one file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HtmlSection {
+    pub title: String,
+    /// Link of the title, to the file on searchfox.
+    pub url: Option<String>,
+    pub items: Vec<HtmlItem>,
+}
+
+/// An entry of a section: a link, and the source lines it points to if any.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HtmlItem {
+    pub label: String,
+    pub url: String,
+    pub snippet: Option<HtmlSnippet>,
+}
+
+/// Consecutive source lines of a file, the hit among them highlighted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HtmlSnippet {
+    /// Path of the file, for the language of the code.
+    pub path: String,
+    pub first_line: usize,
+    pub lines: Vec<String>,
+    pub hit_line: Option<usize>,
+}
+
+const STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222}\
+h1{font-size:1.4em}\
+details{margin:.5em 0;border:1px solid #ddd;border-radius:4px;padding:.3em .6em}\
+summary{cursor:pointer;font-weight:bold}\
+summary .count{font-weight:normal;color:#777}\
+ul{list-style:none;padding-left:.5em}\
+li{margin:.4em 0}\
+pre{background:#f7f7f7;padding:.4em;overflow-x:auto;margin:.2em 0}\
+.ln{color:#999;user-select:none;display:inline-block;min-width:4em;text-align:right;padding-right:1em}\
+.hit{background:#fff3b0;display:block}\
+.k{color:#a626a4}.s{color:#50a14f}.c{color:#a0a1a7;font-style:italic}.n{color:#986801}\
+footer{margin-top:2em;color:#777;font-size:.9em}";
+
+/// `text` with the characters HTML gives a meaning to escaped.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn keywords(language: &str) -> &'static [&'static str] {
+    match language {
+        "c" | "cpp" | "idl" | "webidl" => &[
+            "alignas",
+            "auto",
+            "bool",
+            "break",
+            "case",
+            "catch",
+            "char",
+            "class",
+            "const",
+            "constexpr",
+            "continue",
+            "default",
+            "delete",
+            "do",
+            "double",
+            "else",
+            "enum",
+            "explicit",
+            "extern",
+            "false",
+            "final",
+            "float",
+            "for",
+            "friend",
+            "goto",
+            "if",
+            "inline",
+            "int",
+            "interface",
+            "long",
+            "mutable",
+            "namespace",
+            "new",
+            "noexcept",
+            "nullptr",
+            "operator",
+            "override",
+            "private",
+            "protected",
+            "public",
+            "readonly",
+            "return",
+            "short",
+            "signed",
+            "sizeof",
+            "static",
+            "static_cast",
+            "struct",
+            "switch",
+            "template",
+            "this",
+            "throw",
+            "true",
+            "try",
+            "typedef",
+            "typename",
+            "union",
+            "unsigned",
+            "using",
+            "virtual",
+            "void",
+            "volatile",
+            "while",
+        ],
+        "rust" => &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+            "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+            "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+            "trait", "true", "type", "unsafe", "use", "where", "while",
+        ],
+        "javascript" | "typescript" => &[
+            "async",
+            "await",
+            "break",
+            "case",
+            "catch",
+            "class",
+            "const",
+            "continue",
+            "default",
+            "delete",
+            "do",
+            "else",
+            "export",
+            "extends",
+            "false",
+            "finally",
+            "for",
+            "function",
+            "if",
+            "import",
+            "in",
+            "instanceof",
+            "interface",
+            "let",
+            "new",
+            "null",
+            "of",
+            "return",
+            "static",
+            "super",
+            "switch",
+            "this",
+            "throw",
+            "true",
+            "try",
+            "typeof",
+            "undefined",
+            "var",
+            "void",
+            "while",
+            "yield",
+        ],
+        "python" => &[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+            "elif", "else", "except", "False", "finally", "for", "from", "if", "import", "in",
+            "is", "lambda", "None", "not", "or", "pass", "raise", "return", "self", "True", "try",
+            "while", "with", "yield",
+        ],
+        "java" | "kotlin" => &[
+            "abstract",
+            "boolean",
+            "break",
+            "case",
+            "catch",
+            "class",
+            "else",
+            "extends",
+            "false",
+            "final",
+            "for",
+            "fun",
+            "if",
+            "implements",
+            "import",
+            "int",
+            "interface",
+            "new",
+            "null",
+            "override",
+            "package",
+            "private",
+            "protected",
+            "public",
+            "return",
+            "static",
+            "super",
+            "this",
+            "throw",
+            "true",
+            "try",
+            "val",
+            "var",
+            "void",
+            "when",
+            "while",
+        ],
+        _ => &[],
+    }
+}
+
+/// Tokens worth colouring in `language`: comments, strings, numbers and
+/// words, the latter coloured when they are keywords.
+fn token_regex(language: &str) -> Regex {
+    let (comment, string) = match language {
+        "python" => (r"#.*", r#""(?:\\.|[^"\\])*"?|'(?:\\.|[^'\\])*'?"#),
+        "rust" => (
+            r"//.*|/\*.*?(?:\*/|$)",
+            r#""(?:\\.|[^"\\])*"?|'(?:\\.|[^'\\])'"#,
+        ),
+        "javascript" | "typescript" => (
+            r"//.*|/\*.*?(?:\*/|$)",
+            r#""(?:\\.|[^"\\])*"?|'(?:\\.|[^'\\])*'?|`(?:\\.|[^`\\])*`?"#,
+        ),
+        "plaintext" => (r"[^\s\S]", r"[^\s\S]"),
+        _ => (
+            r"//.*|/\*.*?(?:\*/|$)|^\s*\*(?:[ /]|$).*|^\s*#\s*\w+",
+            r#""(?:\\.|[^"\\])*"?|'(?:\\.|[^'\\])*'"#,
+        ),
+    };
+    Regex::new(&format!(
+        r"(?P<c>{comment})|(?P<s>{string})|(?P<n>\b\d[\w.]*)|(?P<w>\b[A-Za-z_]\w*)"
+    ))
+    .expect("valid regex")
+}
+
+/// A line of code in `language` (see `language_id`) as escaped HTML, its
+/// comments, strings, numbers and keywords in spans of class `c`, `s`, `n`
+/// and `k`. Lines are highlighted on their own, so the inside of a comment
+/// spanning several lines is only recognized by its leading `*`.
+pub fn highlight(line: &str, language: &str) -> String {
+    highlight_with(&token_regex(language), keywords(language), line)
+}
+
+fn highlight_with(tokens: &Regex, keywords: &[&str], line: &str) -> String {
+    let mut html = String::new();
+    let mut last = 0;
+    for captures in tokens.captures_iter(line) {
+        let token = captures.get(0).expect("whole match");
+        let class = class_of(&captures, keywords);
+        html.push_str(&escape(&line[last..token.start()]));
+        match class {
+            Some(class) => html.push_str(&format!(
+                "<span class=\"{}\">{}</span>",
+                class,
+                escape(token.as_str())
+            )),
+            None => html.push_str(&escape(token.as_str())),
+        }
+        last = token.end();
+    }
+    html.push_str(&escape(&line[last..]));
+    html
+}
+
+fn class_of(captures: &Captures, keywords: &[&str]) -> Option<&'static str> {
+    if captures.name("c").is_some() {
+        Some("c")
+    } else if captures.name("s").is_some() {
+        Some("s")
+    } else if captures.name("n").is_some() {
+        Some("n")
+    } else {
+        captures
+            .name("w")
+            .filter(|w| keywords.contains(&w.as_str()))
+            .map(|_| "k")
+    }
+}
+
+fn render_snippet(snippet: &HtmlSnippet) -> String {
+    let language = language_id(&snippet.path);
+    let tokens = token_regex(language);
+    let keywords = keywords(language);
+    let mut html = String::from("<pre><code>");
+    for (i, line) in snippet.lines.iter().enumerate() {
+        let number = snippet.first_line + i;
+        let code = format!(
+            "<span class=\"ln\">{}</span>{}",
+            number,
+            highlight_with(&tokens, keywords, line)
+        );
+        if snippet.hit_line == Some(number) {
+            html.push_str(&format!("<span class=\"hit\">{code}</span>"));
+        } else {
+            html.push_str(&code);
+            html.push('\n');
+        }
+    }
+    html.push_str("</code></pre>");
+    html
+}
+
+/// A standalone HTML page titled `title`, one collapsible section per entry
+/// of `sections`, with `footer` below them. Styles are inline, so the file
+/// can be shared and opened as is.
+pub fn render_html_report(title: &str, sections: &[HtmlSection], footer: &str) -> String {
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{title}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n<h1>{title}</h1>\n",
+        title = escape(title)
+    );
+    if sections.is_empty() {
+        html.push_str("<p>No results.</p>\n");
+    }
+    for section in sections {
+        let title = match &section.url {
+            Some(url) => format!("<a href=\"{}\">{}</a>", escape(url), escape(&section.title)),
+            None => escape(&section.title),
+        };
+        html.push_str(&format!(
+            "<details open>\n<summary>{} <span class=\"count\">({})</span></summary>\n<ul>\n",
+            title,
+            section.items.len()
+        ));
+        for item in &section.items {
+            html.push_str(&format!(
+                "<li><a href=\"{}\">{}</a>",
+                escape(&item.url),
+                escape(&item.label)
+            ));
+            if let Some(snippet) = &item.snippet {
+                html.push_str(&render_snippet(snippet));
+            }
+            html.push_str("</li>\n");
+        }
+        html.push_str("</ul>\n</details>\n");
+    }
+    html.push_str(&format!(
+        "<footer>{}</footer>\n</body>\n</html>\n",
+        escape(footer)
+    ));
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlights_and_escapes_code() {
+        assert_eq!(
+            highlight("if (a < 2) return \"x\"; // done", "cpp"),
+            "<span class=\"k\">if</span> (a &lt; <span class=\"n\">2</span>) \
+             <span class=\"k\">return</span> <span class=\"s\">&quot;x&quot;</span>; \
+             <span class=\"c\">// done</span>"
+        );
+        // Lifetimes are not character literals.
+        assert_eq!(
+            highlight("fn f<'a>(c: char) -> bool { c == 'x' }", "rust"),
+            "<span class=\"k\">fn</span> f&lt;&#39;a&gt;(c: char) -&gt; bool { c == \
+             <span class=\"s\">&#39;x&#39;</span> }"
+        );
+        assert_eq!(highlight("<b>if</b>", "plaintext"), "&lt;b&gt;if&lt;/b&gt;");
+    }
+
+    #[test]
+    fn renders_collapsible_sections() {
+        let sections = vec![HtmlSection {
+            title: "dom/a.cpp".into(),
+            url: Some("https://example.org/a?x=1&y=2".into()),
+            items: vec![HtmlItem {
+                label: "Line 3".into(),
+                url: "https://example.org/a#3".into(),
+                snippet: Some(HtmlSnippet {
+                    path: "dom/a.cpp".into(),
+                    first_line: 2,
+                    lines: vec!["void f() {".into(), "  g();".into()],
+                    hit_line: Some(3),
+                }),
+            }],
+        }];
+        let html = render_html_report("Search for <g>", &sections, "footer");
+        assert!(html.contains("<title>Search for &lt;g&gt;</title>"));
+        assert!(html.contains(
+            "<summary><a href=\"https://example.org/a?x=1&amp;y=2\">dom/a.cpp</a> \
+             <span class=\"count\">(1)</span></summary>"
+        ));
+        assert!(html.contains(
+            "<span class=\"ln\">2</span><span class=\"k\">void</span> f() {\n\
+             <span class=\"hit\"><span class=\"ln\">3</span>  g();</span></code></pre>"
+        ));
+        assert!(render_html_report("t", &[], "").contains("<p>No results.</p>"));
+    }
+}
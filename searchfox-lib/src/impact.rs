@@ -0,0 +1,203 @@
+use crate::call_graph::CallGraphQuery;
+use crate::client::SearchfoxClient;
+use crate::search::CategoryFilter;
+use crate::utils::path_category;
+use anyhow::Result;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A function that directly or transitively calls the analyzed symbol.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ImpactedCaller {
+    pub pretty: String,
+    pub symbol: String,
+    /// File defining (or declaring) the caller, when known.
+    pub path: Option<String>,
+}
+
+/// Transitive callers of a symbol and where they live.
+#[derive(Debug, Default)]
+pub struct ImpactReport {
+    pub callers: Vec<ImpactedCaller>,
+    /// Caller count per file.
+    pub by_file: BTreeMap<String, usize>,
+    /// Caller count per directory.
+    pub by_dir: BTreeMap<String, usize>,
+}
+
+/// Collect the callers of a calls-to graph, keeping those whose file
+/// passes `filter`. Callers without a known location are always kept.
+pub fn analyze_impact(json: &serde_json::Value, filter: CategoryFilter) -> ImpactReport {
+    let jumprefs = json.get("jumprefs").and_then(|v| v.as_object());
+
+    let mut caller_syms = BTreeSet::new();
+    for graph in json
+        .get("graphs")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+    {
+        for edge in graph
+            .get("edges")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+        {
+            if let Some(from) = edge.get("from").and_then(|v| v.as_str()) {
+                caller_syms.insert(from.to_string());
+            }
+        }
+    }
+
+    let mut report = ImpactReport::default();
+    let mut callers = BTreeSet::new();
+    for sym in caller_syms {
+        let info = jumprefs.and_then(|j| j.get(&sym));
+        let pretty = info
+            .and_then(|i| i.get("pretty"))
+            .and_then(|v| v.as_str())
+            .unwrap_or(&sym)
+            .to_string();
+        let jumps = info.and_then(|i| i.get("jumps"));
+        let path = jumps
+            .and_then(|j| j.get("def").or_else(|| j.get("decl")))
+            .and_then(|v| v.as_str())
+            .map(|loc| loc.split('#').next().unwrap_or(loc).to_string())
+            .filter(|p| !p.is_empty());
+
+        if let Some(ref path) = path {
+            if !filter.should_include(path_category(path)) {
+                continue;
+            }
+            *report.by_file.entry(path.clone()).or_default() += 1;
+            let dir = path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or(".");
+            *report.by_dir.entry(dir.to_string()).or_default() += 1;
+        }
+        callers.insert(ImpactedCaller {
+            pretty,
+            symbol: sym,
+            path,
+        });
+    }
+    report.callers = callers.into_iter().collect();
+    report
+}
+
+impl SearchfoxClient {
+    /// Transitive callers of `symbol` up to `depth` levels, for estimating
+    /// the blast radius of changing it.
+    pub async fn impact(
+        &self,
+        symbol: &str,
+        depth: u32,
+        filter: CategoryFilter,
+    ) -> Result<ImpactReport> {
+        let query = CallGraphQuery {
+            calls_from: None,
+            calls_to: Some(symbol.to_string()),
+            calls_between: None,
+            depth,
+        };
+        let json = self.search_call_graph(&query).await?;
+        Ok(analyze_impact(&json, filter))
+    }
+}
+
+pub fn format_impact_report(symbol: &str, depth: u32, report: &ImpactReport) -> String {
+    let mut output = format!("# Impact of {} (calls-to depth {})\n\n", symbol, depth);
+    if report.callers.is_empty() {
+        output.push_str("No callers found.\n");
+        return output;
+    }
+
+    output.push_str(&format!(
+        "{} callers in {} files across {} directories\n\n",
+        report.callers.len(),
+        report.by_file.len(),
+        report.by_dir.len()
+    ));
+
+    let mut dirs: Vec<(&String, &usize)> = report.by_dir.iter().collect();
+    dirs.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    output.push_str("## Directories\n\n");
+    for (dir, count) in dirs {
+        output.push_str(&format!("- {} ({})\n", dir, count));
+    }
+
+    output.push_str("\n## Files\n\n");
+    for (file, count) in &report.by_file {
+        let names: Vec<&str> = report
+            .callers
+            .iter()
+            .filter(|c| c.path.as_deref() == Some(file.as_str()))
+            .map(|c| c.pretty.as_str())
+            .collect();
+        output.push_str(&format!("- {} ({}): {}\n", file, count, names.join(", ")));
+    }
+
+    let unknown: Vec<&str> = report
+        .callers
+        .iter()
+        .filter(|c| c.path.is_none())
+        .map(|c| c.pretty.as_str())
+        .collect();
+    if !unknown.is_empty() {
+        output.push_str(&format!(
+            "\n## Unknown location\n\n- {}\n",
+            unknown.join(", ")
+        ));
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph() -> serde_json::Value {
+        serde_json::json!({
+            "graphs": [{
+                "edges": [
+                    {"from": "_ZA", "to": "_ZTarget"},
+                    {"from": "_ZB", "to": "_ZA"},
+                    {"from": "_ZT", "to": "_ZTarget"}
+                ]
+            }],
+            "jumprefs": {
+                "_ZA": {"pretty": "ns::A", "jumps": {"def": "dom/media/A.cpp#10"}},
+                "_ZB": {"pretty": "ns::B", "jumps": {"decl": "dom/base/B.h#5"}},
+                "_ZT": {"pretty": "TestThing", "jumps": {"def": "dom/media/gtest/TestA.cpp#3"}}
+            }
+        })
+    }
+
+    #[test]
+    fn collects_transitive_callers_by_file_and_dir() {
+        let report = analyze_impact(&graph(), CategoryFilter::All);
+        assert_eq!(report.callers.len(), 3);
+        assert_eq!(report.by_file["dom/media/A.cpp"], 1);
+        assert_eq!(report.by_file["dom/base/B.h"], 1);
+        assert_eq!(report.by_dir["dom/media"], 1);
+        assert_eq!(report.by_dir["dom/media/gtest"], 1);
+    }
+
+    #[test]
+    fn excludes_tests() {
+        let report = analyze_impact(&graph(), CategoryFilter::ExcludeTests);
+        let names: Vec<&str> = report.callers.iter().map(|c| c.pretty.as_str()).collect();
+        assert_eq!(names, vec!["ns::A", "ns::B"]);
+        assert!(!report.by_dir.contains_key("dom/media/gtest"));
+    }
+
+    #[test]
+    fn path_categories() {
+        assert_eq!(path_category("dom/media/gtest/TestA.cpp"), "test");
+        assert_eq!(path_category("dom/media/test/test_foo.html"), "test");
+        assert_eq!(
+            path_category("__GENERATED__/dist/include/Foo.h"),
+            "generated"
+        );
+        assert_eq!(path_category("dom/media/AudioStream.cpp"), "normal");
+        assert_eq!(path_category("dom/media/TestingUtils.h"), "normal");
+    }
+}
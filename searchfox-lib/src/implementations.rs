@@ -0,0 +1,394 @@
+use crate::client::SearchfoxClient;
+use crate::search::SearchOptions;
+use crate::types::{File, SearchfoxResponse};
+use anyhow::Result;
+use futures::stream::{self, StreamExt};
+use std::collections::BTreeMap;
+
+const CONCURRENT_FETCHES: usize = 8;
+
+/// How far above a QueryInterface entry its map or macro may start.
+const MAX_QI_BLOCK_LINES: usize = 200;
+
+/// A class implementing an XPCOM interface.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Implementation {
+    /// Class name, qualified when known.
+    pub class: String,
+    /// Where the class declares the interface methods (`NS_DECL_NSIFOO`).
+    pub declared_at: Option<(String, usize)>,
+    /// Where its QueryInterface implementation lists the interface.
+    pub qi_at: Option<(String, usize)>,
+    /// Every interface its QueryInterface answers to, in source order.
+    pub qi_entries: Vec<String>,
+}
+
+/// The macro declaring the methods of an XPIDL interface in a class.
+pub fn decl_macro(interface: &str) -> String {
+    format!("NS_DECL_{}", interface.to_uppercase())
+}
+
+/// Name and arguments of the first macro call starting in `text`, which may
+/// span several lines. `None` when the parentheses are not closed.
+fn macro_call(text: &str) -> Option<(&str, Vec<String>)> {
+    let open = text.find('(')?;
+    let name = text[..open].trim();
+    let name = name.rsplit(|c: char| c.is_whitespace()).next()?;
+    let mut depth = 0;
+    let mut args = vec![String::new()];
+    for c in text[open..].chars() {
+        match c {
+            '(' => {
+                depth += 1;
+                if depth == 1 {
+                    continue;
+                }
+            }
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    let args = args
+                        .into_iter()
+                        .map(|a| a.trim().to_string())
+                        .filter(|a| !a.is_empty())
+                        .collect();
+                    return Some((name, args));
+                }
+            }
+            ',' if depth == 1 => {
+                args.push(String::new());
+                continue;
+            }
+            '\\' | '\n' => continue,
+            _ => {}
+        }
+        if let Some(last) = args.last_mut() {
+            last.push(c);
+        }
+    }
+    None
+}
+
+fn is_map_start(name: &str) -> bool {
+    name.starts_with("NS_INTERFACE_MAP_BEGIN") || name.starts_with("NS_INTERFACE_TABLE_HEAD")
+}
+
+fn is_map_end(name: &str) -> bool {
+    name.starts_with("NS_INTERFACE_MAP_END") || name.starts_with("NS_INTERFACE_TABLE_TAIL")
+}
+
+/// Interfaces listed by a one-macro QueryInterface implementation such as
+/// `NS_IMPL_ISUPPORTS(Class, nsIA, nsIB)`, with the class it is for.
+fn isupports_macro(name: &str, args: &[String]) -> Option<(String, Vec<String>)> {
+    let is_qi = name.starts_with("NS_IMPL_")
+        && (name.contains("ISUPPORTS") || name.contains("QUERY_INTERFACE"))
+        || name == "NS_INTERFACE_TABLE";
+    if !is_qi {
+        return None;
+    }
+    let (class, rest) = args.split_first()?;
+    // The second argument of the _INHERITED variants is the base class.
+    let skip = usize::from(name.contains("INHERITED"));
+    Some((class.clone(), rest.iter().skip(skip).cloned().collect()))
+}
+
+/// The QueryInterface implementation covering `line` (1-based) of `source`:
+/// the class it is for and every interface it lists. Handles the
+/// `NS_IMPL_*ISUPPORTS*` / `NS_IMPL_QUERY_INTERFACE*` macros and
+/// `NS_INTERFACE_MAP_BEGIN` ... `NS_INTERFACE_MAP_END` blocks.
+pub fn parse_qi_block(source: &str, line: usize) -> Option<(String, Vec<String>)> {
+    let lines: Vec<&str> = source.lines().collect();
+    let index = line.checked_sub(1)?;
+    if index >= lines.len() {
+        return None;
+    }
+    let call_at = |i: usize| {
+        let text = lines[i..lines.len().min(i + 50)].join("\n");
+        macro_call(&text).map(|(name, args)| (name.to_string(), args))
+    };
+
+    for start in (index.saturating_sub(MAX_QI_BLOCK_LINES)..=index).rev() {
+        if !lines[start].contains("NS_") {
+            continue;
+        }
+        let Some((name, args)) = call_at(start) else {
+            continue;
+        };
+        if let Some(found) = isupports_macro(&name, &args) {
+            return Some(found);
+        }
+        if is_map_start(&name) {
+            let class = args.first()?.clone();
+            let mut entries = Vec::new();
+            for (i, text) in lines.iter().enumerate().skip(start + 1) {
+                if !text.contains("NS_") {
+                    continue;
+                }
+                let Some((name, args)) = call_at(i) else {
+                    continue;
+                };
+                if is_map_end(&name) {
+                    break;
+                }
+                if let Some((_, listed)) = isupports_macro(&name, &args) {
+                    entries.extend(listed);
+                } else if name.contains("ENTRY") {
+                    entries.extend(args.into_iter().next());
+                }
+            }
+            return Some((class, entries));
+        }
+        if is_map_end(&name) && start != index {
+            // The line is after the end of a map, not inside one.
+            return None;
+        }
+    }
+    None
+}
+
+/// `(path, line)` of every matched line in a search response.
+fn matched_lines(json: &SearchfoxResponse, options: &SearchOptions) -> Vec<(String, usize)> {
+    let mut found = Vec::new();
+    let mut add = |file: &serde_json::Value| {
+        let Ok(file) = serde_json::from_value::<File>(file.clone()) else {
+            return;
+        };
+        if options.matches_language_filter(&file.path) {
+            found.extend(file.lines.iter().map(|l| (file.path.clone(), l.lno)));
+        }
+    };
+    for (category, value) in json {
+        if category.starts_with('*') || !options.category_filter.should_include(category) {
+            continue;
+        }
+        if let Some(file_list) = value.as_array() {
+            file_list.iter().for_each(&mut add);
+        } else if let Some(obj) = value.as_object() {
+            for file_list in obj.values() {
+                file_list
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .for_each(&mut add);
+            }
+        }
+    }
+    found.sort();
+    found.dedup();
+    found
+}
+
+/// Whether `qualified` and `written` (as it appears in a macro argument,
+/// possibly partially qualified) name the same class.
+fn same_class(qualified: &str, written: &str) -> bool {
+    qualified == written || qualified.ends_with(&format!("::{written}"))
+}
+
+impl SearchfoxClient {
+    /// Find the classes implementing XPCOM interface `interface`, from the
+    /// `NS_DECL_` macro declaring its methods in a class and from the
+    /// QueryInterface implementations listing it. Classes only inheriting
+    /// the interface from an implementing base class are not found.
+    pub async fn find_implementations(
+        &self,
+        interface: &str,
+        options: &SearchOptions,
+    ) -> Result<Vec<Implementation>> {
+        let search = |query: String| SearchOptions {
+            query: Some(query),
+            symbol: None,
+            id: None,
+            regexp: true,
+            case: true,
+            context: None,
+            limit: usize::MAX,
+            ..options.clone()
+        };
+        let decl_options = search(format!(r"\b{}\b", decl_macro(interface)));
+        let qi_options = search(format!(
+            r"NS_(IMPL_\w*(ISUPPORTS|QUERY_INTERFACE)|INTERFACE_)\w*\(.*\b{}\b",
+            regex::escape(interface)
+        ));
+        let (decl_json, qi_json) = futures::try_join!(
+            self.search_response(&decl_options),
+            self.search_response(&qi_options)
+        )?;
+
+        // Classes declaring the interface methods, from searchfox's nesting data.
+        let declarations: Vec<(String, (String, usize))> =
+            stream::iter(matched_lines(&decl_json, &decl_options))
+                .map(|(path, line)| async move {
+                    let block = self.get_enclosing_block(&path, line).await;
+                    (block, path, line)
+                })
+                .buffered(CONCURRENT_FETCHES)
+                .filter_map(
+                    |(block, path, line)| async move { Some((block.ok()??.sym, (path, line))) },
+                )
+                .collect()
+                .await;
+
+        // QueryInterface implementations, one fetch per file.
+        let mut qi_lines: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for (path, line) in matched_lines(&qi_json, &qi_options) {
+            qi_lines.entry(path).or_default().push(line);
+        }
+        let qi_maps: Vec<(String, usize, String, Vec<String>)> = stream::iter(qi_lines)
+            .map(|(path, lines)| async move {
+                let source = self.get_file(&path).await;
+                (path, lines, source)
+            })
+            .buffered(CONCURRENT_FETCHES)
+            .flat_map(|(path, lines, source)| {
+                let found: Vec<_> = match source {
+                    Ok(source) => lines
+                        .into_iter()
+                        .filter_map(|line| {
+                            let (class, entries) = parse_qi_block(&source, line)?;
+                            entries
+                                .iter()
+                                .any(|e| e == interface)
+                                .then(|| (path.clone(), line, class, entries))
+                        })
+                        .collect(),
+                    Err(e) => {
+                        log::warn!("Could not fetch {}: {}", path, e);
+                        Vec::new()
+                    }
+                };
+                stream::iter(found)
+            })
+            .collect()
+            .await;
+
+        let mut implementations: Vec<Implementation> = Vec::new();
+        for (class, at) in declarations {
+            if !implementations.iter().any(|i| i.class == class) {
+                implementations.push(Implementation {
+                    class,
+                    declared_at: Some(at),
+                    ..Default::default()
+                });
+            }
+        }
+        for (path, line, class, entries) in qi_maps {
+            let existing = implementations
+                .iter_mut()
+                .find(|i| same_class(&i.class, &class) || same_class(&class, &i.class));
+            let implementation = match existing {
+                Some(implementation) => implementation,
+                None => {
+                    implementations.push(Implementation {
+                        class,
+                        ..Default::default()
+                    });
+                    implementations.last_mut().expect("just pushed")
+                }
+            };
+            if implementation.qi_at.is_none() {
+                implementation.qi_at = Some((path, line));
+                implementation.qi_entries = entries;
+            }
+        }
+        implementations.sort_by(|a, b| a.class.cmp(&b.class));
+        Ok(implementations)
+    }
+}
+
+pub fn format_implementations(interface: &str, implementations: &[Implementation]) -> String {
+    let mut output = format!("# Implementations of {}\n\n", interface);
+    if implementations.is_empty() {
+        output.push_str("No implementations found.\n");
+        return output;
+    }
+    for implementation in implementations {
+        output.push_str(&format!("## {}\n", implementation.class));
+        if let Some((path, line)) = &implementation.declared_at {
+            output.push_str(&format!(
+                "- Declared: {}:{} ({})\n",
+                path,
+                line,
+                decl_macro(interface)
+            ));
+        }
+        match &implementation.qi_at {
+            Some((path, line)) => {
+                output.push_str(&format!("- QueryInterface: {}:{}\n", path, line));
+                output.push_str(&format!("  {}\n", implementation.qi_entries.join(", ")));
+            }
+            None => output.push_str("- QueryInterface: not found (inherited or hand-written)\n"),
+        }
+        output.push('\n');
+    }
+    output.push_str(&format!(
+        "{} implementation(s). Classes inheriting {} from an implementing base are not listed.\n",
+        implementations.len(),
+        interface
+    ));
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_isupports_macros() {
+        let source = "\
+#include \"Foo.h\"
+NS_IMPL_ISUPPORTS(Foo, nsIStreamListener,
+                  nsIRequestObserver)
+NS_IMPL_ISUPPORTS_INHERITED(Bar, BaseChannel, nsIStreamListener)
+";
+        assert_eq!(
+            parse_qi_block(source, 3),
+            Some((
+                "Foo".to_string(),
+                vec!["nsIStreamListener".into(), "nsIRequestObserver".into()]
+            ))
+        );
+        assert_eq!(
+            parse_qi_block(source, 4),
+            Some(("Bar".to_string(), vec!["nsIStreamListener".into()]))
+        );
+        assert_eq!(parse_qi_block(source, 1), None);
+    }
+
+    #[test]
+    fn parses_interface_maps() {
+        let source = "\
+NS_INTERFACE_MAP_BEGIN(HttpChannelChild)
+  NS_INTERFACE_MAP_ENTRY(nsIStreamListener)
+  NS_INTERFACE_MAP_ENTRY_AMBIGUOUS(nsIRequestObserver, nsIStreamListener)
+  NS_INTERFACE_MAP_ENTRY_CONDITIONAL(nsIThreadRetargetableStreamListener, mRetarget)
+NS_INTERFACE_MAP_END_INHERITING(HttpBaseChannel)
+
+void HttpChannelChild::Foo() {}
+";
+        assert_eq!(
+            parse_qi_block(source, 2),
+            Some((
+                "HttpChannelChild".to_string(),
+                vec![
+                    "nsIStreamListener".into(),
+                    "nsIRequestObserver".into(),
+                    "nsIThreadRetargetableStreamListener".into()
+                ]
+            ))
+        );
+        assert_eq!(parse_qi_block(source, 7), None);
+    }
+
+    #[test]
+    fn decl_macro_is_uppercased() {
+        assert_eq!(decl_macro("nsIStreamListener"), "NS_DECL_NSISTREAMLISTENER");
+        assert!(same_class(
+            "mozilla::net::HttpChannelChild",
+            "net::HttpChannelChild"
+        ));
+        assert!(!same_class(
+            "mozilla::net::HttpChannelChild",
+            "ChannelChild"
+        ));
+    }
+}
@@ -0,0 +1,200 @@
+use crate::client::SearchfoxClient;
+use crate::search::SearchOptions;
+use crate::types::{File, SearchfoxResponse};
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+/// Files including a header, with the include directive found in each.
+#[derive(Debug, Default)]
+pub struct IncludeReport {
+    /// `(path, line number, include path as written)` of each directive.
+    pub includers: Vec<(String, usize, String)>,
+    /// Including file count per directory.
+    pub by_dir: BTreeMap<String, usize>,
+}
+
+/// The path of an `#include "..."` or `#include <...>` directive.
+fn include_path(line: &str) -> Option<&str> {
+    let rest = line.trim_start().strip_prefix('#')?.trim_start();
+    let rest = rest.strip_prefix("include")?.trim_start();
+    let close = match rest.chars().next()? {
+        '"' => '"',
+        '<' => '>',
+        _ => return None,
+    };
+    let rest = &rest[1..];
+    rest.find(close).map(|end| &rest[..end])
+}
+
+/// Whether `include` (as written in a directive) can refer to `header` (a
+/// path from the repository root). Bare file names and paths that are a
+/// suffix of `header` match, as do exported paths (`mozilla/...`), which do
+/// not mirror the source tree.
+fn includes_header(include: &str, header: &str) -> bool {
+    let file_name = header.rsplit('/').next().unwrap_or(header);
+    if include.rsplit('/').next() != Some(file_name) {
+        return false;
+    }
+    !include.contains('/')
+        || header == include
+        || header.ends_with(&format!("/{include}"))
+        || include.starts_with("mozilla/")
+}
+
+pub fn collect_includers(
+    json: &SearchfoxResponse,
+    header: &str,
+    options: &SearchOptions,
+) -> IncludeReport {
+    let mut report = IncludeReport::default();
+    let mut add = |file: &serde_json::Value| {
+        let Ok(file) = serde_json::from_value::<File>(file.clone()) else {
+            return;
+        };
+        if !options.matches_language_filter(&file.path) {
+            return;
+        }
+        let mut found = false;
+        for line in &file.lines {
+            let Some(include) = include_path(&line.line) else {
+                continue;
+            };
+            if includes_header(include, header) {
+                report
+                    .includers
+                    .push((file.path.clone(), line.lno, include.to_string()));
+                found = true;
+            }
+        }
+        if found {
+            let dir = file
+                .path
+                .rsplit_once('/')
+                .map(|(dir, _)| dir)
+                .unwrap_or(".");
+            *report.by_dir.entry(dir.to_string()).or_default() += 1;
+        }
+    };
+
+    for (category, value) in json {
+        if category.starts_with('*') || !options.category_filter.should_include(category) {
+            continue;
+        }
+        if let Some(file_list) = value.as_array() {
+            file_list.iter().for_each(&mut add);
+        } else if let Some(obj) = value.as_object() {
+            for file_list in obj.values() {
+                file_list
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .for_each(&mut add);
+            }
+        }
+    }
+    report.includers.sort();
+    report
+}
+
+impl SearchfoxClient {
+    /// Find the files including `header`, either by its path in the tree or
+    /// by its exported name. `options.path` restricts where to look.
+    pub async fn included_by(
+        &self,
+        header: &str,
+        options: &SearchOptions,
+    ) -> Result<IncludeReport> {
+        let file_name = header.rsplit('/').next().unwrap_or(header);
+        let include_options = SearchOptions {
+            query: Some(format!(
+                r#"#include\s*["<]([^">]*/)?{}[">]"#,
+                regex::escape(file_name)
+            )),
+            symbol: None,
+            id: None,
+            regexp: true,
+            case: true,
+            context: None,
+            ..options.clone()
+        };
+        let json = self.search_response(&include_options).await?;
+        Ok(collect_includers(&json, header, &include_options))
+    }
+}
+
+pub fn format_include_report(header: &str, report: &IncludeReport) -> String {
+    let mut output = format!("# Files including {}\n\n", header);
+    if report.includers.is_empty() {
+        output.push_str("No includes found.\n");
+        return output;
+    }
+
+    let files: usize = report.by_dir.values().sum();
+    output.push_str(&format!(
+        "{} files across {} directories\n\n",
+        files,
+        report.by_dir.len()
+    ));
+
+    let mut dirs: Vec<(&String, &usize)> = report.by_dir.iter().collect();
+    dirs.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    output.push_str("## Directories\n\n");
+    for (dir, count) in dirs {
+        output.push_str(&format!("- {} ({})\n", dir, count));
+    }
+
+    output.push_str("\n## Files\n\n");
+    for (path, line_number, include) in &report.includers {
+        output.push_str(&format!("- {}:{}: {}\n", path, line_number, include));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_include_directives() {
+        assert_eq!(
+            include_path(r#"#include "AudioStream.h""#),
+            Some("AudioStream.h")
+        );
+        assert_eq!(
+            include_path("#  include <mozilla/dom/AudioStream.h> // why"),
+            Some("mozilla/dom/AudioStream.h")
+        );
+        assert_eq!(include_path("// #include \"AudioStream.h\""), None);
+    }
+
+    #[test]
+    fn matches_relative_and_exported_paths() {
+        let header = "dom/media/AudioStream.h";
+        assert!(includes_header("AudioStream.h", header));
+        assert!(includes_header("media/AudioStream.h", header));
+        assert!(includes_header("mozilla/AudioStream.h", header));
+        assert!(!includes_header("AudioStreamTrack.h", header));
+        assert!(!includes_header("third_party/other/AudioStream.h", header));
+    }
+
+    #[test]
+    fn groups_includers_by_directory() {
+        let json: SearchfoxResponse = serde_json::from_str(
+            r##"{
+                "normal": [
+                    {"path": "dom/media/AudioStream.cpp", "lines": [{"lno": 6, "line": "#include \"AudioStream.h\""}]},
+                    {"path": "dom/media/MediaDecoder.cpp", "lines": [{"lno": 9, "line": "#include \"AudioStream.h\""}]},
+                    {"path": "dom/webaudio/AudioContext.cpp", "lines": [{"lno": 12, "line": "#include \"mozilla/AudioStream.h\""}]},
+                    {"path": "other/Thing.cpp", "lines": [{"lno": 3, "line": "#include \"vendor/AudioStream.h\""}]}
+                ]
+            }"##,
+        )
+        .unwrap();
+        let options = SearchOptions::default();
+        let report = collect_includers(&json, "dom/media/AudioStream.h", &options);
+        assert_eq!(report.includers.len(), 3);
+        assert_eq!(report.by_dir["dom/media"], 2);
+        assert_eq!(report.by_dir["dom/webaudio"], 1);
+        assert!(!report.by_dir.contains_key("other"));
+    }
+}
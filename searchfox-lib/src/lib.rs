@@ -1,19 +1,66 @@
+pub mod annotation;
+pub mod archive;
+pub mod batch;
 pub mod blame;
+pub mod bug_component;
 pub mod cache;
 pub mod call_graph;
 pub mod can_gc;
+pub mod cassette;
+pub mod checkout;
+pub mod class_diagram;
+pub mod class_hierarchy;
 pub mod client;
+pub mod complete;
+pub mod config;
+pub mod contract;
+pub mod crash_frame;
 pub mod definition;
+pub mod demangle;
+pub mod duplicates;
+pub mod error;
+pub mod examples;
 pub mod field_layout;
 pub mod file_reader;
+pub mod find_file;
+pub mod html;
+pub mod impact;
+pub mod implementations;
+pub mod includes;
+pub mod log_module;
+pub mod lsif;
+pub mod module_graph;
 pub mod nesting;
+pub mod overrides;
+pub mod process;
+pub mod progress;
+pub mod regex_check;
+pub mod rename;
+pub mod report;
+pub mod repos;
+pub mod resolve;
 pub mod search;
+pub mod session;
+pub mod similar;
 pub mod spec_refs;
+pub mod stats;
+pub mod style;
+pub mod surrounding;
+pub mod tags;
+pub mod tests_for;
+pub mod tokens;
 pub mod types;
+pub mod unused;
+pub mod uses;
 pub mod utils;
+pub mod watch;
+pub mod wpt;
+pub mod wrap;
 
 pub use blame::parse_commit_header;
-pub use client::SearchfoxClient;
+pub use client::{SearchfoxClient, DEFAULT_SERVER};
+pub use error::{error_report, BinaryFileError, ErrorReport, NoMatchesError, RequestError};
+pub use progress::{ProgressEvent, ProgressSink};
 pub use search::{CategoryFilter, Lang, SearchOptions};
 pub use spec_refs::{categorize_spec_ref, spec_ref_category_names, spec_refs_query};
 pub use types::*;
@@ -0,0 +1,368 @@
+use crate::client::SearchfoxClient;
+use crate::contract::lines_of;
+use crate::search::SearchOptions;
+use anyhow::Result;
+use futures::stream::{self, StreamExt};
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+
+const CONCURRENT_QUERIES: usize = 8;
+
+/// Path, line number and text of a matched line.
+pub(crate) type MatchedLine = (String, usize, String);
+
+/// `mozilla::LogLevel` values, in the order of the numbers `MOZ_LOG`
+/// accepts (`module:1` is `Error`, `module:5` is `Verbose`).
+pub const LOG_LEVELS: &[&str] = &["Error", "Warning", "Info", "Debug", "Verbose"];
+
+/// A `LazyLogModule` declared with the module name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogModuleDeclaration {
+    pub variable: String,
+    pub path: String,
+    pub line: usize,
+}
+
+/// A macro wrapping `MOZ_LOG` for a module, e.g.
+/// `#define LOG(...) MOZ_LOG(gCubebLog, LogLevel::Debug, (__VA_ARGS__))`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogWrapper {
+    pub name: String,
+    pub path: String,
+    pub line: usize,
+    /// Level fixed by the wrapper, if any.
+    pub level: Option<&'static str>,
+}
+
+/// A line logging to the module, directly or through a wrapper macro.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogSite {
+    pub path: String,
+    pub line: usize,
+    pub level: Option<&'static str>,
+    pub text: String,
+    /// Wrapper macro the site goes through.
+    pub via: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LogModuleReport {
+    pub declarations: Vec<LogModuleDeclaration>,
+    pub wrappers: Vec<LogWrapper>,
+    pub sites: Vec<LogSite>,
+}
+
+/// The `LogLevel` named on a line.
+pub fn log_level(text: &str) -> Option<&'static str> {
+    let re = Regex::new(r"LogLevel::(\w+)").expect("valid regex");
+    let level = re
+        .captures_iter(text)
+        .find_map(|c| LOG_LEVELS.iter().find(|l| **l == &c[1]).copied());
+    level
+}
+
+/// Variable of a `LazyLogModule gFoo("module")` declaration of `module`.
+fn declared_variable<'a>(text: &'a str, module: &str) -> Option<&'a str> {
+    let re = Regex::new(r#"LazyLogModule\s+(\w+)\s*[({]\s*"([^"]*)""#).expect("valid regex");
+    let captures = re.captures(text)?;
+    (&captures[2] == module).then(|| captures.get(1).expect("group").as_str())
+}
+
+/// Name and line of the `#define` whose body spans `line` (1-based),
+/// following backslash continuations up.
+pub fn enclosing_macro(source: &str, line: usize) -> Option<(String, usize)> {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut index = line.checked_sub(1)?;
+    lines.get(index)?;
+    while index > 0 && lines[index - 1].trim_end().ends_with('\\') {
+        index -= 1;
+    }
+    let rest = lines[index].trim_start().strip_prefix('#')?.trim_start();
+    let rest = rest.strip_prefix("define")?.trim_start();
+    let name: String = rest
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_')
+        .collect();
+    (!name.is_empty()).then_some((name, index + 1))
+}
+
+/// Whether a line logs (rather than tests whether logging is enabled).
+fn is_log_call(text: &str) -> bool {
+    text.contains("MOZ_LOG") && !text.contains("MOZ_LOG_TEST")
+}
+
+impl SearchfoxClient {
+    pub(crate) async fn regex_lines(
+        &self,
+        pattern: String,
+        path: Option<String>,
+        options: &SearchOptions,
+    ) -> Result<Vec<MatchedLine>> {
+        let search = SearchOptions {
+            query: Some(pattern),
+            symbol: None,
+            id: None,
+            regexp: true,
+            case: true,
+            context: None,
+            path: path.or_else(|| options.path.clone()),
+            ..options.clone()
+        };
+        let json = self.search_response(&search).await?;
+        Ok(lines_of(&json, &search))
+    }
+
+    /// Find the `LazyLogModule` declarations named `module` and the
+    /// `MOZ_LOG` call sites using them, including those going through a
+    /// wrapper macro defined in the same file as the call.
+    pub async fn log_module(
+        &self,
+        module: &str,
+        options: &SearchOptions,
+    ) -> Result<LogModuleReport> {
+        let mut report = LogModuleReport::default();
+        let pattern = format!(
+            r#"LazyLogModule\s+\w+\s*[({{]\s*"{}""#,
+            regex::escape(module)
+        );
+        for (path, line, text) in self.regex_lines(pattern, None, options).await? {
+            if let Some(variable) = declared_variable(&text, module) {
+                report.declarations.push(LogModuleDeclaration {
+                    variable: variable.to_string(),
+                    path,
+                    line,
+                });
+            }
+        }
+
+        let mut variables: Vec<&str> = report
+            .declarations
+            .iter()
+            .map(|d| d.variable.as_str())
+            .collect();
+        variables.sort();
+        variables.dedup();
+        let uses: Vec<MatchedLine> = stream::iter(variables)
+            .map(|variable| self.regex_lines(format!(r"\b{variable}\b"), None, options))
+            .buffered(CONCURRENT_QUERIES)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .flatten()
+            .filter(|(_, _, text)| !text.contains("LazyLogModule") && is_log_call(text))
+            .collect();
+
+        // Lines inside a #define are wrappers; their files tell which macro.
+        let mut in_macros: BTreeMap<String, Vec<(usize, String)>> = BTreeMap::new();
+        for (path, line, text) in uses {
+            if text.contains("#define") || text.trim_end().ends_with('\\') {
+                in_macros.entry(path).or_default().push((line, text));
+            } else {
+                report.sites.push(LogSite {
+                    path,
+                    line,
+                    level: log_level(&text),
+                    text: text.trim().to_string(),
+                    via: None,
+                });
+            }
+        }
+        let sources: Vec<(_, _, Result<String>)> = stream::iter(in_macros)
+            .map(|(path, lines)| async move {
+                let source = self.get_file(&path).await;
+                (path, lines, source)
+            })
+            .buffered(CONCURRENT_QUERIES)
+            .collect()
+            .await;
+        for (path, lines, source) in sources {
+            let source = source?;
+            for (line, text) in lines {
+                if let Some((name, define_line)) = enclosing_macro(&source, line) {
+                    report.wrappers.push(LogWrapper {
+                        name,
+                        path: path.clone(),
+                        line: define_line,
+                        level: log_level(&text),
+                    });
+                }
+            }
+        }
+        report
+            .wrappers
+            .sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+        report
+            .wrappers
+            .dedup_by(|a, b| a.path == b.path && a.name == b.name);
+
+        let wrapper_sites: Vec<(usize, Vec<MatchedLine>)> =
+            stream::iter(report.wrappers.iter().enumerate())
+                .map(|(index, wrapper)| async move {
+                    let lines = self
+                        .regex_lines(
+                            format!(r"\b{}\(", wrapper.name),
+                            Some(format!("^{}$", regex::escape(&wrapper.path))),
+                            options,
+                        )
+                        .await;
+                    lines.map(|lines| (index, lines))
+                })
+                .buffered(CONCURRENT_QUERIES)
+                .collect::<Vec<_>>()
+                .await
+                .into_iter()
+                .collect::<Result<_>>()?;
+        for (index, lines) in wrapper_sites {
+            let wrapper = &report.wrappers[index];
+            for (path, line, text) in lines {
+                let directive = text.trim_start().starts_with('#');
+                if directive || path != wrapper.path {
+                    continue;
+                }
+                report.sites.push(LogSite {
+                    path,
+                    line,
+                    level: log_level(&text).or(wrapper.level),
+                    text: text.trim().to_string(),
+                    via: Some(wrapper.name.clone()),
+                });
+            }
+        }
+        report
+            .sites
+            .sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+        report
+            .sites
+            .dedup_by(|a, b| a.path == b.path && a.line == b.line);
+        Ok(report)
+    }
+}
+
+pub fn format_log_module(module: &str, report: &LogModuleReport) -> String {
+    let mut output = format!("# Log module \"{}\"\n\n", module);
+    if report.declarations.is_empty() {
+        output.push_str("No LazyLogModule declaration found.\n");
+        return output;
+    }
+    output.push_str("Declared:\n");
+    for d in &report.declarations {
+        output.push_str(&format!("- {}: {}:{}\n", d.variable, d.path, d.line));
+    }
+    if !report.wrappers.is_empty() {
+        output.push_str("\nWrapper macros:\n");
+        for w in &report.wrappers {
+            let level = w.level.map(|l| format!(" ({l})")).unwrap_or_default();
+            output.push_str(&format!("- {}{}: {}:{}\n", w.name, level, w.path, w.line));
+        }
+    }
+    output.push('\n');
+
+    let mut by_file: BTreeMap<&str, Vec<&LogSite>> = BTreeMap::new();
+    for site in &report.sites {
+        by_file.entry(&site.path).or_default().push(site);
+    }
+    for (path, sites) in &by_file {
+        output.push_str(&format!("## {} ({})\n", path, sites.len()));
+        for site in sites {
+            output.push_str(&format!(
+                "{:>6} [{}] {}\n",
+                site.line,
+                site.level.unwrap_or("?"),
+                site.text
+            ));
+        }
+        output.push('\n');
+    }
+
+    let mut per_level: HashMap<Option<&str>, usize> = HashMap::new();
+    for site in &report.sites {
+        *per_level.entry(site.level).or_default() += 1;
+    }
+    output.push_str(&format!("{} call site(s)", report.sites.len()));
+    let counts: Vec<String> = LOG_LEVELS
+        .iter()
+        .enumerate()
+        .filter_map(|(i, level)| {
+            let count = per_level.get(&Some(*level))?;
+            Some(format!("{}={} {}", i + 1, level, count))
+        })
+        .chain(per_level.get(&None).map(|n| format!("unknown level {n}")))
+        .collect();
+    if !counts.is_empty() {
+        output.push_str(&format!(": {}", counts.join(", ")));
+    }
+    output.push_str(&format!(
+        ".\nMOZ_LOG={}:N prints the sites at level N and below.\n",
+        module
+    ));
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_declarations_and_levels() {
+        assert_eq!(
+            declared_variable(
+                r#"static mozilla::LazyLogModule gCubebLog("cubeb");"#,
+                "cubeb"
+            ),
+            Some("gCubebLog")
+        );
+        assert_eq!(
+            declared_variable(r#"LazyLogModule gAudioStreamLog("AudioStream");"#, "cubeb"),
+            None
+        );
+        assert_eq!(
+            log_level("MOZ_LOG(gCubebLog, mozilla::LogLevel::Verbose, (\"x\"));"),
+            Some("Verbose")
+        );
+        assert_eq!(log_level("LOG(\"x\")"), None);
+    }
+
+    #[test]
+    fn finds_enclosing_macro() {
+        let source = "\
+#include \"CubebUtils.h\"
+#define LOG(level, ...) \\
+  MOZ_LOG(gCubebLog, level, \\
+          (__VA_ARGS__))
+#define LOGV(...) MOZ_LOG(gCubebLog, LogLevel::Verbose, (__VA_ARGS__))
+void f() { LOG(LogLevel::Debug, \"hi\"); }
+";
+        assert_eq!(enclosing_macro(source, 3), Some(("LOG".to_string(), 2)));
+        assert_eq!(enclosing_macro(source, 5), Some(("LOGV".to_string(), 5)));
+        assert_eq!(enclosing_macro(source, 6), None);
+    }
+
+    #[test]
+    fn formats_sites_by_file_and_level() {
+        let site = |path: &str, line, level| LogSite {
+            path: path.to_string(),
+            line,
+            level,
+            text: "LOG(\"x\");".to_string(),
+            via: Some("LOG".to_string()),
+        };
+        let report = LogModuleReport {
+            declarations: vec![LogModuleDeclaration {
+                variable: "gCubebLog".into(),
+                path: "dom/media/CubebUtils.cpp".into(),
+                line: 50,
+            }],
+            wrappers: Vec::new(),
+            sites: vec![
+                site("dom/media/AudioStream.cpp", 10, Some("Debug")),
+                site("dom/media/AudioStream.cpp", 20, Some("Error")),
+                site("dom/media/CubebUtils.cpp", 70, None),
+            ],
+        };
+        let output = format_log_module("cubeb", &report);
+        assert!(output.contains("## dom/media/AudioStream.cpp (2)\n    10 [Debug] LOG(\"x\");\n"));
+        assert!(output.contains("3 call site(s): 1=Error 1, 4=Debug 1, unknown level 1.\n"));
+    }
+}
@@ -0,0 +1,572 @@
+use crate::call_graph::CallGraphQuery;
+use crate::client::SearchfoxClient;
+use crate::complete::symbol_kind;
+use crate::error::NoMatchesError;
+use crate::progress::ProgressEvent;
+use crate::search::SearchOptions;
+use crate::types::{File, SearchfoxResponse};
+use crate::utils::language_id;
+use anyhow::Result;
+use futures::stream::{self, StreamExt};
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet};
+
+const CONCURRENT_QUERIES: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    Definition,
+    Declaration,
+    Reference,
+}
+
+/// Where a symbol appears. Lines are 1-based as in searchfox, columns are
+/// UTF-16 code units into the line, as LSIF positions are.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Occurrence {
+    pub path: String,
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+    pub role: Role,
+    /// Source text of the line, when known.
+    pub text: String,
+}
+
+/// The definitions, declarations and uses of a class or namespace and its
+/// members, and the functions each of them calls.
+#[derive(Debug, Default)]
+pub struct Neighborhood {
+    /// Occurrences by qualified symbol name.
+    pub symbols: BTreeMap<String, BTreeSet<Occurrence>>,
+    /// Callees by qualified caller name.
+    pub calls: BTreeMap<String, BTreeSet<String>>,
+}
+
+/// Whether `name` is `root` itself or one of its members.
+fn in_neighborhood(name: &str, root: &str) -> bool {
+    name == root
+        || name
+            .strip_prefix(root)
+            .is_some_and(|rest| rest.starts_with("::"))
+}
+
+/// UTF-16 column of the byte offset `byte` into `line`.
+fn utf16_column(line: &str, byte: usize) -> usize {
+    line.char_indices()
+        .take_while(|(i, _)| *i < byte)
+        .map(|(_, c)| c.len_utf16())
+        .sum()
+}
+
+/// Occurrences of `root` and its members in the identifier results of a
+/// search.
+pub fn collect_occurrences(
+    json: &SearchfoxResponse,
+    root: &str,
+    options: &SearchOptions,
+) -> BTreeMap<String, BTreeSet<Occurrence>> {
+    let mut symbols: BTreeMap<String, BTreeSet<Occurrence>> = BTreeMap::new();
+    for (category, value) in json {
+        if category.starts_with('*') || !options.category_filter.should_include(category) {
+            continue;
+        }
+        let Some(obj) = value.as_object() else {
+            continue;
+        };
+        for (label, file_list) in obj {
+            let Some((kind, name)) = label
+                .strip_suffix(')')
+                .and_then(|rest| rest.split_once(" ("))
+            else {
+                continue;
+            };
+            if !in_neighborhood(name, root) {
+                continue;
+            }
+            let role = match kind {
+                "Definitions" => Role::Definition,
+                "Declarations" => Role::Declaration,
+                _ => Role::Reference,
+            };
+            let files = file_list
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|file| serde_json::from_value::<File>(file.clone()).ok())
+                .filter(|file| options.matches_language_filter(&file.path));
+            for file in files {
+                for line in file.lines {
+                    let (start, end) = match line.bounds.as_deref() {
+                        Some([start, end, ..]) => (*start, *end),
+                        _ => (0, line.line.len()),
+                    };
+                    let (start, end) = (
+                        utf16_column(&line.line, start),
+                        utf16_column(&line.line, end),
+                    );
+                    symbols
+                        .entry(name.to_string())
+                        .or_default()
+                        .insert(Occurrence {
+                            path: file.path.clone(),
+                            line: line.lno,
+                            start,
+                            end,
+                            role,
+                            text: line.line.trim().to_string(),
+                        });
+                }
+            }
+        }
+    }
+    symbols
+}
+
+/// Direct callees in a calls-from graph, with the location of their
+/// definition (`path#line`) when known.
+fn callees(json: &serde_json::Value) -> Vec<(String, Option<(String, usize)>)> {
+    let jumprefs = json.get("jumprefs").and_then(|v| v.as_object());
+    let mut found = BTreeMap::new();
+    for graph in json
+        .get("graphs")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+    {
+        for edge in graph
+            .get("edges")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+        {
+            let Some(to) = edge.get("to").and_then(|v| v.as_str()) else {
+                continue;
+            };
+            let info = jumprefs.and_then(|j| j.get(to));
+            let pretty = info
+                .and_then(|i| i.get("pretty"))
+                .and_then(|v| v.as_str())
+                .unwrap_or(to);
+            let location = info
+                .and_then(|i| i.get("jumps"))
+                .and_then(|j| j.get("def"))
+                .and_then(|v| v.as_str())
+                .and_then(|loc| loc.split_once('#'))
+                .and_then(|(path, line)| Some((path.to_string(), line.parse().ok()?)));
+            found.insert(pretty.to_string(), location);
+        }
+    }
+    found.into_iter().collect()
+}
+
+impl SearchfoxClient {
+    /// Gather the neighborhood of `root`: its members' occurrences from an
+    /// identifier search, and one level of calls from each member function.
+    pub async fn symbol_neighborhood(
+        &self,
+        root: &str,
+        options: &SearchOptions,
+    ) -> Result<Neighborhood> {
+        let search = SearchOptions {
+            query: Some(root.to_string()),
+            symbol: None,
+            id: None,
+            context: None,
+            regexp: false,
+            ..options.clone()
+        };
+        let json = self.search_response(&search).await?;
+        let mut neighborhood = Neighborhood {
+            symbols: collect_occurrences(&json, root, &search),
+            ..Default::default()
+        };
+        if neighborhood.symbols.is_empty() {
+            return Err(
+                NoMatchesError::new(format!("No definitions or uses found for '{root}'")).into(),
+            );
+        }
+
+        let functions: Vec<String> = neighborhood
+            .symbols
+            .iter()
+            .filter(|(name, _)| name.as_str() != root)
+            .filter(|(_, occurrences)| {
+                occurrences
+                    .iter()
+                    .any(|o| o.role != Role::Reference && symbol_kind(&o.text) == "function")
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        let operation = format!("neighborhood {root}");
+        let total = functions.len();
+        self.emit(ProgressEvent::Started {
+            operation: operation.clone(),
+            total,
+        });
+        let mut graphs = stream::iter(functions)
+            .map(|caller| async move {
+                let query = CallGraphQuery {
+                    calls_from: Some(caller.clone()),
+                    calls_to: None,
+                    calls_between: None,
+                    depth: 1,
+                };
+                let graph = self.search_call_graph(&query).await;
+                (caller, graph)
+            })
+            .buffered(CONCURRENT_QUERIES);
+        let mut completed = 0;
+        while let Some((caller, graph)) = graphs.next().await {
+            for (callee, location) in callees(&graph?) {
+                if let Some((path, line)) = location {
+                    let occurrences = neighborhood.symbols.entry(callee.clone()).or_default();
+                    if !occurrences.iter().any(|o| o.role == Role::Definition) {
+                        occurrences.insert(Occurrence {
+                            path,
+                            line,
+                            start: 0,
+                            end: 0,
+                            role: Role::Definition,
+                            text: String::new(),
+                        });
+                    }
+                }
+                neighborhood
+                    .calls
+                    .entry(caller.clone())
+                    .or_default()
+                    .insert(callee);
+            }
+            completed += 1;
+            self.emit(ProgressEvent::Advanced {
+                operation: operation.clone(),
+                completed,
+                total,
+            });
+        }
+        self.emit(ProgressEvent::Finished { operation });
+        Ok(neighborhood)
+    }
+}
+
+/// Serialize a neighborhood as an LSIF 0.6 dump, one JSON object per line.
+/// Documents are addressed relative to `file:///`, the root of the tree.
+/// LSIF has no notion of call edges: each function's callees are listed in
+/// its hover, and callees are included as symbols so that jumping to them
+/// resolves.
+pub fn format_lsif(neighborhood: &Neighborhood) -> String {
+    let mut lines: Vec<serde_json::Value> = Vec::new();
+    let mut next_id = 0u64;
+    let mut id = || {
+        next_id += 1;
+        next_id
+    };
+    let vertex = |lines: &mut Vec<serde_json::Value>, id: u64, mut v: serde_json::Value| {
+        v["id"] = json!(id);
+        v["type"] = json!("vertex");
+        lines.push(v);
+    };
+    let edge = |lines: &mut Vec<serde_json::Value>, id: u64, mut e: serde_json::Value| {
+        e["id"] = json!(id);
+        e["type"] = json!("edge");
+        lines.push(e);
+    };
+
+    vertex(
+        &mut lines,
+        id(),
+        json!({
+            "label": "metaData",
+            "version": "0.6.0",
+            "projectRoot": "file:///",
+            "positionEncoding": "utf-16",
+            "toolInfo": {"name": "searchfox-cli", "version": crate::VERSION},
+        }),
+    );
+    // The project is of the language most of its documents are in.
+    let paths: BTreeSet<&str> = neighborhood
+        .symbols
+        .values()
+        .flatten()
+        .map(|o| o.path.as_str())
+        .collect();
+    let mut languages: BTreeMap<&str, usize> = BTreeMap::new();
+    for path in paths {
+        *languages.entry(language_id(path)).or_default() += 1;
+    }
+    let kind = languages
+        .iter()
+        .max_by_key(|(_, count)| **count)
+        .map_or("plaintext", |(language, _)| language);
+    let project = id();
+    vertex(
+        &mut lines,
+        project,
+        json!({"label": "project", "kind": kind}),
+    );
+
+    // Ranges per document, and per symbol the ranges of each role.
+    let mut documents: BTreeMap<&str, Vec<u64>> = BTreeMap::new();
+    let mut ranges: BTreeMap<&str, Vec<(u64, &Occurrence)>> = BTreeMap::new();
+    for (name, occurrences) in &neighborhood.symbols {
+        for occurrence in occurrences {
+            let range = id();
+            vertex(
+                &mut lines,
+                range,
+                json!({
+                    "label": "range",
+                    "start": {"line": occurrence.line.saturating_sub(1), "character": occurrence.start},
+                    "end": {"line": occurrence.line.saturating_sub(1), "character": occurrence.end},
+                }),
+            );
+            documents.entry(&occurrence.path).or_default().push(range);
+            ranges.entry(name).or_default().push((range, occurrence));
+        }
+    }
+
+    let mut document_ids: BTreeMap<&str, u64> = BTreeMap::new();
+    for (path, range_ids) in &documents {
+        let document = id();
+        vertex(
+            &mut lines,
+            document,
+            json!({"label": "document", "uri": format!("file:///{path}"), "languageId": language_id(path)}),
+        );
+        document_ids.insert(path, document);
+        edge(
+            &mut lines,
+            id(),
+            json!({"label": "contains", "outV": document, "inVs": range_ids}),
+        );
+    }
+    let all_documents: Vec<u64> = document_ids.values().copied().collect();
+    edge(
+        &mut lines,
+        id(),
+        json!({"label": "contains", "outV": project, "inVs": all_documents}),
+    );
+
+    for (name, symbol_ranges) in &ranges {
+        let result_set = id();
+        vertex(&mut lines, result_set, json!({"label": "resultSet"}));
+        for (range, _) in symbol_ranges {
+            edge(
+                &mut lines,
+                id(),
+                json!({"label": "next", "outV": range, "inV": result_set}),
+            );
+        }
+
+        let moniker = id();
+        vertex(
+            &mut lines,
+            moniker,
+            json!({"label": "moniker", "scheme": "searchfox", "identifier": name, "kind": "export"}),
+        );
+        edge(
+            &mut lines,
+            id(),
+            json!({"label": "moniker", "outV": result_set, "inV": moniker}),
+        );
+
+        let mut hover = format!("```\n{name}\n```");
+        if let Some(callees) = neighborhood.calls.get(*name) {
+            hover.push_str("\n\nCalls:\n");
+            for callee in callees {
+                hover.push_str(&format!("- `{callee}`\n"));
+            }
+        }
+        let hover_result = id();
+        vertex(
+            &mut lines,
+            hover_result,
+            json!({"label": "hoverResult", "result": {"contents": {"kind": "markdown", "value": hover}}}),
+        );
+        edge(
+            &mut lines,
+            id(),
+            json!({"label": "textDocument/hover", "outV": result_set, "inV": hover_result}),
+        );
+
+        // Ranges of each role, grouped by document as LSIF `item` edges are.
+        let by_role = |role: Role| -> BTreeMap<u64, Vec<u64>> {
+            let mut grouped: BTreeMap<u64, Vec<u64>> = BTreeMap::new();
+            for (range, occurrence) in symbol_ranges.iter().filter(|(_, o)| o.role == role) {
+                grouped
+                    .entry(document_ids[occurrence.path.as_str()])
+                    .or_default()
+                    .push(*range);
+            }
+            grouped
+        };
+        let definitions = by_role(Role::Definition);
+        let declarations = by_role(Role::Declaration);
+        let references = by_role(Role::Reference);
+
+        for (label, request, grouped) in [
+            ("definitionResult", "textDocument/definition", &definitions),
+            (
+                "declarationResult",
+                "textDocument/declaration",
+                &declarations,
+            ),
+        ] {
+            if grouped.is_empty() {
+                continue;
+            }
+            let result = id();
+            vertex(&mut lines, result, json!({"label": label}));
+            edge(
+                &mut lines,
+                id(),
+                json!({"label": request, "outV": result_set, "inV": result}),
+            );
+            for (document, range_ids) in grouped {
+                edge(
+                    &mut lines,
+                    id(),
+                    json!({"label": "item", "outV": result, "inVs": range_ids, "document": document}),
+                );
+            }
+        }
+
+        let reference_result = id();
+        vertex(
+            &mut lines,
+            reference_result,
+            json!({"label": "referenceResult"}),
+        );
+        edge(
+            &mut lines,
+            id(),
+            json!({"label": "textDocument/references", "outV": result_set, "inV": reference_result}),
+        );
+        for (property, grouped) in [
+            ("definitions", &definitions),
+            ("declarations", &declarations),
+            ("references", &references),
+        ] {
+            for (document, range_ids) in grouped {
+                edge(
+                    &mut lines,
+                    id(),
+                    json!({"label": "item", "outV": reference_result, "inVs": range_ids, "document": document, "property": property}),
+                );
+            }
+        }
+    }
+
+    lines.iter().map(|line| format!("{line}\n")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response() -> SearchfoxResponse {
+        serde_json::from_str(
+            r#"{
+                "*title*": "AudioContext",
+                "normal": {
+                    "Definitions (mozilla::dom::AudioContext)": [
+                        {"path": "dom/media/webaudio/AudioContext.h", "lines": [{"lno": 120, "bounds": [6, 18], "line": "class AudioContext final"}]}
+                    ],
+                    "Definitions (mozilla::dom::AudioContext::CreateGain)": [
+                        {"path": "dom/media/webaudio/AudioContext.cpp", "lines": [{"lno": 469, "bounds": [41, 51], "line": "already_AddRefed<GainNode> AudioContext::CreateGain(ErrorResult& aRv) {"}]}
+                    ],
+                    "Uses (mozilla::dom::AudioContext::CreateGain)": [
+                        {"path": "dom/media/webaudio/AudioNode.cpp", "lines": [{"lno": 3, "bounds": [7, 17], "line": "  ctx->CreateGain(rv);"}]}
+                    ],
+                    "Uses (mozilla::dom::AudioContextState)": [
+                        {"path": "dom/media/webaudio/AudioNode.cpp", "lines": [{"lno": 9, "line": "AudioContextState s;"}]}
+                    ]
+                }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn collects_root_and_members_only() {
+        let symbols = collect_occurrences(
+            &response(),
+            "mozilla::dom::AudioContext",
+            &SearchOptions::default(),
+        );
+        assert_eq!(
+            symbols.keys().collect::<Vec<_>>(),
+            vec![
+                "mozilla::dom::AudioContext",
+                "mozilla::dom::AudioContext::CreateGain"
+            ]
+        );
+        let gain = &symbols["mozilla::dom::AudioContext::CreateGain"];
+        assert_eq!(gain.len(), 2);
+        assert_eq!(gain.first().unwrap().role, Role::Definition);
+        assert_eq!(gain.first().unwrap().start, 41);
+    }
+
+    #[test]
+    fn columns_are_utf16_code_units() {
+        let json: SearchfoxResponse = serde_json::from_str(
+            r#"{"normal": {"Uses (mozilla::dom::AudioContext)": [
+                {"path": "dom/media/webaudio/Résumé.cpp", "lines": [{"lno": 1, "bounds": [17, 29], "line": "/* é😀 */ new AudioContext();"}]}
+            ]}}"#,
+        )
+        .unwrap();
+        let symbols = collect_occurrences(
+            &json,
+            "mozilla::dom::AudioContext",
+            &SearchOptions::default(),
+        );
+        let occurrence = symbols["mozilla::dom::AudioContext"].first().unwrap();
+        assert_eq!((occurrence.start, occurrence.end), (14, 26));
+    }
+
+    #[test]
+    fn lsif_links_ranges_to_results() {
+        let mut neighborhood = Neighborhood {
+            symbols: collect_occurrences(
+                &response(),
+                "mozilla::dom::AudioContext",
+                &SearchOptions::default(),
+            ),
+            ..Default::default()
+        };
+        neighborhood.calls.insert(
+            "mozilla::dom::AudioContext::CreateGain".into(),
+            BTreeSet::from(["mozilla::dom::GainNode::Create".to_string()]),
+        );
+        let dump = format_lsif(&neighborhood);
+        let entries: Vec<serde_json::Value> = dump
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        let labelled = |label: &str| entries.iter().filter(|e| e["label"] == label).count();
+        assert_eq!(entries[0]["label"], "metaData");
+        assert_eq!(entries[1]["kind"], "cpp");
+        assert_eq!(labelled("range"), 3);
+        assert_eq!(labelled("document"), 3);
+        assert_eq!(labelled("resultSet"), 2);
+        assert_eq!(labelled("definitionResult"), 2);
+        assert!(dump.contains("mozilla::dom::GainNode::Create"));
+        // Every edge points at vertices defined earlier.
+        let mut seen = BTreeSet::new();
+        for entry in &entries {
+            if entry["type"] == "edge" {
+                let mut targets = vec![entry["outV"].as_u64().unwrap()];
+                if let Some(v) = entry["inV"].as_u64() {
+                    targets.push(v);
+                }
+                for v in entry["inVs"].as_array().into_iter().flatten() {
+                    targets.push(v.as_u64().unwrap());
+                }
+                assert!(targets.iter().all(|t| seen.contains(t)), "{entry}");
+            }
+            seen.insert(entry["id"].as_u64().unwrap());
+        }
+    }
+}
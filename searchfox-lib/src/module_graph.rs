@@ -0,0 +1,211 @@
+use crate::client::SearchfoxClient;
+use crate::search::SearchOptions;
+use anyhow::Result;
+use futures::stream::{self, StreamExt};
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+
+const CONCURRENT_QUERIES: usize = 8;
+
+/// URL schemes JS modules are loaded from.
+const MODULE_SCHEMES: &[&str] = &["resource://", "chrome://", "moz-src://"];
+
+/// A line of `importer` loading `module`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ImportEdge {
+    /// The imported module: the URL for the root, a file path below it.
+    pub module: String,
+    pub importer: String,
+    pub line: usize,
+    pub kind: &'static str,
+}
+
+/// How a line refers to a module URL.
+pub fn import_kind(text: &str) -> &'static str {
+    let trimmed = text.trim_start();
+    let getter = Regex::new(r#"^["']?[\w$]+["']?\s*:\s*["']"#).expect("valid regex");
+    if text.contains("importESModule") {
+        "importESModule"
+    } else if text.contains("defineESModuleGetters") || getter.is_match(trimmed) {
+        "lazy getter"
+    } else if trimmed.starts_with("import ")
+        || trimmed.starts_with("export ")
+        || trimmed.starts_with("} from ")
+    {
+        "static import"
+    } else if text.contains("ChromeUtils.import") || text.contains("Cu.import") {
+        "JSM import"
+    } else if trimmed.starts_with("import(") || text.contains(" import(") {
+        "dynamic import"
+    } else {
+        "reference"
+    }
+}
+
+/// Whether a file is a JS module other modules can import.
+fn is_module(path: &str) -> bool {
+    path.ends_with(".mjs") || path.ends_with(".jsm")
+}
+
+/// Text to search for to find the importers of the module at `path`: its
+/// file name, which ends the URL it is loaded from whatever the mapping of
+/// the URL to the tree.
+fn module_search_text(path: &str) -> String {
+    format!("/{}", path.rsplit('/').next().unwrap_or(path))
+}
+
+impl SearchfoxClient {
+    /// The files importing the JS module at `url`, and with `depth` above 1
+    /// the files importing those, up to `depth` levels. Modules above the
+    /// root are matched by file name, as the URL a file is loaded from is
+    /// set by build manifests.
+    pub async fn module_graph(
+        &self,
+        url: &str,
+        depth: u32,
+        options: &SearchOptions,
+    ) -> Result<Vec<ImportEdge>> {
+        let mut edges = BTreeSet::new();
+        let mut visited = BTreeSet::new();
+        let mut frontier = vec![(url.to_string(), url.to_string())];
+        for _ in 0..depth.max(1) {
+            let found: Vec<(String, Vec<_>)> = stream::iter(frontier)
+                .map(|(module, text)| async move {
+                    let lines = self.text_lines(&text, options).await;
+                    lines.map(|lines| (module, lines))
+                })
+                .buffered(CONCURRENT_QUERIES)
+                .collect::<Vec<_>>()
+                .await
+                .into_iter()
+                .collect::<Result<_>>()?;
+
+            frontier = Vec::new();
+            for (module, lines) in found {
+                for (importer, line, text) in lines {
+                    let is_root = module == url;
+                    if importer == module
+                        || (!is_root && !MODULE_SCHEMES.iter().any(|s| text.contains(s)))
+                    {
+                        continue;
+                    }
+                    if is_module(&importer) && visited.insert(importer.clone()) {
+                        frontier.push((importer.clone(), module_search_text(&importer)));
+                    }
+                    edges.insert(ImportEdge {
+                        module: module.clone(),
+                        importer,
+                        line,
+                        kind: import_kind(&text),
+                    });
+                }
+            }
+        }
+        Ok(edges.into_iter().collect())
+    }
+}
+
+pub fn format_module_graph(url: &str, edges: &[ImportEdge]) -> String {
+    let mut output = format!("# Importers of {}\n\n", url);
+    if edges.is_empty() {
+        output.push_str("No importers found.\n");
+        return output;
+    }
+
+    let mut by_module: BTreeMap<&str, Vec<&ImportEdge>> = BTreeMap::new();
+    for edge in edges {
+        by_module.entry(&edge.module).or_default().push(edge);
+    }
+
+    fn write_importers(
+        output: &mut String,
+        module: &str,
+        by_module: &BTreeMap<&str, Vec<&ImportEdge>>,
+        indent: usize,
+        shown: &mut BTreeSet<String>,
+    ) {
+        for edge in by_module.get(module).into_iter().flatten() {
+            output.push_str(&format!(
+                "{}- {}:{} ({})\n",
+                "  ".repeat(indent),
+                edge.importer,
+                edge.line,
+                edge.kind
+            ));
+            if shown.insert(edge.importer.clone()) {
+                write_importers(output, &edge.importer, by_module, indent + 1, shown);
+            }
+        }
+    }
+
+    let mut shown = BTreeSet::new();
+    write_importers(&mut output, url, &by_module, 0, &mut shown);
+
+    let files: BTreeSet<&str> = edges.iter().map(|e| e.importer.as_str()).collect();
+    output.push_str(&format!(
+        "\n{} import(s) in {} file(s)\n",
+        edges.len(),
+        files.len()
+    ));
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_imports() {
+        for (text, kind) in [
+            (
+                r#"const { AppConstants } = ChromeUtils.importESModule("resource://gre/modules/AppConstants.sys.mjs");"#,
+                "importESModule",
+            ),
+            (
+                r#"  AppConstants: "resource://gre/modules/AppConstants.sys.mjs","#,
+                "lazy getter",
+            ),
+            (
+                r#"import { AppConstants } from "resource://gre/modules/AppConstants.sys.mjs";"#,
+                "static import",
+            ),
+            (
+                r#"} from "resource://gre/modules/AppConstants.sys.mjs";"#,
+                "static import",
+            ),
+            (
+                r#"const { X } = ChromeUtils.import("resource://gre/modules/X.jsm");"#,
+                "JSM import",
+            ),
+            (
+                r#"// See resource://gre/modules/AppConstants.sys.mjs"#,
+                "reference",
+            ),
+        ] {
+            assert_eq!(import_kind(text), kind, "{text}");
+        }
+    }
+
+    #[test]
+    fn formats_nested_importers() {
+        let edge = |module: &str, importer: &str, line| ImportEdge {
+            module: module.into(),
+            importer: importer.into(),
+            line,
+            kind: "static import",
+        };
+        let url = "resource://gre/modules/AppConstants.sys.mjs";
+        let edges = vec![
+            edge(url, "toolkit/modules/Foo.sys.mjs", 3),
+            edge(url, "browser/app/test.js", 10),
+            edge("toolkit/modules/Foo.sys.mjs", "browser/Bar.sys.mjs", 7),
+        ];
+        assert_eq!(module_search_text(&edges[0].importer), "/Foo.sys.mjs");
+        let output = format_module_graph(url, &edges);
+        assert!(output.contains(
+            "- toolkit/modules/Foo.sys.mjs:3 (static import)\n  - browser/Bar.sys.mjs:7 (static import)\n"
+        ));
+        assert!(output.contains("- browser/app/test.js:10 (static import)\n"));
+        assert!(output.ends_with("3 import(s) in 3 file(s)\n"));
+    }
+}
@@ -10,6 +10,24 @@ pub struct NestingContext {
     pub pretty_line: String,
 }
 
+/// A class, namespace or function block of a file, as found in searchfox's
+/// nesting data.
+#[derive(Debug, Clone)]
+pub struct OutlineEntry {
+    pub sym: String,
+    pub pretty_line: String,
+    /// Line where the block starts.
+    pub line: usize,
+}
+
+/// The innermost block containing a line and the range of lines it spans.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnclosingBlock {
+    pub sym: String,
+    pub start: usize,
+    pub end: usize,
+}
+
 impl SearchfoxClient {
     /// Return the nesting context (function/class/namespace chain) that contains `line` in `path`.
     ///
@@ -29,6 +47,88 @@ impl SearchfoxClient {
         let html = self.get_html(&url).await?;
         Ok(parse_nesting_at_line(&html, line))
     }
+
+    /// Return every nesting block (namespace, class, function) of `path`, in
+    /// source order.
+    pub async fn get_outline(&self, path: &str) -> Result<Vec<OutlineEntry>> {
+        let url = format!(
+            "{}/{}/source/{}",
+            self.base_url,
+            searchfox_url_repo(&self.repo),
+            path
+        );
+        let html = self.get_html(&url).await?;
+        Ok(parse_outline(&html))
+    }
+
+    /// Return the innermost nesting block containing `line` in `path`, or
+    /// `None` when the line is at file scope.
+    pub async fn get_enclosing_block(
+        &self,
+        path: &str,
+        line: usize,
+    ) -> Result<Option<EnclosingBlock>> {
+        let url = format!(
+            "{}/{}/source/{}",
+            self.base_url,
+            searchfox_url_repo(&self.repo),
+            path
+        );
+        let html = self.get_html(&url).await?;
+        Ok(parse_enclosing_block(&html, line))
+    }
+}
+
+fn row_line(row: ElementRef) -> Option<usize> {
+    row.value().attr("id")?.strip_prefix("line-")?.parse().ok()
+}
+
+fn parse_enclosing_block(html: &str, target_line: usize) -> Option<EnclosingBlock> {
+    let document = Html::parse_document(html);
+    let row_selector = Selector::parse(&format!("div[id=\"line-{}\"]", target_line)).unwrap();
+    let rows_selector = Selector::parse("div[role='row']").unwrap();
+
+    let row = document.select(&row_selector).next()?;
+    let container = row.ancestors().filter_map(ElementRef::wrap).find(|elem| {
+        elem.value()
+            .has_class("nesting-container", scraper::CaseSensitivity::CaseSensitive)
+            && elem.value().attr("data-nesting-sym").is_some()
+    })?;
+    let lines: Vec<usize> = container
+        .select(&rows_selector)
+        .filter_map(row_line)
+        .collect();
+    Some(EnclosingBlock {
+        sym: container.value().attr("data-nesting-sym")?.to_string(),
+        start: *lines.iter().min()?,
+        end: *lines.iter().max()?,
+    })
+}
+
+fn parse_outline(html: &str) -> Vec<OutlineEntry> {
+    let document = Html::parse_document(html);
+    let container_selector = Selector::parse("div.nesting-container[data-nesting-sym]").unwrap();
+    let row_selector = Selector::parse("div[role='row']").unwrap();
+    let code_selector = Selector::parse("code.source-line").unwrap();
+
+    document
+        .select(&container_selector)
+        .filter_map(|container| {
+            let sym = container.value().attr("data-nesting-sym")?;
+            let row = container.select(&row_selector).next()?;
+            let line = row_line(row)?;
+            let pretty_line = row
+                .select(&code_selector)
+                .next()
+                .map(|code| code.text().collect::<String>().trim().to_string())
+                .unwrap_or_default();
+            Some(OutlineEntry {
+                sym: sym.to_string(),
+                pretty_line,
+                line,
+            })
+        })
+        .collect()
 }
 
 fn parse_nesting_at_line(html: &str, target_line: usize) -> Vec<NestingContext> {
@@ -124,4 +224,32 @@ mod tests {
         assert!(!result[0].pretty_line.is_empty());
         assert!(result[0].pretty_line.contains("innerMethod"));
     }
+
+    #[test]
+    fn outline_lists_blocks_in_source_order() {
+        let html = make_html("    return 42;");
+        let outline = parse_outline(&html);
+        let entries: Vec<(&str, usize)> =
+            outline.iter().map(|e| (e.sym.as_str(), e.line)).collect();
+        assert_eq!(
+            entries,
+            vec![("OuterClass", 1), ("OuterClass::innerMethod", 2)]
+        );
+        assert_eq!(outline[1].pretty_line, "void innerMethod() {");
+    }
+
+    #[test]
+    fn enclosing_block_spans_its_rows() {
+        let html = make_html("    return 42;");
+        let block = parse_enclosing_block(&html, 3).unwrap();
+        assert_eq!(
+            block,
+            EnclosingBlock {
+                sym: "OuterClass::innerMethod".into(),
+                start: 2,
+                end: 3,
+            }
+        );
+        assert!(parse_enclosing_block(&html, 4).is_none());
+    }
 }
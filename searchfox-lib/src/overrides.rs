@@ -0,0 +1,230 @@
+use crate::call_graph::{CallGraphQuery, JumpRef, SymbolGraphCollection, SymbolRef};
+use crate::can_gc::symbol_matches;
+use crate::class_hierarchy::{format_nodes, hierarchy_node, HierarchyNode};
+use crate::client::SearchfoxClient;
+use anyhow::Result;
+use futures::stream::{self, StreamExt};
+use log::debug;
+use std::collections::{BTreeMap, BTreeSet};
+
+const CONCURRENT_LOOKUPS: usize = 8;
+
+/// Which way to follow the overrides of a virtual method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverrideDirection {
+    /// The base class methods it overrides.
+    Overrides,
+    /// The subclass methods overriding it.
+    OverriddenBy,
+}
+
+impl OverrideDirection {
+    fn related(self, info: &JumpRef) -> &[SymbolRef] {
+        match self {
+            OverrideDirection::Overrides => &info.meta.overrides,
+            OverrideDirection::OverriddenBy => &info.meta.overridden_by,
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            OverrideDirection::Overrides => "overrides",
+            OverrideDirection::OverriddenBy => "overridden by",
+        }
+    }
+}
+
+/// A tree per method of `roots`, whose children are the methods it
+/// overrides or is overridden by, following only the symbols of `expanded`.
+pub fn override_trees(
+    graph: &SymbolGraphCollection,
+    roots: &[String],
+    expanded: &BTreeSet<String>,
+    direction: OverrideDirection,
+) -> Vec<HierarchyNode> {
+    let mut links: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for (symbol, info) in &graph.jumprefs {
+        if expanded.contains(symbol) {
+            for related in direction.related(info) {
+                links
+                    .entry(symbol.as_str())
+                    .or_default()
+                    .insert(related.sym.as_str());
+            }
+        }
+    }
+    roots
+        .iter()
+        .map(|root| hierarchy_node(graph, &links, root, &mut Vec::new()))
+        .collect()
+}
+
+/// Each method with, under it, the methods it overrides or that override
+/// it, as an indented tree with locations.
+pub fn format_overrides(methods: &[HierarchyNode], direction: OverrideDirection) -> String {
+    let mut output = String::new();
+    for method in methods {
+        output.push_str(&method.pretty);
+        if let Some(location) = &method.location {
+            output.push_str(&format!(" ({location})"));
+        }
+        output.push_str(&format!("\n  {}:\n", direction.title()));
+        if method.children.is_empty() {
+            output.push_str("    (none)\n");
+        }
+        format_nodes(&method.children, 2, &mut output);
+    }
+    output
+}
+
+impl SearchfoxClient {
+    /// What is known about `method`, and the functions it calls, from its
+    /// calls-from graph.
+    async fn method_jumprefs(&self, method: &str) -> Result<BTreeMap<String, JumpRef>> {
+        let query = CallGraphQuery {
+            calls_from: Some(method.to_string()),
+            calls_to: None,
+            calls_between: None,
+            depth: 1,
+        };
+        Ok(self.call_graph(&query).await?.jumprefs)
+    }
+
+    /// The methods `method` overrides, or that override it, following
+    /// `depth` levels of overrides from each of its overloads.
+    pub async fn method_overrides(
+        &self,
+        method: &str,
+        direction: OverrideDirection,
+        depth: u32,
+    ) -> Result<Vec<HierarchyNode>> {
+        let mut known = self.method_jumprefs(method).await?;
+        let roots: Vec<String> = known
+            .iter()
+            .filter(|(_, info)| info.pretty().is_some_and(|p| symbol_matches(p, method)))
+            .map(|(symbol, _)| symbol.clone())
+            .collect();
+
+        let mut expanded = BTreeSet::new();
+        let mut frontier = roots.clone();
+        for _ in 0..depth {
+            let mut next: Vec<SymbolRef> = Vec::new();
+            for symbol in &frontier {
+                expanded.insert(symbol.clone());
+                if let Some(info) = known.get(symbol) {
+                    next.extend(direction.related(info).iter().cloned());
+                }
+            }
+            next.retain(|related| !expanded.contains(&related.sym));
+            if next.is_empty() {
+                break;
+            }
+
+            // Methods of other classes are described by their own graphs.
+            let missing: Vec<String> = next
+                .iter()
+                .filter(|related| !known.contains_key(&related.sym))
+                .filter_map(|related| related.pretty.clone())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect();
+            let fetched: Vec<BTreeMap<String, JumpRef>> = stream::iter(missing)
+                .map(|pretty| async move {
+                    self.method_jumprefs(&pretty).await.unwrap_or_else(|e| {
+                        debug!("No call graph for {pretty}: {e}");
+                        BTreeMap::new()
+                    })
+                })
+                .buffered(CONCURRENT_LOOKUPS)
+                .collect()
+                .await;
+            for jumprefs in fetched {
+                for (symbol, info) in jumprefs {
+                    known.entry(symbol).or_insert(info);
+                }
+            }
+            for related in &next {
+                known.entry(related.sym.clone()).or_insert_with(|| JumpRef {
+                    pretty: related.pretty.clone(),
+                    ..Default::default()
+                });
+            }
+            frontier = next.into_iter().map(|related| related.sym).collect();
+        }
+
+        let graph = SymbolGraphCollection {
+            jumprefs: known,
+            ..Default::default()
+        };
+        Ok(override_trees(&graph, &roots, &expanded, direction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_overrides_of_expanded_methods() {
+        let json = serde_json::json!({
+            "jumprefs": {
+                "_ZN7mozilla3dom9AudioNode17DestroyMediaTrackEv": {
+                    "pretty": "mozilla::dom::AudioNode::DestroyMediaTrack",
+                    "jumps": {"def": "dom/media/webaudio/AudioNode.cpp#380"},
+                    "meta": {"overriddenBy": [
+                        {"sym": "_ZN7mozilla3dom8GainNode17DestroyMediaTrackEv"},
+                        {"sym": "_ZN7mozilla3dom24AudioScheduledSourceNode17DestroyMediaTrackEv"}
+                    ]}
+                },
+                "_ZN7mozilla3dom24AudioScheduledSourceNode17DestroyMediaTrackEv": {
+                    "pretty": "mozilla::dom::AudioScheduledSourceNode::DestroyMediaTrack",
+                    "meta": {"overriddenBy": [
+                        {"sym": "_ZN7mozilla3dom21AudioBufferSourceNode17DestroyMediaTrackEv"}
+                    ]}
+                },
+                "_ZN7mozilla3dom21AudioBufferSourceNode17DestroyMediaTrackEv": {
+                    "pretty": "mozilla::dom::AudioBufferSourceNode::DestroyMediaTrack",
+                    "jumps": {"def": "dom/media/webaudio/AudioBufferSourceNode.cpp#610"},
+                    "meta": {"overrides": [
+                        {"sym": "_ZN7mozilla3dom24AudioScheduledSourceNode17DestroyMediaTrackEv"}
+                    ]}
+                },
+                "_ZN7mozilla3dom8GainNode17DestroyMediaTrackEv": {
+                    "pretty": "mozilla::dom::GainNode::DestroyMediaTrack",
+                    "jumps": {"decl": "dom/media/webaudio/GainNode.h#40"}
+                }
+            }
+        });
+        let graph = SymbolGraphCollection::from_json(&json).unwrap();
+        let root = "_ZN7mozilla3dom9AudioNode17DestroyMediaTrackEv".to_string();
+        let scheduled =
+            "_ZN7mozilla3dom24AudioScheduledSourceNode17DestroyMediaTrackEv".to_string();
+        let direction = OverrideDirection::OverriddenBy;
+
+        let expanded = BTreeSet::from([root.clone(), scheduled]);
+        let trees = override_trees(&graph, std::slice::from_ref(&root), &expanded, direction);
+        assert_eq!(
+            format_overrides(&trees, direction),
+            "mozilla::dom::AudioNode::DestroyMediaTrack (dom/media/webaudio/AudioNode.cpp#380)
+  overridden by:
+    mozilla::dom::AudioScheduledSourceNode::DestroyMediaTrack
+      mozilla::dom::AudioBufferSourceNode::DestroyMediaTrack (dom/media/webaudio/AudioBufferSourceNode.cpp#610)
+    mozilla::dom::GainNode::DestroyMediaTrack (dom/media/webaudio/GainNode.h#40)
+"
+        );
+
+        // One level only: the subclasses of the overriding methods are left out.
+        let expanded = BTreeSet::from([root.clone()]);
+        let trees = override_trees(&graph, &[root], &expanded, direction);
+        assert!(trees[0].children.iter().all(|c| c.children.is_empty()));
+
+        let leaf = "_ZN7mozilla3dom21AudioBufferSourceNode17DestroyMediaTrackEv".to_string();
+        let expanded = BTreeSet::from([leaf.clone()]);
+        let direction = OverrideDirection::Overrides;
+        let trees = override_trees(&graph, &[leaf], &expanded, direction);
+        assert_eq!(
+            trees[0].children[0].pretty,
+            "mozilla::dom::AudioScheduledSourceNode::DestroyMediaTrack"
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use std::fmt;
+
+/// Side of an IPDL actor, from its class name (`FooParent`, `FooChild`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActorSide {
+    Parent,
+    Child,
+}
+
+/// The process a function runs in, as far as names tell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessTag {
+    Parent,
+    Content,
+    Gpu,
+    Rdd,
+    Socket,
+    Utility,
+    /// An actor of a protocol not in `PROTOCOLS`: only its side is known.
+    Actor(ActorSide),
+}
+
+impl fmt::Display for ProcessTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ProcessTag::Parent => "parent process",
+            ProcessTag::Content => "content process",
+            ProcessTag::Gpu => "GPU process",
+            ProcessTag::Rdd => "RDD process",
+            ProcessTag::Socket => "socket process",
+            ProcessTag::Utility => "utility process",
+            ProcessTag::Actor(ActorSide::Parent) => "IPC parent side",
+            ProcessTag::Actor(ActorSide::Child) => "IPC child side",
+        })
+    }
+}
+
+/// Processes the parent and child actors of common protocols live in,
+/// keyed by protocol name without the `P` prefix. The top-level protocols
+/// of the GPU and RDD processes have their parent actor in that process.
+const PROTOCOLS: &[(&str, ProcessTag, ProcessTag)] = &[
+    ("Content", ProcessTag::Parent, ProcessTag::Content),
+    ("Browser", ProcessTag::Parent, ProcessTag::Content),
+    ("BrowserBridge", ProcessTag::Parent, ProcessTag::Content),
+    ("WindowGlobal", ProcessTag::Parent, ProcessTag::Content),
+    ("Necko", ProcessTag::Parent, ProcessTag::Content),
+    ("HttpChannel", ProcessTag::Parent, ProcessTag::Content),
+    ("Cookie", ProcessTag::Parent, ProcessTag::Content),
+    ("GPU", ProcessTag::Gpu, ProcessTag::Parent),
+    ("CompositorManager", ProcessTag::Gpu, ProcessTag::Content),
+    ("CompositorBridge", ProcessTag::Gpu, ProcessTag::Content),
+    ("ImageBridge", ProcessTag::Gpu, ProcessTag::Content),
+    ("WebRenderBridge", ProcessTag::Gpu, ProcessTag::Content),
+    ("APZ", ProcessTag::Gpu, ProcessTag::Content),
+    ("APZCTreeManager", ProcessTag::Gpu, ProcessTag::Content),
+    ("CanvasManager", ProcessTag::Gpu, ProcessTag::Content),
+    ("VideoBridge", ProcessTag::Gpu, ProcessTag::Content),
+    ("RDD", ProcessTag::Rdd, ProcessTag::Parent),
+    ("RemoteDecoderManager", ProcessTag::Rdd, ProcessTag::Content),
+    ("SocketProcess", ProcessTag::Parent, ProcessTag::Socket),
+    ("UtilityProcess", ProcessTag::Parent, ProcessTag::Utility),
+];
+
+/// Protocol name (without `P`) and side of an actor class name such as
+/// `ContentParent` or `PContentChild`.
+fn actor_of_class(class: &str) -> Option<(&str, ActorSide)> {
+    let (base, side) = if let Some(base) = class.strip_suffix("Parent") {
+        (base, ActorSide::Parent)
+    } else {
+        (class.strip_suffix("Child")?, ActorSide::Child)
+    };
+    let base = match base.strip_prefix('P') {
+        Some(rest) if rest.starts_with(|c: char| c.is_ascii_uppercase()) => rest,
+        _ => base,
+    };
+    (!base.is_empty()).then_some((base, side))
+}
+
+/// The IPDL actor a function belongs to: its innermost class named like an
+/// actor, or else the name of the file defining it.
+pub fn actor_of<'a>(symbol: &'a str, path: &'a str) -> Option<(&'a str, ActorSide)> {
+    let components: Vec<&str> = symbol.split("::").collect();
+    let classes = &components[..components.len().saturating_sub(1)];
+    classes
+        .iter()
+        .rev()
+        .find_map(|class| actor_of_class(class))
+        .or_else(|| {
+            let name = path.rsplit('/').next().unwrap_or(path);
+            actor_of_class(name.split('.').next().unwrap_or(name))
+        })
+}
+
+/// The process a function runs in, when it belongs to an IPDL actor.
+pub fn process_tag(symbol: &str, path: &str) -> Option<ProcessTag> {
+    let (protocol, side) = actor_of(symbol, path)?;
+    let tag = match PROTOCOLS.iter().find(|(name, _, _)| *name == protocol) {
+        Some((_, parent, _)) if side == ActorSide::Parent => *parent,
+        Some((_, _, child)) => *child,
+        None => ProcessTag::Actor(side),
+    };
+    Some(tag)
+}
+
+/// The message name of an IPDL `SendFoo` or `RecvFoo` method, and whether
+/// it is the sending side.
+pub fn ipc_message(symbol: &str) -> Option<(&str, bool)> {
+    let method = symbol.rsplit("::").next().unwrap_or(symbol);
+    let (message, send) = if let Some(message) = method.strip_prefix("Send") {
+        (message, true)
+    } else {
+        (method.strip_prefix("Recv")?, false)
+    };
+    message
+        .starts_with(|c: char| c.is_ascii_uppercase())
+        .then_some((message, send))
+}
+
+/// Whether a call edge crosses a process boundary: a call to a `Send*`
+/// message, a `Send*` reaching the matching `Recv*`, or caller and callee
+/// known to run in different processes.
+pub fn is_ipc_edge(from: (&str, &str), to: (&str, &str)) -> bool {
+    if let Some((message, true)) = ipc_message(to.0) {
+        return ipc_message(from.0) != Some((message, true));
+    }
+    if let (Some((sent, true)), Some((received, false))) = (ipc_message(from.0), ipc_message(to.0))
+    {
+        return sent == received;
+    }
+    match (process_tag(from.0, from.1), process_tag(to.0, to.1)) {
+        (Some(a), Some(b)) => a != b,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_actors_with_their_process() {
+        let tag = |symbol: &str, path: &str| process_tag(symbol, path).map(|t| t.to_string());
+        assert_eq!(
+            tag("mozilla::dom::ContentParent::RecvCreateWindow", "").as_deref(),
+            Some("parent process")
+        );
+        assert_eq!(
+            tag("mozilla::dom::PContentChild::SendCreateWindow", "").as_deref(),
+            Some("content process")
+        );
+        assert_eq!(
+            tag("mozilla::gfx::GPUParent::RecvInit", "").as_deref(),
+            Some("GPU process")
+        );
+        assert_eq!(
+            tag(
+                "mozilla::layers::CompositorBridgeChild::SendFlush",
+                "gfx/layers/ipc/CompositorBridgeChild.cpp"
+            )
+            .as_deref(),
+            Some("content process")
+        );
+        assert_eq!(
+            tag("mozilla::dom::FooParent::RecvBar", "").as_deref(),
+            Some("IPC parent side")
+        );
+        assert_eq!(
+            tag("Helper", "dom/media/ipc/RemoteDecoderManagerChild.cpp").as_deref(),
+            Some("content process")
+        );
+        assert_eq!(tag("mozilla::dom::Element::GetParent", ""), None);
+        assert_eq!(tag("nsINode::GetParent", "dom/base/nsINode.cpp"), None);
+    }
+
+    #[test]
+    fn detects_ipc_edges() {
+        let caller = (
+            "mozilla::dom::BrowserChild::DoFoo",
+            "dom/ipc/BrowserChild.cpp",
+        );
+        let send = (
+            "mozilla::dom::PBrowserChild::SendFoo",
+            "ipc/ipdl/PBrowserChild.cpp",
+        );
+        let recv = (
+            "mozilla::dom::BrowserParent::RecvFoo",
+            "dom/ipc/BrowserParent.cpp",
+        );
+        assert!(is_ipc_edge(caller, send));
+        assert!(is_ipc_edge(send, recv));
+        assert!(!is_ipc_edge(
+            caller,
+            ("mozilla::dom::BrowserChild::Bar", "")
+        ));
+        assert!(!is_ipc_edge(("a::Foo", ""), ("a::Bar", "")));
+    }
+}
@@ -0,0 +1,76 @@
+/// Structured progress notifications for long-running operations, delivered
+/// to the [`ProgressSink`] registered on a `SearchfoxClient`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProgressEvent {
+    /// An operation made of `total` items started.
+    Started {
+        operation: String,
+        total: usize,
+    },
+    /// `completed` of the operation's `total` items are done.
+    Advanced {
+        operation: String,
+        completed: usize,
+        total: usize,
+    },
+    Finished {
+        operation: String,
+    },
+    /// A request is about to be issued.
+    Request {
+        url: String,
+    },
+}
+
+/// Receiver for [`ProgressEvent`]s. Events may be emitted from any task, so
+/// implementations must be thread-safe and should return quickly.
+pub trait ProgressSink: Send + Sync {
+    fn on_event(&self, event: &ProgressEvent);
+}
+
+impl<F> ProgressSink for F
+where
+    F: Fn(&ProgressEvent) + Send + Sync,
+{
+    fn on_event(&self, event: &ProgressEvent) {
+        self(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::SearchfoxClient;
+    use std::sync::{Arc, Mutex};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn sink_receives_request_events() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/firefox-main/source/some/file.js"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"<html><body><code class="source-line">let x;</code></body></html>"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink_events = events.clone();
+        let mut client =
+            SearchfoxClient::new_for_test("mozilla-central".into(), server.uri()).unwrap();
+        client.set_progress_sink(Arc::new(move |event: &ProgressEvent| {
+            sink_events.lock().unwrap().push(event.clone());
+        }));
+        client.get_file("some/file.js").await.unwrap();
+
+        let events = events.lock().unwrap();
+        assert_eq!(
+            *events,
+            vec![ProgressEvent::Request {
+                url: format!("{}/firefox-main/source/some/file.js", server.uri()),
+            }]
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use anyhow::Result;
+
+/// A regex query rewritten into the syntax searchfox accepts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckedRegex {
+    pub pattern: String,
+    /// What was rewritten and why, for the user.
+    pub notes: Vec<String>,
+}
+
+/// Constructs searchfox's regex engine (RE2, which matches one line at a
+/// time) rejects or never matches, with an explanation.
+const UNSUPPORTED: &[(&str, &str)] = &[
+    (
+        "(?=",
+        "lookahead `(?=...)` is not supported by searchfox's regex engine (RE2); search without it and narrow the results with --filter or -p",
+    ),
+    (
+        "(?!",
+        "negative lookahead `(?!...)` is not supported by searchfox's regex engine (RE2); search without it and narrow the results with -p",
+    ),
+    (
+        "(?<=",
+        "lookbehind `(?<=...)` is not supported by searchfox's regex engine (RE2); search without it and narrow the results with --filter or -p",
+    ),
+    (
+        "(?<!",
+        "negative lookbehind `(?<!...)` is not supported by searchfox's regex engine (RE2); search without it and narrow the results with -p",
+    ),
+];
+
+/// Validate a regex query locally before sending it, translating common
+/// PCRE-isms into equivalent RE2 syntax. Constructs with no equivalent
+/// (lookarounds, backreferences, patterns spanning lines) are errors
+/// explaining why, instead of a server error or zero results.
+pub fn check_regex(pattern: &str) -> Result<CheckedRegex> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut out = String::new();
+    let mut notes = Vec::new();
+    let mut note = |n: &str| {
+        if !notes.iter().any(|existing| existing == n) {
+            notes.push(n.to_string());
+        }
+    };
+    let mut in_class = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let rest: String = chars[i..].iter().take(7).collect();
+        if c == '\\' && i + 1 < chars.len() {
+            let e = chars[i + 1];
+            match e {
+                '1'..='9' => anyhow::bail!(
+                    "Unsupported regex: backreferences like `\\{}` are not supported by searchfox's regex engine (RE2)",
+                    e
+                ),
+                'n' | 'r' => anyhow::bail!(
+                    "Unsupported regex: searchfox matches one line at a time, so `\\{}` never matches; search for one of the lines instead",
+                    e
+                ),
+                'Z' if !in_class => {
+                    out.push('$');
+                    note("`\\Z` was rewritten as `$`");
+                }
+                'h' => {
+                    out.push_str(if in_class { " \\t" } else { "[ \\t]" });
+                    note("`\\h` was rewritten as `[ \\t]`");
+                }
+                _ => {
+                    out.push(c);
+                    out.push(e);
+                }
+            }
+            i += 2;
+            continue;
+        }
+        if in_class {
+            if c == ']' {
+                in_class = false;
+            }
+            out.push(c);
+            i += 1;
+            continue;
+        }
+        // After escapes and classes, where `(?=` is made of literals.
+        if let Some((_, reason)) = UNSUPPORTED
+            .iter()
+            .find(|(construct, _)| rest.starts_with(construct))
+        {
+            anyhow::bail!("Unsupported regex: {}", reason);
+        }
+        if rest.starts_with("[[:<:]]") || rest.starts_with("[[:>:]]") {
+            out.push_str("\\b");
+            note("BSD word boundaries `[[:<:]]` and `[[:>:]]` were rewritten as `\\b`");
+            i += 7;
+            continue;
+        }
+        if rest.starts_with("(?>") {
+            out.push_str("(?:");
+            note("atomic groups `(?>...)` were rewritten as plain groups `(?:...)`");
+            i += 3;
+            continue;
+        }
+        if rest.starts_with("(?<") {
+            out.push_str("(?P<");
+            note("named groups `(?<name>...)` were rewritten as `(?P<name>...)`");
+            i += 3;
+            continue;
+        }
+        match c {
+            '[' => {
+                in_class = true;
+                out.push(c);
+                // A leading `]` (or `^]`) is a literal, not the end of the class.
+                for _ in 0..2 {
+                    match chars.get(i + 1) {
+                        Some(&next @ ('^' | ']')) => {
+                            out.push(next);
+                            i += 1;
+                            if next == ']' {
+                                break;
+                            }
+                        }
+                        _ => break,
+                    }
+                }
+            }
+            '*' | '+' | '?' | '}' if chars.get(i + 1) == Some(&'+') => {
+                out.push(c);
+                note("possessive quantifiers like `*+` were rewritten as greedy ones");
+                i += 1;
+            }
+            _ => out.push(c),
+        }
+        i += 1;
+    }
+
+    regex::Regex::new(&out).map_err(|e| anyhow::anyhow!("Invalid regex: {}", e))?;
+    Ok(CheckedRegex {
+        pattern: out,
+        notes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_patterns_are_untouched() {
+        let checked = check_regex(r"^Audio\w+::Init\(").unwrap();
+        assert_eq!(checked.pattern, r"^Audio\w+::Init\(");
+        assert!(checked.notes.is_empty());
+        assert_eq!(check_regex(r"a\+\+[+*]+").unwrap().pattern, r"a\+\+[+*]+");
+    }
+
+    #[test]
+    fn translates_pcre_isms() {
+        let checked = check_regex(r"[[:<:]]mFoo(?>Bar|Baz)++\h*(?<n>\d+)\Z").unwrap();
+        assert_eq!(checked.pattern, r"\bmFoo(?:Bar|Baz)+[ \t]*(?P<n>\d+)$");
+        assert_eq!(checked.notes.len(), 6);
+    }
+
+    #[test]
+    fn explains_unsupported_constructs() {
+        let err = check_regex("Foo(?!Bar)").unwrap_err().to_string();
+        assert!(err.contains("negative lookahead"));
+        let err = check_regex(r"(\w+) \1").unwrap_err().to_string();
+        assert!(err.contains("backreferences"));
+        let err = check_regex(r"foo\nbar").unwrap_err().to_string();
+        assert!(err.contains("one line at a time"));
+        let err = check_regex("Foo(").unwrap_err().to_string();
+        assert!(err.starts_with("Invalid regex"));
+        // An optional literal `(` followed by `=` is no lookahead.
+        assert_eq!(check_regex(r"foo\(?=bar").unwrap().pattern, r"foo\(?=bar");
+        assert_eq!(check_regex("[(?<=]x").unwrap().pattern, "[(?<=]x");
+    }
+}
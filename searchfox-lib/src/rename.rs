@@ -0,0 +1,382 @@
+use crate::client::SearchfoxClient;
+use crate::search::SearchOptions;
+use crate::types::{File, SearchfoxResponse};
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Kinds of occurrences, in checklist order.
+pub const RENAME_KINDS: &[&str] = &[
+    "definition",
+    "declaration",
+    "use",
+    "string",
+    "build",
+    "docs",
+    "comment",
+    "text",
+];
+
+/// Every occurrence of a name to rename, grouped by kind then by file.
+#[derive(Debug, Default, Serialize)]
+pub struct RenamePlan {
+    pub name: String,
+    pub groups: Vec<RenameGroup>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RenameGroup {
+    pub kind: &'static str,
+    pub files: Vec<RenameFile>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RenameFile {
+    pub path: String,
+    pub lines: Vec<usize>,
+}
+
+/// Kind of an indexed (semantic) hit, from its searchfox label.
+fn semantic_kind(label: &str) -> &'static str {
+    if label.starts_with("Definitions") {
+        "definition"
+    } else if label.starts_with("Declarations") {
+        "declaration"
+    } else {
+        "use"
+    }
+}
+
+fn is_build_file(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    matches!(
+        name,
+        "moz.build" | "Makefile.in" | "Cargo.toml" | "jar.mn" | "components.conf" | "moz.configure"
+    ) || [".mozbuild", ".mk", ".gn", ".gni", ".manifest", ".build"]
+        .iter()
+        .any(|ext| name.ends_with(ext))
+}
+
+fn is_doc_file(path: &str) -> bool {
+    [".md", ".rst", ".txt"]
+        .iter()
+        .any(|ext| path.ends_with(ext))
+}
+
+/// Length in bytes of the char literal (`'x'`, `'\n'`, `'\x41'`) `rest`
+/// starts with, if it starts with one rather than with a lone apostrophe.
+fn char_literal_len(rest: &str) -> Option<usize> {
+    let body = rest.strip_prefix('\'')?;
+    let mut chars = body.char_indices();
+    let (_, first) = chars.next()?;
+    if first == '\\' {
+        let (escaped, c) = chars.next()?;
+        let tail = &body[escaped + c.len_utf8()..];
+        let close = tail.find('\'')?;
+        tail[..close]
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric())
+            .then(|| 1 + escaped + c.len_utf8() + close + 1)
+    } else {
+        let (close, c) = chars.next()?;
+        (first != '\'' && c == '\'').then_some(1 + close + 1)
+    }
+}
+
+/// Whether `name` appears inside a double-quoted string literal on `line`,
+/// outside of comments. Apostrophes only count when they enclose a char
+/// literal.
+fn in_string_literal(line: &str, name: &str) -> bool {
+    let mut string_start: Option<usize> = None;
+    let mut skip_to = 0;
+    for (i, c) in line.char_indices() {
+        if i < skip_to {
+            continue;
+        }
+        match (string_start, c) {
+            (Some(_), '\\') => skip_to = i + 2,
+            (Some(start), '"') => {
+                if line[start..i].contains(name) {
+                    return true;
+                }
+                string_start = None;
+            }
+            (Some(_), _) => {}
+            (None, '"') => string_start = Some(i + 1),
+            (None, '\'') => skip_to = i + char_literal_len(&line[i..]).unwrap_or(1),
+            (None, '/') if line[i..].starts_with("//") => return false,
+            (None, '/') if line[i..].starts_with("/*") => match line[i + 2..].find("*/") {
+                Some(end) => skip_to = i + 2 + end + 2,
+                None => return false,
+            },
+            (None, _) => {}
+        }
+    }
+    false
+}
+
+/// Kind of a textual hit that searchfox did not resolve to a symbol.
+pub fn text_kind(path: &str, line: &str, name: &str) -> &'static str {
+    let trimmed = line.trim_start();
+    if is_build_file(path) {
+        "build"
+    } else if is_doc_file(path) {
+        "docs"
+    } else if ["//", "/*", "*", "#", "<!--"]
+        .iter()
+        .any(|c| trimmed.starts_with(c))
+    {
+        "comment"
+    } else if in_string_literal(line, name) {
+        "string"
+    } else {
+        "text"
+    }
+}
+
+fn files_of(value: &serde_json::Value) -> impl Iterator<Item = File> + '_ {
+    value
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|file| serde_json::from_value::<File>(file.clone()).ok())
+}
+
+/// Build the plan from an identifier search (`semantic`) and a whole-word
+/// text search (`text`) for `name`. A line found by both is classified by
+/// its indexed hit.
+pub fn build_rename_plan(
+    name: &str,
+    semantic: &SearchfoxResponse,
+    text: &SearchfoxResponse,
+    options: &SearchOptions,
+) -> RenamePlan {
+    let mut kinds: BTreeMap<(String, usize), &'static str> = BTreeMap::new();
+    let categories = |json: &'_ SearchfoxResponse| {
+        json.iter()
+            .filter(|(category, _)| {
+                !category.starts_with('*') && options.category_filter.should_include(category)
+            })
+            .map(|(_, value)| value.clone())
+            .collect::<Vec<_>>()
+    };
+
+    for value in categories(semantic) {
+        for (label, file_list) in value.as_object().into_iter().flatten() {
+            for file in files_of(file_list) {
+                if !options.matches_language_filter(&file.path) {
+                    continue;
+                }
+                for line in file.lines {
+                    kinds.insert((file.path.clone(), line.lno), semantic_kind(label));
+                }
+            }
+        }
+    }
+    for value in categories(text) {
+        let lists: Vec<&serde_json::Value> = match value.as_object() {
+            Some(obj) => obj.values().collect(),
+            None => vec![&value],
+        };
+        for file in lists.into_iter().flat_map(files_of) {
+            if !options.matches_language_filter(&file.path) {
+                continue;
+            }
+            for line in file.lines {
+                kinds
+                    .entry((file.path.clone(), line.lno))
+                    .or_insert_with(|| text_kind(&file.path, &line.line, name));
+            }
+        }
+    }
+
+    let mut by_kind: BTreeMap<&str, BTreeMap<String, BTreeSet<usize>>> = BTreeMap::new();
+    for ((path, line), kind) in kinds {
+        by_kind
+            .entry(kind)
+            .or_default()
+            .entry(path)
+            .or_default()
+            .insert(line);
+    }
+    let groups = RENAME_KINDS
+        .iter()
+        .filter_map(|kind| {
+            let files = by_kind.remove(kind)?;
+            Some(RenameGroup {
+                kind,
+                files: files
+                    .into_iter()
+                    .map(|(path, lines)| RenameFile {
+                        path,
+                        lines: lines.into_iter().collect(),
+                    })
+                    .collect(),
+            })
+        })
+        .collect();
+    RenamePlan {
+        name: name.to_string(),
+        groups,
+    }
+}
+
+impl SearchfoxClient {
+    /// Collect every occurrence of `name` for a rename: indexed definitions,
+    /// declarations and uses, then whole-word text matches in strings,
+    /// build files, docs and comments.
+    pub async fn rename_plan(&self, name: &str, options: &SearchOptions) -> Result<RenamePlan> {
+        let search = |id: Option<String>, query: Option<String>| SearchOptions {
+            regexp: query.is_some(),
+            query,
+            symbol: None,
+            id,
+            case: true,
+            context: None,
+            ..options.clone()
+        };
+        let semantic = search(Some(name.to_string()), None);
+        let text = search(None, Some(format!(r"\b{}\b", regex::escape(name))));
+        let (semantic_json, text_json) =
+            futures::try_join!(self.search_response(&semantic), self.search_response(&text))?;
+        Ok(build_rename_plan(name, &semantic_json, &text_json, options))
+    }
+}
+
+fn plural(kind: &str) -> String {
+    match kind {
+        "docs" | "text" => kind.to_string(),
+        "build" => "build files".to_string(),
+        _ => format!("{kind}s"),
+    }
+}
+
+/// The plan as a markdown checklist, one item per file.
+pub fn format_rename_plan(plan: &RenamePlan) -> String {
+    let mut output = format!("# Rename plan for {}\n\n", plan.name);
+    if plan.groups.is_empty() {
+        output.push_str("No occurrences found.\n");
+        return output;
+    }
+    let mut total = 0;
+    let mut files = BTreeSet::new();
+    for group in &plan.groups {
+        let count: usize = group.files.iter().map(|f| f.lines.len()).sum();
+        total += count;
+        output.push_str(&format!(
+            "## {} ({} in {} file(s))\n\n",
+            plural(group.kind),
+            count,
+            group.files.len()
+        ));
+        for file in &group.files {
+            files.insert(&file.path);
+            let lines: Vec<String> = file.lines.iter().map(|l| l.to_string()).collect();
+            output.push_str(&format!("- [ ] {}: {}\n", file.path, lines.join(", ")));
+        }
+        output.push('\n');
+    }
+    output.push_str(&format!(
+        "{} occurrence(s) in {} file(s)\n",
+        total,
+        files.len()
+    ));
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_text_hits() {
+        assert_eq!(
+            text_kind("dom/media/moz.build", "    'AudioSink.cpp',", "AudioSink"),
+            "build"
+        );
+        assert_eq!(
+            text_kind("dom/media/doc/index.rst", "AudioSink plays", "AudioSink"),
+            "docs"
+        );
+        assert_eq!(
+            text_kind(
+                "dom/media/Foo.cpp",
+                r#"  LOG("AudioSink=%p", this);"#,
+                "AudioSink"
+            ),
+            "string"
+        );
+        assert_eq!(
+            text_kind(
+                "dom/media/Foo.cpp",
+                "  // The AudioSink owns it",
+                "AudioSink"
+            ),
+            "comment"
+        );
+        assert_eq!(
+            text_kind(
+                "dom/media/Foo.cpp",
+                "  // Can't free this until 'mFoo' is gone",
+                "mFoo"
+            ),
+            "comment"
+        );
+        assert_eq!(
+            text_kind(
+                "dom/media/Foo.cpp",
+                "  Free(mFoo); // Can't use 'mFoo' after this",
+                "mFoo"
+            ),
+            "text"
+        );
+        assert_eq!(
+            text_kind(
+                "dom/media/Foo.cpp",
+                r#"  Append('"', '\n'); Log("mFoo");"#,
+                "mFoo"
+            ),
+            "string"
+        );
+        assert_eq!(
+            text_kind("dom/media/Foo.cpp", "  X(AudioSink)", "AudioSink"),
+            "text"
+        );
+    }
+
+    #[test]
+    fn groups_by_kind_preferring_indexed_hits() {
+        let semantic: SearchfoxResponse = serde_json::from_str(
+            r#"{
+                "normal": {
+                    "Definitions (mozilla::AudioSink)": [
+                        {"path": "dom/media/AudioSink.h", "lines": [{"lno": 30, "line": "class AudioSink {"}]}
+                    ],
+                    "Uses (mozilla::AudioSink)": [
+                        {"path": "dom/media/Foo.cpp", "lines": [{"lno": 5, "line": "AudioSink* s;"}, {"lno": 9, "line": "AudioSink* t;"}]}
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+        let text: SearchfoxResponse = serde_json::from_str(
+            r#"{
+                "normal": {
+                    "Textual Occurrences": [
+                        {"path": "dom/media/Foo.cpp", "lines": [{"lno": 5, "line": "AudioSink* s;"}, {"lno": 12, "line": "LOG(\"AudioSink\");"}]},
+                        {"path": "dom/media/moz.build", "lines": [{"lno": 40, "line": "'AudioSink.cpp',"}]}
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+        let plan = build_rename_plan("AudioSink", &semantic, &text, &SearchOptions::default());
+        let kinds: Vec<&str> = plan.groups.iter().map(|g| g.kind).collect();
+        assert_eq!(kinds, vec!["definition", "use", "string", "build"]);
+        assert_eq!(plan.groups[1].files[0].lines, vec![5, 9]);
+
+        let output = format_rename_plan(&plan);
+        assert!(output.contains("## uses (2 in 1 file(s))\n\n- [ ] dom/media/Foo.cpp: 5, 9\n"));
+        assert!(output.ends_with("5 occurrence(s) in 3 file(s)\n"));
+    }
+}
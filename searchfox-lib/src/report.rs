@@ -0,0 +1,229 @@
+use crate::call_graph::{format_call_graph_markdown, CallGraphQuery, SymbolGraphCollection};
+use crate::client::SearchfoxClient;
+use crate::field_layout::{format_field_layout, has_field_layout, FieldLayoutQuery};
+use crate::search::SearchOptions;
+use crate::stats::{compute_stats, SearchStats};
+use crate::types::{File, SearchfoxResponse};
+use anyhow::Result;
+
+/// Everything searchfox knows about a symbol, gathered by `symbol_report`.
+#[derive(Debug)]
+pub struct SymbolReport {
+    pub symbol: String,
+    pub definition: String,
+    /// `(path, line number, line)` of each declaration.
+    pub declarations: Vec<(String, usize, String)>,
+    /// Formatted field layout, when the symbol is a C++ or Rust class or struct.
+    pub field_layout: Option<String>,
+    /// Markdown list of direct callers, if any.
+    pub callers: Option<String>,
+    /// Markdown list of direct callees, if any.
+    pub callees: Option<String>,
+    pub usage: SearchStats,
+}
+
+fn declarations(json: &SearchfoxResponse, options: &SearchOptions) -> Vec<(String, usize, String)> {
+    let mut declarations = Vec::new();
+    for (category, value) in json {
+        if category.starts_with('*') || !options.category_filter.should_include(category) {
+            continue;
+        }
+        let Some(obj) = value.as_object() else {
+            continue;
+        };
+        for (label, file_list) in obj {
+            if !label.starts_with("Declarations") {
+                continue;
+            }
+            for file in file_list.as_array().into_iter().flatten() {
+                let Ok(file) = serde_json::from_value::<File>(file.clone()) else {
+                    continue;
+                };
+                if !options.matches_language_filter(&file.path) {
+                    continue;
+                }
+                for line in file.lines {
+                    declarations.push((file.path.clone(), line.lno, line.line.trim().to_string()));
+                }
+            }
+        }
+    }
+    declarations.sort();
+    declarations
+}
+
+/// The call graph section of a formatted call graph, without its title.
+fn call_graph_section(query_text: &str, graph: &SymbolGraphCollection) -> Option<String> {
+    if graph.is_empty() {
+        return None;
+    }
+    let markdown = format_call_graph_markdown(query_text, graph);
+    let body = markdown
+        .strip_prefix(&format!("# {}\n\n", query_text))
+        .unwrap_or(&markdown)
+        .trim_end();
+    (!body.is_empty()).then(|| body.to_string())
+}
+
+impl SearchfoxClient {
+    /// Gather the definition, declarations, field layout, direct callers and
+    /// callees, and usage counts of `symbol`. Parts that cannot be fetched
+    /// are left empty rather than failing the whole report.
+    pub async fn symbol_report(
+        &self,
+        symbol: &str,
+        options: &SearchOptions,
+    ) -> Result<SymbolReport> {
+        let id_options = SearchOptions {
+            query: None,
+            symbol: None,
+            id: Some(symbol.to_string()),
+            ..options.clone()
+        };
+        let callers_query = CallGraphQuery {
+            calls_from: None,
+            calls_to: Some(symbol.to_string()),
+            calls_between: None,
+            depth: 1,
+        };
+        let callees_query = CallGraphQuery {
+            calls_from: Some(symbol.to_string()),
+            calls_to: None,
+            calls_between: None,
+            depth: 1,
+        };
+        let layout_query = FieldLayoutQuery {
+            class_name: symbol.to_string(),
+        };
+
+        let (definition, id_response, callers, callees, layout) = tokio::join!(
+            self.find_and_display_definition(symbol, options.path.as_deref(), options),
+            self.search_response(&id_options),
+            self.call_graph(&callers_query),
+            self.call_graph(&callees_query),
+            self.search_field_layout(&layout_query),
+        );
+
+        let id_response = id_response?;
+        Ok(SymbolReport {
+            symbol: symbol.to_string(),
+            definition: definition.unwrap_or_default(),
+            declarations: declarations(&id_response, &id_options),
+            field_layout: layout
+                .ok()
+                .filter(|json| has_field_layout(symbol, json))
+                .map(|json| format_field_layout(symbol, &json)),
+            callers: callers.ok().and_then(|graph| {
+                call_graph_section(&format!("calls-to:'{symbol}' depth:1"), &graph)
+            }),
+            callees: callees.ok().and_then(|graph| {
+                call_graph_section(&format!("calls-from:'{symbol}' depth:1"), &graph)
+            }),
+            usage: compute_stats(&id_response, &id_options),
+        })
+    }
+}
+
+pub fn format_symbol_report(report: &SymbolReport) -> String {
+    let mut output = format!("# {}\n\n", report.symbol);
+
+    output.push_str("## Definition\n\n");
+    if report.definition.is_empty() {
+        output.push_str("No definition found.\n\n");
+    } else {
+        output.push_str(&format!("```\n{}\n```\n\n", report.definition.trim_end()));
+    }
+
+    output.push_str("## Declarations\n\n");
+    if report.declarations.is_empty() {
+        output.push_str("No declarations found.\n\n");
+    } else {
+        for (path, line_number, line) in &report.declarations {
+            output.push_str(&format!("- {path}:{line_number}: {line}\n"));
+        }
+        output.push('\n');
+    }
+
+    if let Some(ref layout) = report.field_layout {
+        output.push_str("## Field Layout\n\n");
+        output.push_str(layout.trim_end());
+        output.push_str("\n\n");
+    }
+
+    output.push_str("## Direct Callers\n\n");
+    output.push_str(report.callers.as_deref().unwrap_or("No callers found."));
+    output.push_str("\n\n## Direct Callees\n\n");
+    output.push_str(report.callees.as_deref().unwrap_or("No callees found."));
+
+    output.push_str("\n\n## Usage\n\n");
+    if report.usage.total == 0 {
+        output.push_str("No matches found.\n");
+    } else {
+        for (kind, count) in &report.usage.by_kind {
+            output.push_str(&format!("- {kind}: {count}\n"));
+        }
+        let by_category: Vec<String> = report
+            .usage
+            .by_category
+            .iter()
+            .map(|(category, count)| format!("{category} {count}"))
+            .collect();
+        output.push_str(&format!(
+            "- Total: {} in {} files ({})\n",
+            report.usage.total,
+            report.usage.files,
+            by_category.join(", ")
+        ));
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collects_declarations_only() {
+        let json: SearchfoxResponse = serde_json::from_str(
+            r#"{
+                "normal": {
+                    "Declarations (AudioContext::CreateGain)": [
+                        {"path": "dom/media/webaudio/AudioContext.h", "lines": [{"lno": 42, "line": "  already_AddRefed<GainNode> CreateGain(ErrorResult&);"}]}
+                    ],
+                    "Definitions (AudioContext::CreateGain)": [
+                        {"path": "dom/media/webaudio/AudioContext.cpp", "lines": [{"lno": 100, "line": "AudioContext::CreateGain(ErrorResult& aRv) {"}]}
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+        let decls = declarations(&json, &SearchOptions::default());
+        assert_eq!(
+            decls,
+            vec![(
+                "dom/media/webaudio/AudioContext.h".to_string(),
+                42,
+                "already_AddRefed<GainNode> CreateGain(ErrorResult&);".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn formats_missing_sections() {
+        let report = SymbolReport {
+            symbol: "Foo".into(),
+            definition: String::new(),
+            declarations: vec![],
+            field_layout: None,
+            callers: None,
+            callees: None,
+            usage: SearchStats::default(),
+        };
+        let text = format_symbol_report(&report);
+        assert!(text.starts_with("# Foo\n"));
+        assert!(text.contains("No definition found."));
+        assert!(text.contains("No callers found."));
+        assert!(!text.contains("## Field Layout"));
+    }
+}
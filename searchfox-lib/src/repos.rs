@@ -0,0 +1,202 @@
+use crate::client::SearchfoxClient;
+use crate::config;
+use crate::types::IndexInfo;
+use crate::utils::{edit_distance, known_repos};
+use anyhow::Result;
+use futures::stream::{self, StreamExt};
+use log::debug;
+use scraper::{Html, Selector};
+
+const CONCURRENT_LOOKUPS: usize = 8;
+
+/// A tree indexed by the server, as listed on its front page.
+#[derive(Debug, Clone)]
+pub struct IndexedTree {
+    /// Name of the tree in URLs, which `--repo` accepts as-is.
+    pub name: String,
+    /// Revision the tree was indexed at, None when its commit info could
+    /// not be fetched.
+    pub index: Option<IndexInfo>,
+}
+
+/// The tree of a link to `<tree>/source`, relative or absolute.
+fn source_link_tree(href: &str) -> Option<&str> {
+    let path = href.split(['?', '#']).next()?.trim_end_matches('/');
+    let tree = path.strip_suffix("/source")?.rsplit('/').next()?;
+    let valid = !tree.is_empty()
+        && tree
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    valid.then_some(tree)
+}
+
+/// Names of the trees linked from a searchfox front page, in page order,
+/// without duplicates.
+pub fn parse_tree_list(html: &str) -> Vec<String> {
+    let document = Html::parse_document(html);
+    let link_selector = Selector::parse("a[href]").unwrap();
+    let mut trees: Vec<String> = Vec::new();
+    for link in document.select(&link_selector) {
+        let Some(tree) = link.value().attr("href").and_then(source_link_tree) else {
+            continue;
+        };
+        if !trees.iter().any(|t| t == tree) {
+            trees.push(tree.to_string());
+        }
+    }
+    trees
+}
+
+/// The candidate closest to `repo`, when it is close enough to be a typo.
+pub fn suggest_repo<'a>(repo: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    let max_distance = (repo.chars().count() / 4).clamp(1, 3);
+    candidates
+        .iter()
+        .map(|candidate| (edit_distance(repo, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// One line per tree: its name, then the revision it was indexed at and the
+/// commit date of that revision.
+pub fn format_tree_list(trees: &[IndexedTree]) -> String {
+    let width = trees.iter().map(|t| t.name.len()).max().unwrap_or(0);
+    let mut output = String::new();
+    for tree in trees {
+        match &tree.index {
+            Some(index) => output.push_str(&format!(
+                "{:width$}  {}  {}\n",
+                tree.name, index.revision, index.date
+            )),
+            None => output.push_str(&format!("{:width$}  (unknown revision)\n", tree.name)),
+        }
+    }
+    output
+}
+
+impl SearchfoxClient {
+    /// Every tree the server indexes, from its front page, with the revision
+    /// each was last indexed at.
+    pub async fn list_trees(&self) -> Result<Vec<IndexedTree>> {
+        let names = self.tree_names().await?;
+        if names.is_empty() {
+            anyhow::bail!("No trees listed on {}/", self.base_url);
+        }
+        Ok(stream::iter(names)
+            .map(|name| async move {
+                let index = self
+                    .tree_index_info(&name)
+                    .await
+                    .inspect_err(|e| debug!("No commit info for {name}: {e}"))
+                    .ok();
+                IndexedTree { name, index }
+            })
+            .buffered(CONCURRENT_LOOKUPS)
+            .collect()
+            .await)
+    }
+
+    async fn tree_names(&self) -> Result<Vec<String>> {
+        let html = self.get_html(&format!("{}/", self.base_url)).await?;
+        Ok(parse_tree_list(&html))
+    }
+
+    /// Fail, suggesting the closest name, when the repository is neither a
+    /// known one nor a tree listed by the server, instead of letting the
+    /// first query fail with a 404. When the tree list cannot be fetched,
+    /// the repository is assumed to exist.
+    pub async fn validate_repo(&self) -> Result<()> {
+        let config = config::global();
+        let mut candidates = known_repos(config);
+        if candidates.contains(&self.repo.as_str()) {
+            return Ok(());
+        }
+        let trees = match self.tree_names().await {
+            Ok(trees) if !trees.is_empty() => trees,
+            Ok(_) => return Ok(()),
+            Err(e) => {
+                debug!("Could not fetch the tree list to check {}: {e}", self.repo);
+                return Ok(());
+            }
+        };
+        if trees.contains(&self.repo) {
+            return Ok(());
+        }
+        candidates.extend(trees.iter().map(String::as_str));
+        match suggest_repo(&self.repo, &candidates) {
+            Some(suggestion) => anyhow::bail!(
+                "Unknown repository '{}'. Did you mean '{}'? (--list-repos lists them all)",
+                self.repo,
+                suggestion
+            ),
+            None => anyhow::bail!(
+                "Unknown repository '{}' (--list-repos lists the available ones)",
+                self.repo
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_linked_trees() {
+        let html = r#"<html><body>
+            <h2>Firefox</h2>
+            <ul>
+              <li><a href="/firefox-main/source">firefox-main</a> (mozilla-central)</li>
+              <li><a href="firefox-beta/source/">firefox-beta</a></li>
+              <li><a href="https://searchfox.org/comm-central/source">comm-central</a></li>
+              <li><a href="/firefox-main/source/dom">again</a></li>
+              <li><a href="/firefox-main/source">firefox-main</a></li>
+            </ul>
+            <a href="https://github.com/mozsearch/mozsearch">mozsearch</a>
+            <a href="/firefox-main/search?q=source">search</a>
+        </body></html>"#;
+        assert_eq!(
+            parse_tree_list(html),
+            ["firefox-main", "firefox-beta", "comm-central"]
+        );
+
+        let trees = [
+            IndexedTree {
+                name: "firefox-main".into(),
+                index: Some(IndexInfo {
+                    revision: "0123456789abcdef0123456789abcdef01234567".into(),
+                    date: "2026-10-15 09:12:44 +0000".into(),
+                    summary: String::new(),
+                    bug_number: None,
+                }),
+            },
+            IndexedTree {
+                name: "nss".into(),
+                index: None,
+            },
+        ];
+        assert_eq!(
+            format_tree_list(&trees),
+            "firefox-main  0123456789abcdef0123456789abcdef01234567  2026-10-15 09:12:44 +0000
+nss           (unknown revision)
+"
+        );
+    }
+
+    #[test]
+    fn suggests_close_repository_names() {
+        let candidates = ["mozilla-central", "mozilla-beta", "comm-central", "nss"];
+        assert_eq!(edit_distance("mozila-central", "mozilla-central"), 1);
+        assert_eq!(
+            suggest_repo("mozila-central", &candidates),
+            Some("mozilla-central")
+        );
+        assert_eq!(
+            suggest_repo("mozilla-bta", &candidates),
+            Some("mozilla-beta")
+        );
+        assert_eq!(suggest_repo("nsx", &candidates), Some("nss"));
+        assert_eq!(suggest_repo("chromium", &candidates), None);
+    }
+}
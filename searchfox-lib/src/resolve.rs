@@ -0,0 +1,196 @@
+use crate::client::SearchfoxClient;
+use crate::demangle::demangle;
+use crate::search::{categories, SearchOptions};
+use crate::types::{File, SearchfoxResponse};
+use anyhow::Result;
+
+/// A concrete symbol an identifier names, e.g. one overload of a method.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedSymbol {
+    /// Symbol as searchfox knows it, mangled for C++, as accepted by
+    /// --symbol and the call graph queries.
+    pub symbol: String,
+    /// Qualified name, from the label of the result.
+    pub pretty: String,
+    /// C++ signature demangled from the symbol, None for other languages.
+    pub signature: Option<String>,
+    /// Whether it was found defined, rather than only declared.
+    pub defined: bool,
+    pub path: String,
+    pub line: usize,
+}
+
+impl ResolvedSymbol {
+    /// The signature, or the qualified name when there is none.
+    pub fn display_name(&self) -> &str {
+        self.signature.as_deref().unwrap_or(&self.pretty)
+    }
+}
+
+/// The symbols of the lines defining or declaring `name`, qualified or not,
+/// in an `id:` response, from their `symbol:` upsearch. Each symbol is listed
+/// once, at its definition when it has one.
+pub fn collect_resolved_symbols(
+    json: &SearchfoxResponse,
+    name: &str,
+    options: &SearchOptions,
+) -> Vec<ResolvedSymbol> {
+    let qualified = format!("::{name}");
+    let mut resolved: Vec<ResolvedSymbol> = Vec::new();
+    for (category, value) in categories(json) {
+        if !options.category_filter.should_include(category) {
+            continue;
+        }
+        let Some(kinds) = value.as_object() else {
+            continue;
+        };
+        for (kind, files) in kinds {
+            let Some((kind_label, pretty)) = kind
+                .strip_suffix(')')
+                .and_then(|rest| rest.split_once(" ("))
+            else {
+                continue;
+            };
+            let defined = match kind_label {
+                "Definitions" => true,
+                "Declarations" => false,
+                _ => continue,
+            };
+            if pretty != name && !pretty.ends_with(&qualified) {
+                continue;
+            }
+            for file in files.as_array().into_iter().flatten() {
+                let Ok(file) = serde_json::from_value::<File>(file.clone()) else {
+                    continue;
+                };
+                if !options.matches_language_filter(&file.path) {
+                    continue;
+                }
+                for line in &file.lines {
+                    let Some(symbols) = line
+                        .upsearch
+                        .as_deref()
+                        .and_then(|u| u.strip_prefix("symbol:"))
+                    else {
+                        continue;
+                    };
+                    for symbol in symbols.split(',').filter(|s| !s.is_empty()) {
+                        let found = ResolvedSymbol {
+                            symbol: symbol.to_string(),
+                            pretty: pretty.to_string(),
+                            signature: demangle(symbol),
+                            defined,
+                            path: file.path.clone(),
+                            line: line.lno,
+                        };
+                        match resolved.iter_mut().find(|r| r.symbol == symbol) {
+                            Some(known) if defined && !known.defined => *known = found,
+                            Some(_) => {}
+                            None => resolved.push(found),
+                        }
+                    }
+                }
+            }
+        }
+    }
+    resolved
+}
+
+/// One symbol per line, as `symbol<TAB>signature<TAB>kind<TAB>path:line`,
+/// the kind being `definition` or `declaration`.
+pub fn format_resolved_symbols(symbols: &[ResolvedSymbol]) -> String {
+    symbols
+        .iter()
+        .map(|s| {
+            let kind = if s.defined {
+                "definition"
+            } else {
+                "declaration"
+            };
+            format!(
+                "{}\t{}\t{}\t{}:{}\n",
+                s.symbol,
+                s.display_name(),
+                kind,
+                s.path,
+                s.line
+            )
+        })
+        .collect()
+}
+
+impl SearchfoxClient {
+    /// The concrete symbols `name` stands for, one per overload or
+    /// same-named function of another class, to pick the right one for a
+    /// call graph query.
+    pub async fn resolve_symbol(
+        &self,
+        name: &str,
+        options: &SearchOptions,
+    ) -> Result<Vec<ResolvedSymbol>> {
+        let id_options = SearchOptions {
+            query: None,
+            symbol: None,
+            id: Some(name.to_string()),
+            ..options.clone()
+        };
+        let json = self.search_response(&id_options).await?;
+        Ok(collect_resolved_symbols(&json, name, &id_options))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_overloads_to_their_symbols() {
+        let json: SearchfoxResponse = serde_json::from_str(
+            r#"{
+                "*title*": "id:AudioContext::CreateGain",
+                "normal": {
+                    "Declarations (mozilla::dom::AudioContext::CreateGain)": [
+                        {"path": "dom/media/webaudio/AudioContext.h", "lines": [
+                            {"lno": 210, "line": "already_AddRefed<GainNode> CreateGain(ErrorResult& aRv);",
+                             "upsearch": "symbol:_ZN7mozilla3dom12AudioContext10CreateGainERNS_11ErrorResultE"}
+                        ]}
+                    ],
+                    "Definitions (mozilla::dom::AudioContext::CreateGain)": [
+                        {"path": "dom/media/webaudio/AudioContext.cpp", "lines": [
+                            {"lno": 469, "line": "already_AddRefed<GainNode> AudioContext::CreateGain(ErrorResult& aRv) {",
+                             "upsearch": "symbol:_ZN7mozilla3dom12AudioContext10CreateGainERNS_11ErrorResultE"}
+                        ]}
+                    ],
+                    "Uses (mozilla::dom::AudioContext::CreateGain)": [
+                        {"path": "dom/media/webaudio/Other.cpp", "lines": [
+                            {"lno": 3, "line": "ctx->CreateGain(rv);", "upsearch": "symbol:_ZOther"}
+                        ]}
+                    ],
+                    "Definitions (mozilla::dom::OfflineAudioContext::CreateGainLater)": [
+                        {"path": "dom/media/webaudio/Offline.cpp", "lines": [
+                            {"lno": 9, "line": "void CreateGainLater()", "upsearch": "symbol:_ZLater"}
+                        ]}
+                    ]
+                },
+                "generated": {
+                    "Definitions (AudioContext::CreateGain)": [
+                        {"path": "dom/bindings/AudioContextBinding.cpp", "lines": [
+                            {"lno": 80, "line": "createGain(JSContext* cx) {", "upsearch": "symbol:#createGain,_ZBinding"}
+                        ]}
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+        let symbols =
+            collect_resolved_symbols(&json, "AudioContext::CreateGain", &SearchOptions::default());
+        assert_eq!(
+            format_resolved_symbols(&symbols),
+            "_ZN7mozilla3dom12AudioContext10CreateGainERNS_11ErrorResultE\t\
+             mozilla::dom::AudioContext::CreateGain(mozilla::ErrorResult&)\tdefinition\t\
+             dom/media/webaudio/AudioContext.cpp:469\n\
+             #createGain\tAudioContext::CreateGain\tdefinition\tdom/bindings/AudioContextBinding.cpp:80\n\
+             _ZBinding\tAudioContext::CreateGain\tdefinition\tdom/bindings/AudioContextBinding.cpp:80\n"
+        );
+    }
+}
@@ -1,8 +1,12 @@
 use crate::client::SearchfoxClient;
+use crate::error::RequestError;
 use crate::types::{File, SearchfoxResponse};
+use crate::utils::path_category;
 use anyhow::Result;
 use log::{debug, warn};
+use regex::Regex;
 use reqwest::Url;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 fn is_constructor_pattern(symbol: &str) -> bool {
     if let Some(colon_pos) = symbol.rfind("::") {
@@ -29,60 +33,109 @@ pub enum Lang {
     C,
     Js,
     WebIdl,
+    Idl,
+    Ipdl,
     Java,
     Kotlin,
     Rust,
     Python,
     Html,
     Css,
+    Toml,
+    Yaml,
 }
 
+/// Each language with its names, the first one canonical, and the file
+/// extensions it covers.
+const LANGUAGES: &[(Lang, &[&str], &[&str])] = &[
+    (
+        Lang::Cpp,
+        &["cpp", "c++", "cxx"],
+        &["cc", "cpp", "cxx", "h", "hh", "hpp", "mm"],
+    ),
+    (Lang::C, &["c"], &["c", "h"]),
+    (
+        Lang::Js,
+        &["js", "javascript", "typescript", "ts"],
+        &["js", "mjs", "cjs", "jsx", "ts", "tsx"],
+    ),
+    (Lang::WebIdl, &["webidl"], &["webidl"]),
+    (Lang::Idl, &["idl", "xpidl"], &["idl"]),
+    (Lang::Ipdl, &["ipdl"], &["ipdl", "ipdlh"]),
+    (Lang::Java, &["java"], &["java"]),
+    (Lang::Kotlin, &["kotlin", "kt"], &["kt", "kts"]),
+    (Lang::Rust, &["rust", "rs"], &["rs"]),
+    (Lang::Python, &["python", "py"], &["py"]),
+    (Lang::Html, &["html"], &["html", "xhtml", "htm"]),
+    (Lang::Css, &["css"], &["css"]),
+    (Lang::Toml, &["toml"], &["toml"]),
+    (Lang::Yaml, &["yaml", "yml"], &["yaml", "yml"]),
+];
+
 impl Lang {
+    fn entry(&self) -> &'static (Lang, &'static [&'static str], &'static [&'static str]) {
+        LANGUAGES
+            .iter()
+            .find(|(lang, _, _)| lang == self)
+            .expect("every language is in LANGUAGES")
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.entry().1[0]
+    }
+
+    pub fn extensions(&self) -> &'static [&'static str] {
+        self.entry().2
+    }
+
     pub fn matches(&self, path: &str) -> bool {
         let p = path.to_lowercase();
-        match self {
-            Lang::Cpp => {
-                p.ends_with(".cc")
-                    || p.ends_with(".cpp")
-                    || p.ends_with(".h")
-                    || p.ends_with(".hh")
-                    || p.ends_with(".hpp")
-            }
-            Lang::C => p.ends_with(".c") || p.ends_with(".h"),
-            Lang::Js => {
-                p.ends_with(".js")
-                    || p.ends_with(".mjs")
-                    || p.ends_with(".ts")
-                    || p.ends_with(".cjs")
-                    || p.ends_with(".jsx")
-                    || p.ends_with(".tsx")
-            }
-            Lang::WebIdl => p.ends_with(".webidl"),
-            Lang::Java | Lang::Kotlin => p.ends_with(".java") || p.ends_with(".kt"),
-            Lang::Rust => p.ends_with(".rs"),
-            Lang::Python => p.ends_with(".py"),
-            Lang::Html => p.ends_with(".html") || p.ends_with(".xhtml") || p.ends_with(".htm"),
-            Lang::Css => p.ends_with(".css"),
-        }
+        p.rsplit_once('.')
+            .is_some_and(|(_, ext)| self.extensions().contains(&ext))
     }
 
     pub fn parse(s: &str) -> Option<Self> {
-        // "c" is an alias for Cpp (same extensions in Mozilla's codebase).
-        // "kotlin"/"kt" are aliases for Java (same filter: .java and .kt files).
-        match s.to_lowercase().as_str() {
-            "cpp" | "c++" | "c" => Some(Lang::Cpp),
-            "js" | "javascript" | "typescript" | "ts" => Some(Lang::Js),
-            "webidl" => Some(Lang::WebIdl),
-            "java" | "kotlin" | "kt" => Some(Lang::Java),
-            "rust" | "rs" => Some(Lang::Rust),
-            "python" | "py" => Some(Lang::Python),
-            "html" => Some(Lang::Html),
-            "css" => Some(Lang::Css),
-            _ => None,
+        let s = s.trim().to_lowercase();
+        LANGUAGES
+            .iter()
+            .find(|(_, names, _)| names.contains(&s.as_str()))
+            .map(|(lang, _, _)| *lang)
+    }
+
+    /// A comma-separated list of languages, such as `rust,cpp,idl`.
+    pub fn parse_list(spec: &str) -> Result<Vec<Self>> {
+        let mut langs = Vec::new();
+        for name in spec.split(',').filter(|name| !name.trim().is_empty()) {
+            let Some(lang) = Self::parse(name) else {
+                anyhow::bail!(
+                    "Unknown language '{}', expected one of {}",
+                    name.trim(),
+                    Self::names().join(", ")
+                );
+            };
+            if !langs.contains(&lang) {
+                langs.push(lang);
+            }
         }
+        Ok(langs)
+    }
+
+    /// Canonical names of the known languages.
+    pub fn names() -> Vec<&'static str> {
+        LANGUAGES.iter().map(|(_, names, _)| names[0]).collect()
     }
 }
 
+/// A comma-separated list of file extensions, such as `.rs,.toml` or
+/// `sys.mjs`, as lowercase suffixes starting with a dot.
+pub fn parse_extensions(spec: &str) -> Vec<String> {
+    spec.split(',')
+        .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+        .filter(|ext| !ext.is_empty())
+        .map(|ext| format!(".{ext}"))
+        .collect()
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CategoryFilter {
     All,
@@ -114,14 +167,96 @@ impl CategoryFilter {
 pub struct SearchOptions {
     pub query: Option<String>,
     pub path: Option<String>,
+    /// Further path filters ORed with `path`: `search` runs once per path
+    /// and merges the results.
+    pub extra_paths: Vec<String>,
     pub case: bool,
     pub regexp: bool,
+    /// Send `query` exactly as given, without the `context:`/`text:`
+    /// prefixes `build_query` would add.
+    pub verbatim: bool,
     pub limit: usize,
+    /// Results to skip before the first one returned, to walk through a
+    /// large result set `limit` results at a time.
+    pub offset: usize,
     pub context: Option<usize>,
     pub symbol: Option<String>,
     pub id: Option<String>,
     pub lang: Vec<Lang>,
+    /// File suffixes such as `.rs` or `.sys.mjs`, lowercase. A path passes
+    /// the language filter when it has one of them or is in one of `lang`.
+    pub extensions: Vec<String>,
     pub category_filter: CategoryFilter,
+    /// Applied client-side to the text of each matched line (or to the path
+    /// for path-only searches) once results are back.
+    pub filter: Option<Regex>,
+    /// Order results by relevance (see [`rank_key`]) instead of the order
+    /// of the response.
+    pub rank: bool,
+    /// Sort the results, before `offset` and `limit` apply, instead of
+    /// keeping the order of the response.
+    pub sort: Option<SortOrder>,
+    /// List a line once per kind searchfox lists it under, instead of once
+    /// with the other kinds in `SearchResult::other_kinds`. Ranked results
+    /// are always deduplicated.
+    pub keep_duplicates: bool,
+    /// Take at most this many lines of each file, so that one noisy file
+    /// does not fill `limit`.
+    pub max_per_file: Option<usize>,
+}
+
+/// Orders `SearchOptions::sort` can put results in. Ties are broken by
+/// path, then line, so that the output is reproducible.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    /// By path, then line.
+    Path,
+    /// By line number, whatever the file.
+    Line,
+    /// Normal code, then tests, then generated code.
+    Category,
+    /// Files with the most results first.
+    Matches,
+}
+
+impl SortOrder {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "path" => Some(Self::Path),
+            "line" => Some(Self::Line),
+            "category" => Some(Self::Category),
+            "matches" => Some(Self::Matches),
+            _ => None,
+        }
+    }
+
+    pub fn names() -> &'static [&'static str] {
+        &["path", "line", "category", "matches"]
+    }
+}
+
+/// Sort `results` in `order`.
+pub fn sort_results(results: &mut [SearchResult], order: SortOrder) {
+    let mut matches: HashMap<String, usize> = HashMap::new();
+    if order == SortOrder::Matches {
+        for result in results.iter() {
+            *matches.entry(result.path.clone()).or_default() += 1;
+        }
+    }
+    results.sort_by(|a, b| {
+        let by_location = || (&a.path, a.line_number).cmp(&(&b.path, b.line_number));
+        match order {
+            SortOrder::Path => by_location(),
+            SortOrder::Line => a.line_number.cmp(&b.line_number).then_with(by_location),
+            SortOrder::Category => category_rank(&a.category)
+                .cmp(&category_rank(&b.category))
+                .then_with(|| a.category.cmp(&b.category))
+                .then_with(by_location),
+            SortOrder::Matches => matches[&b.path]
+                .cmp(&matches[&a.path])
+                .then_with(by_location),
+        }
+    });
 }
 
 impl Default for SearchOptions {
@@ -129,24 +264,39 @@ impl Default for SearchOptions {
         Self {
             query: None,
             path: None,
+            extra_paths: Vec::new(),
             case: false,
             regexp: false,
+            verbatim: false,
             limit: 50,
+            offset: 0,
             context: None,
             symbol: None,
             id: None,
             lang: Vec::new(),
+            extensions: Vec::new(),
             category_filter: CategoryFilter::All,
+            filter: None,
+            rank: false,
+            sort: None,
+            keep_duplicates: false,
+            max_per_file: None,
         }
     }
 }
 
 impl SearchOptions {
     pub fn matches_language_filter(&self, path: &str) -> bool {
-        if self.lang.is_empty() {
+        if self.lang.is_empty() && self.extensions.is_empty() {
             return true;
         }
+        let lowercase = path.to_lowercase();
         self.lang.iter().any(|lang| lang.matches(path))
+            || self.extensions.iter().any(|ext| lowercase.ends_with(ext))
+    }
+
+    pub fn matches_line_filter(&self, text: &str) -> bool {
+        self.filter.as_ref().is_none_or(|re| re.is_match(text))
     }
 
     pub fn build_query(&self) -> String {
@@ -155,6 +305,9 @@ impl SearchOptions {
         } else if let Some(id) = &self.id {
             format!("id:{id}")
         } else if let Some(q) = &self.query {
+            if self.verbatim {
+                return q.clone();
+            }
             let has_prefix = q.contains("path:")
                 || q.contains("pathre:")
                 || q.contains("symbol:")
@@ -176,149 +329,591 @@ impl SearchOptions {
     }
 }
 
+/// Sort key of a hit for `rank`: definitions, then declarations, then other
+/// indexed uses, then plain text matches; within each, files outside tests
+/// and generated code first, then shorter paths.
+pub fn rank_key(category: &str, label: Option<&str>, path: &str) -> (u8, bool, usize) {
+    let kind = match label {
+        Some(label) if label.starts_with("Definitions") => 0,
+        Some(label) if label.starts_with("Declarations") => 1,
+        Some(label) if !label.starts_with("Textual") => 2,
+        _ => 3,
+    };
+    let secondary = category != "normal" || path_category(path) != "normal";
+    (kind, secondary, path.len())
+}
+
+/// Sort `results` in [`rank_key`] order, then by path and line.
+fn rank_sort(results: &mut [SearchResult]) {
+    results.sort_by_cached_key(|r| {
+        (
+            rank_key(&r.category, r.kind.as_deref(), &r.path),
+            r.path.clone(),
+            r.line_number,
+        )
+    });
+}
+
+/// Every matched line of a response in [`rank_key`] order, each line once,
+/// at most `options.limit` of them.
+fn ranked_results(json: &SearchfoxResponse, options: &SearchOptions) -> Vec<SearchResult> {
+    let mut ranked = Vec::new();
+    for (category, value) in json {
+        if category.starts_with('*') || !options.category_filter.should_include(category) {
+            continue;
+        }
+        let lists: Vec<(Option<&str>, &serde_json::Value)> = match value.as_object() {
+            Some(obj) => obj.iter().map(|(l, v)| (Some(l.as_str()), v)).collect(),
+            None => vec![(None, value)],
+        };
+        for (label, file_list) in lists {
+            for file in file_list.as_array().into_iter().flatten() {
+                let Ok(file) = serde_json::from_value::<File>(file.clone()) else {
+                    continue;
+                };
+                if !options.matches_language_filter(&file.path) {
+                    continue;
+                }
+                for line in file.lines {
+                    if !options.matches_line_filter(&line.line) {
+                        continue;
+                    }
+                    ranked.push(SearchResult {
+                        path: file.path.clone(),
+                        line_number: line.lno,
+                        line: line.line.trim_end().to_string(),
+                        context_before: line.context_before.unwrap_or_default(),
+                        context_after: line.context_after.unwrap_or_default(),
+                        category: category.clone(),
+                        kind: label.map(String::from),
+                        bounds: match_bounds(line.bounds.as_deref()),
+                        other_kinds: Vec::new(),
+                    });
+                }
+            }
+        }
+    }
+    rank_sort(&mut ranked);
+
+    let ranked = dedup_results(ranked);
+    cap_per_file(ranked, options.max_per_file)
+        .into_iter()
+        .skip(options.offset)
+        .take(options.limit)
+        .collect()
+}
+
+/// The first `max` results of each file in `results`, all of them when
+/// `max` is None.
+fn cap_per_file(results: Vec<SearchResult>, max: Option<usize>) -> Vec<SearchResult> {
+    let Some(max) = max else {
+        return results;
+    };
+    let mut per_file: HashMap<String, usize> = HashMap::new();
+    results
+        .into_iter()
+        .filter(|result| {
+            let taken = per_file.entry(result.path.clone()).or_default();
+            *taken += 1;
+            *taken <= max
+        })
+        .collect()
+}
+
+/// `results` with each line once, at its first occurrence, which gets the
+/// kinds of the later ones in `other_kinds`.
+pub fn dedup_results(results: Vec<SearchResult>) -> Vec<SearchResult> {
+    let mut positions: HashMap<(String, usize), usize> = HashMap::new();
+    let mut unique: Vec<SearchResult> = Vec::with_capacity(results.len());
+    for result in results {
+        let key = (result.path.clone(), result.line_number);
+        let Some(&position) = positions.get(&key) else {
+            positions.insert(key, unique.len());
+            unique.push(result);
+            continue;
+        };
+        let first = &mut unique[position];
+        for kind in result.kind.into_iter().chain(result.other_kinds) {
+            if first.kind.as_ref() != Some(&kind) && !first.other_kinds.contains(&kind) {
+                first.other_kinds.push(kind);
+            }
+        }
+    }
+    unique
+}
+
+#[derive(Debug, Clone)]
 pub struct SearchResult {
     pub path: String,
     pub line_number: usize,
     pub line: String,
     pub context_before: Vec<String>,
     pub context_after: Vec<String>,
+    /// Category of the file in searchfox's response: normal, test or
+    /// generated.
+    pub category: String,
+    /// Kind of match of structured results, e.g. `Definitions (AudioContext)`,
+    /// None for text matches.
+    pub kind: Option<String>,
+    /// Byte ranges of the match within `line`, from searchfox's `bounds`;
+    /// empty when unknown.
+    pub bounds: Vec<(usize, usize)>,
+    /// Other kinds the line is listed under, when searchfox lists it more
+    /// than once, e.g. as a definition and as a use; see
+    /// `SearchOptions::keep_duplicates`.
+    pub other_kinds: Vec<String>,
 }
 
-impl SearchfoxClient {
-    pub async fn search(&self, options: &SearchOptions) -> Result<Vec<SearchResult>> {
-        let query = options.build_query();
-
-        let mut url = Url::parse(&format!("https://searchfox.org/{}/search", self.repo))?;
-        url.query_pairs_mut()
-            .append_pair("q", &query)
-            .append_pair("case", if options.case { "true" } else { "false" })
-            .append_pair("regexp", if options.regexp { "true" } else { "false" });
-        if let Some(path) = &options.path {
-            url.query_pairs_mut().append_pair("path", path);
+/// How to sum results up instead of listing them, as grep's -c and -l do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultSummary {
+    /// The number of results.
+    Count,
+    /// The files with results.
+    FilesWithMatches,
+}
+
+/// Paths of the files `results` are in, once each, in the order they first
+/// appear.
+pub fn files_with_matches(results: &[SearchResult]) -> Vec<&str> {
+    let mut paths: Vec<&str> = Vec::new();
+    for result in results {
+        if !paths.contains(&result.path.as_str()) {
+            paths.push(&result.path);
         }
+    }
+    paths
+}
 
-        let response = self.get(url).await?;
+/// The ranges of a `bounds` array, which lists start and end offsets in
+/// pairs.
+fn match_bounds(bounds: Option<&[usize]>) -> Vec<(usize, usize)> {
+    bounds
+        .unwrap_or_default()
+        .chunks_exact(2)
+        .map(|pair| (pair[0], pair[1]))
+        .filter(|(start, end)| start < end)
+        .collect()
+}
 
-        if !response.status().is_success() {
-            anyhow::bail!("Request failed: {}", response.status());
-        }
+/// Results of several searches in order, without the lines found by an
+/// earlier one, windowed by `offset` and `limit`.
+fn merge_results(
+    batches: Vec<Vec<SearchResult>>,
+    offset: usize,
+    limit: usize,
+) -> Vec<SearchResult> {
+    let mut seen = HashSet::new();
+    batches
+        .into_iter()
+        .flatten()
+        .filter(|r| seen.insert((r.path.clone(), r.line_number)))
+        .skip(offset)
+        .take(limit)
+        .collect()
+}
 
-        let response_text = response.text().await?;
-        let json: SearchfoxResponse = serde_json::from_str(&response_text)?;
+/// Position of a category in normal, test, generated, then any other.
+fn category_rank(category: &str) -> usize {
+    ["normal", "test", "generated"]
+        .iter()
+        .position(|c| *c == category)
+        .unwrap_or(3)
+}
 
-        let mut results = Vec::new();
-        let mut count = 0;
+/// The categories of a response in a fixed order, normal, test, generated
+/// and then any other, so that windows of results are stable across runs.
+pub(crate) fn categories(json: &SearchfoxResponse) -> Vec<(&String, &serde_json::Value)> {
+    let mut categories: Vec<_> = json
+        .iter()
+        .filter(|(key, _)| !key.starts_with('*'))
+        .collect();
+    categories.sort_by(|(a, _), (b, _)| (category_rank(a), a).cmp(&(category_rank(b), b)));
+    categories
+}
 
-        for (key, value) in &json {
-            if key.starts_with('*') {
-                continue;
+/// The `*`-prefixed keys of a search response, which describe the search
+/// rather than its results.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchMetadata {
+    pub title: Option<String>,
+    /// Searchfox stopped before searching everything.
+    pub timed_out: bool,
+    /// Other metadata keys, stars included.
+    pub other: BTreeMap<String, serde_json::Value>,
+}
+
+impl SearchMetadata {
+    pub fn from_response(json: &SearchfoxResponse) -> Self {
+        let mut metadata = Self::default();
+        for (key, value) in json.iter().filter(|(key, _)| key.starts_with('*')) {
+            match key.as_str() {
+                "*title*" => metadata.title = value.as_str().map(String::from),
+                "*timedout*" => metadata.timed_out = value.as_bool().unwrap_or(false),
+                _ => {
+                    metadata.other.insert(key.clone(), value.clone());
+                }
             }
+        }
+        metadata
+    }
 
-            if !options.category_filter.should_include(key) {
-                continue;
+    /// Whether results may be missing: the search timed out, or searchfox
+    /// set another flag, such as a result limit being hit.
+    pub fn is_partial(&self) -> bool {
+        self.timed_out
+            || self
+                .other
+                .values()
+                .any(|value| value.as_bool() == Some(true))
+    }
+
+    /// Combine the metadata of searches whose results were merged.
+    fn merge(&mut self, other: SearchMetadata) {
+        self.title = self.title.take().or(other.title);
+        self.timed_out |= other.timed_out;
+        for (key, value) in other.other {
+            if value.as_bool() != Some(false) || !self.other.contains_key(&key) {
+                self.other.insert(key, value);
             }
+        }
+    }
+}
 
-            if let Some(files_array) = value.as_array() {
-                for file in files_array {
-                    let file: File = match serde_json::from_value(file.clone()) {
-                        Ok(f) => f,
-                        Err(e) => {
-                            warn!("Failed to parse file JSON: {e}");
-                            continue;
-                        }
-                    };
+/// URL of the search for `options`. Parameters are percent-encoded, so the
+/// query reaches searchfox unchanged whatever characters it contains.
+fn search_url(base_url: &str, repo: &str, options: &SearchOptions) -> Result<Url> {
+    let mut url = Url::parse(&format!("{}/{}/search", base_url, repo))?;
+    url.query_pairs_mut()
+        .append_pair("q", &options.build_query())
+        .append_pair("case", if options.case { "true" } else { "false" })
+        .append_pair("regexp", if options.regexp { "true" } else { "false" });
+    if let Some(path) = &options.path {
+        url.query_pairs_mut().append_pair("path", path);
+    }
+    Ok(url)
+}
+
+/// The results of a search response, filtered and windowed by `options`.
+fn response_results(json: &SearchfoxResponse, options: &SearchOptions) -> Vec<SearchResult> {
+    // Deduplicating and sorting change which results are in the window, so
+    // they need every result first.
+    if options.sort.is_some() || !options.keep_duplicates {
+        let everything = SearchOptions {
+            offset: 0,
+            limit: usize::MAX,
+            sort: None,
+            keep_duplicates: true,
+            max_per_file: None,
+            ..options.clone()
+        };
+        let mut results = response_results(json, &everything);
+        if !options.keep_duplicates {
+            results = dedup_results(results);
+        }
+        if let Some(order) = options.sort {
+            sort_results(&mut results, order);
+        }
+        return cap_per_file(results, options.max_per_file)
+            .into_iter()
+            .skip(options.offset)
+            .take(options.limit)
+            .collect();
+    }
+
+    // Results are counted up to the end of the window, then the offset is
+    // skipped.
+    let end = options.offset.saturating_add(options.limit);
+
+    let path_only = options.query.is_none() && options.symbol.is_none() && options.id.is_none();
+    if options.rank && !path_only {
+        return ranked_results(json, options);
+    }
 
-                    if !options.matches_language_filter(&file.path) {
+    let mut results = Vec::new();
+    let mut count = 0;
+    let mut per_file: HashMap<String, usize> = HashMap::new();
+    // Whether a matched line of `path` is one more than --max-per-file.
+    let mut over_cap = |path: &str| {
+        let taken = per_file.entry(path.to_string()).or_default();
+        *taken += 1;
+        options.max_per_file.is_some_and(|max| *taken > max)
+    };
+
+    for (key, value) in categories(json) {
+        if !options.category_filter.should_include(key) {
+            continue;
+        }
+
+        if let Some(files_array) = value.as_array() {
+            for file in files_array {
+                let file: File = match serde_json::from_value(file.clone()) {
+                    Ok(f) => f,
+                    Err(e) => {
+                        warn!("Failed to parse file JSON: {e}");
                         continue;
                     }
+                };
 
-                    if options.path.is_some()
-                        && options.query.is_none()
-                        && options.symbol.is_none()
-                        && options.id.is_none()
-                    {
-                        if count >= options.limit {
+                if !options.matches_language_filter(&file.path) {
+                    continue;
+                }
+
+                if options.path.is_some()
+                    && options.query.is_none()
+                    && options.symbol.is_none()
+                    && options.id.is_none()
+                {
+                    if count >= end {
+                        break;
+                    }
+                    if !options.matches_line_filter(&file.path) {
+                        continue;
+                    }
+                    results.push(SearchResult {
+                        path: file.path.clone(),
+                        line_number: 0,
+                        line: String::new(),
+                        context_before: vec![],
+                        context_after: vec![],
+                        category: key.clone(),
+                        kind: None,
+                        bounds: Vec::new(),
+                        other_kinds: Vec::new(),
+                    });
+                    count += 1;
+                } else {
+                    for line in file.lines {
+                        if count >= end {
+                            break;
+                        }
+                        if !options.matches_line_filter(&line.line) {
+                            continue;
+                        }
+                        if over_cap(&file.path) {
                             break;
                         }
                         results.push(SearchResult {
                             path: file.path.clone(),
-                            line_number: 0,
-                            line: String::new(),
-                            context_before: vec![],
-                            context_after: vec![],
+                            line_number: line.lno,
+                            line: line.line.trim_end().to_string(),
+                            context_before: line.context_before.unwrap_or_default(),
+                            context_after: line.context_after.unwrap_or_default(),
+                            category: key.clone(),
+                            kind: None,
+                            bounds: match_bounds(line.bounds.as_deref()),
+                            other_kinds: Vec::new(),
                         });
                         count += 1;
-                    } else {
-                        for line in file.lines {
-                            if count >= options.limit {
+                    }
+                }
+            }
+        } else if let Some(obj) = value.as_object() {
+            for (kind, file_list) in obj {
+                if let Some(files) = file_list.as_array() {
+                    for file in files {
+                        let file: File = match serde_json::from_value(file.clone()) {
+                            Ok(f) => f,
+                            Err(_) => continue,
+                        };
+
+                        if !options.matches_language_filter(&file.path) {
+                            continue;
+                        }
+
+                        if options.path.is_some()
+                            && options.query.is_none()
+                            && options.symbol.is_none()
+                            && options.id.is_none()
+                        {
+                            if count >= end {
                                 break;
                             }
+                            if !options.matches_line_filter(&file.path) {
+                                continue;
+                            }
                             results.push(SearchResult {
                                 path: file.path.clone(),
-                                line_number: line.lno,
-                                line: line.line.trim_end().to_string(),
-                                context_before: line.context_before.unwrap_or_default(),
-                                context_after: line.context_after.unwrap_or_default(),
+                                line_number: 0,
+                                line: String::new(),
+                                context_before: vec![],
+                                context_after: vec![],
+                                category: key.clone(),
+                                kind: Some(kind.clone()),
+                                bounds: Vec::new(),
+                                other_kinds: Vec::new(),
                             });
                             count += 1;
-                        }
-                    }
-                }
-            } else if let Some(obj) = value.as_object() {
-                for (_category, file_list) in obj {
-                    if let Some(files) = file_list.as_array() {
-                        for file in files {
-                            let file: File = match serde_json::from_value(file.clone()) {
-                                Ok(f) => f,
-                                Err(_) => continue,
-                            };
-
-                            if !options.matches_language_filter(&file.path) {
-                                continue;
-                            }
-
-                            if options.path.is_some()
-                                && options.query.is_none()
-                                && options.symbol.is_none()
-                                && options.id.is_none()
-                            {
-                                if count >= options.limit {
+                        } else {
+                            for line in file.lines {
+                                if count >= end {
+                                    break;
+                                }
+                                if !options.matches_line_filter(&line.line) {
+                                    continue;
+                                }
+                                if over_cap(&file.path) {
                                     break;
                                 }
                                 results.push(SearchResult {
                                     path: file.path.clone(),
-                                    line_number: 0,
-                                    line: String::new(),
-                                    context_before: vec![],
-                                    context_after: vec![],
+                                    line_number: line.lno,
+                                    line: line.line.trim_end().to_string(),
+                                    context_before: line.context_before.unwrap_or_default(),
+                                    context_after: line.context_after.unwrap_or_default(),
+                                    category: key.clone(),
+                                    kind: Some(kind.clone()),
+                                    bounds: match_bounds(line.bounds.as_deref()),
+                                    other_kinds: Vec::new(),
                                 });
                                 count += 1;
-                            } else {
-                                for line in file.lines {
-                                    if count >= options.limit {
-                                        break;
-                                    }
-                                    results.push(SearchResult {
-                                        path: file.path.clone(),
-                                        line_number: line.lno,
-                                        line: line.line.trim_end().to_string(),
-                                        context_before: line.context_before.unwrap_or_default(),
-                                        context_after: line.context_after.unwrap_or_default(),
-                                    });
-                                    count += 1;
-                                }
                             }
                         }
                     }
                 }
             }
+        }
 
-            if count >= options.limit {
-                break;
+        if count >= end {
+            break;
+        }
+    }
+
+    results.drain(..options.offset.min(results.len()));
+    results
+}
+
+impl SearchfoxClient {
+    pub async fn search(&self, options: &SearchOptions) -> Result<Vec<SearchResult>> {
+        Ok(self.search_with_metadata(options).await?.0)
+    }
+
+    /// `search`, along with the metadata of the response, which tells
+    /// whether the results are partial.
+    pub async fn search_with_metadata(
+        &self,
+        options: &SearchOptions,
+    ) -> Result<(Vec<SearchResult>, SearchMetadata)> {
+        if !options.extra_paths.is_empty() {
+            let searches: Vec<SearchOptions> = options
+                .path
+                .iter()
+                .chain(&options.extra_paths)
+                .map(|path| SearchOptions {
+                    path: Some(path.clone()),
+                    extra_paths: Vec::new(),
+                    limit: options.offset.saturating_add(options.limit),
+                    offset: 0,
+                    ..options.clone()
+                })
+                .collect();
+            let searched = futures::future::try_join_all(
+                searches
+                    .iter()
+                    .map(|s| Box::pin(self.search_with_metadata(s))),
+            )
+            .await?;
+            let mut metadata = SearchMetadata::default();
+            let mut batches = Vec::new();
+            for (results, other) in searched {
+                metadata.merge(other);
+                batches.push(results);
             }
+            // Each search returned its first results in the same order, so
+            // the first ones overall are among them.
+            let results = if options.sort.is_some() || options.rank {
+                let mut merged = merge_results(batches, 0, usize::MAX);
+                match options.sort {
+                    Some(order) => sort_results(&mut merged, order),
+                    None => rank_sort(&mut merged),
+                }
+                merged
+                    .into_iter()
+                    .skip(options.offset)
+                    .take(options.limit)
+                    .collect()
+            } else {
+                merge_results(batches, options.offset, options.limit)
+            };
+            return Ok((results, metadata));
+        }
+
+        let json = self.search_response(options).await?;
+        Ok((
+            response_results(&json, options),
+            SearchMetadata::from_response(&json),
+        ))
+    }
+
+    /// Fetch the raw search response for `options`, before any filtering.
+    pub(crate) async fn search_response(
+        &self,
+        options: &SearchOptions,
+    ) -> Result<SearchfoxResponse> {
+        let response = self
+            .get(search_url(&self.base_url, &self.repo, options)?)
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(RequestError::new(response.url().as_str(), response.status()).into());
         }
 
-        Ok(results)
+        let response_text = response.text().await?;
+        Ok(serde_json::from_str(&response_text)?)
+    }
+
+    /// List every file under `dir`, honoring the language and category
+    /// filters of `options`. The result limit is ignored.
+    pub async fn list_directory_files(
+        &self,
+        dir: &str,
+        options: &SearchOptions,
+    ) -> Result<Vec<String>> {
+        let prefix = format!("{}/", dir.trim_end_matches('/'));
+        let listing = SearchOptions {
+            query: None,
+            path: Some(prefix.clone()),
+            extra_paths: Vec::new(),
+            regexp: false,
+            limit: usize::MAX,
+            offset: 0,
+            context: None,
+            symbol: None,
+            id: None,
+            filter: None,
+            ..options.clone()
+        };
+
+        let mut files: Vec<String> = self
+            .search(&listing)
+            .await?
+            .into_iter()
+            .map(|r| r.path)
+            .filter(|path| path.starts_with(&prefix))
+            .collect();
+        files.sort();
+        files.dedup();
+        Ok(files)
+    }
+
+    /// The `id:` search response for `symbol`, as used to find its
+    /// definitions: constructors are looked up by their class.
+    pub(crate) async fn definition_response(
+        &self,
+        symbol: &str,
+        path_filter: Option<&str>,
+    ) -> Result<SearchfoxResponse> {
+        let search_symbol = if is_constructor_pattern(symbol) {
+            extract_class_name_from_constructor(symbol)
+        } else {
+            symbol.to_string()
+        };
+        let options = SearchOptions {
+            id: Some(search_symbol),
+            path: path_filter.map(String::from),
+            ..SearchOptions::default()
+        };
+        self.search_response(&options).await
     }
 
     pub async fn find_symbol_locations(
@@ -334,7 +929,7 @@ impl SearchfoxClient {
             symbol.to_string()
         };
         let query = format!("id:{search_symbol}");
-        let mut url = Url::parse(&format!("https://searchfox.org/{}/search", self.repo))?;
+        let mut url = Url::parse(&format!("{}/{}/search", self.base_url, self.repo))?;
         url.query_pairs_mut().append_pair("q", &query);
         if let Some(path) = path_filter {
             url.query_pairs_mut().append_pair("path", path);
@@ -343,7 +938,7 @@ impl SearchfoxClient {
         let response = self.get(url).await?;
 
         if !response.status().is_success() {
-            anyhow::bail!("Request failed: {}", response.status());
+            return Err(RequestError::new(response.url().as_str(), response.status()).into());
         }
 
         let response_text = response.text().await?;
@@ -549,3 +1144,527 @@ impl SearchfoxClient {
         Ok(file_locations)
     }
 }
+
+/// Every line of the `Definitions (...)` results of `symbol` in an `id:`
+/// response, as path, line and trimmed text, in a stable order.
+pub(crate) fn definition_candidates(
+    json: &SearchfoxResponse,
+    symbol: &str,
+    options: &SearchOptions,
+) -> Vec<(String, usize, String)> {
+    kind_candidates(json, "Definitions", symbol, options)
+}
+
+/// Same as `definition_candidates`, for the `Declarations (...)` results.
+pub(crate) fn declaration_candidates(
+    json: &SearchfoxResponse,
+    symbol: &str,
+    options: &SearchOptions,
+) -> Vec<(String, usize, String)> {
+    kind_candidates(json, "Declarations", symbol, options)
+}
+
+fn kind_candidates(
+    json: &SearchfoxResponse,
+    kind_name: &str,
+    symbol: &str,
+    options: &SearchOptions,
+) -> Vec<(String, usize, String)> {
+    let prefix = format!("{kind_name} (");
+    let qualified = format!("::{symbol}");
+    let mut seen = HashSet::new();
+    let mut candidates = Vec::new();
+    for (category, value) in categories(json) {
+        if !options.category_filter.should_include(category) {
+            continue;
+        }
+        let Some(kinds) = value.as_object() else {
+            continue;
+        };
+        for (kind, files) in kinds {
+            let Some(name) = kind
+                .strip_prefix(prefix.as_str())
+                .and_then(|k| k.strip_suffix(')'))
+            else {
+                continue;
+            };
+            if name != symbol && !name.ends_with(&qualified) {
+                continue;
+            }
+            for file in files.as_array().into_iter().flatten() {
+                let Ok(file) = serde_json::from_value::<File>(file.clone()) else {
+                    continue;
+                };
+                if !options.matches_language_filter(&file.path) {
+                    continue;
+                }
+                for line in file.lines {
+                    if seen.insert((file.path.clone(), line.lno)) {
+                        candidates.push((
+                            file.path.clone(),
+                            line.lno,
+                            line.line.trim().to_string(),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::DEFAULT_SERVER;
+
+    #[test]
+    fn finds_every_definition_candidate() {
+        let json: SearchfoxResponse = serde_json::from_str(
+            r#"{
+                "*title*": "id:AudioNode::Connect",
+                "normal": {
+                    "Definitions (mozilla::dom::AudioNode::Connect)": [
+                        {"path": "dom/media/webaudio/AudioNode.cpp", "lines": [
+                            {"lno": 220, "line": "AudioNode* AudioNode::Connect(AudioNode& aDestination,"},
+                            {"lno": 330, "line": "void AudioNode::Connect(AudioParam& aDestination,"}
+                        ]}
+                    ],
+                    "Declarations (mozilla::dom::AudioNode::Connect)": [
+                        {"path": "dom/media/webaudio/AudioNode.h", "lines": [{"lno": 90, "line": "AudioNode* Connect("}]}
+                    ],
+                    "Definitions (mozilla::dom::OtherAudioNode::Connect)": [
+                        {"path": "dom/media/webaudio/Other.cpp", "lines": [{"lno": 5, "line": "void OtherAudioNode::Connect() {"}]}
+                    ]
+                },
+                "test": {
+                    "Definitions (mozilla::dom::AudioNode::Connect)": [
+                        {"path": "dom/media/webaudio/test/Fake.cpp", "lines": [{"lno": 7, "line": "  void AudioNode::Connect() {}"}]}
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+        let options = SearchOptions::default();
+        let candidates = definition_candidates(&json, "AudioNode::Connect", &options);
+        let locations: Vec<_> = candidates
+            .iter()
+            .map(|(p, l, _)| (p.as_str(), *l))
+            .collect();
+        assert_eq!(
+            locations,
+            vec![
+                ("dom/media/webaudio/AudioNode.cpp", 220),
+                ("dom/media/webaudio/AudioNode.cpp", 330),
+                ("dom/media/webaudio/test/Fake.cpp", 7),
+            ]
+        );
+        assert_eq!(candidates[2].2, "void AudioNode::Connect() {}");
+
+        let options = SearchOptions {
+            category_filter: CategoryFilter::ExcludeTests,
+            ..Default::default()
+        };
+        assert_eq!(
+            definition_candidates(&json, "AudioNode::Connect", &options).len(),
+            2
+        );
+        assert_eq!(
+            declaration_candidates(&json, "AudioNode::Connect", &options),
+            vec![(
+                "dom/media/webaudio/AudioNode.h".to_string(),
+                90,
+                "AudioNode* Connect(".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn parses_language_lists() {
+        assert_eq!(
+            Lang::parse_list("rust, cpp,idl,RS").unwrap(),
+            vec![Lang::Rust, Lang::Cpp, Lang::Idl]
+        );
+        assert!(Lang::parse_list("cobol")
+            .unwrap_err()
+            .to_string()
+            .contains("expected one of cpp, c, js"));
+        assert!(Lang::Ipdl.matches("dom/ipc/PContent.ipdl"));
+        assert!(Lang::Js.matches("toolkit/modules/Foo.sys.mjs"));
+        assert!(!Lang::Idl.matches("dom/webidl/AudioContext.webidl"));
+        assert!(!Lang::Toml.matches("toml"));
+        assert_eq!(Lang::Kotlin.name(), "kotlin");
+    }
+
+    #[test]
+    fn merges_results_of_several_paths() {
+        let result = |path: &str, line_number| SearchResult {
+            path: path.into(),
+            line_number,
+            line: String::new(),
+            context_before: vec![],
+            context_after: vec![],
+            category: "normal".into(),
+            kind: None,
+            bounds: Vec::new(),
+            other_kinds: Vec::new(),
+        };
+        let merged = merge_results(
+            vec![
+                vec![result("dom/media/A.cpp", 1), result("dom/media/A.cpp", 2)],
+                vec![
+                    result("dom/media/A.cpp", 2),
+                    result("media/libcubeb/B.c", 3),
+                ],
+            ],
+            0,
+            10,
+        );
+        let lines: Vec<_> = merged
+            .iter()
+            .map(|r| (r.path.as_str(), r.line_number))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                ("dom/media/A.cpp", 1),
+                ("dom/media/A.cpp", 2),
+                ("media/libcubeb/B.c", 3)
+            ]
+        );
+        let second = merge_results(vec![vec![result("a", 1)], vec![result("b", 1)]], 1, 1);
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].path, "b");
+
+        // Ranked batches are ranked again once merged.
+        let definition = SearchResult {
+            kind: Some("Definitions (B)".into()),
+            ..result("media/libcubeb/B.c", 3)
+        };
+        let mut merged = merge_results(
+            vec![vec![result("dom/media/A.cpp", 1)], vec![definition]],
+            0,
+            usize::MAX,
+        );
+        rank_sort(&mut merged);
+        assert_eq!(merged[0].path, "media/libcubeb/B.c");
+    }
+
+    #[test]
+    fn verbatim_queries_are_sent_unchanged() {
+        let query = "/* text: ctx:3 «AudioStream» & 100% #x+y";
+        let options = SearchOptions {
+            query: Some(query.into()),
+            context: Some(2),
+            verbatim: true,
+            ..Default::default()
+        };
+        let url = search_url(DEFAULT_SERVER, "mozilla-central", &options).unwrap();
+        let sent = url.query_pairs().find(|(k, _)| k == "q").unwrap().1;
+        assert_eq!(sent, query);
+        assert!(!url.as_str().contains(' ') && !url.as_str().contains('«'));
+
+        let url = search_url("https://searchfox.example.com", "servo", &options).unwrap();
+        assert!(url
+            .as_str()
+            .starts_with("https://searchfox.example.com/servo/search?q="));
+
+        let rewritten = SearchOptions {
+            verbatim: false,
+            ..options
+        };
+        assert_eq!(rewritten.build_query(), format!("context:2 {query}"));
+    }
+
+    #[test]
+    fn reads_metadata_keys() {
+        let json: SearchfoxResponse = serde_json::from_str(
+            r#"{"*title*": "AudioStream", "*timedout*": true, "*limited*": false, "normal": []}"#,
+        )
+        .unwrap();
+        let metadata = SearchMetadata::from_response(&json);
+        assert_eq!(metadata.title.as_deref(), Some("AudioStream"));
+        assert!(metadata.timed_out && metadata.is_partial());
+        assert_eq!(metadata.other.len(), 1);
+
+        let mut complete = SearchMetadata::default();
+        complete
+            .other
+            .insert("*limited*".into(), serde_json::json!(false));
+        assert!(!complete.is_partial());
+        complete.merge(metadata);
+        assert!(complete.timed_out);
+        let mut limited = SearchMetadata::default();
+        limited
+            .other
+            .insert("*limited*".into(), serde_json::json!(true));
+        assert!(limited.is_partial());
+    }
+
+    #[test]
+    fn categories_come_in_a_stable_order() {
+        let json: SearchfoxResponse = serde_json::from_str(
+            r#"{"*title*": "x", "test": [], "generated": [], "normal": [], "thirdparty": []}"#,
+        )
+        .unwrap();
+        let order: Vec<&str> = categories(&json).iter().map(|(c, _)| c.as_str()).collect();
+        assert_eq!(order, vec!["normal", "test", "generated", "thirdparty"]);
+    }
+
+    #[test]
+    fn filters_by_extension() {
+        let options = SearchOptions {
+            lang: vec![Lang::Rust],
+            extensions: parse_extensions(".toml, sys.mjs,"),
+            ..Default::default()
+        };
+        assert_eq!(options.extensions, vec![".toml", ".sys.mjs"]);
+        assert!(options.matches_language_filter("Cargo.toml"));
+        assert!(options.matches_language_filter("toolkit/modules/Foo.SYS.MJS"));
+        assert!(options.matches_language_filter("servo/src/lib.rs"));
+        assert!(!options.matches_language_filter("toolkit/modules/Foo.mjs"));
+        assert!(!options.matches_language_filter("dom/base/nsINode.cpp"));
+    }
+
+    #[test]
+    fn ranks_definitions_first_and_tests_last() {
+        let json: SearchfoxResponse = serde_json::from_str(
+            r#"{
+                "*title*": "AudioSink",
+                "test": {
+                    "Definitions (FakeAudioSink)": [
+                        {"path": "dom/media/gtest/TestAudioSink.cpp", "lines": [{"lno": 3, "line": "class FakeAudioSink"}]}
+                    ]
+                },
+                "normal": {
+                    "Uses (mozilla::AudioSink)": [
+                        {"path": "dom/media/mediasink/AudioSinkWrapper.cpp", "lines": [{"lno": 20, "line": "AudioSink* s;"}]},
+                        {"path": "dom/media/Foo.cpp", "lines": [{"lno": 9, "line": "AudioSink* t;"}]}
+                    ],
+                    "Textual Occurrences": [
+                        {"path": "dom/media/A.cpp", "lines": [{"lno": 1, "line": "// AudioSink"}]},
+                        {"path": "dom/media/Foo.cpp", "lines": [{"lno": 9, "line": "AudioSink* t;"}]}
+                    ],
+                    "Definitions (mozilla::AudioSink)": [
+                        {"path": "dom/media/mediasink/AudioSink.h", "lines": [{"lno": 30, "line": "class AudioSink {"}]}
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+        let options = SearchOptions {
+            rank: true,
+            ..Default::default()
+        };
+        let order: Vec<(String, usize)> = ranked_results(&json, &options)
+            .into_iter()
+            .map(|r| (r.path, r.line_number))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                ("dom/media/mediasink/AudioSink.h".to_string(), 30),
+                ("dom/media/gtest/TestAudioSink.cpp".to_string(), 3),
+                ("dom/media/Foo.cpp".to_string(), 9),
+                ("dom/media/mediasink/AudioSinkWrapper.cpp".to_string(), 20),
+                ("dom/media/A.cpp".to_string(), 1),
+            ]
+        );
+
+        let limited = SearchOptions {
+            limit: 2,
+            ..options
+        };
+        assert_eq!(ranked_results(&json, &limited).len(), 2);
+    }
+
+    #[test]
+    fn lists_each_file_once() {
+        let result = |path: &str, line_number| SearchResult {
+            path: path.into(),
+            line_number,
+            line: String::new(),
+            context_before: vec![],
+            context_after: vec![],
+            category: "normal".into(),
+            kind: None,
+            bounds: Vec::new(),
+            other_kinds: Vec::new(),
+        };
+        let results = [result("b.cpp", 3), result("a.cpp", 1), result("b.cpp", 9)];
+        assert_eq!(files_with_matches(&results), ["b.cpp", "a.cpp"]);
+    }
+
+    #[test]
+    fn sorts_results_reproducibly() {
+        let result = |path: &str, line_number, category: &str| SearchResult {
+            path: path.into(),
+            line_number,
+            line: String::new(),
+            context_before: vec![],
+            context_after: vec![],
+            category: category.into(),
+            kind: None,
+            bounds: Vec::new(),
+            other_kinds: Vec::new(),
+        };
+        let results = vec![
+            result("dom/test/a.cpp", 1, "test"),
+            result("gen/b.cpp", 7, "generated"),
+            result("dom/c.cpp", 30, "normal"),
+            result("dom/c.cpp", 2, "normal"),
+            result("dom/a.cpp", 5, "normal"),
+        ];
+        let order = |order| {
+            let mut sorted = results.clone();
+            sort_results(&mut sorted, order);
+            sorted
+                .iter()
+                .map(|r| format!("{}:{}", r.path, r.line_number))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            order(SortOrder::Path),
+            [
+                "dom/a.cpp:5",
+                "dom/c.cpp:2",
+                "dom/c.cpp:30",
+                "dom/test/a.cpp:1",
+                "gen/b.cpp:7"
+            ]
+        );
+        assert_eq!(
+            order(SortOrder::Line),
+            [
+                "dom/test/a.cpp:1",
+                "dom/c.cpp:2",
+                "dom/a.cpp:5",
+                "gen/b.cpp:7",
+                "dom/c.cpp:30"
+            ]
+        );
+        assert_eq!(
+            order(SortOrder::Category),
+            [
+                "dom/a.cpp:5",
+                "dom/c.cpp:2",
+                "dom/c.cpp:30",
+                "dom/test/a.cpp:1",
+                "gen/b.cpp:7"
+            ]
+        );
+        assert_eq!(
+            order(SortOrder::Matches),
+            [
+                "dom/c.cpp:2",
+                "dom/c.cpp:30",
+                "dom/a.cpp:5",
+                "dom/test/a.cpp:1",
+                "gen/b.cpp:7"
+            ]
+        );
+    }
+
+    #[test]
+    fn sorts_before_windowing() {
+        let json: SearchfoxResponse = serde_json::from_str(
+            r#"{
+                "normal": [
+                    {"path": "z.cpp", "lines": [{"lno": 1, "line": "a"}]},
+                    {"path": "b.cpp", "lines": [{"lno": 4, "line": "a"}, {"lno": 2, "line": "a"}]}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let options = SearchOptions {
+            query: Some("a".into()),
+            sort: Some(SortOrder::Path),
+            offset: 1,
+            limit: 1,
+            ..Default::default()
+        };
+        let results = response_results(&json, &options);
+        assert_eq!(
+            (results[0].path.as_str(), results[0].line_number),
+            ("b.cpp", 4)
+        );
+    }
+
+    #[test]
+    fn merges_lines_listed_under_several_kinds() {
+        let json: SearchfoxResponse = serde_json::from_str(
+            r#"{
+                "normal": {
+                    "Definitions (Foo)": [
+                        {"path": "a.cpp", "lines": [{"lno": 3, "line": "void Foo() {}"}]}
+                    ],
+                    "Uses (Foo)": [
+                        {"path": "a.cpp", "lines": [{"lno": 3, "line": "void Foo() {}"}, {"lno": 9, "line": "Foo();"}]}
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+        let options = SearchOptions {
+            id: Some("Foo".into()),
+            limit: 2,
+            ..Default::default()
+        };
+        let results = response_results(&json, &options);
+        let lines: Vec<usize> = results.iter().map(|r| r.line_number).collect();
+        assert_eq!(lines, [3, 9]);
+        assert_eq!(results[0].kind.as_deref(), Some("Definitions (Foo)"));
+        assert_eq!(results[0].other_kinds, ["Uses (Foo)"]);
+
+        let kept = SearchOptions {
+            keep_duplicates: true,
+            ..options
+        };
+        let lines: Vec<usize> = response_results(&json, &kept)
+            .iter()
+            .map(|r| r.line_number)
+            .collect();
+        assert_eq!(lines, [3, 3]);
+    }
+
+    #[test]
+    fn caps_lines_per_file_before_the_limit() {
+        let json: SearchfoxResponse = serde_json::from_str(
+            r#"{
+                "generated": [
+                    {"path": "gen.cpp", "lines": [
+                        {"lno": 1, "line": "a"}, {"lno": 2, "line": "a"}, {"lno": 3, "line": "a"}
+                    ]}
+                ],
+                "normal": [
+                    {"path": "b.cpp", "lines": [{"lno": 5, "line": "a"}, {"lno": 6, "line": "a"}]}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let options = SearchOptions {
+            query: Some("a".into()),
+            limit: 3,
+            max_per_file: Some(1),
+            ..Default::default()
+        };
+        let lines = |options: &SearchOptions| {
+            response_results(&json, options)
+                .iter()
+                .map(|r| format!("{}:{}", r.path, r.line_number))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(lines(&options), ["b.cpp:5", "gen.cpp:1"]);
+
+        let kept = SearchOptions {
+            keep_duplicates: true,
+            ..options.clone()
+        };
+        assert_eq!(lines(&kept), ["b.cpp:5", "gen.cpp:1"]);
+        let ranked = SearchOptions {
+            rank: true,
+            ..options
+        };
+        assert_eq!(lines(&ranked), ["b.cpp:5", "gen.cpp:1"]);
+    }
+}
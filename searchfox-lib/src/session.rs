@@ -0,0 +1,127 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// An investigation recorded with `--save-session`: the invocations that
+/// were run, in order, along with the requests each of them issued.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Session {
+    pub entries: Vec<SessionEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionEntry {
+    /// Command line arguments, without the program name.
+    pub args: Vec<String>,
+    pub repo: String,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub requests: Vec<RecordedRequest>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub url: String,
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<String>,
+}
+
+impl Session {
+    /// Load a session file, or start an empty session if it does not exist yet.
+    pub fn load_or_default(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Self::load(path)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Could not read session {}: {}", path.display(), e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Invalid session file {}: {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn push(&mut self, args: Vec<String>, repo: &str, requests: Vec<RecordedRequest>) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        self.entries.push(SessionEntry {
+            args,
+            repo: repo.to_string(),
+            timestamp,
+            requests,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::SearchfoxClient;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let dir = std::env::temp_dir().join(format!("searchfox-session-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("session.json");
+
+        let mut session = Session::load_or_default(&file).unwrap();
+        assert!(session.entries.is_empty());
+        session.push(
+            vec!["-q".into(), "AudioStream".into()],
+            "mozilla-central",
+            vec![RecordedRequest {
+                url: "https://searchfox.org/mozilla-central/search?q=AudioStream".into(),
+                status: 200,
+                response: None,
+            }],
+        );
+        session.save(&file).unwrap();
+
+        let loaded = Session::load(&file).unwrap();
+        assert_eq!(loaded.entries.len(), 1);
+        assert_eq!(loaded.entries[0].args, vec!["-q", "AudioStream"]);
+        assert_eq!(loaded.entries[0].requests[0].status, 200);
+        assert!(loaded.entries[0].requests[0].response.is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn client_records_requests_and_optional_responses() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/firefox-main/source/some/file.js"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"<html><body><code class="source-line">let x;</code></body></html>"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let mut client =
+            SearchfoxClient::new_for_test("mozilla-central".into(), server.uri()).unwrap();
+        client.set_cache_enabled(false);
+        client.enable_session_recording(true);
+        client.get_file("some/file.js").await.unwrap();
+
+        let requests = client.take_recorded_requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0]
+            .url
+            .ends_with("/firefox-main/source/some/file.js"));
+        assert_eq!(requests[0].status, 200);
+        assert!(requests[0].response.as_deref().unwrap().contains("let x;"));
+        assert!(client.take_recorded_requests().is_empty());
+    }
+}
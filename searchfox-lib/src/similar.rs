@@ -0,0 +1,336 @@
+use crate::client::SearchfoxClient;
+use crate::search::SearchOptions;
+use anyhow::{bail, Result};
+use futures::stream::{self, StreamExt};
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+
+const CONCURRENT_QUERIES: usize = 8;
+
+/// Identifiers searched for: more queries find more copies that were
+/// partially renamed, at the cost of one request each.
+const MAX_IDENTIFIERS: usize = 8;
+
+/// Candidates whose file is fetched to compare token trigrams.
+const MAX_COMPARED: usize = 10;
+
+/// Words too common in source code to tell one piece of code from another.
+const COMMON_WORDS: &[&str] = &[
+    "auto",
+    "bool",
+    "break",
+    "case",
+    "catch",
+    "char",
+    "class",
+    "const",
+    "continue",
+    "default",
+    "delete",
+    "double",
+    "else",
+    "enum",
+    "explicit",
+    "extern",
+    "false",
+    "final",
+    "float",
+    "for",
+    "function",
+    "if",
+    "impl",
+    "inline",
+    "int",
+    "let",
+    "long",
+    "match",
+    "mut",
+    "namespace",
+    "new",
+    "null",
+    "nullptr",
+    "override",
+    "private",
+    "protected",
+    "public",
+    "pub",
+    "return",
+    "self",
+    "short",
+    "signed",
+    "size_t",
+    "static",
+    "struct",
+    "switch",
+    "template",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typename",
+    "uint8_t",
+    "uint16_t",
+    "uint32_t",
+    "uint64_t",
+    "int32_t",
+    "int64_t",
+    "undefined",
+    "unsigned",
+    "using",
+    "var",
+    "virtual",
+    "void",
+    "while",
+    "nsresult",
+    "NS_OK",
+    "NS_FAILED",
+    "NS_SUCCEEDED",
+    "RefPtr",
+    "nsCOMPtr",
+    "MOZ_ASSERT",
+    "mozilla",
+];
+
+/// A region of the tree sharing identifiers with the snippet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimilarCode {
+    pub path: String,
+    pub start: usize,
+    pub end: usize,
+    /// Snippet identifiers found in the region.
+    pub identifiers: Vec<String>,
+    /// Fraction of the snippet's token trigrams found in the region, or of
+    /// its identifiers when the file could not be compared.
+    pub overlap: f64,
+}
+
+fn identifiers(text: &str) -> Vec<&str> {
+    let re = Regex::new(r"[A-Za-z_][A-Za-z0-9_]*").expect("valid regex");
+    re.find_iter(text).map(|m| m.as_str()).collect()
+}
+
+/// The identifiers of `snippet` most likely to single out copies of it:
+/// not keywords or common types, and preferably long and compound
+/// (`mBufferedFrames`, `kMaxChannels`, `resample_into`).
+pub fn distinctive_identifiers(snippet: &str) -> Vec<String> {
+    let mut seen = BTreeSet::new();
+    let mut candidates: Vec<&str> = identifiers(snippet)
+        .into_iter()
+        .filter(|id| id.len() >= 4 && !COMMON_WORDS.contains(id) && seen.insert(*id))
+        .collect();
+    let compound =
+        |id: &str| id.contains('_') || id.chars().skip(1).any(|c| c.is_ascii_uppercase());
+    candidates.sort_by_key(|id| (!compound(id), std::cmp::Reverse(id.len())));
+    candidates
+        .into_iter()
+        .take(MAX_IDENTIFIERS)
+        .map(String::from)
+        .collect()
+}
+
+/// Token trigrams of `text`, insensitive to whitespace and formatting.
+pub fn token_trigrams(text: &str) -> BTreeSet<(String, String, String)> {
+    let re = Regex::new(r"[A-Za-z0-9_]+|[^\sA-Za-z0-9_]").expect("valid regex");
+    let tokens: Vec<&str> = re.find_iter(text).map(|m| m.as_str()).collect();
+    tokens
+        .windows(3)
+        .map(|w| (w[0].to_string(), w[1].to_string(), w[2].to_string()))
+        .collect()
+}
+
+/// Fraction of the trigrams of `snippet` present in `region`.
+pub fn trigram_overlap(snippet: &str, region: &str) -> f64 {
+    let wanted = token_trigrams(snippet);
+    if wanted.is_empty() {
+        return 0.0;
+    }
+    let found = token_trigrams(region);
+    wanted.intersection(&found).count() as f64 / wanted.len() as f64
+}
+
+fn contains_word(text: &str, word: &str) -> bool {
+    identifiers(text).contains(&word)
+}
+
+/// Group hits `(path, line, identifier)` into regions no longer than the
+/// snippet plus some slack, keeping those holding at least half of the
+/// identifiers (and two at least).
+pub fn cluster_hits(
+    hits: &[(String, usize, String)],
+    snippet_lines: usize,
+    identifier_count: usize,
+) -> Vec<SimilarCode> {
+    let mut by_path: BTreeMap<&str, Vec<(usize, &str)>> = BTreeMap::new();
+    for (path, line, id) in hits {
+        by_path.entry(path).or_default().push((*line, id));
+    }
+    let gap = snippet_lines + 10;
+    let needed = identifier_count.div_ceil(2).max(2).min(identifier_count);
+    let mut regions = Vec::new();
+    for (path, mut lines) in by_path {
+        lines.sort();
+        let mut groups: Vec<Vec<(usize, &str)>> = Vec::new();
+        for hit in lines {
+            match groups.last_mut() {
+                Some(group) if hit.0 - group[group.len() - 1].0 <= gap => group.push(hit),
+                _ => groups.push(vec![hit]),
+            }
+        }
+        for group in groups {
+            let ids: BTreeSet<&str> = group.iter().map(|(_, id)| *id).collect();
+            if ids.len() < needed {
+                continue;
+            }
+            regions.push(SimilarCode {
+                path: path.to_string(),
+                start: group[0].0,
+                end: group[group.len() - 1].0,
+                overlap: ids.len() as f64 / identifier_count as f64,
+                identifiers: ids.into_iter().map(String::from).collect(),
+            });
+        }
+    }
+    regions
+}
+
+impl SearchfoxClient {
+    /// Find code resembling `snippet`, which is lines `start`-`end` of
+    /// `path`: search for its distinctive identifiers, group the hits into
+    /// regions, and rank the regions by the share of the snippet's token
+    /// trigrams they contain. The snippet's own location is left out.
+    pub async fn similar_code(
+        &self,
+        path: &str,
+        start: usize,
+        end: usize,
+        snippet: &str,
+        options: &SearchOptions,
+    ) -> Result<Vec<SimilarCode>> {
+        let ids = distinctive_identifiers(snippet);
+        if ids.is_empty() {
+            bail!("No distinctive identifiers in {}:{}-{}", path, start, end);
+        }
+
+        let found: Vec<(String, Vec<_>)> = stream::iter(&ids)
+            .map(|id| async move {
+                let lines = self.text_lines(id, options).await;
+                lines.map(|lines| (id.clone(), lines))
+            })
+            .buffered(CONCURRENT_QUERIES)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<_>>()?;
+        let mut hits = Vec::new();
+        for (id, lines) in found {
+            for (file, line, text) in lines {
+                if contains_word(&text, &id) {
+                    hits.push((file, line, id.clone()));
+                }
+            }
+        }
+
+        let snippet_lines = end + 1 - start;
+        let mut regions: Vec<SimilarCode> = cluster_hits(&hits, snippet_lines, ids.len())
+            .into_iter()
+            .filter(|r| !(r.path == path && r.start <= end && r.end >= start))
+            .collect();
+        regions.sort_by(|a, b| b.overlap.total_cmp(&a.overlap));
+
+        let compared: Vec<Option<f64>> = stream::iter(regions.iter().take(MAX_COMPARED))
+            .map(|region| async move {
+                let source = self.get_file(&region.path).await.ok()?;
+                let lines: Vec<&str> = source.lines().collect();
+                // Widen the region to the snippet's length, as identifiers
+                // rarely sit on its first and last lines.
+                let slack = snippet_lines.saturating_sub(region.end + 1 - region.start);
+                let from = region.start.saturating_sub(slack + 1);
+                let to = (region.end + slack).min(lines.len());
+                Some(trigram_overlap(
+                    snippet,
+                    &lines[from.min(to)..to].join("\n"),
+                ))
+            })
+            .buffered(CONCURRENT_QUERIES)
+            .collect()
+            .await;
+        for (region, overlap) in regions.iter_mut().zip(compared) {
+            if let Some(overlap) = overlap {
+                region.overlap = overlap;
+            }
+        }
+        regions.sort_by(|a, b| {
+            b.overlap
+                .total_cmp(&a.overlap)
+                .then(b.identifiers.len().cmp(&a.identifiers.len()))
+        });
+        Ok(regions)
+    }
+}
+
+pub fn format_similar_code(location: &str, regions: &[SimilarCode], limit: usize) -> String {
+    let mut output = format!("# Code similar to {}\n\n", location);
+    if regions.is_empty() {
+        output.push_str("No similar code found.\n");
+        return output;
+    }
+    for (i, region) in regions.iter().take(limit).enumerate() {
+        output.push_str(&format!(
+            "{}. {}:{}-{} ({:.0}% overlap)\n   shared: {}\n",
+            i + 1,
+            region.path,
+            region.start,
+            region.end,
+            region.overlap * 100.0,
+            region.identifiers.join(", ")
+        ));
+    }
+    if regions.len() > limit {
+        output.push_str(&format!(
+            "\n{} more candidate(s); raise -l to see them\n",
+            regions.len() - limit
+        ));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SNIPPET: &str = "  if (mBufferedFrames > kMaxFrames) {\n    uint32_t dropped = mBufferedFrames - kMaxFrames;\n    DropFrames(dropped);\n  }\n";
+
+    #[test]
+    fn picks_distinctive_identifiers() {
+        assert_eq!(
+            distinctive_identifiers(SNIPPET),
+            vec!["mBufferedFrames", "kMaxFrames", "DropFrames", "dropped"]
+        );
+    }
+
+    #[test]
+    fn trigram_overlap_ignores_formatting() {
+        let reformatted = "if (mBufferedFrames>kMaxFrames)\n{\n  uint32_t dropped = mBufferedFrames - kMaxFrames;\n  DropFrames(dropped);\n}";
+        assert_eq!(trigram_overlap(SNIPPET, reformatted), 1.0);
+        assert!(trigram_overlap(SNIPPET, "DropFrames(count);") < 0.2);
+    }
+
+    #[test]
+    fn clusters_hits_by_file_and_distance() {
+        let hit = |path: &str, line, id: &str| (path.to_string(), line, id.to_string());
+        let hits = vec![
+            hit("a.cpp", 10, "mBufferedFrames"),
+            hit("a.cpp", 11, "kMaxFrames"),
+            hit("a.cpp", 12, "DropFrames"),
+            hit("a.cpp", 200, "mBufferedFrames"),
+            hit("b.cpp", 5, "mBufferedFrames"),
+            hit("b.cpp", 7, "dropped"),
+        ];
+        let regions = cluster_hits(&hits, 4, 4);
+        assert_eq!(regions.len(), 2);
+        assert_eq!((regions[0].start, regions[0].end), (10, 12));
+        assert_eq!(regions[0].overlap, 0.75);
+        assert_eq!(regions[1].path, "b.cpp");
+        assert_eq!(regions[1].identifiers, vec!["dropped", "mBufferedFrames"]);
+    }
+}
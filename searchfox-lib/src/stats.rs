@@ -0,0 +1,242 @@
+use crate::client::SearchfoxClient;
+use crate::search::SearchOptions;
+use crate::types::{File, SearchfoxResponse};
+use anyhow::{bail, Result};
+use std::collections::BTreeMap;
+
+/// Hit counts for a search, broken down by searchfox category
+/// (normal/test/generated) and by kind of match (definitions, uses, ...).
+#[derive(Debug, Default)]
+pub struct SearchStats {
+    pub by_category: BTreeMap<String, usize>,
+    pub by_kind: BTreeMap<String, usize>,
+    /// Hits per file path.
+    pub by_file: BTreeMap<String, usize>,
+    pub total: usize,
+    pub files: usize,
+}
+
+/// Hits under a directory, as `--group-by dir` lists them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectoryCount {
+    /// Directory prefix, `.` for files at the root of the tree.
+    pub directory: String,
+    pub hits: usize,
+    pub files: usize,
+}
+
+/// Depth of the directories to group by, from a `--group-by` value:
+/// `dir`, two levels, or `dir:DEPTH`.
+pub fn parse_group_by(spec: &str) -> Result<usize> {
+    match spec.split_once(':') {
+        None if spec == "dir" => Ok(2),
+        Some(("dir", depth)) => match depth.parse() {
+            Ok(depth) if depth > 0 => Ok(depth),
+            _ => bail!("Invalid --group-by depth '{depth}', expected a positive number"),
+        },
+        _ => bail!("Invalid --group-by '{spec}', expected dir or dir:DEPTH"),
+    }
+}
+
+/// The directory of `path` cut to its first `depth` components.
+fn directory_prefix(path: &str, depth: usize) -> &str {
+    let Some((directory, _)) = path.rsplit_once('/') else {
+        return ".";
+    };
+    match directory.match_indices('/').nth(depth.saturating_sub(1)) {
+        Some((end, _)) => &directory[..end],
+        None => directory,
+    }
+}
+
+/// Hits and files per directory prefix of `depth` components, most hits
+/// first.
+pub fn group_by_directory(stats: &SearchStats, depth: usize) -> Vec<DirectoryCount> {
+    let mut groups: BTreeMap<&str, DirectoryCount> = BTreeMap::new();
+    for (path, hits) in &stats.by_file {
+        let directory = directory_prefix(path, depth);
+        let group = groups.entry(directory).or_insert_with(|| DirectoryCount {
+            directory: directory.to_string(),
+            hits: 0,
+            files: 0,
+        });
+        group.hits += hits;
+        group.files += 1;
+    }
+    let mut groups: Vec<DirectoryCount> = groups.into_values().collect();
+    groups.sort_by(|a, b| {
+        b.hits
+            .cmp(&a.hits)
+            .then_with(|| a.directory.cmp(&b.directory))
+    });
+    groups
+}
+
+/// Label used for matches that searchfox does not group under a kind,
+/// e.g. plain text searches.
+const TEXT_MATCHES: &str = "Text matches";
+
+/// "Definitions (AudioContext)" -> "Definitions"
+fn kind_from_label(label: &str) -> &str {
+    match label.find(" (") {
+        Some(pos) => &label[..pos],
+        None => label,
+    }
+}
+
+/// Count the hits in a search response. Language, category and line filters
+/// of `options` apply, the result limit does not.
+pub fn compute_stats(json: &SearchfoxResponse, options: &SearchOptions) -> SearchStats {
+    let path_only = options.query.is_none() && options.symbol.is_none() && options.id.is_none();
+    let mut stats = SearchStats::default();
+
+    let mut count = |category: &str, kind: &str, file: &serde_json::Value| {
+        let Ok(file) = serde_json::from_value::<File>(file.clone()) else {
+            return;
+        };
+        if !options.matches_language_filter(&file.path) {
+            return;
+        }
+        let hits = if path_only {
+            usize::from(options.matches_line_filter(&file.path))
+        } else {
+            file.lines
+                .iter()
+                .filter(|line| options.matches_line_filter(&line.line))
+                .count()
+        };
+        if hits == 0 {
+            return;
+        }
+        *stats.by_category.entry(category.to_string()).or_default() += hits;
+        *stats.by_kind.entry(kind.to_string()).or_default() += hits;
+        stats.total += hits;
+        *stats.by_file.entry(file.path).or_default() += hits;
+    };
+
+    for (category, value) in json {
+        if category.starts_with('*') || !options.category_filter.should_include(category) {
+            continue;
+        }
+
+        if let Some(file_list) = value.as_array() {
+            for file in file_list {
+                count(category, TEXT_MATCHES, file);
+            }
+        } else if let Some(obj) = value.as_object() {
+            for (label, file_list) in obj {
+                for file in file_list.as_array().into_iter().flatten() {
+                    count(category, kind_from_label(label), file);
+                }
+            }
+        }
+    }
+
+    stats.files = stats.by_file.len();
+    stats
+}
+
+impl SearchfoxClient {
+    pub async fn search_stats(&self, options: &SearchOptions) -> Result<SearchStats> {
+        let json = self.search_response(options).await?;
+        Ok(compute_stats(&json, options))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::CategoryFilter;
+
+    fn response() -> SearchfoxResponse {
+        serde_json::from_str(
+            r#"{
+                "*title*": "ignored",
+                "normal": {
+                    "Definitions (AudioContext)": [
+                        {"path": "dom/media/AudioContext.cpp", "lines": [{"lno": 10, "line": "AudioContext::AudioContext()"}]}
+                    ],
+                    "Uses (AudioContext)": [
+                        {"path": "dom/media/AudioContext.cpp", "lines": [{"lno": 20, "line": "a"}, {"lno": 30, "line": "b"}]},
+                        {"path": "dom/media/AudioNode.cpp", "lines": [{"lno": 5, "line": "c"}]}
+                    ]
+                },
+                "test": {
+                    "Uses (AudioContext)": [
+                        {"path": "dom/media/test/test_audio.html", "lines": [{"lno": 1, "line": "d"}]}
+                    ]
+                }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn counts_by_category_and_kind() {
+        let options = SearchOptions {
+            id: Some("AudioContext".into()),
+            ..Default::default()
+        };
+        let stats = compute_stats(&response(), &options);
+        assert_eq!(stats.total, 5);
+        assert_eq!(stats.files, 3);
+        assert_eq!(stats.by_category["normal"], 4);
+        assert_eq!(stats.by_category["test"], 1);
+        assert_eq!(stats.by_kind["Definitions"], 1);
+        assert_eq!(stats.by_kind["Uses"], 4);
+    }
+
+    #[test]
+    fn honors_filters() {
+        let options = SearchOptions {
+            id: Some("AudioContext".into()),
+            category_filter: CategoryFilter::ExcludeTests,
+            lang: vec![crate::search::Lang::Cpp],
+            ..Default::default()
+        };
+        let stats = compute_stats(&response(), &options);
+        assert_eq!(stats.total, 4);
+        assert!(!stats.by_category.contains_key("test"));
+    }
+
+    #[test]
+    fn honors_line_filter() {
+        let options = SearchOptions {
+            id: Some("AudioContext".into()),
+            filter: Some(regex::Regex::new("^[ab]$").unwrap()),
+            ..Default::default()
+        };
+        let stats = compute_stats(&response(), &options);
+        assert_eq!(stats.total, 2);
+        assert_eq!(stats.files, 1);
+    }
+
+    #[test]
+    fn groups_hits_by_directory() {
+        let options = SearchOptions {
+            id: Some("AudioContext".into()),
+            ..Default::default()
+        };
+        let stats = compute_stats(&response(), &options);
+        let groups = |depth| {
+            group_by_directory(&stats, depth)
+                .into_iter()
+                .map(|g| (g.directory, g.hits, g.files))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(groups(1), [("dom".to_string(), 5, 3)]);
+        assert_eq!(
+            groups(3),
+            [
+                ("dom/media".to_string(), 4, 2),
+                ("dom/media/test".to_string(), 1, 1)
+            ]
+        );
+        assert_eq!(directory_prefix("README.md", 2), ".");
+
+        assert_eq!(parse_group_by("dir").unwrap(), 2);
+        assert_eq!(parse_group_by("dir:4").unwrap(), 4);
+        assert!(parse_group_by("dir:0").is_err());
+        assert!(parse_group_by("file").is_err());
+    }
+}
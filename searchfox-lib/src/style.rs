@@ -0,0 +1,116 @@
+use std::io::IsTerminal;
+use std::sync::OnceLock;
+use tabled::settings::{object::Rows, Color, Modify};
+use tabled::Table;
+
+/// Start and end of a highlighted search match: bold red, as grep does.
+const MATCH_START: &str = "\x1b[1;31m";
+const MATCH_END: &str = "\x1b[0m";
+
+static COLOR_OVERRIDE: OnceLock<bool> = OnceLock::new();
+
+/// Turn colors on or off whatever the environment says, as `--color
+/// always` and `--color never` do. Only the first call has an effect.
+pub fn set_color_enabled(enabled: bool) {
+    let _ = COLOR_OVERRIDE.set(enabled);
+}
+
+/// Whether output should be colored: as set by `set_color_enabled`, else
+/// following the NO_COLOR and CLICOLOR/CLICOLOR_FORCE conventions, then
+/// whether stdout is a terminal.
+pub fn color_enabled() -> bool {
+    if let Some(enabled) = COLOR_OVERRIDE.get() {
+        return *enabled;
+    }
+    color_decision(
+        std::env::var("NO_COLOR").ok().as_deref(),
+        std::env::var("CLICOLOR_FORCE").ok().as_deref(),
+        std::env::var("CLICOLOR").ok().as_deref(),
+        std::io::stdout().is_terminal(),
+    )
+}
+
+fn color_decision(
+    no_color: Option<&str>,
+    clicolor_force: Option<&str>,
+    clicolor: Option<&str>,
+    is_terminal: bool,
+) -> bool {
+    let set = |var: Option<&str>| var.is_some_and(|v| !v.is_empty() && v != "0");
+    if no_color.is_some_and(|v| !v.is_empty()) {
+        return false;
+    }
+    if set(clicolor_force) {
+        return true;
+    }
+    is_terminal && clicolor != Some("0")
+}
+
+/// Color the header row of a table, when colors are enabled.
+pub(crate) fn color_table_header(table: &mut Table, color: Color) {
+    if color_enabled() {
+        table.with(Modify::new(Rows::first()).with(color));
+    }
+}
+
+/// `line` with the byte ranges of `bounds` highlighted for a terminal.
+/// Ranges overlapping an earlier one or not on character boundaries of the
+/// line are left out.
+pub fn highlight_ranges(line: &str, bounds: &[(usize, usize)]) -> String {
+    let mut ranges = bounds.to_vec();
+    ranges.sort();
+    let mut output = String::with_capacity(line.len());
+    let mut done = 0;
+    for (start, end) in ranges {
+        let end = end.min(line.len());
+        if start < done
+            || start >= end
+            || !line.is_char_boundary(start)
+            || !line.is_char_boundary(end)
+        {
+            continue;
+        }
+        output.push_str(&line[done..start]);
+        output.push_str(MATCH_START);
+        output.push_str(&line[start..end]);
+        output.push_str(MATCH_END);
+        done = end;
+    }
+    output.push_str(&line[done..]);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlights_valid_ranges() {
+        assert_eq!(
+            highlight_ranges("  AudioStream* s;", &[(2, 13)]),
+            "  \x1b[1;31mAudioStream\x1b[0m* s;"
+        );
+        assert_eq!(
+            highlight_ranges("a b a", &[(4, 5), (0, 1), (0, 3), (2, 99)]),
+            "\x1b[1;31ma\x1b[0m \x1b[1;31mb a\x1b[0m"
+        );
+        // Not a character boundary: the range is ignored.
+        assert_eq!(highlight_ranges("é", &[(1, 2)]), "é");
+    }
+
+    #[test]
+    fn no_color_wins() {
+        assert!(!color_decision(Some("1"), Some("1"), None, true));
+        // An empty NO_COLOR is ignored.
+        assert!(color_decision(Some(""), None, None, true));
+    }
+
+    #[test]
+    fn clicolor_force_and_terminal_detection() {
+        assert!(color_decision(None, Some("1"), None, false));
+        assert!(!color_decision(None, Some("0"), None, false));
+        assert!(color_decision(None, None, None, true));
+        assert!(!color_decision(None, None, None, false));
+        assert!(!color_decision(None, None, Some("0"), true));
+    }
+}
@@ -0,0 +1,115 @@
+use crate::client::SearchfoxClient;
+use crate::search::SearchResult;
+use log::debug;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Set the context of each result with a line to the `before` lines above
+/// it and the `after` lines below it in its file, from `sources` by path.
+/// Results whose file is missing keep the context they have.
+pub fn fill_context(
+    results: &mut [SearchResult],
+    sources: &HashMap<String, String>,
+    before: usize,
+    after: usize,
+) {
+    let mut lines_by_path: HashMap<&str, Vec<&str>> = HashMap::new();
+    for result in results.iter_mut().filter(|r| r.line_number > 0) {
+        let Some(source) = sources.get(&result.path) else {
+            continue;
+        };
+        let lines = lines_by_path
+            .entry(result.path.as_str())
+            .or_insert_with(|| source.lines().collect());
+        let index = result.line_number - 1;
+        if index >= lines.len() {
+            continue;
+        }
+        result.context_before = lines[index.saturating_sub(before)..index]
+            .iter()
+            .map(|line| line.to_string())
+            .collect();
+        result.context_after = lines[index + 1..(index + 1 + after).min(lines.len())]
+            .iter()
+            .map(|line| line.to_string())
+            .collect();
+    }
+}
+
+impl SearchfoxClient {
+    /// Give each result `before` and `after` lines of context, grep-style,
+    /// whatever the query. Files are read from `checkout` when given and
+    /// present there, and fetched concurrently otherwise; results of files
+    /// that cannot be read keep their context.
+    pub async fn add_context(
+        &self,
+        results: &mut [SearchResult],
+        before: usize,
+        after: usize,
+        checkout: Option<&Path>,
+    ) {
+        let mut paths: Vec<String> = results
+            .iter()
+            .filter(|r| r.line_number > 0)
+            .map(|r| r.path.clone())
+            .collect();
+        paths.sort();
+        paths.dedup();
+
+        let mut sources = HashMap::new();
+        let mut remote = Vec::new();
+        for path in paths {
+            match checkout.and_then(|root| std::fs::read_to_string(root.join(&path)).ok()) {
+                Some(source) => {
+                    sources.insert(path, source);
+                }
+                None => remote.push(path),
+            }
+        }
+        for (path, content) in self.get_files(&remote, None).await {
+            match content {
+                Ok(content) => {
+                    sources.insert(path, content);
+                }
+                Err(e) => debug!("No context for {path}: {e}"),
+            }
+        }
+        fill_context(results, &sources, before, after);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_lines_around_each_hit() {
+        let result = |path: &str, line_number| SearchResult {
+            path: path.into(),
+            line_number,
+            line: String::new(),
+            context_before: vec![],
+            context_after: vec!["kept".into()],
+            category: "normal".into(),
+            kind: None,
+            bounds: Vec::new(),
+            other_kinds: Vec::new(),
+        };
+        let mut results = vec![
+            result("a.cpp", 1),
+            result("a.cpp", 4),
+            result("a.cpp", 9),
+            result("missing.cpp", 2),
+        ];
+        let sources = HashMap::from([("a.cpp".to_string(), "1\n2\n3\n4\n5\n".to_string())]);
+        fill_context(&mut results, &sources, 2, 1);
+
+        assert!(results[0].context_before.is_empty());
+        assert_eq!(results[0].context_after, ["2"]);
+        assert_eq!(results[1].context_before, ["2", "3"]);
+        assert_eq!(results[1].context_after, ["5"]);
+        // Past the end of the file, or no file: left alone.
+        assert_eq!(results[2].context_after, ["kept"]);
+        assert_eq!(results[3].context_after, ["kept"]);
+    }
+}
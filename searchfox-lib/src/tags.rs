@@ -0,0 +1,234 @@
+use crate::client::SearchfoxClient;
+use crate::complete::symbol_kind;
+use crate::search::SearchOptions;
+use crate::types::{File, SearchfoxResponse};
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+/// A definition found by a search, as an entry of a tags file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagEntry {
+    /// Fully qualified name, e.g. `mozilla::dom::AudioContext::CreateGain`.
+    pub qualified: String,
+    pub path: String,
+    pub line: usize,
+    /// Source text of the definition line.
+    pub text: String,
+    /// Kind of symbol, as guessed by [`symbol_kind`].
+    pub kind: &'static str,
+}
+
+impl TagEntry {
+    /// The name editors look up: the last component of the qualified name.
+    pub fn name(&self) -> &str {
+        let name = self
+            .qualified
+            .rsplit("::")
+            .next()
+            .unwrap_or(&self.qualified);
+        name.rsplit('.').next().unwrap_or(name)
+    }
+}
+
+/// Definitions in the identifier results of a search, at most
+/// `options.limit` of them, in path and line order.
+pub fn collect_tag_entries(json: &SearchfoxResponse, options: &SearchOptions) -> Vec<TagEntry> {
+    let mut entries = Vec::new();
+    for (category, value) in json {
+        if category.starts_with('*') || !options.category_filter.should_include(category) {
+            continue;
+        }
+        let Some(obj) = value.as_object() else {
+            continue;
+        };
+        for (label, file_list) in obj {
+            let Some(qualified) = label
+                .strip_prefix("Definitions (")
+                .and_then(|rest| rest.strip_suffix(')'))
+            else {
+                continue;
+            };
+            let files = file_list
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|file| serde_json::from_value::<File>(file.clone()).ok())
+                .filter(|file| options.matches_language_filter(&file.path));
+            for file in files {
+                for line in file.lines {
+                    if !options.matches_line_filter(&line.line) {
+                        continue;
+                    }
+                    entries.push(TagEntry {
+                        qualified: qualified.to_string(),
+                        path: file.path.clone(),
+                        line: line.lno,
+                        text: line.line.trim_end().to_string(),
+                        kind: symbol_kind(&line.line),
+                    });
+                }
+            }
+        }
+    }
+    entries.sort_by(|a, b| (&a.path, a.line, &a.qualified).cmp(&(&b.path, b.line, &b.qualified)));
+    entries.dedup();
+    entries.truncate(options.limit);
+    entries
+}
+
+impl SearchfoxClient {
+    /// Definitions matched by a search, for writing a tags file.
+    pub async fn tag_entries(&self, options: &SearchOptions) -> Result<Vec<TagEntry>> {
+        let json = self.search_response(options).await?;
+        Ok(collect_tag_entries(&json, options))
+    }
+}
+
+/// Single-letter ctags kind for a [`symbol_kind`].
+fn ctags_kind(kind: &str) -> char {
+    match kind {
+        "class" => 'c',
+        "struct" => 's',
+        "enum" => 'g',
+        "union" => 'u',
+        "namespace" => 'n',
+        "type" => 't',
+        "function" => 'f',
+        "macro" => 'd',
+        _ => 'v',
+    }
+}
+
+fn local_path(root: Option<&str>, path: &str) -> String {
+    match root {
+        Some(root) => format!("{}/{}", root.trim_end_matches('/'), path),
+        None => path.to_string(),
+    }
+}
+
+/// A sorted ctags file (the format vim reads), with one tag for the short name
+/// of each definition and one for its qualified name. Paths are relative to
+/// `root`, the local checkout, or to the tags file itself when `None`.
+pub fn format_ctags(entries: &[TagEntry], root: Option<&str>) -> String {
+    let mut lines = Vec::new();
+    for entry in entries {
+        let path = local_path(root, &entry.path);
+        let mut names = vec![entry.name()];
+        if entry.qualified != entry.name() {
+            names.push(&entry.qualified);
+        }
+        for name in names {
+            lines.push(format!(
+                "{}\t{}\t{};\"\t{}\tline:{}",
+                name,
+                path,
+                entry.line,
+                ctags_kind(entry.kind),
+                entry.line
+            ));
+        }
+    }
+    lines.sort();
+    lines.dedup();
+
+    let mut output = String::from(
+        "!_TAG_FILE_FORMAT\t2\t/extended format/\n\
+         !_TAG_FILE_SORTED\t1\t/0=unsorted, 1=sorted, 2=foldcase/\n\
+         !_TAG_PROGRAM_NAME\tsearchfox-cli\t//\n",
+    );
+    for line in lines {
+        output.push_str(&line);
+        output.push('\n');
+    }
+    output
+}
+
+/// An etags file (the `TAGS` format Emacs reads). Entries carry line numbers
+/// but no byte offsets, which searchfox does not provide; Emacs then jumps by
+/// line.
+pub fn format_etags(entries: &[TagEntry], root: Option<&str>) -> String {
+    let mut by_file: BTreeMap<&str, Vec<&TagEntry>> = BTreeMap::new();
+    for entry in entries {
+        by_file.entry(&entry.path).or_default().push(entry);
+    }
+
+    let mut output = String::new();
+    for (path, entries) in by_file {
+        let section: String = entries
+            .iter()
+            .map(|e| format!("{}\x7f{}\x01{},\n", e.text, e.qualified, e.line))
+            .collect();
+        output.push_str(&format!(
+            "\x0c\n{},{}\n{}",
+            local_path(root, path),
+            section.len(),
+            section
+        ));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response() -> SearchfoxResponse {
+        serde_json::from_str(
+            r#"{
+                "*title*": "AudioContext",
+                "normal": {
+                    "Definitions (mozilla::dom::AudioContext::CreateGain)": [
+                        {"path": "dom/media/webaudio/AudioContext.cpp", "lines": [{"lno": 469, "line": "already_AddRefed<GainNode> AudioContext::CreateGain(ErrorResult& aRv) {"}]}
+                    ],
+                    "Uses (mozilla::dom::AudioContext)": [
+                        {"path": "dom/media/webaudio/AudioNode.cpp", "lines": [{"lno": 3, "line": "AudioContext* c;"}]}
+                    ],
+                    "Definitions (mozilla::dom::AudioContext)": [
+                        {"path": "dom/media/webaudio/AudioContext.h", "lines": [{"lno": 120, "line": "class AudioContext final"}]}
+                    ]
+                }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn collects_definitions_only() {
+        let entries = collect_tag_entries(&response(), &SearchOptions::default());
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].path, "dom/media/webaudio/AudioContext.cpp");
+        assert_eq!(entries[0].name(), "CreateGain");
+        assert_eq!(entries[0].kind, "function");
+        assert_eq!(entries[1].name(), "AudioContext");
+        assert_eq!(entries[1].kind, "class");
+    }
+
+    #[test]
+    fn formats_sorted_ctags() {
+        let entries = collect_tag_entries(&response(), &SearchOptions::default());
+        let tags = format_ctags(&entries, Some("/src/gecko/"));
+        let lines: Vec<&str> = tags.lines().filter(|l| !l.starts_with("!_")).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "AudioContext\t/src/gecko/dom/media/webaudio/AudioContext.h\t120;\"\tc\tline:120",
+                "CreateGain\t/src/gecko/dom/media/webaudio/AudioContext.cpp\t469;\"\tf\tline:469",
+                "mozilla::dom::AudioContext\t/src/gecko/dom/media/webaudio/AudioContext.h\t120;\"\tc\tline:120",
+                "mozilla::dom::AudioContext::CreateGain\t/src/gecko/dom/media/webaudio/AudioContext.cpp\t469;\"\tf\tline:469",
+            ]
+        );
+    }
+
+    #[test]
+    fn formats_etags_sections() {
+        let entries = collect_tag_entries(&response(), &SearchOptions::default());
+        let tags = format_etags(&entries, None);
+        let entry = "class AudioContext final\x7fmozilla::dom::AudioContext\x01120,\n";
+        assert!(tags.contains(&format!(
+            "\x0c\ndom/media/webaudio/AudioContext.h,{}\n{}",
+            entry.len(),
+            entry
+        )));
+        assert_eq!(tags.matches('\x0c').count(), 2);
+    }
+}
@@ -0,0 +1,197 @@
+use crate::client::SearchfoxClient;
+use crate::search::SearchOptions;
+use crate::types::{File, SearchfoxResponse};
+use crate::utils::path_category;
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+/// A test file referencing a symbol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestFile {
+    pub path: String,
+    /// Test harness guessed from the path, e.g. "gtest" or "mochitest".
+    pub kind: &'static str,
+    /// Number of references to the symbol in the file.
+    pub hits: usize,
+}
+
+/// Guess the harness running a test file from its path.
+pub fn test_kind(path: &str) -> &'static str {
+    let components: Vec<&str> = path.split('/').collect();
+    let file_name = components.last().copied().unwrap_or(path);
+    let has_dir = |names: &[&str]| components.iter().any(|c| names.contains(c));
+
+    if path.starts_with("testing/web-platform/") {
+        "web-platform"
+    } else if has_dir(&["gtest", "gtests"]) {
+        "gtest"
+    } else if has_dir(&["crashtests"]) {
+        "crashtest"
+    } else if has_dir(&["reftests"]) {
+        "reftest"
+    } else if has_dir(&["unit", "xpcshell"]) && file_name.ends_with(".js") {
+        "xpcshell"
+    } else if has_dir(&["mochitest", "chrome", "browser"])
+        || (file_name.starts_with("test_")
+            && (file_name.ends_with(".html") || file_name.ends_with(".xhtml")))
+        || file_name.starts_with("browser_")
+    {
+        "mochitest"
+    } else {
+        "other"
+    }
+}
+
+/// Test files referencing a symbol in an identifier search, most references
+/// first. Files count as tests when searchfox says so or when their path
+/// looks like one; definitions and declarations are not references.
+pub fn collect_test_files(json: &SearchfoxResponse, options: &SearchOptions) -> Vec<TestFile> {
+    let mut hits: BTreeMap<String, usize> = BTreeMap::new();
+    let mut count = |category: &str, file: &serde_json::Value| {
+        let Ok(file) = serde_json::from_value::<File>(file.clone()) else {
+            return;
+        };
+        if category != "test" && path_category(&file.path) != "test" {
+            return;
+        }
+        if !options.matches_language_filter(&file.path) || file.lines.is_empty() {
+            return;
+        }
+        *hits.entry(file.path).or_default() += file.lines.len();
+    };
+
+    for (category, value) in json {
+        if category.starts_with('*') || category == "generated" {
+            continue;
+        }
+        if let Some(file_list) = value.as_array() {
+            for file in file_list {
+                count(category, file);
+            }
+        } else if let Some(obj) = value.as_object() {
+            for (label, file_list) in obj {
+                if label.starts_with("Definitions") || label.starts_with("Declarations") {
+                    continue;
+                }
+                for file in file_list.as_array().into_iter().flatten() {
+                    count(category, file);
+                }
+            }
+        }
+    }
+
+    let mut files: Vec<TestFile> = hits
+        .into_iter()
+        .map(|(path, hits)| TestFile {
+            kind: test_kind(&path),
+            path,
+            hits,
+        })
+        .collect();
+    files.sort_by(|a, b| b.hits.cmp(&a.hits).then(a.path.cmp(&b.path)));
+    files
+}
+
+impl SearchfoxClient {
+    /// Find the test files referencing `symbol`.
+    pub async fn tests_for(&self, symbol: &str, options: &SearchOptions) -> Result<Vec<TestFile>> {
+        let id_options = SearchOptions {
+            query: None,
+            symbol: None,
+            id: Some(symbol.to_string()),
+            ..options.clone()
+        };
+        let json = self.search_response(&id_options).await?;
+        Ok(collect_test_files(&json, &id_options))
+    }
+}
+
+pub fn format_test_files(symbol: &str, files: &[TestFile]) -> String {
+    let mut output = format!("# Tests referencing {}\n\n", symbol);
+    if files.is_empty() {
+        output.push_str("No test files found.\n");
+        return output;
+    }
+
+    let mut by_kind: BTreeMap<&str, Vec<&TestFile>> = BTreeMap::new();
+    for file in files {
+        by_kind.entry(file.kind).or_default().push(file);
+    }
+    for (kind, files) in by_kind {
+        output.push_str(&format!("## {}\n\n", kind));
+        for file in files {
+            output.push_str(&format!("- {} ({})\n", file.path, file.hits));
+        }
+        output.push('\n');
+    }
+
+    // gtests are selected by test name rather than path.
+    let runnable: Vec<&str> = files
+        .iter()
+        .filter(|f| f.kind != "gtest" && f.kind != "other")
+        .map(|f| f.path.as_str())
+        .collect();
+    if !runnable.is_empty() {
+        output.push_str(&format!("Run with: ./mach test {}\n", runnable.join(" ")));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guesses_test_kinds() {
+        assert_eq!(test_kind("dom/media/gtest/TestAudioBuffers.cpp"), "gtest");
+        assert_eq!(test_kind("dom/media/test/test_play.html"), "mochitest");
+        assert_eq!(
+            test_kind("dom/media/test/browser/browser_tab.js"),
+            "mochitest"
+        );
+        assert_eq!(test_kind("netwerk/test/unit/test_http.js"), "xpcshell");
+        assert_eq!(
+            test_kind("testing/web-platform/tests/webaudio/idlharness.window.js"),
+            "web-platform"
+        );
+        assert_eq!(
+            test_kind("dom/media/test/crashtests/1234.html"),
+            "crashtest"
+        );
+    }
+
+    #[test]
+    fn keeps_test_references_only() {
+        let json: SearchfoxResponse = serde_json::from_str(
+            r#"{
+                "normal": {
+                    "Definitions (AudioStream::Init)": [
+                        {"path": "dom/media/AudioStream.cpp", "lines": [{"lno": 1, "line": "a"}]}
+                    ],
+                    "Uses (AudioStream::Init)": [
+                        {"path": "dom/media/MediaDecoder.cpp", "lines": [{"lno": 2, "line": "b"}]},
+                        {"path": "dom/media/gtest/TestAudioStream.cpp", "lines": [{"lno": 3, "line": "c"}]}
+                    ]
+                },
+                "test": {
+                    "Uses (AudioStream::Init)": [
+                        {"path": "dom/media/test/test_audio.html", "lines": [{"lno": 4, "line": "d"}, {"lno": 5, "line": "e"}]}
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+        let files = collect_test_files(&json, &SearchOptions::default());
+        let paths: Vec<(&str, &str, usize)> = files
+            .iter()
+            .map(|f| (f.path.as_str(), f.kind, f.hits))
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                ("dom/media/test/test_audio.html", "mochitest", 2),
+                ("dom/media/gtest/TestAudioStream.cpp", "gtest", 1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,82 @@
+/// Estimate how many tokens a BPE tokenizer (as used by LLMs) splits `text`
+/// into. Identifiers are split into their camelCase, snake_case and digit
+/// parts: a part of up to six letters is a token, longer ones take one more
+/// per four letters. Digits go three to a token, punctuation two, a single
+/// space is merged into the next word and non-ASCII characters are a token
+/// each. The estimate is within a few tens of percent of real tokenizers on
+/// source code and prose.
+pub fn estimate_tokens(text: &str) -> usize {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        if c.is_ascii_alphabetic() {
+            i += 1;
+            while i < chars.len() && chars[i].is_ascii_lowercase() {
+                i += 1;
+            }
+            // An acronym such as `HTML` in `HTMLElement` is one part.
+            if i == start + 1 && c.is_ascii_uppercase() {
+                while i < chars.len()
+                    && chars[i].is_ascii_uppercase()
+                    && !chars.get(i + 1).is_some_and(|n| n.is_ascii_lowercase())
+                {
+                    i += 1;
+                }
+            }
+            tokens += 1 + (i - start).saturating_sub(6).div_ceil(4);
+        } else if c.is_ascii_digit() {
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            tokens += (i - start).div_ceil(3);
+        } else if c == ' ' || c == '\t' {
+            while i < chars.len() && (chars[i] == ' ' || chars[i] == '\t') {
+                i += 1;
+            }
+            let next_is_word = chars.get(i).is_some_and(|n| n.is_ascii_alphanumeric());
+            if !(i - start == 1 && next_is_word) {
+                tokens += (i - start).div_ceil(4);
+            }
+        } else if c == '\n' || c == '\r' {
+            while i < chars.len() && (chars[i] == '\n' || chars[i] == '\r') {
+                i += 1;
+            }
+            tokens += 1;
+        } else if c.is_ascii_punctuation() {
+            while i < chars.len() && chars[i].is_ascii_punctuation() && chars[i] != '_' {
+                i += 1;
+            }
+            // `_` inside an identifier is a separator, not a token.
+            i = i.max(start + 1);
+            if c != '_' {
+                tokens += (i - start).div_ceil(2);
+            }
+        } else {
+            i += 1;
+            tokens += 1;
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimates_code_and_prose() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("the quick brown fox"), 4);
+        // HTML Media Elem-ent :: Play ();
+        assert_eq!(estimate_tokens("HTMLMediaElement::Play();"), 8);
+        // dom / media / Audio Stream . cpp : 12-34 \n
+        assert_eq!(estimate_tokens("dom/media/AudioStream.cpp:1234\n"), 12);
+        // 8 spaces of indentation, then `return` and `rv`
+        assert_eq!(estimate_tokens("        return rv;"), 5);
+        assert_eq!(estimate_tokens("moz_audio_stream"), 3);
+        assert_eq!(estimate_tokens("é€"), 2);
+    }
+}
@@ -5,11 +5,8 @@ use std::collections::HashMap;
 pub struct Line {
     pub lno: usize,
     pub line: String,
-    #[allow(dead_code)]
     pub bounds: Option<Vec<usize>>,
-    #[allow(dead_code)]
     pub context: Option<String>,
-    #[allow(dead_code)]
     pub contextsym: Option<String>,
     #[serde(rename = "peekRange")]
     #[allow(dead_code)]
@@ -61,6 +58,16 @@ pub struct BlameInfo {
     pub commit_info: Option<CommitInfo>,
 }
 
+#[derive(Debug, Clone)]
+pub struct IndexInfo {
+    /// Revision the index was built from, as used in searchfox permalinks.
+    pub revision: String,
+    /// Commit date of that revision, as reported by commit-info.
+    pub date: String,
+    pub summary: String,
+    pub bug_number: Option<u64>,
+}
+
 #[derive(Debug, Clone)]
 pub struct ParsedCommitInfo {
     pub bug_number: Option<u64>,
@@ -0,0 +1,199 @@
+use crate::call_graph::CallGraphQuery;
+use crate::client::SearchfoxClient;
+use crate::impact::analyze_impact;
+use crate::nesting::OutlineEntry;
+use crate::progress::ProgressEvent;
+use crate::search::{CategoryFilter, SearchOptions};
+use anyhow::Result;
+use futures::stream::{self, StreamExt};
+
+const CONCURRENT_QUERIES: usize = 8;
+
+/// A function defined in the analyzed class or file with no non-test callers.
+#[derive(Debug, Clone)]
+pub struct UnusedCandidate {
+    pub sym: String,
+    pub path: String,
+    pub line: usize,
+    /// Number of callers defined in test files.
+    pub test_callers: usize,
+}
+
+/// Whether `target` names a file rather than a class.
+fn is_path(target: &str) -> bool {
+    target.contains('/')
+        || target
+            .rsplit_once('.')
+            .is_some_and(|(_, ext)| !ext.is_empty())
+}
+
+/// Outline entries that look like function definitions. Destructors are
+/// skipped: they are never called explicitly.
+pub(crate) fn is_function(entry: &OutlineEntry) -> bool {
+    let line = entry.pretty_line.trim_start();
+    let is_type = ["class ", "struct ", "union ", "namespace ", "enum "]
+        .iter()
+        .any(|kw| line.starts_with(kw) || line.contains(&format!(" {kw}")));
+    let name = entry.sym.rsplit("::").next().unwrap_or("");
+    !is_type && line.contains('(') && !name.starts_with('~')
+}
+
+/// Files worth scanning for `class`: where it is declared, plus the
+/// matching implementation file for headers.
+fn class_files(header: &str) -> Vec<String> {
+    let mut files = vec![header.to_string()];
+    if let Some(stem) = header.strip_suffix(".h") {
+        files.push(format!("{stem}.cpp"));
+    }
+    files
+}
+
+impl SearchfoxClient {
+    /// Find functions defined in a class or file (by outline) with no callers
+    /// outside of tests. This is a heuristic: virtual overrides, callbacks and
+    /// functions only referenced through pointers will show up too.
+    pub async fn find_unused(
+        &self,
+        target: &str,
+        options: &SearchOptions,
+    ) -> Result<Vec<UnusedCandidate>> {
+        let (files, prefix) = if is_path(target) {
+            (vec![target.to_string()], None)
+        } else {
+            let locations = self.find_symbol_locations(target, None, options).await?;
+            let Some((header, _)) = locations.first() else {
+                anyhow::bail!("Could not find class '{}'", target);
+            };
+            let class_name = target.rsplit("::").next().unwrap_or(target);
+            (class_files(header), Some(format!("{class_name}::")))
+        };
+
+        let mut functions = Vec::new();
+        for (i, file) in files.iter().enumerate() {
+            let outline = match self.get_outline(file).await {
+                Ok(outline) => outline,
+                // The implementation file guessed for a header may not exist.
+                Err(_) if i > 0 => continue,
+                Err(e) => return Err(e),
+            };
+            for entry in outline {
+                let in_class = prefix
+                    .as_ref()
+                    .is_none_or(|p| entry.sym.contains(p.as_str()));
+                if in_class && is_function(&entry) {
+                    functions.push((file.clone(), entry));
+                }
+            }
+        }
+
+        let operation = format!("unused-in {target}");
+        let total = functions.len();
+        self.emit(ProgressEvent::Started {
+            operation: operation.clone(),
+            total,
+        });
+
+        let mut candidates = Vec::new();
+        let mut checks = stream::iter(functions)
+            .map(|(path, entry)| async move {
+                let query = CallGraphQuery {
+                    calls_from: None,
+                    calls_to: Some(entry.sym.clone()),
+                    calls_between: None,
+                    depth: 1,
+                };
+                let graph = self.search_call_graph(&query).await;
+                (path, entry, graph)
+            })
+            .buffered(CONCURRENT_QUERIES);
+
+        let mut completed = 0;
+        while let Some((path, entry, graph)) = checks.next().await {
+            let graph = graph?;
+            let all = analyze_impact(&graph, CategoryFilter::All).callers.len();
+            let non_test = analyze_impact(&graph, CategoryFilter::ExcludeTests)
+                .callers
+                .len();
+            if non_test == 0 {
+                candidates.push(UnusedCandidate {
+                    sym: entry.sym,
+                    path,
+                    line: entry.line,
+                    test_callers: all,
+                });
+            }
+            completed += 1;
+            self.emit(ProgressEvent::Advanced {
+                operation: operation.clone(),
+                completed,
+                total,
+            });
+        }
+        self.emit(ProgressEvent::Finished { operation });
+
+        Ok(candidates)
+    }
+}
+
+pub fn format_unused_candidates(target: &str, candidates: &[UnusedCandidate]) -> String {
+    let mut output = format!("# Unused candidates in {}\n\n", target);
+    if candidates.is_empty() {
+        output.push_str("Every function has at least one non-test caller.\n");
+        return output;
+    }
+    for c in candidates {
+        if c.test_callers > 0 {
+            output.push_str(&format!(
+                "- {} ({}:{}) — only called from tests ({})\n",
+                c.sym, c.path, c.line, c.test_callers
+            ));
+        } else {
+            output.push_str(&format!("- {} ({}:{})\n", c.sym, c.path, c.line));
+        }
+    }
+    output.push_str(
+        "\nHeuristic: virtual overrides, callbacks and functions only used through pointers are listed too.\n",
+    );
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(sym: &str, pretty_line: &str) -> OutlineEntry {
+        OutlineEntry {
+            sym: sym.into(),
+            pretty_line: pretty_line.into(),
+            line: 1,
+        }
+    }
+
+    #[test]
+    fn detects_paths() {
+        assert!(is_path("dom/media/AudioStream.cpp"));
+        assert!(is_path("AudioStream.h"));
+        assert!(!is_path("mozilla::AudioStream"));
+        assert!(!is_path("AudioStream"));
+    }
+
+    #[test]
+    fn keeps_functions_only() {
+        assert!(is_function(&entry(
+            "A::Init",
+            "nsresult A::Init(int aRate) {"
+        )));
+        assert!(!is_function(&entry("A", "class A : public B {")));
+        assert!(!is_function(&entry("mozilla", "namespace mozilla {")));
+        assert!(!is_function(&entry("A::~A", "A::~A() {")));
+    }
+
+    #[test]
+    fn headers_also_scan_implementation() {
+        assert_eq!(
+            class_files("dom/media/AudioStream.h"),
+            vec!["dom/media/AudioStream.h", "dom/media/AudioStream.cpp"]
+        );
+        assert_eq!(class_files("dom/media/Foo.cpp"), vec!["dom/media/Foo.cpp"]);
+    }
+}
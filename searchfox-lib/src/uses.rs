@@ -0,0 +1,152 @@
+use crate::client::SearchfoxClient;
+use crate::search::{categories, SearchOptions};
+use crate::types::{File, SearchfoxResponse};
+use anyhow::Result;
+
+/// A line of the index using a symbol: a call, a reference, a read or write.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolUse {
+    pub path: String,
+    pub line_number: usize,
+    /// Text of the line, trimmed.
+    pub line: String,
+    /// Category of the file: normal, test or generated.
+    pub category: String,
+    /// Symbol used, from the `Uses (...)` label of the result.
+    pub symbol: String,
+    /// Pretty name of the function or class containing the use, if any.
+    pub context: Option<String>,
+    /// Symbol of that function or class.
+    pub context_symbol: Option<String>,
+}
+
+/// Every `Uses (...)` line of an identifier search, in a stable order,
+/// honoring the filters, offset and limit of `options`.
+pub fn collect_uses(json: &SearchfoxResponse, options: &SearchOptions) -> Vec<SymbolUse> {
+    let mut uses = Vec::new();
+    for (category, value) in categories(json) {
+        if !options.category_filter.should_include(category) {
+            continue;
+        }
+        let Some(kinds) = value.as_object() else {
+            continue;
+        };
+        for (kind, files) in kinds {
+            let Some(symbol) = kind
+                .strip_prefix("Uses (")
+                .and_then(|k| k.strip_suffix(')'))
+            else {
+                continue;
+            };
+            for file in files.as_array().into_iter().flatten() {
+                let Ok(file) = serde_json::from_value::<File>(file.clone()) else {
+                    continue;
+                };
+                if !options.matches_language_filter(&file.path) {
+                    continue;
+                }
+                for line in file.lines {
+                    uses.push(SymbolUse {
+                        path: file.path.clone(),
+                        line_number: line.lno,
+                        line: line.line.trim().to_string(),
+                        category: category.clone(),
+                        symbol: symbol.to_string(),
+                        context: line.context.filter(|c| !c.is_empty()),
+                        context_symbol: line.contextsym.filter(|c| !c.is_empty()),
+                    });
+                }
+            }
+        }
+    }
+    uses.into_iter()
+        .skip(options.offset)
+        .take(options.limit)
+        .collect()
+}
+
+/// Uses as `path:line: [enclosing function] text` lines.
+pub fn format_uses(uses: &[SymbolUse]) -> String {
+    let mut output = String::new();
+    for symbol_use in uses {
+        output.push_str(&format!("{}:{}: ", symbol_use.path, symbol_use.line_number));
+        if let Some(context) = &symbol_use.context {
+            output.push_str(&format!("[{context}] "));
+        }
+        output.push_str(&symbol_use.line);
+        output.push('\n');
+    }
+    output
+}
+
+impl SearchfoxClient {
+    /// The places the index records as using `symbol`, with the function
+    /// containing each one, unlike a text search which also finds comments
+    /// and unrelated identifiers.
+    pub async fn find_uses(&self, symbol: &str, options: &SearchOptions) -> Result<Vec<SymbolUse>> {
+        let id_options = SearchOptions {
+            query: None,
+            symbol: None,
+            id: Some(symbol.to_string()),
+            ..options.clone()
+        };
+        let json = self.search_response(&id_options).await?;
+        Ok(collect_uses(&json, &id_options))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::CategoryFilter;
+
+    #[test]
+    fn collects_uses_with_their_context() {
+        let json: SearchfoxResponse = serde_json::from_str(
+            r#"{
+                "*title*": "id:CreateGain",
+                "test": {
+                    "Uses (mozilla::dom::AudioContext::CreateGain)": [
+                        {"path": "dom/media/webaudio/test/Test.cpp", "lines": [
+                            {"lno": 12, "line": "  ctx->CreateGain(rv);", "context": "", "contextsym": ""}
+                        ]}
+                    ]
+                },
+                "normal": {
+                    "Definitions (mozilla::dom::AudioContext::CreateGain)": [
+                        {"path": "dom/media/webaudio/AudioContext.cpp", "lines": [{"lno": 469, "line": "already_AddRefed<GainNode> AudioContext::CreateGain("}]}
+                    ],
+                    "Uses (mozilla::dom::AudioContext::CreateGain)": [
+                        {"path": "dom/media/webaudio/MediaElementAudioSourceNode.cpp", "lines": [
+                            {"lno": 40, "line": "    RefPtr<GainNode> gain = aContext.CreateGain(aRv);",
+                             "context": "mozilla::dom::MediaElementAudioSourceNode::Create",
+                             "contextsym": "_ZN7mozilla3dom26MediaElementAudioSourceNode6CreateE"}
+                        ]}
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+        let options = SearchOptions::default();
+        let uses = collect_uses(&json, &options);
+        assert_eq!(uses.len(), 2);
+        assert_eq!(uses[0].symbol, "mozilla::dom::AudioContext::CreateGain");
+        assert_eq!(
+            uses[0].context_symbol.as_deref(),
+            Some("_ZN7mozilla3dom26MediaElementAudioSourceNode6CreateE")
+        );
+        assert_eq!(
+            format_uses(&uses),
+            "dom/media/webaudio/MediaElementAudioSourceNode.cpp:40: \
+             [mozilla::dom::MediaElementAudioSourceNode::Create] \
+             RefPtr<GainNode> gain = aContext.CreateGain(aRv);\n\
+             dom/media/webaudio/test/Test.cpp:12: ctx->CreateGain(rv);\n"
+        );
+
+        let options = SearchOptions {
+            category_filter: CategoryFilter::ExcludeTests,
+            ..Default::default()
+        };
+        assert_eq!(collect_uses(&json, &options).len(), 1);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::config::{self, Config};
 use crate::types::Line;
 
 pub fn is_mozilla_repository() -> bool {
@@ -263,13 +264,140 @@ pub fn is_potential_definition(line: &Line, query: &str) -> bool {
     }
 }
 
+/// Number of single-character insertions, deletions and substitutions
+/// turning `a` into `b`.
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Built-in mapping of repository names to the name of their tree in
+/// searchfox URLs, for the repositories where the two differ. Entries can be
+/// added or overridden in the `[repos]` table of config.toml.
+const SEARCHFOX_TREES: &[(&str, &str)] = &[
+    ("mozilla-central", "firefox-main"),
+    ("mozilla-beta", "firefox-beta"),
+    ("mozilla-release", "firefox-release"),
+    ("mozilla-esr128", "firefox-esr128"),
+    ("mozilla-esr140", "firefox-esr140"),
+];
+
+/// Branches from newest to oldest, as tried by `--branch-fallback`.
+const BRANCH_ORDER: &[&str] = &[
+    "mozilla-central",
+    "mozilla-beta",
+    "mozilla-release",
+    "mozilla-esr140",
+    "mozilla-esr128",
+    "mozilla-esr115",
+];
+
+/// The branches to look for a file missing on `repo` in: the older ones
+/// first, as files usually go missing by being renamed or removed, then the
+/// newer ones. None for repositories that are not one of these branches,
+/// such as comm-central, whose paths the branches do not have.
+pub fn fallback_repos(repo: &str) -> Vec<&'static str> {
+    let Some(pos) = BRANCH_ORDER.iter().position(|r| *r == repo) else {
+        return Vec::new();
+    };
+    let (newer, older) = (&BRANCH_ORDER[..pos], &BRANCH_ORDER[pos + 1..]);
+    older.iter().chain(newer).copied().collect()
+}
+
 pub fn searchfox_url_repo(repo: &str) -> &str {
-    match repo {
-        "mozilla-central" => "firefox-main",
-        "mozilla-beta" => "firefox-beta",
-        "mozilla-release" => "firefox-release",
-        "mozilla-esr128" => "firefox-esr128",
-        "mozilla-esr140" => "firefox-esr140",
-        _ => repo,
+    repo_tree(config::global(), repo)
+}
+
+/// Name of `repo` in searchfox URLs, according to `config` and then the
+/// built-in table.
+pub fn repo_tree<'a>(config: &'a Config, repo: &'a str) -> &'a str {
+    if let Some(tree) = config.repo(repo).and_then(|r| r.searchfox.as_deref()) {
+        return tree;
+    }
+    SEARCHFOX_TREES
+        .iter()
+        .find(|(name, _)| *name == repo)
+        .map_or(repo, |(_, tree)| tree)
+}
+
+/// Repository names `--repo` accepts without asking the server: the built-in
+/// branches and their searchfox trees, and the entries of `config`.
+pub fn known_repos(config: &Config) -> Vec<&str> {
+    let mut repos: Vec<&str> = BRANCH_ORDER.to_vec();
+    repos.push("comm-central");
+    repos.extend(SEARCHFOX_TREES.iter().map(|(_, tree)| *tree));
+    for (name, repo) in &config.repos {
+        repos.push(name);
+        repos.extend(repo.searchfox.as_deref());
+    }
+    repos
+}
+
+/// Path of a repository on hg.mozilla.org, e.g. `releases/mozilla-esr128`.
+pub fn hg_repo_path(repo: &str) -> Option<String> {
+    repo_hg_path(config::global(), repo)
+}
+
+/// Path of `repo` on hg.mozilla.org according to `config`, falling back to
+/// the naming scheme of the central and release repositories.
+pub fn repo_hg_path(config: &Config, repo: &str) -> Option<String> {
+    if let Some(path) = config.repo(repo).and_then(|r| r.hg.clone()) {
+        return Some(path);
+    }
+    if repo == "mozilla-central" || repo == "comm-central" {
+        return Some(repo.to_string());
+    }
+    let branch = repo
+        .strip_prefix("mozilla-")
+        .or_else(|| repo.strip_prefix("comm-"))?;
+    let is_esr = branch
+        .strip_prefix("esr")
+        .is_some_and(|v| !v.is_empty() && v.bytes().all(|b| b.is_ascii_digit()));
+    (matches!(branch, "beta" | "release") || is_esr).then(|| format!("releases/{repo}"))
+}
+
+/// Searchfox category ("normal", "test" or "generated") of a path, for data
+/// that, unlike search results, does not come grouped by category.
+pub fn path_category(path: &str) -> &'static str {
+    if path.starts_with("__GENERATED__/") {
+        return "generated";
+    }
+    let is_test = path.split('/').any(|component| {
+        matches!(
+            component,
+            "test" | "tests" | "gtest" | "gtests" | "mochitest" | "crashtests" | "reftests"
+        ) || component.starts_with("test_")
+    });
+    if is_test {
+        "test"
+    } else {
+        "normal"
+    }
+}
+
+/// Language of a path from its extension, as LSIF language identifiers and
+/// markdown code fences name it, "plaintext" when unknown.
+pub fn language_id(path: &str) -> &'static str {
+    match path.rsplit_once('.').map(|(_, ext)| ext) {
+        Some("c") => "c",
+        Some("cc" | "cpp" | "h" | "hh" | "hpp" | "mm") => "cpp",
+        Some("js" | "mjs" | "cjs" | "jsm" | "jsx") => "javascript",
+        Some("ts" | "tsx") => "typescript",
+        Some("rs") => "rust",
+        Some("java") => "java",
+        Some("kt") => "kotlin",
+        Some("py") => "python",
+        Some("webidl") => "webidl",
+        Some("idl") => "idl",
+        _ => "plaintext",
     }
 }
@@ -0,0 +1,213 @@
+use crate::client::SearchfoxClient;
+use crate::search::{SearchOptions, SearchResult};
+use anyhow::Result;
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+/// Changes in a query's result set since the previous `--watch` run.
+#[derive(Debug)]
+pub struct WatchReport {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub total: usize,
+    /// When the previous snapshot was taken (seconds since the Unix epoch),
+    /// or `None` if this run established the baseline.
+    pub previous_at: Option<u64>,
+}
+
+/// Identity of a match across runs. Line numbers are left out so that
+/// unrelated edits shifting code around do not show up as changes.
+fn match_key(result: &SearchResult) -> String {
+    if result.line_number == 0 {
+        result.path.clone()
+    } else {
+        format!("{}: {}", result.path, result.line.trim())
+    }
+}
+
+fn watch_key(repo: &str, options: &SearchOptions) -> String {
+    let key = format!(
+        "{}|{}|path={}|case={}|regexp={}|lang={:?}|category={:?}",
+        repo,
+        options.build_query(),
+        options.path.as_deref().unwrap_or(""),
+        options.case,
+        options.regexp,
+        options.lang,
+        options.category_filter
+    );
+    // Only added when set, so that snapshots taken before --filter, --ext,
+    // several -p and --max-per-file existed stay valid.
+    let key = match &options.filter {
+        Some(filter) => format!("{}|filter={}", key, filter.as_str()),
+        None => key,
+    };
+    let key = if options.extensions.is_empty() {
+        key
+    } else {
+        format!("{}|ext={}", key, options.extensions.join(","))
+    };
+    let key = if options.extra_paths.is_empty() {
+        key
+    } else {
+        format!("{}|paths={}", key, options.extra_paths.join(","))
+    };
+    match options.max_per_file {
+        Some(max) => format!("{key}|max_per_file={max}"),
+        None => key,
+    }
+}
+
+/// Parse an interval such as `30s`, `15m`, `24h` or `7d`. A bare number is
+/// taken as seconds.
+pub fn parse_interval(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let (number, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(pos) => s.split_at(pos),
+        None => (s, "s"),
+    };
+    let n: u64 = number
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid interval: {}", s))?;
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 24 * 3600,
+        _ => anyhow::bail!("Invalid interval unit in {} (expected s, m, h or d)", s),
+    };
+    let secs = n
+        .checked_mul(unit_secs)
+        .ok_or_else(|| anyhow::anyhow!("Interval too large: {}", s))?;
+    if secs == 0 {
+        anyhow::bail!("Interval must be greater than zero");
+    }
+    Ok(Duration::from_secs(secs))
+}
+
+impl SearchfoxClient {
+    /// Run a search and compare its results with the snapshot stored by the
+    /// previous run of the same query, then store the new snapshot.
+    pub async fn watch_search(&self, options: &SearchOptions) -> Result<WatchReport> {
+        // Every match, so that nothing is missed or seen as removed when
+        // the query has more than --limit of them.
+        let everything = SearchOptions {
+            limit: usize::MAX,
+            offset: 0,
+            ..options.clone()
+        };
+        let results = self.search(&everything).await?;
+        let current = results.iter().map(match_key).collect();
+        self.update_watch_snapshot(&watch_key(&self.repo, options), current)
+    }
+
+    fn update_watch_snapshot(&self, key: &str, current: BTreeSet<String>) -> Result<WatchReport> {
+        let previous = self.watch_get(key);
+        self.watch_set(key, &serde_json::to_string(&current)?);
+
+        let Some((snapshot, taken_at)) = previous else {
+            return Ok(WatchReport {
+                added: Vec::new(),
+                removed: Vec::new(),
+                total: current.len(),
+                previous_at: None,
+            });
+        };
+
+        let previous: BTreeSet<String> = serde_json::from_str(&snapshot)?;
+        Ok(WatchReport {
+            added: current.difference(&previous).cloned().collect(),
+            removed: previous.difference(&current).cloned().collect(),
+            total: current.len(),
+            previous_at: Some(taken_at),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(items: &[&str]) -> BTreeSet<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn first_run_is_baseline_then_reports_changes() {
+        let client =
+            SearchfoxClient::new_for_test("mozilla-central".into(), "https://example.com".into())
+                .unwrap();
+
+        let first = client
+            .update_watch_snapshot("k", set(&["a.cpp: Old()", "b.cpp: Old()"]))
+            .unwrap();
+        assert!(first.previous_at.is_none());
+        assert_eq!(first.total, 2);
+
+        let second = client
+            .update_watch_snapshot("k", set(&["b.cpp: Old()", "c.cpp: Old()"]))
+            .unwrap();
+        assert!(second.previous_at.is_some());
+        assert_eq!(second.added, vec!["c.cpp: Old()"]);
+        assert_eq!(second.removed, vec!["a.cpp: Old()"]);
+    }
+
+    #[test]
+    fn watch_key_tells_result_sets_apart() {
+        let options = |extra: &str| SearchOptions {
+            query: Some("AudioStream".into()),
+            path: Some("dom/media".into()),
+            extra_paths: vec![extra.into()],
+            ..SearchOptions::default()
+        };
+        assert_ne!(
+            watch_key("mozilla-central", &options("media/libcubeb")),
+            watch_key("mozilla-central", &options("media/libav"))
+        );
+        let capped = SearchOptions {
+            max_per_file: Some(3),
+            ..options("media/libcubeb")
+        };
+        assert_ne!(
+            watch_key("mozilla-central", &capped),
+            watch_key("mozilla-central", &options("media/libcubeb"))
+        );
+        // Every match is watched, whatever the limit.
+        let limited = SearchOptions {
+            limit: 5,
+            ..options("media/libcubeb")
+        };
+        assert_eq!(
+            watch_key("mozilla-central", &limited),
+            watch_key("mozilla-central", &options("media/libcubeb"))
+        );
+    }
+
+    #[test]
+    fn match_key_ignores_line_numbers() {
+        let at = |line_number| SearchResult {
+            path: "a.cpp".into(),
+            line_number,
+            line: "  Deprecated();".into(),
+            context_before: vec![],
+            context_after: vec![],
+            category: "normal".into(),
+            kind: None,
+            bounds: Vec::new(),
+            other_kinds: Vec::new(),
+        };
+        assert_eq!(match_key(&at(10)), match_key(&at(42)));
+    }
+
+    #[test]
+    fn parses_intervals() {
+        assert_eq!(parse_interval("24h").unwrap(), Duration::from_secs(86400));
+        assert_eq!(parse_interval("15m").unwrap(), Duration::from_secs(900));
+        assert_eq!(parse_interval("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_interval("2d").unwrap(), Duration::from_secs(172800));
+        assert!(parse_interval("0s").is_err());
+        assert!(parse_interval("3w").is_err());
+        assert!(parse_interval("h").is_err());
+        assert!(parse_interval("99999999999999999d").is_err());
+    }
+}
@@ -0,0 +1,245 @@
+use crate::client::SearchfoxClient;
+use crate::search::SearchOptions;
+use anyhow::{bail, Result};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Where web-platform-tests live, upstream and Mozilla-only.
+const WPT_PATH: &str = "^testing/web-platform/";
+
+/// A WebIDL interface, optionally narrowed to one of its members.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebIdlFeature {
+    pub interface: String,
+    pub member: Option<String>,
+}
+
+impl WebIdlFeature {
+    /// Parse `Interface` or `Interface.member` (`Interface::member` works
+    /// too).
+    pub fn parse(feature: &str) -> Result<Self> {
+        let feature = feature.trim();
+        let (interface, member) = match feature.split_once("::").or_else(|| feature.split_once('.'))
+        {
+            Some((interface, member)) => (interface, Some(member)),
+            None => (feature, None),
+        };
+        let is_name =
+            |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_name(interface) || !member.is_none_or(is_name) {
+            bail!(
+                "Expected a WebIDL feature like 'AudioContext' or 'AudioContext.createGain', got '{}'",
+                feature
+            );
+        }
+        Ok(Self {
+            interface: interface.to_string(),
+            member: member.map(String::from),
+        })
+    }
+}
+
+/// A web-platform-test file likely to exercise a feature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WptCandidate {
+    pub path: String,
+    /// Lines using the member (or the interface, without a member).
+    pub uses: usize,
+    /// Whether the file names the interface, in its text or its path. Uses
+    /// of a member in files that do not may belong to another interface
+    /// with a member of the same name.
+    pub names_interface: bool,
+}
+
+/// Whether a path mentions `name`, case-insensitively and ignoring `-` and
+/// `_` (`the-gainnode-interface` mentions `GainNode`).
+fn path_mentions(path: &str, name: &str) -> bool {
+    let normalized: String = path
+        .chars()
+        .filter(|c| *c != '-' && *c != '_')
+        .collect::<String>()
+        .to_ascii_lowercase();
+    normalized.contains(&name.to_ascii_lowercase())
+}
+
+/// Rank the files using the member (or interface) by whether they name the
+/// interface and then by number of uses. `member_hits` and
+/// `interface_hits` are the matching lines of each search.
+pub fn rank_wpt_candidates(
+    feature: &WebIdlFeature,
+    member_hits: &[(String, usize, String)],
+    interface_hits: &[(String, usize, String)],
+) -> Vec<WptCandidate> {
+    let naming: BTreeSet<&str> = interface_hits.iter().map(|(p, _, _)| p.as_str()).collect();
+    let uses_of = if feature.member.is_some() {
+        member_hits
+    } else {
+        interface_hits
+    };
+    let mut uses: BTreeMap<&str, usize> = BTreeMap::new();
+    for (path, _, _) in uses_of {
+        *uses.entry(path).or_default() += 1;
+    }
+    let mut candidates: Vec<WptCandidate> = uses
+        .into_iter()
+        .map(|(path, uses)| WptCandidate {
+            path: path.to_string(),
+            uses,
+            names_interface: naming.contains(path) || path_mentions(path, &feature.interface),
+        })
+        .collect();
+    candidates.sort_by(|a, b| {
+        b.names_interface
+            .cmp(&a.names_interface)
+            .then(b.uses.cmp(&a.uses))
+            .then(a.path.cmp(&b.path))
+    });
+    candidates
+}
+
+impl SearchfoxClient {
+    /// Find the web-platform-tests exercising a WebIDL interface or member:
+    /// files under testing/web-platform using the member as a whole word,
+    /// ranked by whether they also name the interface.
+    pub async fn wpt_for(
+        &self,
+        feature: &WebIdlFeature,
+        options: &SearchOptions,
+    ) -> Result<Vec<WptCandidate>> {
+        let options = SearchOptions {
+            category_filter: crate::search::CategoryFilter::All,
+            ..options.clone()
+        };
+        let path = Some(options.path.clone().unwrap_or_else(|| WPT_PATH.to_string()));
+        let word = |name: &str| format!(r"\b{}\b", regex::escape(name));
+        let interface = self.regex_lines(word(&feature.interface), path.clone(), &options);
+        let (member_hits, interface_hits) = match &feature.member {
+            Some(member) => {
+                let member = self.regex_lines(word(member), path.clone(), &options);
+                futures::try_join!(member, interface)?
+            }
+            None => (Vec::new(), interface.await?),
+        };
+        Ok(rank_wpt_candidates(feature, &member_hits, &interface_hits))
+    }
+}
+
+fn directory(path: &str) -> &str {
+    path.rsplit_once('/').map_or("", |(dir, _)| dir)
+}
+
+pub fn format_wpt_candidates(
+    feature: &WebIdlFeature,
+    candidates: &[WptCandidate],
+    limit: usize,
+) -> String {
+    let name = match &feature.member {
+        Some(member) => format!("{}.{}", feature.interface, member),
+        None => feature.interface.clone(),
+    };
+    let mut output = format!("# Web-platform tests for {}\n\n", name);
+    if candidates.is_empty() {
+        output.push_str("No web-platform tests found.\n");
+        return output;
+    }
+
+    let (likely, possible): (Vec<&WptCandidate>, Vec<&WptCandidate>) =
+        candidates.iter().partition(|c| c.names_interface);
+    let sections = [
+        ("Likely", likely),
+        (
+            if feature.member.is_some() {
+                "Possible (member name only)"
+            } else {
+                "Possible"
+            },
+            possible,
+        ),
+    ];
+    for (title, files) in sections {
+        if files.is_empty() {
+            continue;
+        }
+        output.push_str(&format!("## {} ({} file(s))\n\n", title, files.len()));
+        for file in files.iter().take(limit) {
+            output.push_str(&format!("- {} ({} use(s))\n", file.path, file.uses));
+        }
+        if files.len() > limit {
+            output.push_str(&format!("- ... {} more\n", files.len() - limit));
+        }
+        output.push('\n');
+    }
+
+    let mut directories: BTreeMap<&str, usize> = BTreeMap::new();
+    for candidate in candidates.iter().filter(|c| c.names_interface) {
+        *directories.entry(directory(&candidate.path)).or_default() += 1;
+    }
+    let mut directories: Vec<(&str, usize)> = directories.into_iter().collect();
+    directories.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    if !directories.is_empty() {
+        output.push_str("## Directories\n\n");
+        for (dir, count) in directories.iter().take(limit) {
+            output.push_str(&format!("- {}/ ({} file(s))\n", dir, count));
+        }
+        output.push_str(&format!("\nRun with: ./mach wpt {}/\n", directories[0].0));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_features() {
+        assert_eq!(
+            WebIdlFeature::parse("AudioContext.createGain").unwrap(),
+            WebIdlFeature {
+                interface: "AudioContext".into(),
+                member: Some("createGain".into()),
+            }
+        );
+        assert_eq!(
+            WebIdlFeature::parse("GainNode").unwrap().member,
+            None::<String>
+        );
+        assert!(WebIdlFeature::parse("AudioContext.").is_err());
+        assert!(WebIdlFeature::parse("a b").is_err());
+    }
+
+    #[test]
+    fn ranks_files_naming_the_interface_first() {
+        let hit = |path: &str| (path.to_string(), 1, String::new());
+        let feature = WebIdlFeature::parse("AudioContext.createGain").unwrap();
+        let member_hits = vec![
+            hit("testing/web-platform/tests/webaudio/gain.html"),
+            hit("testing/web-platform/tests/webaudio/gain.html"),
+            hit("testing/web-platform/tests/webaudio/offline.html"),
+            hit("testing/web-platform/tests/webaudio/offline.html"),
+            hit("testing/web-platform/tests/webaudio/offline.html"),
+            hit("testing/web-platform/tests/webaudio/the-audiocontext-interface/gain.html"),
+        ];
+        let interface_hits = vec![hit("testing/web-platform/tests/webaudio/gain.html")];
+        let candidates = rank_wpt_candidates(&feature, &member_hits, &interface_hits);
+        let ranked: Vec<(&str, usize, bool)> = candidates
+            .iter()
+            .map(|c| (c.path.as_str(), c.uses, c.names_interface))
+            .collect();
+        assert_eq!(
+            ranked,
+            vec![
+                ("testing/web-platform/tests/webaudio/gain.html", 2, true),
+                (
+                    "testing/web-platform/tests/webaudio/the-audiocontext-interface/gain.html",
+                    1,
+                    true
+                ),
+                ("testing/web-platform/tests/webaudio/offline.html", 3, false),
+            ]
+        );
+
+        let output = format_wpt_candidates(&feature, &candidates, 10);
+        assert!(output.contains("## Possible (member name only) (1 file(s))\n"));
+        assert!(output.contains("- testing/web-platform/tests/webaudio/ (1 file(s))\n"));
+        assert!(output.ends_with("Run with: ./mach wpt testing/web-platform/tests/webaudio/\n"));
+    }
+}
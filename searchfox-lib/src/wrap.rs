@@ -0,0 +1,134 @@
+/// Width of the terminal stdout is connected to, or `None` when stdout is not
+/// a terminal (output is then left unwrapped for other programs to consume).
+pub fn terminal_width() -> Option<usize> {
+    terminal_size::terminal_size_of(std::io::stdout()).map(|(w, _)| w.0 as usize)
+}
+
+/// Below this width, wrapping does more harm than good.
+const MIN_WIDTH: usize = 20;
+
+/// `line` split in one slice per displayed character, each with the ANSI
+/// escape sequences before it; the last one also has those after it.
+fn display_cells(line: &str) -> Vec<&str> {
+    let mut cells = Vec::new();
+    let mut start = 0;
+    let mut chars = line.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        if c == '\x1b' {
+            // A CSI sequence: ESC [, parameters, then a final byte.
+            if chars.next_if(|&(_, c)| c == '[').is_some() {
+                while chars.next_if(|&(_, c)| !('@'..='~').contains(&c)).is_some() {}
+                chars.next();
+            }
+            continue;
+        }
+        let end = chars.peek().map_or(line.len(), |&(i, _)| i);
+        cells.push(&line[start..end]);
+        start = end;
+    }
+    if start < line.len() {
+        match cells.last_mut() {
+            Some(last) => *last = &line[start - last.len()..],
+            None => cells.push(line),
+        }
+    }
+    cells
+}
+
+/// Wrap `line` to `width` columns, indenting continuation lines by `indent`.
+/// Breaks happen at a space in the second half of a row when possible. An
+/// indent larger than a third of the width falls back to four spaces, so that
+/// long prefixes such as file paths do not squeeze the wrapped text. ANSI
+/// color sequences take no room.
+pub fn wrap_hanging(line: &str, indent: usize, width: usize) -> String {
+    let cells = display_cells(line);
+    if width < MIN_WIDTH || cells.len() <= width {
+        return line.to_string();
+    }
+    let indent = if indent <= width / 3 { indent } else { 4 };
+
+    let mut output = String::new();
+    let mut start = 0;
+    let mut available = width;
+    while cells.len() - start > available {
+        let end = start + available;
+        let split = (start + available / 2..end)
+            .rev()
+            .find(|&i| cells[i].ends_with(' '))
+            .map_or(end, |i| i + 1);
+        output.push_str(cells[start..split].concat().trim_end());
+        output.push('\n');
+        output.push_str(&" ".repeat(indent));
+        start = split;
+        while start < cells.len() && cells[start] == " " {
+            start += 1;
+        }
+        available = width - indent;
+    }
+    output.push_str(&cells[start..].concat());
+    output
+}
+
+/// Length of the `>>>  123: ` style prefix of a numbered source line, as
+/// printed by `--define` and `--get-file`.
+pub fn line_number_prefix_len(line: &str) -> Option<usize> {
+    let rest = line.strip_prefix(">>>").unwrap_or(line).trim_start();
+    let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits == 0 || !rest[digits..].starts_with(": ") {
+        return None;
+    }
+    Some(line.len() - rest.len() + digits + 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_lines_are_untouched() {
+        assert_eq!(wrap_hanging("a.cpp:1: foo();", 9, 80), "a.cpp:1: foo();");
+    }
+
+    #[test]
+    fn wraps_at_spaces_with_hanging_indent() {
+        let line = "a.cpp:10: auto result = DoSomethingVeryLong(aFirstArgument, aSecondArgument);";
+        let wrapped = wrap_hanging(line, 10, 40);
+        let rows: Vec<&str> = wrapped.lines().collect();
+        assert_eq!(rows[0], "a.cpp:10: auto result =");
+        assert!(rows[1..]
+            .iter()
+            .all(|r| r.starts_with("          ") && r.len() <= 40));
+        let strip = |s: &str| s.replace([' ', '\n'], "");
+        assert_eq!(strip(&wrapped), strip(line));
+    }
+
+    #[test]
+    fn color_sequences_take_no_room() {
+        let line = "a.cpp:1: \x1b[1;31mx\x1b[0m\x1b[0m";
+        assert_eq!(display_cells(line).len(), 10);
+        assert_eq!(wrap_hanging(line, 9, 20), line);
+
+        let line = format!(
+            "a.cpp:10: {}\x1b[1;31mAudioStream\x1b[0m();",
+            "x ".repeat(20)
+        );
+        let wrapped = wrap_hanging(&line, 10, 30);
+        assert!(wrapped.contains("\x1b[1;31mAudioStream\x1b[0m"));
+        assert_eq!(wrapped.replace([' ', '\n'], ""), line.replace(' ', ""));
+    }
+
+    #[test]
+    fn hard_breaks_unbroken_text() {
+        let wrapped = wrap_hanging(&"x".repeat(50), 2, 20);
+        let rows: Vec<&str> = wrapped.lines().collect();
+        assert_eq!(rows[0].len(), 20);
+        assert_eq!(rows[1], format!("  {}", "x".repeat(18)));
+    }
+
+    #[test]
+    fn detects_line_number_prefixes() {
+        assert_eq!(line_number_prefix_len(">>>  469: foo() {"), Some(10));
+        assert_eq!(line_number_prefix_len("     470: bar();"), Some(10));
+        assert_eq!(line_number_prefix_len("void foo() {"), None);
+    }
+}
@@ -206,3 +206,70 @@ fn url_repo_remaps_mozilla_central() {
         "comm-central"
     );
 }
+
+// --- hg_repo_path ---
+
+#[test]
+fn hg_repo_path_maps_release_branches() {
+    use searchfox_lib::utils::hg_repo_path;
+    assert_eq!(
+        hg_repo_path("mozilla-central").as_deref(),
+        Some("mozilla-central")
+    );
+    assert_eq!(
+        hg_repo_path("mozilla-esr140").as_deref(),
+        Some("releases/mozilla-esr140")
+    );
+    assert_eq!(
+        hg_repo_path("comm-beta").as_deref(),
+        Some("releases/comm-beta")
+    );
+    assert_eq!(hg_repo_path("mozilla-esr").as_deref(), None);
+    assert_eq!(hg_repo_path("glean").as_deref(), None);
+}
+
+#[test]
+fn fallback_tries_older_branches_first() {
+    use searchfox_lib::utils::fallback_repos;
+    assert_eq!(
+        fallback_repos("mozilla-release"),
+        vec![
+            "mozilla-esr140",
+            "mozilla-esr128",
+            "mozilla-esr115",
+            "mozilla-central",
+            "mozilla-beta"
+        ]
+    );
+    assert_eq!(fallback_repos("mozilla-central").len(), 5);
+    assert!(fallback_repos("comm-central").is_empty());
+}
+
+#[test]
+fn config_extends_and_overrides_repo_table() {
+    use searchfox_lib::config::Config;
+    use searchfox_lib::utils::{repo_hg_path, repo_tree};
+    let config = Config::parse(
+        r#"
+        [repos.mozilla-esr153]
+        searchfox = "firefox-esr153"
+
+        [repos.mozilla-beta]
+        searchfox = "beta-tree"
+        hg = "beta-mirror"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(repo_tree(&config, "mozilla-esr153"), "firefox-esr153");
+    assert_eq!(repo_tree(&config, "mozilla-beta"), "beta-tree");
+    assert_eq!(repo_tree(&config, "mozilla-central"), "firefox-main");
+    assert_eq!(repo_tree(&config, "glean"), "glean");
+    assert_eq!(
+        repo_hg_path(&config, "mozilla-beta").as_deref(),
+        Some("beta-mirror")
+    );
+    assert_eq!(
+        repo_hg_path(&config, "mozilla-esr153").as_deref(),
+        Some("releases/mozilla-esr153")
+    );
+}
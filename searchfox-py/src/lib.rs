@@ -3,11 +3,16 @@
 use pyo3::create_exception;
 use pyo3::exceptions::PyException;
 use pyo3::prelude::*;
+use pyo3::types::PyDict;
 use pyo3_async_runtimes::tokio::future_into_py;
 use searchfox_lib::{
-    call_graph::CallGraphQuery, can_gc::GcInfo, categorize_spec_ref, classify_error,
-    field_layout::FieldLayoutQuery, search::SearchOptions, CategoryFilter, Lang,
-    SearchfoxClient as RustClient, SearchfoxErrorKind,
+    call_graph::CallGraphQuery,
+    can_gc::GcInfo,
+    categorize_spec_ref, classify_error,
+    field_layout::FieldLayoutQuery,
+    search::{SearchOptions, SortOrder},
+    CategoryFilter, Lang, ProgressEvent, ProgressSink, SearchfoxClient as RustClient,
+    SearchfoxErrorKind,
 };
 use std::sync::Arc;
 use tokio::runtime::Runtime;
@@ -49,8 +54,9 @@ fn parse_langs(langs: Option<Vec<String>>) -> PyResult<Vec<Lang>> {
         .map(|s| {
             Lang::parse(s).ok_or_else(|| {
                 SearchfoxRequestError::new_err(format!(
-                    "Unknown language '{}': expected one of cpp, c, js, webidl, java, kotlin, rust, python, html, css",
-                    s
+                    "Unknown language '{}': expected one of {}",
+                    s,
+                    Lang::names().join(", ")
                 ))
             })
         })
@@ -69,6 +75,66 @@ fn parse_category_filter(tests: Option<&str>) -> PyResult<CategoryFilter> {
     }
 }
 
+fn parse_sort(sort: Option<&str>) -> PyResult<Option<SortOrder>> {
+    match sort {
+        None => Ok(None),
+        Some(v) => SortOrder::parse(v).map(Some).ok_or_else(|| {
+            SearchfoxRequestError::new_err(format!(
+                "Invalid sort value '{}': expected one of {}",
+                v,
+                SortOrder::names().join(", ")
+            ))
+        }),
+    }
+}
+
+/// Forwards progress events to a Python callable as dicts, e.g.
+/// `{"event": "advanced", "operation": "...", "completed": 3, "total": 10}`.
+struct PyProgressSink {
+    callback: PyObject,
+}
+
+impl ProgressSink for PyProgressSink {
+    fn on_event(&self, event: &ProgressEvent) {
+        Python::with_gil(|py| {
+            let dict = PyDict::new(py);
+            let filled = match event {
+                ProgressEvent::Started { operation, total } => dict
+                    .set_item("event", "started")
+                    .and_then(|_| dict.set_item("operation", operation))
+                    .and_then(|_| dict.set_item("total", total)),
+                ProgressEvent::Advanced {
+                    operation,
+                    completed,
+                    total,
+                } => dict
+                    .set_item("event", "advanced")
+                    .and_then(|_| dict.set_item("operation", operation))
+                    .and_then(|_| dict.set_item("completed", completed))
+                    .and_then(|_| dict.set_item("total", total)),
+                ProgressEvent::Finished { operation } => dict
+                    .set_item("event", "finished")
+                    .and_then(|_| dict.set_item("operation", operation)),
+                ProgressEvent::Request { url } => dict
+                    .set_item("event", "request")
+                    .and_then(|_| dict.set_item("url", url)),
+            };
+            if let Err(e) = filled.and_then(|_| self.callback.call1(py, (dict,)).map(|_| ())) {
+                e.print(py);
+            }
+        });
+    }
+}
+
+fn new_client(repo: &str, log_requests: bool, progress: Option<PyObject>) -> PyResult<RustClient> {
+    let mut client = RustClient::new(repo.to_string(), log_requests)
+        .map_err(|e| to_py_err("Failed to create client".into(), e))?;
+    if let Some(callback) = progress {
+        client.set_progress_sink(Arc::new(PyProgressSink { callback }));
+    }
+    Ok(client)
+}
+
 // ---------------------------------------------------------------------------
 // Synchronous client
 // ---------------------------------------------------------------------------
@@ -82,14 +148,13 @@ struct SearchfoxClient {
 #[pymethods]
 impl SearchfoxClient {
     #[new]
-    #[pyo3(signature = (repo="mozilla-central", log_requests=false))]
-    fn new(repo: &str, log_requests: bool) -> PyResult<Self> {
+    #[pyo3(signature = (repo="mozilla-central", log_requests=false, progress=None))]
+    fn new(repo: &str, log_requests: bool, progress: Option<PyObject>) -> PyResult<Self> {
         let runtime = Runtime::new().map_err(|e| {
             SearchfoxNetworkError::new_err(format!("Failed to create runtime: {}", e))
         })?;
 
-        let client = RustClient::new(repo.to_string(), log_requests)
-            .map_err(|e| to_py_err("Failed to create client".into(), e))?;
+        let client = new_client(repo, log_requests, progress)?;
 
         Ok(Self {
             inner: Arc::new(client),
@@ -97,7 +162,7 @@ impl SearchfoxClient {
         })
     }
 
-    #[pyo3(signature = (query=None, path=None, case=None, regexp=None, limit=None, context=None, symbol=None, id=None, langs=None, tests=None))]
+    #[pyo3(signature = (query=None, path=None, case=None, regexp=None, limit=None, context=None, symbol=None, id=None, langs=None, tests=None, sort=None))]
     fn search(
         &self,
         py: Python<'_>,
@@ -111,18 +176,28 @@ impl SearchfoxClient {
         id: Option<String>,
         langs: Option<Vec<String>>,
         tests: Option<String>,
+        sort: Option<String>,
     ) -> PyResult<Vec<(String, usize, String)>> {
         let options = SearchOptions {
             query,
             path,
+            extra_paths: Vec::new(),
             case: case.unwrap_or(false),
             regexp: regexp.unwrap_or(false),
+            verbatim: false,
             limit: limit.unwrap_or(50),
+            offset: 0,
             context,
             symbol,
             id,
             lang: parse_langs(langs)?,
+            extensions: Vec::new(),
             category_filter: parse_category_filter(tests.as_deref())?,
+            filter: None,
+            rank: false,
+            sort: parse_sort(sort.as_deref())?,
+            keep_duplicates: false,
+            max_per_file: None,
         };
 
         let client = self.inner.clone();
@@ -166,6 +241,36 @@ impl SearchfoxClient {
         }
     }
 
+    /// Indexed identifiers starting with `prefix`, qualified or not, as
+    /// `(name, kind, path, line)` tuples sorted by name.
+    #[pyo3(signature = (prefix, path=None, limit=None))]
+    fn complete(
+        &self,
+        py: Python<'_>,
+        prefix: String,
+        path: Option<String>,
+        limit: Option<usize>,
+    ) -> PyResult<Vec<(String, String, String, usize)>> {
+        let options = SearchOptions {
+            path,
+            ..Default::default()
+        };
+        let client = self.inner.clone();
+        let results = py.allow_threads(|| {
+            self.runtime
+                .block_on(async move { client.complete(&prefix, &options).await })
+        });
+
+        match results {
+            Ok(completions) => Ok(completions
+                .into_iter()
+                .take(limit.unwrap_or(50))
+                .map(|c| (c.name, c.kind.to_string(), c.path, c.line))
+                .collect()),
+            Err(e) => Err(to_py_err("Completion failed".into(), e)),
+        }
+    }
+
     fn get_file(&self, py: Python<'_>, path: String) -> PyResult<String> {
         let client = self.inner.clone();
         let result = py.allow_threads(|| {
@@ -176,6 +281,21 @@ impl SearchfoxClient {
         result.map_err(|e| to_py_err("Failed to get file".into(), e))
     }
 
+    fn get_file_lines(
+        &self,
+        py: Python<'_>,
+        path: String,
+        lines: String,
+    ) -> PyResult<Vec<(usize, String)>> {
+        let client = self.inner.clone();
+        let result = py.allow_threads(|| {
+            self.runtime
+                .block_on(async move { client.get_file_lines(&path, &lines).await })
+        });
+
+        result.map_err(|e| to_py_err("Failed to get file".into(), e))
+    }
+
     fn get_file_at_revision(
         &self,
         py: Python<'_>,
@@ -368,17 +488,16 @@ struct AsyncSearchfoxClient {
 #[pymethods]
 impl AsyncSearchfoxClient {
     #[new]
-    #[pyo3(signature = (repo="mozilla-central", log_requests=false))]
-    fn new(repo: &str, log_requests: bool) -> PyResult<Self> {
-        let client = RustClient::new(repo.to_string(), log_requests)
-            .map_err(|e| to_py_err("Failed to create client".into(), e))?;
+    #[pyo3(signature = (repo="mozilla-central", log_requests=false, progress=None))]
+    fn new(repo: &str, log_requests: bool, progress: Option<PyObject>) -> PyResult<Self> {
+        let client = new_client(repo, log_requests, progress)?;
 
         Ok(Self {
             inner: Arc::new(client),
         })
     }
 
-    #[pyo3(signature = (query=None, path=None, case=None, regexp=None, limit=None, context=None, symbol=None, id=None, langs=None, tests=None))]
+    #[pyo3(signature = (query=None, path=None, case=None, regexp=None, limit=None, context=None, symbol=None, id=None, langs=None, tests=None, sort=None))]
     fn search<'py>(
         &self,
         py: Python<'py>,
@@ -392,18 +511,28 @@ impl AsyncSearchfoxClient {
         id: Option<String>,
         langs: Option<Vec<String>>,
         tests: Option<String>,
+        sort: Option<String>,
     ) -> PyResult<Bound<'py, PyAny>> {
         let options = SearchOptions {
             query,
             path,
+            extra_paths: Vec::new(),
             case: case.unwrap_or(false),
             regexp: regexp.unwrap_or(false),
+            verbatim: false,
             limit: limit.unwrap_or(50),
+            offset: 0,
             context,
             symbol,
             id,
             lang: parse_langs(langs)?,
+            extensions: Vec::new(),
             category_filter: parse_category_filter(tests.as_deref())?,
+            filter: None,
+            rank: false,
+            sort: parse_sort(sort.as_deref())?,
+            keep_duplicates: false,
+            max_per_file: None,
         };
 
         let client = self.inner.clone();
@@ -445,6 +574,35 @@ impl AsyncSearchfoxClient {
         })
     }
 
+    /// Indexed identifiers starting with `prefix`, qualified or not, as
+    /// `(name, kind, path, line)` tuples sorted by name.
+    #[pyo3(signature = (prefix, path=None, limit=None))]
+    fn complete<'py>(
+        &self,
+        py: Python<'py>,
+        prefix: String,
+        path: Option<String>,
+        limit: Option<usize>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let options = SearchOptions {
+            path,
+            ..Default::default()
+        };
+        let client = self.inner.clone();
+        future_into_py(py, async move {
+            let completions = client
+                .complete(&prefix, &options)
+                .await
+                .map_err(|e| to_py_err("Completion failed".into(), e))?;
+
+            Ok(completions
+                .into_iter()
+                .take(limit.unwrap_or(50))
+                .map(|c| (c.name, c.kind.to_string(), c.path, c.line))
+                .collect::<Vec<_>>())
+        })
+    }
+
     fn get_file<'py>(&self, py: Python<'py>, path: String) -> PyResult<Bound<'py, PyAny>> {
         let client = self.inner.clone();
         future_into_py(py, async move {
@@ -455,6 +613,21 @@ impl AsyncSearchfoxClient {
         })
     }
 
+    fn get_file_lines<'py>(
+        &self,
+        py: Python<'py>,
+        path: String,
+        lines: String,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let client = self.inner.clone();
+        future_into_py(py, async move {
+            client
+                .get_file_lines(&path, &lines)
+                .await
+                .map_err(|e| to_py_err("Failed to get file".into(), e))
+        })
+    }
+
     fn get_file_at_revision<'py>(
         &self,
         py: Python<'py>,